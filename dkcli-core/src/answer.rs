@@ -0,0 +1,355 @@
+//! Turning an answer file into the settings of an installation, checking
+//! everything in it against the recipe, the machine and the daemon.
+
+use std::{
+    fmt::{self, Display},
+    path::PathBuf,
+};
+
+use anyhow::Result;
+
+use crate::{
+    client::{get_devices, get_partitions, Dbus, DkClient, Notice},
+    config::{
+        input_method_packages, InstallConfig, Keyboard, LocaleConfig, NtpConfig, UserConfig,
+        UserPassword,
+    },
+    dbus::DbusMethod,
+    error::ClientError,
+    gpu::{classify, gpu_driver_packages, list_gpus, Graphics},
+    host::{host_keymaps, host_xkb_layouts},
+    parser::{find_timezone_by_prefix, list_locales, list_zoneinfo, PasswdEntry},
+    platform::{
+        self, board_config, find_board, host_secure_boot, is_firmware_partition, secure_boot_for,
+        BootLayout,
+    },
+    recipe::{
+        get_arch_name, get_variant, is_desktop, is_foreign_machine, memory_shortfall, release_for,
+        Recipe,
+    },
+    validate::{self, normalize_fullname, AvatarError, HostnameError, UsernameError},
+};
+
+/// What is wrong with an answer file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnswerError {
+    /// The recipe has no variant of this name
+    Variant(String),
+    /// There is no board of this name
+    Board(String),
+    /// The machine boots with EFI, but no `efi_disk` is given
+    EfiFieldNotSet,
+    /// The board boots from a firmware partition, but no `firmware_part`
+    /// is given
+    FirmwareFieldNotSet,
+    Fullname(char),
+    Username(UsernameError),
+    Hostname(HostnameError),
+    /// A locale there is no such one of
+    Locale(String),
+    /// A time zone there is no such one of, with one it may have been
+    /// meant to be
+    Timezone {
+        given: String,
+        suggested: Option<String>,
+    },
+    Avatar(AvatarError),
+    PasswordHash,
+    /// Either a password or its hash has to be given, and not both
+    PasswordOrHash,
+    /// A user or group ID outside of the regular range
+    Id(u32),
+    NtpServer(HostnameError),
+    Keymap(String),
+    X11Layout(String),
+    /// `target_part` is not a partition large enough for the release
+    TargetPartition,
+    /// `efi_disk` is not a partition there is
+    EfiPartition,
+    /// `firmware_part` is not a partition the board can boot from
+    FirmwarePartition,
+}
+
+impl Display for AnswerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Variant(name) => write!(f, "Unknown variant: {name}"),
+            Self::Board(id) => write!(f, "Unknown board: {id}"),
+            Self::EfiFieldNotSet => f.write_str("efi_disk is not set"),
+            Self::FirmwareFieldNotSet => f.write_str("firmware_part is not set"),
+            Self::Fullname(c) => write!(f, "Full name contains {c:?}"),
+            Self::Username(e) => write!(f, "Invalid user name: {e:?}"),
+            Self::Hostname(e) => write!(f, "Invalid hostname: {e:?}"),
+            Self::Locale(locale) => write!(f, "Unknown locale: {locale}"),
+            Self::Timezone { given, .. } => write!(f, "Unknown time zone: {given}"),
+            Self::Avatar(e) => write!(f, "Invalid avatar: {e:?}"),
+            Self::PasswordHash => f.write_str("Invalid password hash"),
+            Self::PasswordOrHash => f.write_str("Either password or password_hash is required"),
+            Self::Id(id) => write!(f, "ID out of range: {id}"),
+            Self::NtpServer(e) => write!(f, "Invalid NTP server: {e:?}"),
+            Self::Keymap(keymap) => write!(f, "Unknown keymap: {keymap}"),
+            Self::X11Layout(layout) => write!(f, "Unknown X11 layout: {layout}"),
+            Self::TargetPartition => f.write_str("Invalid target partition"),
+            Self::EfiPartition => f.write_str("Invalid EFI system partition"),
+            Self::FirmwarePartition => f.write_str("Invalid firmware partition"),
+        }
+    }
+}
+
+impl std::error::Error for AnswerError {}
+
+/// The installation `config` asks for, with the variant from `recipe`, the
+/// partitions as the daemon lists them, and the users of `import_users`
+/// carried over, the one of the same name keeping its IDs. `memory` is that
+/// of the machine, if known; nothing is partitioned when installing into
+/// `target_dir`.
+pub async fn from_config(
+    dk_client: &DkClient,
+    recipe: Recipe,
+    config: UserConfig,
+    mut import_users: Vec<PasswdEntry>,
+    memory: Option<u64>,
+    target_dir: Option<PathBuf>,
+) -> Result<InstallConfig> {
+    let variant = get_variant(recipe, &config.variant)
+        .ok_or_else(|| AnswerError::Variant(config.variant.clone()))?;
+    let cand = release_for(&variant)?;
+    if let Some(required) = memory_shortfall(&variant, memory) {
+        dk_client.notice(Notice::LowMemory {
+            variant: &variant,
+            memory: memory.unwrap_or_default(),
+            required,
+        });
+    }
+    // Nothing is partitioned, and no bootloader set up, in a directory
+    let into_dir = target_dir.is_some();
+    let secure_boot = secure_boot_for(host_secure_boot(&dk_client.bus), cand).filter(|_| !into_dir);
+    if let Some(secure_boot) = secure_boot {
+        dk_client.notice(Notice::SecureBoot(secure_boot));
+    }
+
+    let devices = match into_dir {
+        true => vec![],
+        false => get_devices(dk_client).await?,
+    }
+    .into_iter()
+    .filter(|x| {
+        if config.offline_install {
+            x.size as f64 > cand.inst_size as f64 * 1.25
+        } else {
+            x.size > cand.inst_size + cand.download_size
+        }
+    })
+    .collect::<Vec<_>>();
+
+    let board = match &config.board {
+        _ if into_dir => None,
+        Some(id) => match find_board(id) {
+            Some(board) => Some(board),
+            None => return Err(AnswerError::Board(id.clone()).into()),
+        },
+        None => {
+            let board = platform::detect();
+            if board.is_none() && get_arch_name() == Some("loongarch64") {
+                dk_client.notice(Notice::LoongarchFirmwareUnknown);
+            }
+            board
+        }
+    };
+    if board.is_some_and(|x| x.layout == BootLayout::Efi32) {
+        dk_client.notice(Notice::EfiIa32);
+    }
+    let firmware = board.is_some_and(|x| x.layout == BootLayout::FirmwarePartition);
+    let skips_esp = board.is_some_and(|x| x.config().skips_esp());
+
+    let mut target_part = None;
+    let mut efi_disk = None;
+    let mut firmware_part = None;
+
+    // Boards booting from a firmware partition or through Petitboot have no
+    // use for an EFI system partition, even if their firmware offers EFI
+    let is_efi = !into_dir
+        && !skips_esp
+        && Dbus::run(dk_client, DbusMethod::IsEFI)
+            .await?
+            .data
+            .as_bool()
+            .ok_or(ClientError::BadReply("IsEFI"))?;
+
+    let path_is = |x: &Option<PathBuf>, path: &str| {
+        x.as_ref().is_some_and(|x| x.display().to_string() == path)
+    };
+
+    for d in devices {
+        let partitions = get_partitions(dk_client, &d.path).await?;
+        if let Some(path) = &config.target_part {
+            if let Some(v) = partitions.iter().find(|x| path_is(&x.path, path)) {
+                target_part = Some(v.to_owned());
+            }
+        }
+
+        if is_efi {
+            let Some(path) = &config.efi_disk else {
+                return Err(AnswerError::EfiFieldNotSet.into());
+            };
+            if let Some(v) = partitions.iter().find(|x| path_is(&x.path, path)) {
+                efi_disk = Some(v.to_owned());
+            }
+        }
+
+        if firmware {
+            let Some(path) = &config.firmware_part else {
+                return Err(AnswerError::FirmwareFieldNotSet.into());
+            };
+            if let Some(v) = partitions.iter().find(|x| path_is(&x.path, path)) {
+                firmware_part = Some(v.to_owned());
+            }
+        }
+    }
+
+    let fullname = config.fullname.as_deref().map(normalize_fullname);
+    if let Some(fullname) = &fullname {
+        validate::fullname(fullname).map_err(AnswerError::Fullname)?;
+    }
+    validate::username(&config.user).map_err(AnswerError::Username)?;
+    validate::hostname(&config.hostname).map_err(AnswerError::Hostname)?;
+
+    let locales = list_locales()?;
+    // Answer files are written by those who know what they are after
+    let timezones = list_zoneinfo(true)?;
+
+    let locale = LocaleConfig::new(config.locale, config.extra_locales.unwrap_or_default());
+
+    let mut extra_packages = if config.input_method.unwrap_or(false) {
+        input_method_packages(&locale.generate)
+    } else {
+        vec![]
+    };
+
+    match config.gpu_driver {
+        Some(install) => extra_packages.extend(gpu_driver_packages(install)),
+        None if is_desktop(&variant)
+            && !is_foreign_machine()
+            && classify(&list_gpus()) != Graphics::Other =>
+        {
+            dk_client.notice(Notice::GpuDriverSuggested);
+        }
+        None => {}
+    }
+
+    for l in &locale.generate {
+        if locales.iter().all(|x| x.data != *l) {
+            return Err(AnswerError::Locale(l.clone()).into());
+        }
+    }
+
+    if timezones.iter().all(|x| x.name() != config.timezone) {
+        let suggested = find_timezone_by_prefix(&timezones, &config.timezone)
+            .first()
+            .map(|x| x.name().to_string());
+
+        return Err(AnswerError::Timezone {
+            given: config.timezone,
+            suggested,
+        }
+        .into());
+    }
+
+    if let Some(avatar) = &config.avatar {
+        validate::avatar(avatar).map_err(AnswerError::Avatar)?;
+    }
+
+    let existing_user = import_users
+        .iter()
+        .position(|x| x.username == config.user)
+        .map(|i| import_users.remove(i));
+
+    let password = match (config.password, config.password_hash) {
+        (Some(password), None) => UserPassword::Plain(password),
+        (None, Some(hash)) if validate::password_hash(&hash) => UserPassword::Hashed(hash),
+        (None, Some(_)) => return Err(AnswerError::PasswordHash.into()),
+        _ => return Err(AnswerError::PasswordOrHash.into()),
+    };
+
+    if let Some(id) = [config.uid, config.gid]
+        .into_iter()
+        .flatten()
+        .find(|x| !validate::id(*x))
+    {
+        return Err(AnswerError::Id(id).into());
+    }
+
+    let ntp = if config.ntp.is_some() || config.ntp_servers.is_some() {
+        let servers = config.ntp_servers.unwrap_or_default();
+
+        for i in &servers {
+            validate::ntp_server(i).map_err(AnswerError::NtpServer)?;
+        }
+
+        Some(NtpConfig {
+            enable: config.ntp.unwrap_or(true),
+            servers,
+        })
+    } else {
+        None
+    };
+
+    let keyboard = if config.keymap.is_some() || config.x11_layout.is_some() {
+        let console = config.keymap.unwrap_or_else(|| "us".to_string());
+        let x11 = config.x11_layout.unwrap_or_else(|| "us".to_string());
+
+        if host_keymaps(&dk_client.bus)?.iter().all(|x| *x != console) {
+            return Err(AnswerError::Keymap(console).into());
+        }
+
+        if host_xkb_layouts(&dk_client.bus)?
+            .iter()
+            .all(|(x, _)| *x != x11)
+        {
+            return Err(AnswerError::X11Layout(x11).into());
+        }
+
+        Some(Keyboard { console, x11 })
+    } else {
+        None
+    };
+
+    if target_part.is_none() && !into_dir {
+        return Err(AnswerError::TargetPartition.into());
+    }
+
+    if efi_disk.is_none() && is_efi {
+        return Err(AnswerError::EfiPartition.into());
+    }
+
+    if firmware && !firmware_part.as_ref().is_some_and(is_firmware_partition) {
+        return Err(AnswerError::FirmwarePartition.into());
+    }
+
+    Ok(InstallConfig {
+        offline_install: config.offline_install,
+        variant,
+        fullname,
+        user: config.user,
+        uid: config.uid.or(existing_user.as_ref().map(|x| x.uid)),
+        gid: config.gid.or(existing_user.as_ref().map(|x| x.gid)),
+        avatar: config.avatar,
+        import_users,
+        password,
+        autologin: config.autologin.unwrap_or(false),
+        hostname: config.hostname,
+        timezone: config.timezone,
+        rtc_as_localtime: config.rtc_as_localtime,
+        ntp,
+        target_part,
+        target_dir,
+        efi_disk,
+        locale,
+        swapfile_size: config.swapfile_size.unwrap_or(0.0),
+        keyboard,
+        extra_packages,
+        board: board.and_then(board_config),
+        firmware_part,
+        secure_boot,
+    })
+}
@@ -0,0 +1,208 @@
+//! How far dkcli got with an installation, kept in a state file so that
+//! after a crash it can pick up from there, rather than asking everything
+//! again or sending the daemon its settings twice.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::{InstallConfig, UserPassword};
+
+/// Stages of an installation, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// All settings are known, with the release picked from the recipe
+    Configured,
+    /// The daemon holds the settings and found nothing wrong with them
+    ConfigSent,
+    InstallStarted,
+}
+
+/// The password is never saved; a loaded checkpoint has an empty one, to be
+/// asked again and put back with [`Checkpoint::set_password`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub stage: Stage,
+    pub config: InstallConfig,
+    /// Settings as sent to the daemon, by name, once sent
+    #[serde(default)]
+    pub sent_config: Vec<(String, String)>,
+}
+
+impl Checkpoint {
+    /// The checkpoint left behind by an earlier run, if any and readable.
+    pub fn load() -> Option<Self> {
+        Self::load_from(&crate::state_dir().ok()?)
+    }
+
+    /// The checkpoint in the state directory `dir`, if any and readable.
+    pub fn load_from(dir: &Path) -> Option<Self> {
+        let s = fs::read_to_string(checkpoint_path(dir)).ok()?;
+        let mut json: Value = serde_json::from_str(&s).ok()?;
+        let config = json.get_mut("config")?.as_object_mut()?;
+        strip_password(config);
+        config.insert("password".to_string(), Value::String(String::new()));
+
+        let mut checkpoint: Self = serde_json::from_value(json).ok()?;
+        for (field, value) in &mut checkpoint.sent_config {
+            if field == "user" {
+                *value = user_without_password(value);
+            }
+        }
+
+        Some(checkpoint)
+    }
+
+    /// Save the checkpoint, without the password.
+    pub fn save(&self) -> Result<()> {
+        self.save_in(&crate::state_dir()?)
+    }
+
+    /// Save the checkpoint in the state directory `dir`, readable by the
+    /// owner only as it still tells who the user is.
+    pub fn save_in(&self, dir: &Path) -> Result<()> {
+        let mut checkpoint = self.clone();
+        for (field, value) in &mut checkpoint.sent_config {
+            if field == "user" {
+                *value = user_without_password(value);
+            }
+        }
+        let mut json = serde_json::to_value(&checkpoint)?;
+        if let Some(config) = json["config"].as_object_mut() {
+            strip_password(config);
+        }
+
+        fs::create_dir_all(dir)?;
+        let path = checkpoint_path(dir);
+
+        // Written aside and renamed, so that a crash never leaves half of it
+        let tmp = path.with_extension("json.tmp");
+        let mut f = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&tmp)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        f.write_all(serde_json::to_string_pretty(&json)?.as_bytes())?;
+        f.sync_all()?;
+        fs::rename(&tmp, &path).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Put back the password asked again after loading, in the settings and
+    /// in the `user` setting as sent, so that it still compares equal to
+    /// what the daemon holds if the same password was given.
+    pub fn set_password(&mut self, password: UserPassword) -> Result<()> {
+        for (field, value) in &mut self.sent_config {
+            if field != "user" {
+                continue;
+            }
+
+            let mut user: Value = serde_json::from_str(value)?;
+            if let Some(user) = user.as_object_mut() {
+                strip_password(user);
+                let (key, secret) = match &password {
+                    UserPassword::Plain(x) => ("password", x),
+                    UserPassword::Hashed(x) => ("password_hash", x),
+                };
+                user.insert(key.to_string(), Value::String(secret.clone()));
+            }
+            *value = serde_json::to_string(&user)?;
+        }
+        self.config.password = password;
+
+        Ok(())
+    }
+
+    /// Remove the checkpoint, once the installation is over one way or
+    /// another.
+    pub fn clear() -> Result<()> {
+        Self::clear_in(&crate::state_dir()?)
+    }
+
+    /// Remove the checkpoint in the state directory `dir`.
+    pub fn clear_in(dir: &Path) -> Result<()> {
+        let path = checkpoint_path(dir);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn checkpoint_path(dir: &Path) -> PathBuf {
+    dir.join("checkpoint.json")
+}
+
+fn strip_password(object: &mut serde_json::Map<String, Value>) {
+    object.remove("password");
+    object.remove("password_hash");
+}
+
+/// The `user` setting as sent, without the password. Left as is should it
+/// not be JSON, which it always is.
+fn user_without_password(user: &str) -> String {
+    let Ok(mut json) = serde_json::from_str::<Value>(user) else {
+        return user.to_string();
+    };
+    if let Some(object) = json.as_object_mut() {
+        strip_password(object);
+    }
+
+    json.to_string()
+}
+
+#[test]
+fn test_checkpoint() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("dkcli-checkpoint-{}", std::process::id()));
+
+    assert!(Checkpoint::load_from(&dir).is_none());
+    Checkpoint::clear_in(&dir).unwrap();
+
+    let user = r#"{"username":"aosc","full_name":"AOSC User","password":"anthon"}"#;
+    let checkpoint = Checkpoint {
+        stage: Stage::ConfigSent,
+        config: crate::config::test_install_config(),
+        sent_config: vec![
+            ("hostname".to_string(), "aosc-pc".to_string()),
+            ("user".to_string(), user.to_string()),
+        ],
+    };
+    checkpoint.save_in(&dir).unwrap();
+
+    let path = checkpoint_path(&dir);
+    assert_eq!(path.metadata().unwrap().permissions().mode() & 0o777, 0o600);
+    assert!(!fs::read_to_string(&path).unwrap().contains("anthon"));
+
+    let mut loaded = Checkpoint::load_from(&dir).unwrap();
+    assert_eq!(loaded.stage, Stage::ConfigSent);
+    assert_eq!(loaded.sent_config[0], checkpoint.sent_config[0]);
+    assert_eq!(loaded.config.password, UserPassword::Plain(String::new()));
+    assert!(!loaded.sent_config[1].1.contains("password"));
+    assert!(Stage::Configured < Stage::ConfigSent && Stage::ConfigSent < Stage::InstallStarted);
+
+    loaded
+        .set_password(checkpoint.config.password.clone())
+        .unwrap();
+    assert_eq!(loaded.config.password, checkpoint.config.password);
+    assert_eq!(
+        serde_json::from_str::<Value>(&loaded.sent_config[1].1).unwrap(),
+        serde_json::from_str::<Value>(user).unwrap()
+    );
+
+    Checkpoint::clear_in(&dir).unwrap();
+    assert!(Checkpoint::load_from(&dir).is_none());
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,720 @@
+//! The connection to the Deploykit daemon: calling its methods with retries
+//! and timeouts, picking up where it left off when the daemon restarts,
+//! and sending it the installation settings.
+
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use log::debug;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::time::sleep;
+use tokio_util::sync::CancellationToken;
+use zbus::{proxy, zvariant::OwnedObjectPath, Result as zResult};
+
+use crate::{
+    config::{
+        config_value, skipped_config, Device, DkPartition, DkUserConfig, DownloadConfig,
+        InstallConfig, SwapConfig, UserPassword, CONFIG_FIELDS,
+    },
+    dbus::{
+        Bus, Daemon, DaemonVersion, DbusFailure, DbusMethod, DeploykitBackend, RetryPolicy,
+        API_LEVEL_BOARD, API_LEVEL_CLAIM, API_LEVEL_EXTRA_CONFIG, API_LEVEL_SECURE_BOOT,
+        API_LEVEL_TARGET_DIR, API_LEVEL_VALIDATE,
+    },
+    error::{ClientError, DaemonError},
+    host::DEPLOYKIT_UNIT,
+    platform::{BoardConfig, SecureBoot},
+    recipe::{release_for, Variant},
+};
+
+// Seconds to wait for a (re)started daemon
+const RECONNECT_TRIES: u32 = 10;
+
+/// Something for the user to know of, which the frontend puts into words.
+#[derive(Debug)]
+pub enum Notice<'a> {
+    /// The daemon was not running, and is being started
+    StartingDaemon,
+    /// The connection to the daemon broke calling `method`, and is being
+    /// made again
+    Reconnecting {
+        method: &'static str,
+        error: &'a zbus::Error,
+    },
+    /// The daemon is back, with the settings it had
+    Reconnected,
+    /// The daemon is too old for these settings, which are left at its
+    /// defaults
+    SkippedConfig(&'a [&'static str]),
+    /// This LoongArch machine has firmware of unknown generation
+    LoongarchFirmwareUnknown,
+    /// This 64-bit machine was started by 32-bit UEFI
+    EfiIa32,
+    /// A desktop is installed on a machine whose GPU wants a driver, and
+    /// the answer file does not say whether to install it
+    GpuDriverSuggested,
+    /// The machine has less memory than `variant` needs to run well
+    LowMemory {
+        variant: &'a Variant,
+        memory: u64,
+        required: u64,
+    },
+    /// How the installed system is to get past Secure Boot
+    SecureBoot(SecureBoot),
+    /// A partition mounted for a look at it could not be unmounted, and its
+    /// mount point is left in place
+    UmountFailed(&'a str),
+}
+
+/// What a frontend does for the client: telling the user what goes on.
+pub trait Frontend: Send + Sync {
+    fn notice(&self, notice: Notice<'_>);
+}
+
+/// Tells nobody, for tests and tools with nobody to tell.
+impl Frontend for () {
+    fn notice(&self, _: Notice<'_>) {}
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Dbus {
+    pub result: DbusResult,
+    pub data: Value,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+pub enum DbusResult {
+    Ok,
+    Error,
+}
+
+/// Version of the daemon behind `backend`.
+async fn daemon_version(backend: &dyn DeploykitBackend) -> Result<DaemonVersion> {
+    match backend.call(DbusMethod::GetVersion).await {
+        Ok(s) => Ok(serde_json::from_value(Dbus::try_from(s)?.data)?),
+        Err(zbus::Error::MethodError(name, _, _))
+            if name.as_str() == "org.freedesktop.DBus.Error.UnknownMethod" =>
+        {
+            Ok(DaemonVersion::default())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl Dbus {
+    /// Call `method`, giving up with [`ClientError::Interrupted`] after
+    /// Ctrl-C.
+    pub async fn run(client: &DkClient, method: DbusMethod<'_>) -> Result<Self> {
+        if !method.is_cancellable() {
+            return Self::run_uncancelled(client, method).await;
+        }
+
+        tokio::select! {
+            res = Self::run_uncancelled(client, method) => res,
+            _ = client.cancel.cancelled() => Err(ClientError::Interrupted.into()),
+        }
+    }
+
+    async fn run_uncancelled(client: &DkClient, method: DbusMethod<'_>) -> Result<Self> {
+        let mut delays = method
+            .retry_policy()
+            .into_iter()
+            .flat_map(RetryPolicy::delays);
+
+        // Only the transport is retried: errors reported by the daemon
+        // itself come out of try_from below
+        let s = loop {
+            let backend = client.backend();
+            let call = backend.call(method);
+            let res = match client.timeout.filter(|_| !method.is_slow()) {
+                Some(timeout) => match tokio::time::timeout(timeout, call).await {
+                    Ok(res) => res,
+                    // A daemon that sat on a call this long is stuck, e.g. on
+                    // a hung device, and is not going to do better next time
+                    Err(_) => {
+                        return Err(ClientError::Timeout {
+                            method: method.name(),
+                            secs: timeout.as_secs(),
+                        }
+                        .into())
+                    }
+                },
+                None => call.await,
+            };
+
+            let e = match res {
+                Ok(s) => break s,
+                Err(e) => e,
+            };
+
+            let failure = DbusFailure::of(&e);
+            if failure == DbusFailure::Denied {
+                return Err(ClientError::NotAuthorized {
+                    method: method.name(),
+                    source: e,
+                }
+                .into());
+            }
+
+            let Some(delay) = delays.next().filter(|_| failure != DbusFailure::Other) else {
+                return Err(e.into());
+            };
+
+            if failure == DbusFailure::Disconnected {
+                client.notice(Notice::Reconnecting {
+                    method: method.name(),
+                    error: &e,
+                });
+                client.reconnect().await?;
+            } else {
+                debug!(method = method.name(); "D-Bus call failed ({e}), retrying in {delay:?}");
+                sleep(delay).await;
+            }
+        };
+
+        let res = Self::try_from(s).inspect_err(|e| {
+            debug!(method = method.name(); "Daemon call failed: {e:#}");
+        })?;
+
+        match method {
+            DbusMethod::SetConfig(field, value) => client.remember_config(field, value),
+            DbusMethod::ResetConfig => client.forget_config(),
+            _ => {}
+        }
+
+        Ok(res)
+    }
+}
+
+impl TryFrom<String> for Dbus {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> std::prelude::v1::Result<Self, <Dbus as TryFrom<String>>::Error> {
+        let res = serde_json::from_str::<Dbus>(&value)?;
+
+        match res.result {
+            DbusResult::Ok => Ok(res),
+            DbusResult::Error => Err(DaemonError::from(res.data).into()),
+        }
+    }
+}
+
+#[proxy(
+    interface = "org.freedesktop.systemd1.Manager",
+    default_service = "org.freedesktop.systemd1",
+    default_path = "/org/freedesktop/systemd1"
+)]
+trait Systemd {
+    async fn start_unit(&self, name: &str, mode: &str) -> zResult<OwnedObjectPath>;
+}
+
+/// Ask systemd to start the daemon, for live systems where it isn't
+/// started at boot or bus activated, and wait for it to answer.
+async fn start_daemon(bus: &Bus) -> Result<Daemon> {
+    let conn = bus.connect().await?;
+    SystemdProxy::new(&conn.conn)
+        .await?
+        .start_unit(DEPLOYKIT_UNIT, "replace")
+        .await?;
+
+    wait_for_daemon(bus).await
+}
+
+/// How this installer introduces itself to the daemon, so that others it
+/// turns away know who is in control.
+fn controller_name() -> String {
+    format!("dkcli (PID {})", std::process::id())
+}
+
+/// Connect to the daemon, waiting up to RECONNECT_TRIES seconds for it to
+/// answer.
+async fn wait_for_daemon(bus: &Bus) -> Result<Daemon> {
+    let mut tries = 0;
+
+    loop {
+        let res = async {
+            let daemon = Daemon::connect(bus).await?;
+            daemon.proxy.ping().await?;
+            Ok::<_, anyhow::Error>(daemon)
+        }
+        .await;
+
+        match res {
+            Ok(daemon) => return Ok(daemon),
+            Err(_) if tries < RECONNECT_TRIES => {
+                tries += 1;
+                sleep(Duration::from_secs(1)).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Connection to the Deploykit daemon, picking up where it left off if the
+/// daemon restarts.
+pub struct DkClient {
+    pub bus: Bus,
+    timeout: Option<Duration>,
+    api_level: u32,
+    backend: RwLock<Arc<dyn DeploykitBackend>>,
+    frontend: Arc<dyn Frontend>,
+    // Configuration sent so far, to send again to a restarted daemon
+    config: Mutex<Vec<(String, String)>>,
+    /// Cancelled on Ctrl-C, giving up on the calls that may be given up on
+    pub cancel: CancellationToken,
+    // Whether the daemon was claimed, to claim a restarted daemon again
+    claimed: AtomicBool,
+}
+
+impl DkClient {
+    /// Connect to the daemon, starting it if it isn't running.
+    pub async fn connect(bus: Bus, timeout_secs: u64, frontend: Arc<dyn Frontend>) -> Result<Self> {
+        let daemon = Daemon::connect(&bus).await?;
+
+        let daemon = match daemon.proxy.ping().await {
+            Ok(_) => daemon,
+            Err(e) if DbusFailure::of(&e) == DbusFailure::Disconnected => {
+                debug!("Deploykit is not running: {e}");
+                frontend.notice(Notice::StartingDaemon);
+                start_daemon(&bus)
+                    .await
+                    .map_err(|e| ClientError::NotRunning {
+                        unit: DEPLOYKIT_UNIT,
+                        source: e.into(),
+                    })?
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        Self::new(bus, Arc::new(daemon), timeout_secs, frontend).await
+    }
+
+    /// A client of `backend`, which is reached again on `bus` should it go
+    /// away.
+    pub async fn new(
+        bus: Bus,
+        backend: Arc<dyn DeploykitBackend>,
+        timeout_secs: u64,
+        frontend: Arc<dyn Frontend>,
+    ) -> Result<Self> {
+        let version = daemon_version(&*backend).await?;
+        debug!(
+            "Deploykit {} (API level {})",
+            version.version.as_deref().unwrap_or("unknown"),
+            version.api_level
+        );
+
+        Ok(Self {
+            bus,
+            timeout: (timeout_secs > 0).then(|| Duration::from_secs(timeout_secs)),
+            api_level: version.api_level,
+            backend: RwLock::new(backend),
+            frontend,
+            config: Mutex::new(vec![]),
+            cancel: CancellationToken::new(),
+            claimed: AtomicBool::new(false),
+        })
+    }
+
+    /// Whether the daemon implements API level `level`.
+    pub fn supports(&self, level: u32) -> bool {
+        self.api_level >= level
+    }
+
+    /// Tell the user of `notice` through the frontend.
+    pub fn notice(&self, notice: Notice<'_>) {
+        self.frontend.notice(notice);
+    }
+
+    fn backend(&self) -> Arc<dyn DeploykitBackend> {
+        self.backend.read().unwrap().clone()
+    }
+
+    /// Connect again, waiting a little for the daemon to come back, and
+    /// restore the configuration it had.
+    async fn reconnect(&self) -> Result<()> {
+        let backend = wait_for_daemon(&self.bus).await?;
+
+        if self.claimed.load(Ordering::Relaxed) {
+            let owner = controller_name();
+            Dbus::try_from(backend.call(DbusMethod::Claim(&owner)).await?)?;
+        }
+
+        let config = self.config.lock().unwrap().clone();
+        for (field, value) in &config {
+            Dbus::try_from(backend.call(DbusMethod::SetConfig(field, value)).await?)?;
+        }
+
+        *self.backend.write().unwrap() = Arc::new(backend);
+        self.notice(Notice::Reconnected);
+
+        Ok(())
+    }
+
+    /// Become the only client allowed to configure the daemon. Fails with a
+    /// DaemonErrorKind::Busy error if another one got there first. Older
+    /// daemons have no notion of this, so anything goes with them.
+    pub async fn claim(&self) -> Result<()> {
+        if !self.supports(API_LEVEL_CLAIM) {
+            debug!("Deploykit is too old to be claimed");
+            return Ok(());
+        }
+
+        Dbus::run(self, DbusMethod::Claim(&controller_name())).await?;
+        self.claimed.store(true, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    fn remember_config(&self, field: &str, value: &str) {
+        let mut config = self.config.lock().unwrap();
+        config.retain(|(x, _)| x != field);
+        config.push((field.to_string(), value.to_string()));
+    }
+
+    /// Take `config` as sent, after picking up from a checkpoint.
+    pub fn restore_config(&self, config: &[(String, String)]) {
+        for (field, value) in config {
+            self.remember_config(field, value);
+        }
+    }
+
+    /// The settings sent so far, as `(field, value)` pairs.
+    pub fn sent_config(&self) -> Vec<(String, String)> {
+        self.config.lock().unwrap().clone()
+    }
+
+    fn forget_config(&self) {
+        self.config.lock().unwrap().clear();
+    }
+}
+
+/// Whether `e` comes from a call given up on after Ctrl-C.
+pub fn is_interrupted(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<ClientError>(),
+        Some(ClientError::Interrupted)
+    )
+}
+
+/// Disks the daemon can install to.
+pub async fn get_devices(dk_client: &DkClient) -> Result<Vec<Device>> {
+    let devices = Dbus::run(dk_client, DbusMethod::ListDevice).await?;
+    let devices: Vec<Device> = serde_json::from_value(devices.data)?;
+
+    Ok(devices)
+}
+
+/// Partitions on `device` (a path such as `/dev/sda`), as the daemon lists
+/// them.
+pub async fn get_partitions(dk_client: &DkClient, device: &str) -> Result<Vec<DkPartition>> {
+    let partitions = Dbus::run(dk_client, DbusMethod::ListPartitions(device)).await?;
+    let partitions = serde_json::from_value(partitions.data)?;
+
+    Ok(partitions)
+}
+
+/// Memory of the machine, none if the daemon cannot tell, in which case
+/// nothing is checked against it.
+pub async fn get_memory(dk_client: &DkClient) -> Option<u64> {
+    match Dbus::run(dk_client, DbusMethod::GetMemory).await {
+        Ok(reply) => reply.data.as_u64(),
+        Err(e) => {
+            debug!("Could not get the memory size: {e:#}");
+            None
+        }
+    }
+}
+
+/// Whether the daemon still holds all of the `sent` settings.
+pub async fn config_held(dk_client: &DkClient, sent: &[(String, String)]) -> Result<bool> {
+    if sent.is_empty() {
+        return Ok(false);
+    }
+
+    for (field, value) in sent {
+        let recorded = match Dbus::run(dk_client, DbusMethod::GetConfig(field)).await {
+            Ok(res) => config_value(res.data),
+            Err(e) if is_interrupted(&e) => return Err(e),
+            Err(_) => return Ok(false),
+        };
+
+        if recorded != config_value(Value::String(value.clone())) {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// A setting as sent to the daemon and as it recorded it, none if it could
+/// not be read back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedConfig {
+    pub field: String,
+    pub sent: Value,
+    pub recorded: Option<Value>,
+}
+
+impl RecordedConfig {
+    /// Whether the daemon recorded what was sent.
+    pub fn matches(&self) -> bool {
+        self.recorded.as_ref() == Some(&self.sent)
+    }
+}
+
+/// Read back every setting sent to the daemon.
+pub async fn read_back_config(dk_client: &DkClient) -> Result<Vec<RecordedConfig>> {
+    let mut settings = vec![];
+
+    for (field, sent) in dk_client.sent_config() {
+        let recorded = match Dbus::run(dk_client, DbusMethod::GetConfig(&field)).await {
+            Ok(res) => Some(config_value(res.data)),
+            Err(e) if is_interrupted(&e) => return Err(e),
+            Err(e) => {
+                debug!("Failed to read back {field}: {e}");
+                None
+            }
+        };
+
+        settings.push(RecordedConfig {
+            field,
+            sent: config_value(Value::String(sent)),
+            recorded,
+        });
+    }
+
+    Ok(settings)
+}
+
+/// Settings a fresh daemon would not have, left behind by an installer that
+/// did not get to finish or clean up, as `(field, value)` pairs.
+pub async fn leftover_config(dk_client: &DkClient) -> Result<Vec<(&'static str, Value)>> {
+    let mut leftovers = vec![];
+
+    for field in CONFIG_FIELDS {
+        // Unset settings are either null or an error, depending on the daemon
+        let value = match Dbus::run(dk_client, DbusMethod::GetConfig(field)).await {
+            Ok(res) => config_value(res.data),
+            Err(e) if is_interrupted(&e) => return Err(e),
+            Err(_) => continue,
+        };
+
+        if !value.is_null() {
+            leftovers.push((*field, value));
+        }
+    }
+
+    Ok(leftovers)
+}
+
+/// Have the daemon check the settings as a whole, returning every problem it
+/// found. Older daemons only find them while installing.
+pub async fn validate_config(dk_client: &DkClient) -> Result<Vec<DaemonError>> {
+    if !dk_client.supports(API_LEVEL_VALIDATE) {
+        debug!("Deploykit is too old to validate the settings");
+        return Ok(vec![]);
+    }
+
+    let res = Dbus::run(dk_client, DbusMethod::ValidateConfig).await?;
+
+    Ok(DaemonError::list(res.data))
+}
+
+/// Send the daemon all of `config`, refusing what it is too old to take
+/// where the installed system would not boot without it, and leaving out,
+/// with a notice, what it can do without.
+pub async fn set_config(dk_client: &DkClient, config: &InstallConfig) -> Result<()> {
+    let variant = &config.variant;
+    let sqfs = release_for(variant)?;
+
+    // Installing as if it were any other machine would not boot
+    if config.board.is_some() && !dk_client.supports(API_LEVEL_BOARD) {
+        return Err(ClientError::Unsupported("board").into());
+    }
+    if config.secure_boot == Some(SecureBoot::Shim) && !dk_client.supports(API_LEVEL_SECURE_BOOT) {
+        return Err(ClientError::Unsupported("secure_boot").into());
+    }
+    if config.target_dir.is_some() && !dk_client.supports(API_LEVEL_TARGET_DIR) {
+        return Err(ClientError::Unsupported("target_dir").into());
+    }
+    // Without the hash, the user would have no password to log in with
+    if matches!(config.password, UserPassword::Hashed(_))
+        && !dk_client.supports(API_LEVEL_EXTRA_CONFIG)
+    {
+        return Err(ClientError::Unsupported("password_hash").into());
+    }
+
+    let download = if !config.offline_install {
+        DownloadConfig::Http {
+            url: format!("https://releases.aosc.io/{}", sqfs.path),
+            hash: sqfs.sha256sum.clone(),
+        }
+    } else {
+        let variant = config.variant.dir_name.as_ref().unwrap();
+        DownloadConfig::Dir(Path::new("/run/livekit/sysroots").join(variant))
+    };
+
+    let download = serde_json::to_string(&download)?;
+    Dbus::run(dk_client, DbusMethod::SetConfig("download", &download)).await?;
+
+    // Older daemons take a single locale and none of the newer settings,
+    // which are left at their defaults then
+    let extra_config = dk_client.supports(API_LEVEL_EXTRA_CONFIG);
+    if !extra_config {
+        let skipped = skipped_config(config);
+        if !skipped.is_empty() {
+            dk_client.notice(Notice::SkippedConfig(&skipped));
+        }
+    }
+
+    let locale_config = if extra_config {
+        serde_json::to_string(&config.locale)?
+    } else {
+        config.locale.default.clone()
+    };
+    Dbus::run(dk_client, DbusMethod::SetConfig("locale", &locale_config)).await?;
+
+    let (password, password_hash) = match &config.password {
+        UserPassword::Plain(password) => (Some(password.clone()), None),
+        UserPassword::Hashed(hash) => (None, Some(hash.clone())),
+    };
+
+    let user = DkUserConfig {
+        username: config.user.clone(),
+        full_name: config.fullname.clone(),
+        password,
+        password_hash,
+        uid: config.uid.filter(|_| extra_config),
+        gid: config.gid.filter(|_| extra_config),
+        avatar: config.avatar.clone().filter(|_| extra_config),
+    };
+
+    let user = serde_json::to_string(&user)?;
+    Dbus::run(dk_client, DbusMethod::SetConfig("user", &user)).await?;
+
+    // Autologin is off unless asked for, so only send it when enabled. The
+    // daemon picks the display manager or getty depending on the variant.
+    if config.autologin && extra_config {
+        Dbus::run(dk_client, DbusMethod::SetConfig("autologin", "true")).await?;
+    }
+
+    Dbus::run(
+        dk_client,
+        DbusMethod::SetConfig("timezone", &config.timezone),
+    )
+    .await?;
+
+    Dbus::run(
+        dk_client,
+        DbusMethod::SetConfig("hostname", &config.hostname),
+    )
+    .await?;
+    Dbus::run(
+        dk_client,
+        DbusMethod::SetConfig("rtc_as_localtime", &(config.rtc_as_localtime).to_string()),
+    )
+    .await?;
+
+    if let Some(ntp) = config.ntp.as_ref().filter(|_| extra_config) {
+        let ntp_config = serde_json::to_string(ntp)?;
+        Dbus::run(dk_client, DbusMethod::SetConfig("ntp", &ntp_config)).await?;
+    }
+
+    let swap_config = serde_json::to_string(&SwapConfig::from_gib(config.swapfile_size))?;
+    Dbus::run(dk_client, DbusMethod::SetConfig("swapfile", &swap_config)).await?;
+
+    if let Some(target) = &config.target_part {
+        let part_config = serde_json::to_string(target)?;
+
+        Dbus::run(
+            dk_client,
+            DbusMethod::SetConfig("target_partition", &part_config),
+        )
+        .await?;
+    }
+
+    if let Some(dir) = &config.target_dir {
+        let dir_config = serde_json::to_string(dir)?;
+        Dbus::run(dk_client, DbusMethod::SetConfig("target_dir", &dir_config)).await?;
+    }
+
+    if let Some(efi) = &config.efi_disk {
+        let part_config = serde_json::to_string(&efi)?;
+        Dbus::run(
+            dk_client,
+            DbusMethod::SetConfig("efi_partition", &part_config),
+        )
+        .await?;
+    }
+
+    if !config.extra_packages.is_empty() && extra_config {
+        let packages = serde_json::to_string(&config.extra_packages)?;
+        Dbus::run(
+            dk_client,
+            DbusMethod::SetConfig("extra_packages", &packages),
+        )
+        .await?;
+    }
+
+    if !config.import_users.is_empty() && extra_config {
+        let users = serde_json::to_string(&config.import_users)?;
+        Dbus::run(dk_client, DbusMethod::SetConfig("import_users", &users)).await?;
+    }
+
+    if let Some(keyboard) = config.keyboard.as_ref().filter(|_| extra_config) {
+        let keyboard_config = serde_json::to_string(keyboard)?;
+        Dbus::run(
+            dk_client,
+            DbusMethod::SetConfig("keyboard", &keyboard_config),
+        )
+        .await?;
+    }
+
+    if let Some(board) = &config.board {
+        set_board(dk_client, board).await?;
+    }
+
+    if let Some(firmware) = &config.firmware_part {
+        let part_config = serde_json::to_string(firmware)?;
+        Dbus::run(
+            dk_client,
+            DbusMethod::SetConfig("firmware_partition", &part_config),
+        )
+        .await?;
+    }
+
+    // Disabling Secure Boot is for the user to do; only shim needs the
+    // daemon
+    if let Some(secure_boot @ SecureBoot::Shim) = config.secure_boot {
+        let secure_boot = serde_json::to_string(&secure_boot)?;
+        Dbus::run(
+            dk_client,
+            DbusMethod::SetConfig("secure_boot", &secure_boot),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Tell the daemon which board to install for, for the bootloader and
+/// firmware it takes.
+pub async fn set_board(dk_client: &DkClient, board: &BoardConfig) -> Result<()> {
+    if !dk_client.supports(API_LEVEL_BOARD) {
+        return Err(ClientError::Unsupported("board").into());
+    }
+
+    let board_config = serde_json::to_string(board)?;
+    Dbus::run(dk_client, DbusMethod::SetConfig("board", &board_config)).await?;
+
+    Ok(())
+}
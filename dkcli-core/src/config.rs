@@ -0,0 +1,484 @@
+//! Installation settings: those of an answer file, those the wizard ends up
+//! with, and how they are sent to the daemon.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    parser::PasswdEntry,
+    platform::{BoardConfig, SecureBoot},
+    recipe::Variant,
+};
+
+/// Settings a frontend may send to the daemon
+pub const CONFIG_FIELDS: &[&str] = &[
+    "download",
+    "locale",
+    "user",
+    "autologin",
+    "timezone",
+    "hostname",
+    "rtc_as_localtime",
+    "ntp",
+    "swapfile",
+    "target_partition",
+    "efi_partition",
+    "extra_packages",
+    "import_users",
+    "keyboard",
+    "board",
+    "firmware_partition",
+    "secure_boot",
+    "target_dir",
+];
+
+/// Everything the installation needs to know, however it was found out.
+///
+/// Serializes to the same thing once read back, whether as JSON or TOML. The
+/// password comes out as `password` or `password_hash` as in an answer file,
+/// a plain one in the clear, so what is serialized has to be kept from
+/// others or go through the redacting of the frontend first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallConfig {
+    pub offline_install: bool,
+    pub variant: Variant,
+    pub fullname: Option<String>,
+    pub user: String,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub avatar: Option<PathBuf>,
+    pub import_users: Vec<PasswdEntry>,
+    #[serde(flatten)]
+    pub password: UserPassword,
+    pub autologin: bool,
+    pub hostname: String,
+    pub timezone: String,
+    pub rtc_as_localtime: bool,
+    pub ntp: Option<NtpConfig>,
+    /// None when installing into `target_dir`
+    pub target_part: Option<DkPartition>,
+    /// Directory to install into rather than a partition, with nothing
+    /// partitioned and no bootloader set up
+    #[serde(default)]
+    pub target_dir: Option<PathBuf>,
+    pub efi_disk: Option<DkPartition>,
+    pub locale: LocaleConfig,
+    pub swapfile_size: f64,
+    pub keyboard: Option<Keyboard>,
+    pub extra_packages: Vec<String>,
+    /// Single-board computer to lay the disk out for
+    #[serde(default)]
+    pub board: Option<BoardConfig>,
+    /// FAT partition the firmware of the board boots from, in place of an
+    /// EFI system partition
+    #[serde(default)]
+    pub firmware_part: Option<DkPartition>,
+    /// How to boot with Secure Boot enabled, none if it is not
+    #[serde(default)]
+    pub secure_boot: Option<SecureBoot>,
+}
+
+/// An answer file (`--config`), for installing without asking anything.
+#[derive(Debug, Deserialize)]
+pub struct UserConfig {
+    pub offline_install: bool,
+    pub variant: String,
+    pub fullname: Option<String>,
+    pub user: String,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub avatar: Option<PathBuf>,
+    pub import_users_from: Option<PathBuf>,
+    pub password: Option<String>,
+    pub password_hash: Option<String>,
+    pub autologin: Option<bool>,
+    pub hostname: String,
+    pub timezone: String,
+    pub rtc_as_localtime: bool,
+    pub ntp: Option<bool>,
+    pub ntp_servers: Option<Vec<String>>,
+    /// Not needed when installing into a directory
+    pub target_part: Option<String>,
+    pub efi_disk: Option<String>,
+    pub locale: String,
+    pub extra_locales: Option<Vec<String>>,
+    pub swapfile_size: Option<f64>,
+    pub keymap: Option<String>,
+    pub x11_layout: Option<String>,
+    pub input_method: Option<bool>,
+    /// Install the proprietary NVIDIA driver, for desktops on NVIDIA GPUs
+    pub gpu_driver: Option<bool>,
+    /// Board ID, detected on single-board computers if not set
+    pub board: Option<String>,
+    /// FAT firmware partition path, for boards that boot from one
+    pub firmware_part: Option<String>,
+}
+
+/// A storage device, as the daemon lists it.
+#[derive(Debug, Deserialize)]
+pub struct Device {
+    pub model: String,
+    pub path: String,
+    pub size: u64,
+}
+
+/// A partition, as the daemon lists it and takes it back in settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DkPartition {
+    pub path: Option<PathBuf>,
+    pub parent_path: Option<PathBuf>,
+    pub fs_type: Option<String>,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum UserPassword {
+    #[serde(rename = "password")]
+    Plain(String),
+    /// A crypt(3) hash, as found in shadow(5)
+    #[serde(rename = "password_hash")]
+    Hashed(String),
+}
+
+/// Where the daemon gets the system release from (`download` setting).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DownloadConfig {
+    Http {
+        url: String,
+        hash: String,
+    },
+    /// A system release unpacked on the live media
+    Dir(PathBuf),
+}
+
+/// The user to create (`user` setting). Exactly one of `password` and
+/// `password_hash` is set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DkUserConfig {
+    pub username: String,
+    pub full_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    /// Registered with AccountsService, together with the full name, so
+    /// the display manager shows both on first boot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar: Option<PathBuf>,
+}
+
+/// Swap file to create (`swapfile` setting).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SwapConfig {
+    Disable,
+    /// Size in bytes
+    Custom(u64),
+}
+
+impl SwapConfig {
+    /// A swap file of `size` GiB, none if 0.
+    pub fn from_gib(size: f64) -> Self {
+        if size == 0.0 {
+            SwapConfig::Disable
+        } else {
+            SwapConfig::Custom((size * 1024.0 * 1024.0 * 1024.0) as u64)
+        }
+    }
+}
+
+/// Locales to generate on the installed system; `default` is always part of
+/// `generate` and becomes the system-wide `LANG`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocaleConfig {
+    pub default: String,
+    pub generate: Vec<String>,
+}
+
+impl LocaleConfig {
+    pub fn new(default: String, extra: Vec<String>) -> Self {
+        let mut generate = vec![default.clone()];
+        for i in extra {
+            if !generate.contains(&i) {
+                generate.push(i);
+            }
+        }
+
+        Self { default, generate }
+    }
+}
+
+/// systemd-timesyncd settings; an empty server list keeps the defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NtpConfig {
+    pub enable: bool,
+    pub servers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keyboard {
+    pub console: String,
+    pub x11: String,
+}
+
+/// A setting as JSON, whether it was sent or recorded as a JSON string or
+/// as JSON itself, so that the two compare equal.
+pub fn config_value(value: Value) -> Value {
+    match value {
+        Value::String(s) => serde_json::from_str(&s).unwrap_or(Value::String(s)),
+        v => v,
+    }
+}
+
+/// Input method and font packages needed to type and display the given
+/// locales, for scripts that cannot be entered with a plain keyboard layout.
+pub fn input_method_packages(locales: &[String]) -> Vec<String> {
+    let mut packages = vec![];
+
+    for locale in locales {
+        let lang = locale.split(['_', '.', '@']).next().unwrap_or_default();
+        let list: &[&str] = match lang {
+            "zh" => &["fcitx5", "fcitx5-chinese-addons", "noto-cjk-fonts"],
+            "ja" => &["fcitx5", "fcitx5-mozc", "noto-cjk-fonts"],
+            "ko" => &["fcitx5", "fcitx5-hangul", "noto-cjk-fonts"],
+            "hi" | "mr" | "bn" | "gu" | "pa" | "or" | "ta" | "te" | "kn" | "ml" => {
+                &["fcitx5", "fcitx5-m17n", "noto-fonts"]
+            }
+            _ => &[],
+        };
+
+        for i in list {
+            if !packages.iter().any(|x| x == i) {
+                packages.push(i.to_string());
+            }
+        }
+    }
+
+    packages
+}
+
+/// Settings in `config` that a daemon from before API_LEVEL_EXTRA_CONFIG
+/// does not know about.
+pub fn skipped_config(config: &InstallConfig) -> Vec<&'static str> {
+    let mut skipped = vec![];
+    if config.locale.generate.len() > 1 {
+        skipped.push("extra_locales");
+    }
+    if config.autologin {
+        skipped.push("autologin");
+    }
+    if config.ntp.is_some() {
+        skipped.push("ntp");
+    }
+    if !config.extra_packages.is_empty() {
+        skipped.push("extra_packages");
+    }
+    if !config.import_users.is_empty() {
+        skipped.push("import_users");
+    }
+    if config.keyboard.is_some() {
+        skipped.push("keyboard");
+    }
+    if config.uid.is_some() {
+        skipped.push("uid");
+    }
+    if config.gid.is_some() {
+        skipped.push("gid");
+    }
+    if config.avatar.is_some() {
+        skipped.push("avatar");
+    }
+    if matches!(config.password, UserPassword::Hashed(_)) {
+        skipped.push("password_hash");
+    }
+
+    skipped
+}
+
+#[test]
+fn test_config_value() {
+    use serde_json::json;
+
+    assert_eq!(
+        config_value(json!(r#"{"Custom": 1024}"#)),
+        json!({"Custom": 1024})
+    );
+    assert_eq!(config_value(json!("Asia/Shanghai")), json!("Asia/Shanghai"));
+    assert_eq!(config_value(json!(r#""Disable""#)), json!("Disable"));
+    assert_eq!(config_value(json!(true)), config_value(json!("true")));
+}
+
+#[test]
+fn test_config_payloads() {
+    use serde_json::json;
+
+    let download = DownloadConfig::Http {
+        url: "https://releases.aosc.io/os-amd64/base.squashfs".to_string(),
+        hash: "abc".to_string(),
+    };
+    assert_eq!(
+        serde_json::to_value(&download).unwrap(),
+        json!({"Http": {"url": "https://releases.aosc.io/os-amd64/base.squashfs", "hash": "abc"}})
+    );
+    assert_eq!(
+        serde_json::to_value(DownloadConfig::Dir("/run/livekit/sysroots/base".into())).unwrap(),
+        json!({"Dir": "/run/livekit/sysroots/base"})
+    );
+
+    let user = DkUserConfig {
+        username: "aosc".to_string(),
+        full_name: Some("AOSC User".to_string()),
+        password: None,
+        password_hash: Some("$6$salt$hash".to_string()),
+        uid: Some(1001),
+        gid: None,
+        avatar: None,
+    };
+    assert_eq!(
+        serde_json::to_value(&user).unwrap(),
+        json!({
+            "username": "aosc",
+            "full_name": "AOSC User",
+            "password_hash": "$6$salt$hash",
+            "uid": 1001,
+        })
+    );
+
+    assert_eq!(
+        serde_json::to_string(&SwapConfig::from_gib(0.0)).unwrap(),
+        r#""Disable""#
+    );
+    assert_eq!(
+        serde_json::to_value(SwapConfig::from_gib(1.5)).unwrap(),
+        json!({"Custom": 1610612736})
+    );
+}
+
+#[test]
+fn test_input_method_packages() {
+    assert!(input_method_packages(&["en_US.UTF-8".to_string()]).is_empty());
+    assert_eq!(
+        input_method_packages(&["zh_CN.UTF-8".to_string(), "ja_JP.UTF-8".to_string()]),
+        [
+            "fcitx5",
+            "fcitx5-chinese-addons",
+            "noto-cjk-fonts",
+            "fcitx5-mozc"
+        ]
+    );
+}
+
+#[test]
+fn test_answer_file_snapshot() {
+    let config: UserConfig = toml::from_str(include_str!("../../example.toml")).unwrap();
+    insta::assert_debug_snapshot!("example_toml", config);
+
+    // Only what has no default
+    let config: UserConfig = toml::from_str(
+        r#"
+        offline_install = false
+        variant = "Base"
+        user = "aosc"
+        hostname = "aosc"
+        timezone = "UTC"
+        rtc_as_localtime = false
+        target_part = "/dev/vda2"
+        locale = "C.UTF-8"
+        "#,
+    )
+    .unwrap();
+    insta::assert_debug_snapshot!("minimal_answer_file", config);
+}
+
+/// Settings for an installation to a made-up disk, for tests.
+#[cfg(test)]
+pub(crate) fn test_install_config() -> InstallConfig {
+    use crate::recipe::Squashfs;
+
+    let partition = |path: &str, fs_type: &str, size| DkPartition {
+        path: Some(path.into()),
+        parent_path: Some("/dev/sda".into()),
+        fs_type: Some(fs_type.to_string()),
+        size,
+    };
+
+    InstallConfig {
+        offline_install: false,
+        variant: Variant {
+            name: "Base".to_string(),
+            dir_name: Some("base".to_string()),
+            retro: false,
+            description: None,
+            squashfs: vec![Squashfs {
+                arch: "amd64".to_string(),
+                date: "20250101".to_string(),
+                download_size: 1024,
+                inst_size: 4096,
+                path: "os-amd64/base.squashfs".to_string(),
+                sha256sum: "abc".to_string(),
+                inodes: 0,
+                signed_shim: false,
+            }],
+        },
+        fullname: Some("AOSC User".to_string()),
+        user: "aosc".to_string(),
+        uid: Some(1000),
+        gid: None,
+        avatar: None,
+        import_users: vec![PasswdEntry {
+            username: "old".to_string(),
+            uid: 1001,
+            gid: 1001,
+            full_name: "Old User".to_string(),
+        }],
+        password: UserPassword::Plain("anthon".to_string()),
+        autologin: true,
+        hostname: "aosc-pc".to_string(),
+        timezone: "Asia/Shanghai".to_string(),
+        rtc_as_localtime: false,
+        ntp: Some(NtpConfig {
+            enable: true,
+            servers: vec!["pool.ntp.org".to_string()],
+        }),
+        target_part: Some(partition("/dev/sda2", "ext4", 64 << 30)),
+        target_dir: None,
+        efi_disk: Some(partition("/dev/sda1", "vfat", 512 << 20)),
+        locale: LocaleConfig::new("zh_CN.UTF-8".to_string(), vec!["en_US.UTF-8".to_string()]),
+        swapfile_size: 1.5,
+        keyboard: Some(Keyboard {
+            console: "us".to_string(),
+            x11: "us".to_string(),
+        }),
+        extra_packages: vec!["fcitx5".to_string()],
+        board: None,
+        firmware_part: None,
+        secure_boot: None,
+    }
+}
+
+#[test]
+fn test_install_config_round_trip() {
+    let mut config = test_install_config();
+
+    let json = serde_json::to_value(&config).unwrap();
+    insta::assert_json_snapshot!("install_config", json);
+    let back: InstallConfig = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(back.password, config.password);
+    assert_eq!(serde_json::to_value(&back).unwrap(), json);
+
+    config.password = UserPassword::Hashed("$6$salt$hash".to_string());
+    let json = serde_json::to_value(&config).unwrap();
+    assert_eq!(json["password_hash"], "$6$salt$hash");
+    assert!(json.get("password").is_none());
+
+    let toml = toml::to_string(&config).unwrap();
+    let back: InstallConfig = toml::from_str(&toml).unwrap();
+    assert_eq!(back.password, config.password);
+    assert_eq!(toml::to_string(&back).unwrap(), toml);
+    assert_eq!(serde_json::to_value(&back).unwrap(), json);
+}
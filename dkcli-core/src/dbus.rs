@@ -0,0 +1,453 @@
+//! The Deploykit D-Bus interface: its methods, how calls to them may be
+//! retried, and the buses the daemon may be found on.
+
+use std::{
+    future::Future,
+    io,
+    os::fd::OwnedFd,
+    pin::Pin,
+    process::{Child, Stdio},
+    str::FromStr,
+    time::Duration,
+};
+
+use serde::Deserialize;
+use zbus::{proxy, AuthMechanism, Connection, Result as zResult};
+
+/// API level from which the daemon takes the autologin, ntp, extra_packages,
+/// import_users and keyboard settings, a list of locales to generate, and
+/// the IDs, avatar and password hash of the user
+pub const API_LEVEL_EXTRA_CONFIG: u32 = 1;
+/// API level from which the daemon lets one client claim it, so that two
+/// installers cannot interleave their settings
+pub const API_LEVEL_CLAIM: u32 = 2;
+/// API level from which the daemon checks the settings as a whole before
+/// installing
+pub const API_LEVEL_VALIDATE: u32 = 3;
+/// API level from which the daemon takes the board setting, to boot
+/// single-board computers
+pub const API_LEVEL_BOARD: u32 = 4;
+/// API level from which the daemon takes the secure_boot setting, to set up
+/// shim and have its key enrolled
+pub const API_LEVEL_SECURE_BOOT: u32 = 5;
+/// API level from which the daemon installs into the directory of the
+/// target_dir setting, with no partitions or bootloader
+pub const API_LEVEL_TARGET_DIR: u32 = 6;
+
+/// The Deploykit daemon's D-Bus interface. Every method answers with a JSON
+/// [`Dbus`](crate::client::Dbus) reply in a string.
+#[proxy(
+    interface = "io.aosc.Deploykit1",
+    default_service = "io.aosc.Deploykit",
+    default_path = "/io/aosc/Deploykit"
+)]
+pub trait Deploykit {
+    #[zbus(allow_interactive_auth)]
+    async fn set_config(&self, field: &str, value: &str) -> zResult<String>;
+    async fn get_config(&self, field: &str) -> zResult<String>;
+    async fn get_progress(&self) -> zResult<String>;
+    #[zbus(allow_interactive_auth)]
+    async fn reset_config(&self) -> zResult<String>;
+    async fn get_list_devices(&self) -> zResult<String>;
+    #[zbus(allow_interactive_auth)]
+    async fn auto_partition(&self, dev: &str) -> zResult<String>;
+    #[zbus(allow_interactive_auth)]
+    async fn start_install(&self) -> zResult<String>;
+    async fn get_auto_partition_progress(&self) -> zResult<String>;
+    async fn get_list_partitions(&self, dev: &str) -> zResult<String>;
+    async fn get_recommend_swap_size(&self) -> zResult<String>;
+    async fn get_memory(&self) -> zResult<String>;
+    async fn find_esp_partition(&self, dev: &str) -> zResult<String>;
+    #[zbus(allow_interactive_auth)]
+    async fn cancel_install(&self) -> zResult<String>;
+    async fn disk_is_right_combo(&self, dev: &str) -> zResult<String>;
+    async fn ping(&self) -> zResult<String>;
+    async fn get_all_esp_partitions(&self) -> zResult<String>;
+    #[zbus(allow_interactive_auth)]
+    async fn reset_progress_status(&self) -> zResult<String>;
+    #[zbus(allow_interactive_auth)]
+    async fn sync_disk(&self) -> zResult<String>;
+    #[zbus(allow_interactive_auth)]
+    async fn sync_and_reboot(&self) -> zResult<String>;
+    async fn is_lvm_device(&self, dev: &str) -> zResult<String>;
+    async fn is_efi(&self) -> zResult<String>;
+    async fn get_version(&self) -> zResult<String>;
+    #[zbus(allow_interactive_auth)]
+    async fn claim(&self, owner: &str) -> zResult<String>;
+    async fn validate_config(&self) -> zResult<String>;
+}
+
+/// Version of the daemon and of the API it implements. Daemons from before
+/// `get_version` count as API level 0.
+#[derive(Debug, Default, Deserialize)]
+pub struct DaemonVersion {
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub api_level: u32,
+}
+
+/// Something that answers Deploykit calls: the daemon itself, or a stand-in
+/// for trying out the interface without one.
+pub trait DeploykitBackend: Send + Sync {
+    fn call<'a>(&'a self, method: DbusMethod<'a>) -> BoxFuture<'a, zResult<String>>;
+}
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// The daemon, as reached on a bus, together with what it takes to keep
+/// reaching it.
+pub struct Daemon {
+    pub proxy: DeploykitProxy<'static>,
+    _bridge: Option<Bridge>,
+}
+
+impl Daemon {
+    pub async fn connect(bus: &Bus) -> zResult<Self> {
+        let BusConnection { conn, bridge } = bus.connect().await?;
+        let proxy = DeploykitProxy::new(&conn).await?;
+
+        Ok(Self {
+            proxy,
+            _bridge: bridge,
+        })
+    }
+}
+
+impl DeploykitBackend for Daemon {
+    fn call<'a>(&'a self, method: DbusMethod<'a>) -> BoxFuture<'a, zResult<String>> {
+        self.proxy.call(method)
+    }
+}
+
+impl DeploykitBackend for DeploykitProxy<'static> {
+    fn call<'a>(&'a self, method: DbusMethod<'a>) -> BoxFuture<'a, zResult<String>> {
+        Box::pin(async move {
+            let s = match method {
+                DbusMethod::SetConfig(field, value) => self.set_config(field, value).await?,
+                DbusMethod::AutoPartition(p) => self.auto_partition(p).await?,
+                DbusMethod::GetProgress => self.get_progress().await?,
+                DbusMethod::StartInstall => self.start_install().await?,
+                DbusMethod::GetAutoPartitionProgress => self.get_auto_partition_progress().await?,
+                DbusMethod::ListPartitions(dev) => self.get_list_partitions(dev).await?,
+                DbusMethod::ListDevice => self.get_list_devices().await?,
+                DbusMethod::GetRecommendSwapSize => self.get_recommend_swap_size().await?,
+                DbusMethod::GetMemory => self.get_memory().await?,
+                DbusMethod::CancelInstall => self.cancel_install().await?,
+                DbusMethod::DiskIsRightCombo(dev) => self.disk_is_right_combo(dev).await?,
+                DbusMethod::GetAllEspPartitions => self.get_all_esp_partitions().await?,
+                DbusMethod::IsLvmDevice(dev) => self.is_lvm_device(dev).await?,
+                DbusMethod::IsEFI => self.is_efi().await?,
+                DbusMethod::ResetConfig => self.reset_config().await?,
+                DbusMethod::Claim(owner) => self.claim(owner).await?,
+                DbusMethod::GetConfig(field) => self.get_config(field).await?,
+                DbusMethod::ValidateConfig => self.validate_config().await?,
+                DbusMethod::GetVersion => self.get_version().await?,
+                DbusMethod::ResetProgressStatus => self.reset_progress_status().await?,
+                DbusMethod::SyncDisk => self.sync_disk().await?,
+                DbusMethod::SyncAndReboot => self.sync_and_reboot().await?,
+            };
+
+            Ok(s)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum DbusMethod<'a> {
+    SetConfig(&'a str, &'a str),
+    AutoPartition(&'a str),
+    GetProgress,
+    StartInstall,
+    GetAutoPartitionProgress,
+    ListPartitions(&'a str),
+    ListDevice,
+    GetRecommendSwapSize,
+    GetMemory,
+    CancelInstall,
+    DiskIsRightCombo(&'a str),
+    GetAllEspPartitions,
+    IsLvmDevice(&'a str),
+    IsEFI,
+    ResetConfig,
+    Claim(&'a str),
+    GetConfig(&'a str),
+    ValidateConfig,
+    GetVersion,
+    ResetProgressStatus,
+    SyncDisk,
+    SyncAndReboot,
+}
+
+impl DbusMethod<'_> {
+    /// The name of the daemon method.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DbusMethod::SetConfig(..) => "SetConfig",
+            DbusMethod::AutoPartition(_) => "AutoPartition",
+            DbusMethod::GetProgress => "GetProgress",
+            DbusMethod::StartInstall => "StartInstall",
+            DbusMethod::GetAutoPartitionProgress => "GetAutoPartitionProgress",
+            DbusMethod::ListPartitions(_) => "ListPartitions",
+            DbusMethod::ListDevice => "ListDevice",
+            DbusMethod::GetRecommendSwapSize => "GetRecommendSwapSize",
+            DbusMethod::GetMemory => "GetMemory",
+            DbusMethod::CancelInstall => "CancelInstall",
+            DbusMethod::DiskIsRightCombo(_) => "DiskIsRightCombo",
+            DbusMethod::GetAllEspPartitions => "GetAllEspPartitions",
+            DbusMethod::IsLvmDevice(_) => "IsLvmDevice",
+            DbusMethod::IsEFI => "IsEFI",
+            DbusMethod::ResetConfig => "ResetConfig",
+            DbusMethod::Claim(_) => "Claim",
+            DbusMethod::GetConfig(_) => "GetConfig",
+            DbusMethod::ValidateConfig => "ValidateConfig",
+            DbusMethod::GetVersion => "GetVersion",
+            DbusMethod::ResetProgressStatus => "ResetProgressStatus",
+            DbusMethod::SyncDisk => "SyncDisk",
+            DbusMethod::SyncAndReboot => "SyncAndReboot",
+        }
+    }
+
+    /// Whether calling this again after a failed attempt does no harm.
+    pub fn is_idempotent(&self) -> bool {
+        !matches!(
+            self,
+            DbusMethod::AutoPartition(_)
+                | DbusMethod::StartInstall
+                | DbusMethod::CancelInstall
+                | DbusMethod::SyncAndReboot
+        )
+    }
+
+    /// Whether the call may rightly take longer than any timeout, like
+    /// flushing gigabytes to a slow USB stick.
+    pub fn is_slow(&self) -> bool {
+        matches!(self, DbusMethod::SyncDisk | DbusMethod::SyncAndReboot)
+    }
+
+    /// How to retry the call after it failed on the way, if at all.
+    pub fn retry_policy(&self) -> Option<RetryPolicy> {
+        match self {
+            _ if !self.is_idempotent() => None,
+            DbusMethod::ListDevice
+            | DbusMethod::ListPartitions(_)
+            | DbusMethod::GetAllEspPartitions => Some(RetryPolicy::SCAN),
+            _ => Some(RetryPolicy::DEFAULT),
+        }
+    }
+
+    /// Whether the call may be abandoned halfway after Ctrl-C. Those that
+    /// clean up after it, or whose outcome would be unknown, may not.
+    pub fn is_cancellable(&self) -> bool {
+        !matches!(
+            self,
+            DbusMethod::StartInstall
+                | DbusMethod::CancelInstall
+                | DbusMethod::ResetConfig
+                | DbusMethod::ResetProgressStatus
+                | DbusMethod::SyncDisk
+                | DbusMethod::SyncAndReboot
+        )
+    }
+}
+
+/// How many times to call a method again after it failed on the way, waiting
+/// twice as long each time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub tries: u32,
+    pub delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub const DEFAULT: Self = Self {
+        tries: 5,
+        delay: Duration::from_millis(200),
+        max_delay: Duration::from_secs(5),
+    };
+
+    // Device scans wait on udev and slow USB storage, which may take a few
+    // seconds to settle after being plugged in
+    pub const SCAN: Self = Self {
+        tries: 8,
+        ..Self::DEFAULT
+    };
+
+    /// How long to wait before each retry.
+    pub fn delays(self) -> impl Iterator<Item = Duration> {
+        std::iter::successors(Some(self.delay), move |x| {
+            Some((*x * 2).min(self.max_delay))
+        })
+        .take(self.tries as usize)
+    }
+}
+
+/// How a D-Bus call failed, as far as retrying it is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DbusFailure {
+    /// The daemon or the bus went away
+    Disconnected,
+    /// The daemon did not answer in time
+    Timeout,
+    /// Polkit or the bus policy did not allow the call
+    Denied,
+    /// Anything else, which calling again is not going to fix
+    Other,
+}
+
+impl DbusFailure {
+    pub fn of(e: &zbus::Error) -> Self {
+        match e {
+            zbus::Error::InputOutput(e) if e.kind() == io::ErrorKind::TimedOut => Self::Timeout,
+            zbus::Error::InputOutput(_) => Self::Disconnected,
+            zbus::Error::MethodError(name, _, _) => match name.as_str() {
+                "org.freedesktop.DBus.Error.ServiceUnknown"
+                | "org.freedesktop.DBus.Error.NameHasNoOwner"
+                | "org.freedesktop.DBus.Error.Disconnected" => Self::Disconnected,
+                "org.freedesktop.DBus.Error.NoReply"
+                | "org.freedesktop.DBus.Error.Timeout"
+                | "org.freedesktop.DBus.Error.TimedOut" => Self::Timeout,
+                "org.freedesktop.DBus.Error.AccessDenied"
+                | "org.freedesktop.DBus.Error.InteractiveAuthorizationRequired"
+                | "org.freedesktop.PolicyKit1.Error.NotAuthorized" => Self::Denied,
+                _ => Self::Other,
+            },
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Message bus the Deploykit daemon is on. Other than the system bus, this
+/// is for running a daemon without root privileges during development, or
+/// for installing on another machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bus {
+    System,
+    Session,
+    Address(String),
+    /// System bus of a host reachable over SSH
+    Remote(String),
+}
+
+impl FromStr for Bus {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "system" => Ok(Bus::System),
+            "session" => Ok(Bus::Session),
+            _ => match s.strip_prefix("address:") {
+                Some(address) if !address.is_empty() => Ok(Bus::Address(address.to_string())),
+                _ => Err("expected system, session or address:<D-Bus address>".to_string()),
+            },
+        }
+    }
+}
+
+/// The `ssh` process carrying a connection to the bus of another machine,
+/// stopped once the connection is dropped.
+#[derive(Debug)]
+pub struct Bridge(Child);
+
+impl Drop for Bridge {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// A connection to a bus, to be kept together with its bridge, if any.
+pub struct BusConnection {
+    pub conn: Connection,
+    pub bridge: Option<Bridge>,
+}
+
+impl Bus {
+    pub async fn connect(&self) -> zResult<BusConnection> {
+        let conn = match self {
+            Bus::System => Connection::system().await?,
+            Bus::Session => Connection::session().await?,
+            Bus::Address(address) => {
+                zbus::connection::Builder::address(address.as_str())?
+                    .build()
+                    .await?
+            }
+            Bus::Remote(host) => {
+                let (stream, bridge) = tokio::net::UnixStream::pair()?;
+                let bridge = bridge.into_std()?;
+                bridge.set_nonblocking(false)?;
+                let bridge = OwnedFd::from(bridge);
+
+                // As `busctl --host` does: systemd-stdio-bridge forwards
+                // its standard input and output to the remote system bus
+                let bridge = Bridge(
+                    std::process::Command::new("ssh")
+                        .args(["-xT", "--", host, "systemd-stdio-bridge"])
+                        .stdin(Stdio::from(bridge.try_clone()?))
+                        .stdout(Stdio::from(bridge))
+                        .spawn()?,
+                );
+
+                // The bridge takes the local user ID on trust, as it cannot
+                // see who is on the other end of SSH
+                let conn = zbus::connection::Builder::unix_stream(stream)
+                    .auth_mechanism(AuthMechanism::External)
+                    .build()
+                    .await?;
+
+                return Ok(BusConnection {
+                    conn,
+                    bridge: Some(bridge),
+                });
+            }
+        };
+
+        Ok(BusConnection { conn, bridge: None })
+    }
+}
+
+#[test]
+fn test_dbus_failure() {
+    let io = |kind| zbus::Error::InputOutput(std::sync::Arc::new(io::Error::from(kind)));
+    assert_eq!(
+        DbusFailure::of(&io(io::ErrorKind::BrokenPipe)),
+        DbusFailure::Disconnected
+    );
+    assert_eq!(
+        DbusFailure::of(&io(io::ErrorKind::TimedOut)),
+        DbusFailure::Timeout
+    );
+    assert_eq!(
+        DbusFailure::of(&zbus::Error::InvalidReply),
+        DbusFailure::Other
+    );
+    assert_eq!(
+        DbusMethod::GetProgress.retry_policy(),
+        Some(RetryPolicy::DEFAULT)
+    );
+    assert_eq!(
+        DbusMethod::ListDevice.retry_policy(),
+        Some(RetryPolicy::SCAN)
+    );
+    assert_eq!(DbusMethod::StartInstall.retry_policy(), None);
+
+    let ms = |x| Duration::from_millis(x);
+    assert_eq!(
+        RetryPolicy::DEFAULT.delays().collect::<Vec<_>>(),
+        [ms(200), ms(400), ms(800), ms(1600), ms(3200)]
+    );
+    assert_eq!(RetryPolicy::SCAN.delays().last(), Some(ms(5000)));
+}
+
+#[test]
+fn test_parse_bus() {
+    assert_eq!("system".parse(), Ok(Bus::System));
+    assert_eq!("session".parse(), Ok(Bus::Session));
+    assert_eq!(
+        "address:unix:path=/tmp/dk.sock".parse(),
+        Ok(Bus::Address("unix:path=/tmp/dk.sock".to_string()))
+    );
+    assert!("address:".parse::<Bus>().is_err());
+    assert!("user".parse::<Bus>().is_err());
+}
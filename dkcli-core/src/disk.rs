@@ -0,0 +1,255 @@
+//! Disks of the machine and what is on them: how they are attached, whether
+//! they are about to fail, and the users of Linux installations already
+//! there.
+
+use std::{
+    ffi::{CString, OsString},
+    fs, io,
+    os::unix::ffi::OsStringExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, Context, Result};
+use log::debug;
+
+use crate::{
+    client::{get_devices, get_partitions, DkClient, Notice},
+    dbus::Bus,
+    host::host_command,
+    parser::{parse_passwd, PasswdEntry},
+    validate,
+};
+
+/// File systems an existing Linux installation may live on
+pub const LINUX_FS_TYPES: &[&str] = &["ext4", "ext3", "xfs", "btrfs", "f2fs", "jfs"];
+
+/// Transport of a block device (`usb`, `nvme`, ...), going by where it sits
+/// in the sysfs device tree.
+pub fn bus(dev: &str) -> Option<&'static str> {
+    let name = Path::new(dev).file_name()?;
+    let path = fs::canonicalize(Path::new("/sys/block").join(name)).ok()?;
+
+    bus_from_sysfs_path(&path.to_string_lossy())
+}
+
+fn bus_from_sysfs_path(path: &str) -> Option<&'static str> {
+    // USB comes first, as USB-SATA bridges show up as SCSI hosts below it
+    [
+        ("/usb", "usb"),
+        ("/nvme", "nvme"),
+        ("/mmc", "mmc"),
+        ("/virtio", "virtio"),
+        ("/ata", "sata"),
+        ("/target", "scsi"),
+    ]
+    .into_iter()
+    .find(|(needle, _)| path.contains(needle))
+    .map(|(_, bus)| bus)
+}
+
+/// Whether the kernel takes `dev` for removable media.
+pub fn is_removable(dev: &str) -> bool {
+    Path::new(dev)
+        .file_name()
+        .and_then(|name| {
+            fs::read_to_string(Path::new("/sys/block").join(name).join("removable")).ok()
+        })
+        .is_some_and(|x| x.trim() == "1")
+}
+
+/// Whether the SMART health self-assessment of `device` says it is about
+/// to fail. Devices without SMART, or machines without smartctl(8), pass.
+pub fn smart_failing(bus: &Bus, device: &str) -> bool {
+    match host_command(bus, &["smartctl", "-H", device]).output() {
+        // Bit 3 of the exit status: "SMART status check returned DISK
+        // FAILING"
+        Ok(out) => out.status.code().is_some_and(|x| x & 0x08 != 0),
+        Err(e) => {
+            debug!("Failed to run smartctl: {e}");
+            false
+        }
+    }
+}
+
+/// Linux installations on the disks the daemon lists, as the partition
+/// each is on and its regular users, for carrying the users over so that
+/// file ownership on a preserved /home stays consistent.
+pub async fn find_installs(dk_client: &DkClient) -> Result<Vec<(PathBuf, Vec<PasswdEntry>)>> {
+    let mut installs = vec![];
+
+    for device in get_devices(dk_client).await? {
+        for part in get_partitions(dk_client, &device.path).await? {
+            let (Some(path), Some(fs_type)) = (part.path, part.fs_type) else {
+                continue;
+            };
+
+            if !LINUX_FS_TYPES.contains(&fs_type.as_str()) {
+                continue;
+            }
+
+            match read_existing_users(dk_client, &path, &fs_type) {
+                Ok(users) if !users.is_empty() => installs.push((path, users)),
+                Ok(_) => {}
+                Err(e) => debug!("No installation found on {}: {e}", path.display()),
+            }
+        }
+    }
+
+    Ok(installs)
+}
+
+/// Mount `part`, of `fs_type`, read-only and return the regular users of
+/// the installation on it, if any.
+pub fn read_existing_users(
+    dk_client: &DkClient,
+    part: &Path,
+    fs_type: &str,
+) -> Result<Vec<PasswdEntry>> {
+    let passwd = with_mounted(dk_client, part, read_only_options(fs_type), |root| {
+        fs::read_to_string(root.join("etc/passwd"))
+    })?;
+
+    let users = parse_passwd(&passwd?)
+        .into_iter()
+        .filter(|x| validate::id(x.uid))
+        .collect();
+
+    Ok(users)
+}
+
+/// mount(8) options to mount a file system of `fs_type` read-only with.
+/// Journals and logs are not replayed either where the file system would
+/// otherwise do so even read-only, which would write to a disk the user may
+/// not have chosen to install to. Nothing on it is run or opened as a
+/// device, as it is mounted as root and may come from anyone.
+pub fn read_only_options(fs_type: &str) -> &'static str {
+    match fs_type {
+        "ext4" | "ext3" => "ro,noload,nosuid,nodev,noexec",
+        "xfs" | "f2fs" => "ro,norecovery,nosuid,nodev,noexec",
+        "btrfs" => "ro,rescue=nologreplay,nosuid,nodev,noexec",
+        _ => "ro,nosuid,nodev,noexec",
+    }
+}
+
+/// The type of the file system on `part`, as blkid(8) tells.
+pub fn probe_fs_type(part: &Path) -> Result<String> {
+    let out = Command::new("blkid")
+        .args(["--output", "value", "--match-tag", "TYPE"])
+        .arg(part)
+        .output()
+        .context("Failed to run blkid")?;
+    if !out.status.success() {
+        bail!("No file system found on {}", part.display());
+    }
+
+    Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+/// Mount `part` with mount(8) `options` for the duration of `f`, which gets
+/// the mount point.
+pub fn with_mounted<T>(
+    dk_client: &DkClient,
+    part: &Path,
+    options: &str,
+    f: impl FnOnce(&Path) -> T,
+) -> Result<T> {
+    let mountpoint = make_temp_dir("dkcli-mnt-")?;
+
+    let out = Command::new("mount")
+        .arg("-o")
+        .arg(options)
+        .arg(part)
+        .arg(&mountpoint)
+        .output()?;
+
+    if !out.status.success() {
+        let _ = fs::remove_dir(&mountpoint);
+        bail!("{}", String::from_utf8_lossy(&out.stderr).trim());
+    }
+
+    let res = f(&mountpoint);
+
+    // Left in place if still mounted, which a fresh mount point next time
+    // keeps from getting in the way
+    match Command::new("umount").arg(&mountpoint).output() {
+        Ok(out) if out.status.success() => {
+            let _ = fs::remove_dir(&mountpoint);
+        }
+        Ok(out) => dk_client.notice(Notice::UmountFailed(
+            String::from_utf8_lossy(&out.stderr).trim(),
+        )),
+        Err(e) => dk_client.notice(Notice::UmountFailed(&e.to_string())),
+    }
+
+    Ok(res)
+}
+
+/// Make a directory only the user can get into, in the temporary directory
+/// and named `prefix` followed by random characters.
+pub fn make_temp_dir(prefix: &str) -> Result<PathBuf> {
+    let template = std::env::temp_dir().join(format!("{prefix}XXXXXX"));
+    let mut template = CString::new(template.into_os_string().into_vec())?.into_bytes_with_nul();
+
+    // SAFETY: `template` is owned, mutable and NUL-terminated, as mkdtemp
+    // takes it, and outlives the call.
+    if unsafe { libc::mkdtemp(template.as_mut_ptr().cast()) }.is_null() {
+        return Err(io::Error::last_os_error()).context("Failed to make a temporary directory");
+    }
+    template.pop();
+
+    Ok(PathBuf::from(OsString::from_vec(template)))
+}
+
+#[test]
+fn test_bus_from_sysfs_path() {
+    assert_eq!(
+        bus_from_sysfs_path(
+            "/sys/devices/pci0000:00/0000:00:17.0/ata1/host0/target0:0:0/0:0:0:0/block/sda"
+        ),
+        Some("sata")
+    );
+    assert_eq!(
+        bus_from_sysfs_path("/sys/devices/pci0000:00/0000:00:14.0/usb2/2-1/2-1:1.0/host6/target6:0:0/6:0:0:0/block/sdb"),
+        Some("usb")
+    );
+    assert_eq!(
+        bus_from_sysfs_path("/sys/devices/pci0000:00/0000:00:1d.0/0000:3d:00.0/nvme/nvme0/nvme0n1"),
+        Some("nvme")
+    );
+    assert_eq!(
+        bus_from_sysfs_path("/sys/devices/pci0000:00/0000:00:04.0/virtio1/block/vda"),
+        Some("virtio")
+    );
+    assert_eq!(
+        bus_from_sysfs_path("/sys/devices/virtual/block/loop0"),
+        None
+    );
+}
+
+#[test]
+fn test_temp_mount_point() {
+    use std::os::unix::fs::PermissionsExt;
+
+    assert_eq!(read_only_options("ext4"), "ro,noload,nosuid,nodev,noexec");
+    assert_eq!(
+        read_only_options("xfs"),
+        "ro,norecovery,nosuid,nodev,noexec"
+    );
+    assert_eq!(
+        read_only_options("btrfs"),
+        "ro,rescue=nologreplay,nosuid,nodev,noexec"
+    );
+    assert_eq!(
+        read_only_options("f2fs"),
+        "ro,norecovery,nosuid,nodev,noexec"
+    );
+    assert_eq!(read_only_options("jfs"), "ro,nosuid,nodev,noexec");
+
+    let a = make_temp_dir("dkcli-test-").unwrap();
+    let b = make_temp_dir("dkcli-test-").unwrap();
+    assert_ne!(a, b);
+    assert_eq!(a.metadata().unwrap().permissions().mode() & 0o777, 0o700);
+    fs::remove_dir(a).unwrap();
+    fs::remove_dir(b).unwrap();
+}
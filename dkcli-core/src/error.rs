@@ -0,0 +1,234 @@
+//! Errors of the daemon and of talking to it, told apart for frontends to
+//! put into words.
+
+use std::fmt::{self, Display};
+
+use serde_json::Value;
+
+/// What went wrong on the daemon side, as far as the user can do something
+/// about it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DaemonErrorKind {
+    /// Partitioning, formatting or mounting the target device failed
+    Disk,
+    /// The target device or partition is too small
+    NoSpace,
+    /// Fetching the system release failed
+    Network,
+    /// The daemon did not accept the installation settings
+    Config,
+    /// The daemon is busy with another installation
+    Busy,
+    Other,
+}
+
+/// `code` in one spelling, e.g. `not_enough_space` for `Not-Enough-Space`.
+pub fn normalize_code(code: &str) -> String {
+    code.to_ascii_lowercase().replace(['-', ' '], "_")
+}
+
+impl DaemonErrorKind {
+    fn from_code(code: &str) -> Self {
+        match normalize_code(code).as_str() {
+            "partition" | "format" | "mount" | "umount" | "disk" | "device" => Self::Disk,
+            "no_space" | "not_enough_space" | "space" => Self::NoSpace,
+            "download" | "network" | "http" => Self::Network,
+            "config" | "invalid_config" | "validate" => Self::Config,
+            "busy" | "in_progress" => Self::Busy,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// The `data` of a failed daemon call. Older daemons only send a message,
+/// newer ones an object with an error code, a message and some context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaemonError {
+    pub kind: DaemonErrorKind,
+    pub code: Option<String>,
+    pub message: String,
+    pub context: Option<String>,
+}
+
+impl From<Value> for DaemonError {
+    fn from(data: Value) -> Self {
+        let str_of = |v: &Value| match v {
+            Value::String(s) => s.clone(),
+            v => v.to_string(),
+        };
+
+        let (code, message, context) = match &data {
+            Value::Object(map) => {
+                let code = map.get("code").or_else(|| map.get("t")).map(str_of);
+                let message = map.get("message").map_or_else(|| data.to_string(), str_of);
+                let context = map
+                    .get("context")
+                    .or_else(|| map.get("data"))
+                    .filter(|v| !v.is_null())
+                    .map(str_of);
+                (code, message, context)
+            }
+            v => (None, str_of(v), None),
+        };
+
+        Self {
+            kind: code
+                .as_deref()
+                .map_or(DaemonErrorKind::Other, DaemonErrorKind::from_code),
+            code,
+            message,
+            context,
+        }
+    }
+}
+
+impl DaemonError {
+    /// Errors reported together, e.g. all problems found in the settings.
+    pub fn list(data: Value) -> Vec<Self> {
+        match data {
+            Value::Array(errors) => errors.into_iter().map(Self::from).collect(),
+            Value::Null => vec![],
+            data => vec![Self::from(data)],
+        }
+    }
+}
+
+impl Display for DaemonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.context {
+            Some(context) => write!(f, "{} ({context})", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for DaemonError {}
+
+/// Something that went wrong talking to the daemon, rather than on its
+/// side.
+#[derive(Debug)]
+pub enum ClientError {
+    /// The call was given up on after Ctrl-C
+    Interrupted,
+    /// The daemon sat on `method` for `secs` seconds
+    Timeout { method: &'static str, secs: u64 },
+    /// Polkit or the bus policy did not allow calling `method`
+    NotAuthorized {
+        method: &'static str,
+        source: zbus::Error,
+    },
+    /// The daemon was not running, and starting `unit` failed
+    NotRunning {
+        unit: &'static str,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// The daemon answered the method with something it should not have
+    BadReply(&'static str),
+    /// The installation was canceled as asked
+    Canceled,
+    /// The daemon is too old to take `setting`, without which the
+    /// installed system would not boot or be what was asked for
+    Unsupported(&'static str),
+}
+
+impl ClientError {
+    /// A stable, snake_case code for the error, none where what caused it
+    /// tells more.
+    pub fn code(&self) -> Option<&'static str> {
+        match self {
+            // As a prompt interrupted with Ctrl-C
+            Self::Interrupted => Some("prompt"),
+            Self::Canceled => Some("canceled"),
+            Self::Timeout { .. } => Some("daemon_timeout"),
+            Self::NotAuthorized { .. } | Self::NotRunning { .. } => None,
+            Self::BadReply(_) => Some("daemon"),
+            Self::Unsupported("board") => Some("board_unsupported"),
+            Self::Unsupported("secure_boot") => Some("secure_boot_unsupported"),
+            Self::Unsupported("target_dir") => Some("target_dir_unsupported"),
+            Self::Unsupported("password_hash") => Some("password_hash_unsupported"),
+            Self::Unsupported(_) => Some("unsupported"),
+        }
+    }
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Interrupted => f.write_str("Interrupted"),
+            Self::Timeout { method, secs } => {
+                write!(
+                    f,
+                    "The daemon did not answer {method} within {secs} seconds"
+                )
+            }
+            Self::NotAuthorized { method, .. } => write!(f, "Not authorized to call {method}"),
+            Self::NotRunning { unit, .. } => {
+                write!(
+                    f,
+                    "The daemon ({unit}) is not running and could not be started"
+                )
+            }
+            Self::BadReply(method) => write!(f, "Unexpected reply to {method}"),
+            Self::Canceled => f.write_str("The installation was canceled"),
+            Self::Unsupported(setting) => {
+                write!(f, "The daemon is too old to take the {setting} setting")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::NotAuthorized { source, .. } => Some(source),
+            Self::NotRunning { source, .. } => Some(&**source),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_daemon_error() {
+    use serde_json::json;
+
+    let e = DaemonError::from(json!("Something broke"));
+    assert_eq!(e.kind, DaemonErrorKind::Other);
+    assert_eq!(e.code, None);
+    assert_eq!(e.message, "Something broke");
+
+    let e = DaemonError::from(json!({
+        "code": "not-enough-space",
+        "message": "Partition is too small",
+        "context": {"path": "/dev/sda2"},
+    }));
+    assert_eq!(e.kind, DaemonErrorKind::NoSpace);
+    assert_eq!(e.code.as_deref(), Some("not-enough-space"));
+    assert_eq!(e.message, "Partition is too small");
+    assert_eq!(e.context.as_deref(), Some(r#"{"path":"/dev/sda2"}"#));
+    assert_eq!(
+        e.to_string(),
+        r#"Partition is too small ({"path":"/dev/sda2"})"#
+    );
+
+    let e = DaemonError::from(json!({"t": "Mount", "message": "mount failed", "data": null}));
+    assert_eq!(e.kind, DaemonErrorKind::Disk);
+    assert_eq!(e.context, None);
+
+    let e = DaemonError::from(json!({"foo": 1}));
+    assert_eq!(e.message, r#"{"foo":1}"#);
+
+    assert!(DaemonError::list(json!([])).is_empty());
+    assert!(DaemonError::list(json!(null)).is_empty());
+    let errors = DaemonError::list(json!([
+        {"code": "config", "message": "hostname is not set"},
+        "swap file is too large",
+    ]));
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].kind, DaemonErrorKind::Config);
+    assert_eq!(errors[1].message, "swap file is too large");
+
+    assert_eq!(
+        normalize_code("Download-Hash-Mismatch"),
+        "download_hash_mismatch"
+    );
+}
@@ -0,0 +1,111 @@
+//! Graphics hardware of the machine, as found on the PCI bus, for offering
+//! the drivers a fresh desktop needs to show anything at all.
+
+use std::{fs, path::Path};
+
+pub const PCI_DEVICES: &str = "/sys/bus/pci/devices";
+
+pub const VENDOR_NVIDIA: u16 = 0x10de;
+
+/// Packages of the proprietary NVIDIA driver
+pub const NVIDIA_DRIVER_PACKAGES: &[&str] = &["nvidia"];
+
+/// Packages to install for the GPU driver, if it is to be `install`ed.
+pub fn gpu_driver_packages(install: bool) -> Vec<String> {
+    match install {
+        true => NVIDIA_DRIVER_PACKAGES
+            .iter()
+            .map(|x| x.to_string())
+            .collect(),
+        false => vec![],
+    }
+}
+
+/// A display controller on the PCI bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gpu {
+    pub vendor: u16,
+    pub device: u16,
+}
+
+/// What the graphics hardware takes to get a picture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Graphics {
+    /// An NVIDIA GPU only
+    Nvidia,
+    /// An NVIDIA GPU beside another one driving the screen, as on Optimus
+    /// laptops, which are prone to a black screen with the free driver
+    Hybrid,
+    /// Nothing the free drivers do not handle
+    Other,
+}
+
+fn read_hex(path: &Path) -> Option<u32> {
+    let s = fs::read_to_string(path).ok()?;
+    let s = s.trim();
+
+    u32::from_str_radix(s.strip_prefix("0x").unwrap_or(s), 16).ok()
+}
+
+/// Display controllers (PCI class 0x03) among the devices in `dir`, in the
+/// order of their addresses.
+fn list_gpus_in(dir: &Path) -> Vec<Gpu> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut devices = entries.flatten().map(|x| x.path()).collect::<Vec<_>>();
+    devices.sort();
+
+    devices
+        .iter()
+        .filter(|x| read_hex(&x.join("class")).is_some_and(|c| c >> 16 == 0x03))
+        .filter_map(|x| {
+            Some(Gpu {
+                vendor: read_hex(&x.join("vendor"))?.try_into().ok()?,
+                device: read_hex(&x.join("device"))?.try_into().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Display controllers of this machine.
+pub fn list_gpus() -> Vec<Gpu> {
+    list_gpus_in(Path::new(PCI_DEVICES))
+}
+
+/// What the `gpus` of a machine take to get a picture.
+pub fn classify(gpus: &[Gpu]) -> Graphics {
+    let nvidia = gpus.iter().filter(|x| x.vendor == VENDOR_NVIDIA).count();
+
+    match nvidia {
+        0 => Graphics::Other,
+        n if n < gpus.len() => Graphics::Hybrid,
+        _ => Graphics::Nvidia,
+    }
+}
+
+#[test]
+fn test_list_gpus() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/pci");
+    let gpus = list_gpus_in(&dir);
+    assert_eq!(
+        gpus,
+        [
+            Gpu {
+                vendor: 0x8086,
+                device: 0x9a49
+            },
+            Gpu {
+                vendor: VENDOR_NVIDIA,
+                device: 0x25a2
+            },
+        ]
+    );
+
+    assert_eq!(classify(&gpus), Graphics::Hybrid);
+    assert_eq!(classify(&gpus[1..]), Graphics::Nvidia);
+    assert_eq!(classify(&gpus[..1]), Graphics::Other);
+    assert_eq!(classify(&[]), Graphics::Other);
+    assert!(list_gpus_in(&dir.join("missing")).is_empty());
+}
@@ -0,0 +1,149 @@
+//! Running commands on the machine being installed, which is another one,
+//! reached over SSH, when the daemon is on a remote bus.
+
+use std::{path::Path, process::Command};
+
+use anyhow::{bail, Context, Result};
+use log::debug;
+use zbus::{proxy, Result as zResult};
+
+use crate::{
+    dbus::Bus,
+    parser::{list_keymaps, list_xkb_layouts, parse_xkb_layouts, XKB_RULES_PATH},
+    recipe::{get_recipe, Recipe, OFFLINE_RECIPE_PATH},
+};
+
+/// The systemd unit of the daemon
+pub const DEPLOYKIT_UNIT: &str = "deploykit.service";
+
+/// `args` to run on the machine being installed, which is another one
+/// with --remote.
+pub fn host_command(bus: &Bus, args: &[&str]) -> Command {
+    match bus {
+        Bus::Remote(host) => {
+            let mut cmd = Command::new("ssh");
+            cmd.args(["-xT", "--", host]).args(args);
+            cmd
+        }
+        _ => {
+            let mut cmd = Command::new(args[0]);
+            cmd.args(&args[1..]);
+            cmd
+        }
+    }
+}
+
+/// Standard output of `args` run on the machine being installed.
+pub fn run_on_host(bus: &Bus, args: &[&str]) -> Result<String> {
+    let out = host_command(bus, args)
+        .output()
+        .with_context(|| format!("Failed to run {}", args[0]))?;
+    if !out.status.success() {
+        bail!(
+            "{} failed ({}): {}",
+            args[0],
+            out.status,
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// The last `lines` lines the daemon logged, if they can be had.
+pub async fn daemon_log(bus: &Bus, lines: usize) -> Vec<String> {
+    let bus = bus.clone();
+    let lines = lines.to_string();
+    let res = tokio::task::spawn_blocking(move || {
+        run_on_host(
+            &bus,
+            &[
+                "journalctl",
+                "--no-pager",
+                "--boot",
+                "--unit",
+                DEPLOYKIT_UNIT,
+                "--lines",
+                &lines,
+                "--output",
+                "cat",
+            ],
+        )
+    })
+    .await;
+
+    match res {
+        Ok(Ok(out)) => out.lines().map(str::to_string).collect(),
+        Ok(Err(e)) => {
+            debug!("Failed to read the daemon log: {e:#}");
+            vec![]
+        }
+        Err(e) => {
+            debug!("Failed to read the daemon log: {e}");
+            vec![]
+        }
+    }
+}
+
+/// Whether the live media of the machine being installed has a recipe of
+/// its own, with system releases to install from.
+pub fn host_has_offline_recipe(bus: &Bus) -> bool {
+    match bus {
+        Bus::Remote(_) => run_on_host(bus, &["test", "-e", OFFLINE_RECIPE_PATH]).is_ok(),
+        _ => Path::new(OFFLINE_RECIPE_PATH).exists(),
+    }
+}
+
+/// Fetch the recipe from the mirror, or read that of the live media of the
+/// machine being installed.
+pub async fn host_recipe(bus: &Bus, offline_mode: bool) -> Result<Recipe> {
+    if !offline_mode || !matches!(bus, Bus::Remote(_)) {
+        return get_recipe(offline_mode).await;
+    }
+
+    let bus = bus.clone();
+    let recipe =
+        tokio::task::spawn_blocking(move || run_on_host(&bus, &["cat", OFFLINE_RECIPE_PATH]))
+            .await??;
+
+    Ok(serde_json::from_str(&recipe)?)
+}
+
+/// Console keymaps of the machine being installed, as loadkeys(1) takes
+/// them.
+pub fn host_keymaps(bus: &Bus) -> Result<Vec<String>> {
+    match bus {
+        Bus::Remote(_) => Ok(run_on_host(bus, &["localectl", "list-keymaps"])?
+            .lines()
+            .map(str::to_string)
+            .collect()),
+        _ => list_keymaps(),
+    }
+}
+
+/// X11 keyboard layouts of the machine being installed, as `(layout,
+/// description)` pairs.
+pub fn host_xkb_layouts(bus: &Bus) -> Result<Vec<(String, String)>> {
+    match bus {
+        Bus::Remote(_) => parse_xkb_layouts(run_on_host(bus, &["cat", XKB_RULES_PATH])?.as_bytes()),
+        _ => list_xkb_layouts(),
+    }
+}
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Login1 {
+    #[zbus(allow_interactive_auth)]
+    async fn power_off(&self, interactive: bool) -> zResult<()>;
+}
+
+/// Power off the machine being installed, through logind on `bus`.
+pub async fn power_off(bus: &Bus) -> Result<()> {
+    let conn = bus.connect().await?;
+    Login1Proxy::new(&conn.conn).await?.power_off(false).await?;
+
+    Ok(())
+}
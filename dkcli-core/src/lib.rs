@@ -0,0 +1,42 @@
+//! What installing AOSC OS with the Deploykit daemon takes, without any of
+//! the user interface: the installation settings and the recipe they come
+//! from, checks of what the user entered and of the hardware, the daemon's
+//! D-Bus interface, the client driving it and its progress reports.
+//!
+//! dkcli is one frontend built on this; anything shown to the user, and so
+//! anything translated, is left to the frontend.
+
+pub mod answer;
+pub mod checkpoint;
+pub mod client;
+pub mod config;
+pub mod dbus;
+pub mod disk;
+pub mod error;
+pub mod gpu;
+pub mod host;
+pub mod parser;
+pub mod platform;
+pub mod progress;
+pub mod recipe;
+pub mod timings;
+pub mod validate;
+
+// Builds certified not to reach the network must not get it back through
+// the default features, whichever crate they are built from
+#[cfg(all(feature = "offline", feature = "online"))]
+compile_error!("the `offline` feature cannot be enabled together with `online`; build with `--no-default-features --features offline`");
+
+use std::{env, path::PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Where dkcli keeps what it learns across runs.
+pub(crate) fn state_dir() -> Result<PathBuf> {
+    let state = match env::var_os("XDG_STATE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME").context("HOME is not set")?).join(".local/state"),
+    };
+
+    Ok(state.join("dkcli"))
+}
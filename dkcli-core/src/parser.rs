@@ -0,0 +1,569 @@
+use std::{fmt, fs, io::Read, path::Path};
+
+use anyhow::{anyhow, Result};
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_until, take_while1},
+    character::complete::{multispace1, space0, space1},
+    combinator::{map, map_res},
+    multi::many0,
+    sequence::{preceded, terminated, tuple},
+    IResult,
+};
+use serde::{Deserialize, Serialize};
+
+const LOCALE_LIST: &str = include_str!("../lang_select.json");
+
+/// Countries and timezone of a line of `zone1970.tab`.
+#[inline]
+fn zone1970_single_line(input: &[u8]) -> IResult<&[u8], (&[u8], &[u8])> {
+    let (input, (countries, _, _, _, tz, _, _)) = tuple((
+        take_until("\t"),
+        multispace1,
+        take_until("\t"),
+        multispace1,
+        take_while1(|c| c != b'\t' && c != b'\n'),
+        take_until("\n"),
+        line_rest,
+    ))(input)?;
+
+    Ok((input, (countries, tz)))
+}
+
+#[inline]
+fn line_rest(input: &[u8]) -> IResult<&[u8], ()> {
+    map(take_until("\n"), |_| ())(input)
+}
+
+#[inline]
+fn comment(input: &[u8]) -> IResult<&[u8], ()> {
+    map(terminated(tag("#"), line_rest), |_| ())(input)
+}
+
+#[inline]
+fn whitespace(input: &[u8]) -> IResult<&[u8], ()> {
+    alt((map(multispace1, |_| ()), comment))(input)
+}
+
+#[inline]
+fn hr(input: &[u8]) -> IResult<&[u8], ()> {
+    map(many0(whitespace), |_| ())(input)
+}
+
+fn list_zoneinfo_inner(input: &[u8]) -> IResult<&[u8], Vec<(&str, &str)>> {
+    let (input, result) = many0(preceded(
+        hr,
+        map_res(zone1970_single_line, |(countries, tz)| {
+            Ok::<_, std::str::Utf8Error>((
+                std::str::from_utf8(countries)?,
+                std::str::from_utf8(tz)?,
+            ))
+        }),
+    ))(input)?;
+
+    Ok((input, result))
+}
+
+pub const ZONEINFO_PATH: &str = "/usr/share/zoneinfo";
+
+/// A timezone named after a city of its region, such as `Asia/Shanghai`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Zone {
+    pub region: String,
+    /// May have further slashes, as in `Argentina/Buenos_Aires`
+    pub city: String,
+}
+
+impl Zone {
+    /// Split a timezone name into region and city, if it has both.
+    pub fn parse(name: &str) -> Option<Self> {
+        let (region, city) = name.split_once('/')?;
+        if region.is_empty() || city.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            region: region.to_string(),
+            city: city.to_string(),
+        })
+    }
+
+    /// The name as in `TZ` or tzfile(5) paths.
+    pub fn name(&self) -> String {
+        format!("{}/{}", self.region, self.city)
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.region, self.city)
+    }
+}
+
+/// Trees of the same zones with other leap second handling, and the fixed
+/// offsets of `Etc`, which nobody lives in.
+const SPECIAL_REGIONS: &[&str] = &["posix", "right", "Etc"];
+
+/// List timezones people live in, from the tzdata of the live system.
+///
+/// Unless `all` is set, these are the zones of `zone1970.tab`, which leaves
+/// out legacy aliases such as `Asia/Calcutta` and `US/Pacific`, as well as
+/// [`SPECIAL_REGIONS`]. With it, every zone file with a region is listed.
+pub fn list_zoneinfo(all: bool) -> Result<Vec<Zone>> {
+    list_zoneinfo_in(Path::new(ZONEINFO_PATH), all)
+}
+
+fn list_zoneinfo_in(dir: &Path, all: bool) -> Result<Vec<Zone>> {
+    let mut list = if all {
+        let mut list = vec![];
+        collect_zones(dir, dir, &mut list)?;
+        list.sort_unstable();
+        list
+    } else {
+        let s = fs::read(dir.join("zone1970.tab"))?;
+        list_zoneinfo_inner(&s)
+            .map_err(|e| anyhow!("{e}"))?
+            .1
+            .into_iter()
+            .filter_map(|(_, tz)| Zone::parse(tz))
+            .filter(|x| !SPECIAL_REGIONS.contains(&x.region.as_str()))
+            .collect::<Vec<_>>()
+    };
+
+    if let Some(pos) = list.iter().position(|x| x.name() == "Asia/Shanghai") {
+        let entry = list.remove(pos);
+        list.insert(0, entry);
+    }
+
+    list.insert(
+        0,
+        Zone {
+            region: "Asia".to_string(),
+            city: "Beijing".to_string(),
+        },
+    );
+
+    Ok(list)
+}
+
+/// Zones whose name, or the name of their city, starts with `prefix`,
+/// ignoring case and taking spaces for underscores (so `new y` finds
+/// `America/New_York`). An exact match comes first.
+pub fn find_timezone_by_prefix<'a>(timezones: &'a [Zone], prefix: &str) -> Vec<&'a Zone> {
+    let normalize = |x: &str| x.to_lowercase().replace(' ', "_");
+    let prefix = normalize(prefix);
+    if prefix.is_empty() {
+        return vec![];
+    }
+
+    let mut found = timezones
+        .iter()
+        .filter(|zone| {
+            let name = normalize(&zone.name());
+            let city = normalize(&zone.city);
+            let place = city.rsplit('/').next().unwrap_or_default();
+
+            [name.as_str(), city.as_str(), place]
+                .iter()
+                .any(|x| x.starts_with(&prefix))
+        })
+        .collect::<Vec<_>>();
+
+    found.sort_by_key(|zone| normalize(&zone.name()) != prefix);
+    found
+}
+
+/// Where the name of the language of a territory is not its country code,
+/// for the territories with a locale in `lang_select.json`.
+const TERRITORY_LANGUAGES: &[(&str, &str)] = &[
+    ("AL", "sq"),
+    ("BA", "bs"),
+    ("BD", "bn"),
+    ("BR", "pt"),
+    ("BT", "dz"),
+    ("BY", "be"),
+    ("CN", "zh"),
+    ("CZ", "cs"),
+    ("DK", "da"),
+    ("EE", "et"),
+    ("EG", "ar"),
+    ("ET", "am"),
+    ("GE", "ka"),
+    ("GR", "el"),
+    ("IL", "he"),
+    ("IN", "hi"),
+    ("IR", "fa"),
+    ("JP", "ja"),
+    ("KH", "km"),
+    ("KR", "ko"),
+    ("KZ", "kk"),
+    ("LA", "lo"),
+    ("LK", "si"),
+    ("MM", "my"),
+    ("MY", "ms"),
+    ("NO", "nb"),
+    ("NP", "ne"),
+    ("PH", "tl"),
+    ("PK", "ur"),
+    ("RS", "sr"),
+    ("SE", "sv"),
+    ("SI", "sl"),
+    ("SN", "wo"),
+    ("TJ", "tg"),
+    ("TW", "zh"),
+    ("UA", "uk"),
+    ("US", "en"),
+    ("VN", "vi"),
+];
+
+/// A language dkcli offers, from `lang_select.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Locale {
+    /// Language identifier, such as `zh-CN`
+    pub id: String,
+    pub lang_english: String,
+    pub locale: String,
+    pub lang: String,
+    /// Name of the language in itself
+    pub text: String,
+    /// The POSIX locale, such as `zh_CN.UTF-8`
+    pub data: String,
+}
+
+impl Locale {
+    /// Language and territory, such as `zh` and `CN`.
+    fn language_territory(&self) -> (&str, Option<&str>) {
+        let name = self.data.split(['.', '@']).next().unwrap_or_default();
+        match name.split_once('_') {
+            Some((language, territory)) => (language, Some(territory)),
+            None => (name, None),
+        }
+    }
+}
+
+/// List the languages dkcli offers for the installed system.
+pub fn list_locales() -> Result<Vec<Locale>> {
+    Ok(serde_json::from_str(LOCALE_LIST)?)
+}
+
+/// The locale of a language identifier or POSIX locale such as `zh-CN`,
+/// `zh_CN.UTF-8` or just `zh`, or else the first one of its language.
+pub fn locale_for_language_code<'a>(locales: &'a [Locale], code: &str) -> Option<&'a Locale> {
+    let code = code
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .replace('-', "_");
+    let (language, territory) = match code.split_once('_') {
+        Some((language, territory)) => (language, Some(territory)),
+        None => (code.as_str(), None),
+    };
+
+    locales
+        .iter()
+        .find(|x| {
+            let (l, t) = x.language_territory();
+            l.eq_ignore_ascii_case(language)
+                && territory
+                    .is_some_and(|territory| t.is_some_and(|t| t.eq_ignore_ascii_case(territory)))
+        })
+        .or_else(|| {
+            locales
+                .iter()
+                .find(|x| x.language_territory().0.eq_ignore_ascii_case(language))
+        })
+}
+
+/// The locale of the main language of the country `timezone` is in, going
+/// by the first country `zone1970.tab` lists for it.
+pub fn default_locale_for_timezone<'a>(
+    locales: &'a [Locale],
+    timezone: &str,
+) -> Option<&'a Locale> {
+    default_locale_for_timezone_in(Path::new(ZONEINFO_PATH), locales, timezone)
+}
+
+fn default_locale_for_timezone_in<'a>(
+    dir: &Path,
+    locales: &'a [Locale],
+    timezone: &str,
+) -> Option<&'a Locale> {
+    let s = fs::read(dir.join("zone1970.tab")).ok()?;
+    let (_, list) = list_zoneinfo_inner(&s).ok()?;
+    let (countries, _) = list.into_iter().find(|(_, tz)| *tz == timezone)?;
+    let country = countries.split(',').next()?;
+
+    let language = TERRITORY_LANGUAGES
+        .iter()
+        .find(|(territory, _)| *territory == country)
+        .map(|(_, language)| language.to_string())
+        .unwrap_or_else(|| country.to_lowercase());
+
+    locales
+        .iter()
+        .find(|x| x.language_territory() == (language.as_str(), Some(country)))
+}
+
+fn collect_zones(root: &Path, dir: &Path, list: &mut Vec<Zone>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if entry.file_type()?.is_dir() {
+            collect_zones(root, &path, list)?;
+            continue;
+        }
+
+        // Skip the tables and other data next to the zone files
+        let mut magic = [0; 4];
+        let is_tzif = fs::File::open(&path)
+            .and_then(|mut f| f.read_exact(&mut magic))
+            .is_ok_and(|()| magic == *b"TZif");
+        if !is_tzif {
+            continue;
+        }
+
+        let name = path.strip_prefix(root)?.to_string_lossy().into_owned();
+        list.extend(Zone::parse(&name));
+    }
+
+    Ok(())
+}
+
+#[inline]
+fn xkb_section_header(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    preceded(tag("! "), take_until("\n"))(input)
+}
+
+#[inline]
+fn xkb_single_line(input: &[u8]) -> IResult<&[u8], (&[u8], &[u8])> {
+    let (input, (_, name, _, desc)) = tuple((
+        space1,
+        take_while1(|c: u8| !c.is_ascii_whitespace()),
+        space0,
+        take_until("\n"),
+    ))(input)?;
+
+    Ok((input, (name, desc)))
+}
+
+fn list_xkb_layouts_inner(input: &[u8]) -> IResult<&[u8], Vec<(&str, &str)>> {
+    let (input, _) = take_until("! layout")(input)?;
+    let (input, _) = terminated(xkb_section_header, tag("\n"))(input)?;
+    let (input, result) = many0(terminated(
+        map_res(xkb_single_line, |(name, desc)| {
+            Ok::<_, std::str::Utf8Error>((std::str::from_utf8(name)?, std::str::from_utf8(desc)?))
+        }),
+        tag("\n"),
+    ))(input)?;
+
+    Ok((input, result))
+}
+
+/// Where the XKB rules listing the X11 keyboard layouts is
+pub const XKB_RULES_PATH: &str = "/usr/share/X11/xkb/rules/base.lst";
+
+/// List X11 keyboard layouts as `(layout, description)` pairs, read from the
+/// `! layout` section of the XKB rules listing.
+pub fn list_xkb_layouts() -> Result<Vec<(String, String)>> {
+    parse_xkb_layouts(&fs::read(XKB_RULES_PATH)?)
+}
+
+/// X11 keyboard layouts as `(layout, description)` pairs, from the
+/// `! layout` section of an XKB rules listing.
+pub fn parse_xkb_layouts(input: &[u8]) -> Result<Vec<(String, String)>> {
+    let list = list_xkb_layouts_inner(input)
+        .map_err(|e| anyhow!("{e}"))?
+        .1
+        .into_iter()
+        .map(|(name, desc)| (name.to_string(), desc.to_string()))
+        .collect::<Vec<_>>();
+
+    Ok(list)
+}
+
+fn collect_keymaps(dir: &Path, list: &mut Vec<String>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if entry.file_type()?.is_dir() {
+            if entry.file_name() != "include" {
+                collect_keymaps(&path, list)?;
+            }
+            continue;
+        }
+
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if let Some(name) = name
+            .strip_suffix(".map.gz")
+            .or_else(|| name.strip_suffix(".map"))
+        {
+            list.push(name.to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// List console keymaps shipped by kbd, as accepted by `loadkeys(1)`.
+pub fn list_keymaps() -> Result<Vec<String>> {
+    let mut list = vec![];
+    collect_keymaps(Path::new("/usr/share/kbd/keymaps"), &mut list)?;
+
+    list.sort_unstable();
+    list.dedup();
+
+    Ok(list)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PasswdEntry {
+    pub username: String,
+    pub uid: u32,
+    pub gid: u32,
+    pub full_name: String,
+}
+
+/// Parse passwd(5), skipping malformed lines.
+pub fn parse_passwd(input: &str) -> Vec<PasswdEntry> {
+    input
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split(':').collect::<Vec<_>>();
+            if fields.len() != 7 {
+                return None;
+            }
+
+            Some(PasswdEntry {
+                username: fields[0].to_string(),
+                uid: fields[2].parse().ok()?,
+                gid: fields[3].parse().ok()?,
+                full_name: fields[4].split(',').next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_passwd() {
+    let s = "root:x:0:0:root:/root:/bin/bash\naosc:x:1000:1001:AOSC User,,,:/home/aosc:/bin/bash\nbroken:x:abc\n";
+    assert_eq!(
+        parse_passwd(s),
+        vec![
+            PasswdEntry {
+                username: "root".to_string(),
+                uid: 0,
+                gid: 0,
+                full_name: "root".to_string(),
+            },
+            PasswdEntry {
+                username: "aosc".to_string(),
+                uid: 1000,
+                gid: 1001,
+                full_name: "AOSC User".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_xkb_layouts() {
+    let s = b"! model\n  pc105           Generic 105-key PC\n\n! layout\n  us              English (US)\n  cn              Chinese\n\n! variant\n  chr             us: Cherokee\n";
+    let (_, list) = list_xkb_layouts_inner(s).unwrap();
+    assert_eq!(list, vec![("us", "English (US)"), ("cn", "Chinese")]);
+}
+
+#[test]
+fn test_zoneinfo() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/zoneinfo");
+    let names = |all| {
+        list_zoneinfo_in(&dir, all)
+            .unwrap()
+            .iter()
+            .map(|x| x.name())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        names(false),
+        [
+            "Asia/Beijing",
+            "Asia/Shanghai",
+            "Europe/Paris",
+            "America/Argentina/Buenos_Aires",
+            "America/New_York",
+        ]
+    );
+    assert_eq!(
+        names(true),
+        [
+            "Asia/Beijing",
+            "Asia/Shanghai",
+            "America/Argentina/Buenos_Aires",
+            "America/New_York",
+            "Asia/Calcutta",
+            "Etc/GMT+8",
+            "Europe/Paris",
+            "US/Pacific",
+            "posix/Asia/Shanghai",
+            "right/Asia/Shanghai",
+        ]
+    );
+
+    let zone = Zone::parse("America/Argentina/Buenos_Aires").unwrap();
+    assert_eq!(zone.region, "America");
+    assert_eq!(zone.city, "Argentina/Buenos_Aires");
+    assert_eq!(zone.to_string(), zone.name());
+    assert_eq!(Zone::parse("UTC"), None);
+}
+
+#[test]
+fn test_locale_lookups() {
+    let locales = list_locales().unwrap();
+    let data = |x: Option<&Locale>| x.map(|x| x.data.clone());
+
+    assert_eq!(
+        data(locale_for_language_code(&locales, "zh-TW")),
+        Some("zh_TW.UTF-8".to_string())
+    );
+    assert_eq!(
+        data(locale_for_language_code(&locales, "pt_BR.UTF-8")),
+        Some("pt_BR.UTF-8".to_string())
+    );
+    assert_eq!(
+        data(locale_for_language_code(&locales, "en_GB.UTF-8")),
+        Some("en_US.UTF-8".to_string())
+    );
+    assert_eq!(
+        data(locale_for_language_code(&locales, "de")),
+        Some("de_DE.UTF-8".to_string())
+    );
+    assert_eq!(data(locale_for_language_code(&locales, "C.UTF-8")), None);
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/zoneinfo");
+    let default = |tz| data(default_locale_for_timezone_in(&dir, &locales, tz));
+    assert_eq!(default("Asia/Shanghai"), Some("zh_CN.UTF-8".to_string()));
+    assert_eq!(default("Europe/Paris"), Some("fr_FR.UTF-8".to_string()));
+    assert_eq!(default("America/New_York"), Some("en_US.UTF-8".to_string()));
+    // No Spanish of Argentina to offer
+    assert_eq!(default("America/Argentina/Buenos_Aires"), None);
+    assert_eq!(default("Asia/Calcutta"), None);
+
+    let timezones = list_zoneinfo_in(&dir, false).unwrap();
+    let names = |prefix| {
+        find_timezone_by_prefix(&timezones, prefix)
+            .iter()
+            .map(|x| x.name())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names("buenos a"), ["America/Argentina/Buenos_Aires"]);
+    assert_eq!(
+        names("america/"),
+        ["America/Argentina/Buenos_Aires", "America/New_York"]
+    );
+    assert_eq!(names("NEW"), ["America/New_York"]);
+    assert_eq!(names("asia/shanghai"), ["Asia/Shanghai"]);
+    assert!(names("").is_empty());
+}
@@ -0,0 +1,516 @@
+//! What it takes to boot the machine being installed to, beyond what its
+//! architecture tells: single-board computers each have their own firmware
+//! layout, LoongArch firmware comes in two incompatible generations and
+//! OpenPOWER machines boot through Petitboot, which the daemon needs to be
+//! told about. On RISC-V, what a machine can run and boot is checked
+//! instead.
+
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::DkPartition,
+    dbus::Bus,
+    host::host_command,
+    recipe::{get_arch_name, is_foreign_arch, is_foreign_machine, Squashfs},
+};
+
+/// Device tree compatible strings of the machine, most specific first
+pub const DEVICE_TREE_COMPATIBLE: &str = "/proc/device-tree/compatible";
+/// Firmware version, as given by SMBIOS
+pub const DMI_BIOS_VERSION: &str = "/sys/class/dmi/id/bios_version";
+pub const CPUINFO: &str = "/proc/cpuinfo";
+/// Present if the machine was booted through EFI
+pub const EFI_DIR: &str = "/sys/firmware/efi";
+/// Whether the EFI firmware is 32 or 64-bit, whatever the CPU is
+pub const EFI_PLATFORM_SIZE: &str = "/sys/firmware/efi/fw_platform_size";
+/// The SecureBoot variable of the EFI global namespace
+pub const SECURE_BOOT_VAR: &str =
+    "/sys/firmware/efi/efivars/SecureBoot-8be4df61-93ca-11d2-aa0d-e98032b8c39c";
+/// Left in the device tree by U-Boot when it boots the kernel
+pub const U_BOOT_VERSION: &str = "/proc/device-tree/chosen/u-boot,version";
+
+/// Where the boot firmware of a board looks for what to boot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BootLayout {
+    /// U-Boot written to the disk itself, `offset` bytes in, so the first
+    /// partition has to start after it
+    UBoot { offset: u64 },
+    /// Firmware and boot files on a FAT partition of their own
+    FirmwarePartition,
+    /// LoongArch firmware predating the standard boot protocol ("old
+    /// world"), which only boots a bootloader built for it, from an EFI
+    /// system partition
+    OldWorld,
+    /// 32-bit UEFI on a 64-bit CPU, as on some cheap x86 tablets, which only
+    /// starts an ia32 EFI bootloader
+    Efi32,
+    /// Petitboot on OPAL firmware, which finds the kernel and initramfs
+    /// through the GRUB configuration in `/boot` of the system partition
+    Petitboot,
+    /// UEFI in flash, booting from an EFI system partition as usual
+    Efi,
+}
+
+/// A single-board computer dkcli knows the boot layout of.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Board {
+    pub id: &'static str,
+    /// Architectures the board runs, any if none
+    pub arch: &'static [&'static str],
+    pub name: &'static str,
+    /// Device tree compatible strings of the board or its SoC
+    pub compatible: &'static [&'static str],
+    pub layout: BootLayout,
+}
+
+/// Known boards, the more specific before the SoCs they are built on.
+pub const BOARDS: &[Board] = &[
+    Board {
+        id: "rpi3",
+        arch: &["arm64"],
+        name: "Raspberry Pi 3 / CM3",
+        compatible: &[
+            "raspberrypi,3-model-b",
+            "raspberrypi,3-model-b-plus",
+            "brcm,bcm2837",
+        ],
+        layout: BootLayout::FirmwarePartition,
+    },
+    Board {
+        id: "rpi4",
+        arch: &["arm64"],
+        name: "Raspberry Pi 4 / 400 / CM4",
+        compatible: &["raspberrypi,4-model-b", "raspberrypi,400", "brcm,bcm2711"],
+        layout: BootLayout::FirmwarePartition,
+    },
+    Board {
+        id: "rpi5",
+        arch: &["arm64"],
+        name: "Raspberry Pi 5",
+        compatible: &["raspberrypi,5-model-b", "brcm,bcm2712"],
+        layout: BootLayout::FirmwarePartition,
+    },
+    Board {
+        id: "rk3588",
+        arch: &["arm64"],
+        name: "Rockchip RK3588 (Radxa ROCK 5, Orange Pi 5)",
+        compatible: &["rockchip,rk3588", "rockchip,rk3588s"],
+        layout: BootLayout::UBoot { offset: 32 * 1024 },
+    },
+    Board {
+        id: "rk3399",
+        arch: &["arm64"],
+        name: "Rockchip RK3399 (Pinebook Pro, ROCK Pi 4)",
+        compatible: &["rockchip,rk3399"],
+        layout: BootLayout::UBoot { offset: 32 * 1024 },
+    },
+    Board {
+        id: "sun50i",
+        arch: &["arm64"],
+        name: "Allwinner A64 / H6 / H616 (Pine64, Orange Pi)",
+        compatible: &[
+            "allwinner,sun50i-a64",
+            "allwinner,sun50i-h6",
+            "allwinner,sun50i-h616",
+        ],
+        layout: BootLayout::UBoot { offset: 8 * 1024 },
+    },
+    Board {
+        id: "loongarch-ow",
+        arch: &["loongarch64"],
+        name: "LoongArch, old-world firmware",
+        compatible: &[],
+        layout: BootLayout::OldWorld,
+    },
+    Board {
+        id: "efi-ia32",
+        arch: &["amd64"],
+        name: "32-bit UEFI on a 64-bit CPU",
+        compatible: &[],
+        layout: BootLayout::Efi32,
+    },
+    Board {
+        id: "powernv",
+        arch: &["ppc64el", "ppc64"],
+        name: "OpenPOWER (OPAL firmware with Petitboot)",
+        compatible: &["ibm,powernv"],
+        layout: BootLayout::Petitboot,
+    },
+    Board {
+        id: "uefi",
+        arch: &[],
+        name: "UEFI firmware in flash",
+        compatible: &[],
+        layout: BootLayout::Efi,
+    },
+];
+
+/// The `board` setting, for the daemon to lay out the disk and write the
+/// boot firmware accordingly.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoardConfig {
+    pub board: String,
+    pub layout: BootLayout,
+}
+
+impl Board {
+    pub fn config(&self) -> BoardConfig {
+        BoardConfig {
+            board: self.id.to_string(),
+            layout: self.layout,
+        }
+    }
+}
+
+impl BoardConfig {
+    /// Whether the board boots from a firmware partition, which takes the
+    /// place of the EFI system partition.
+    pub fn has_firmware_partition(&self) -> bool {
+        self.layout == BootLayout::FirmwarePartition
+    }
+
+    /// Whether the board boots without an EFI system partition, even if its
+    /// firmware offers EFI.
+    pub fn skips_esp(&self) -> bool {
+        matches!(
+            self.layout,
+            BootLayout::FirmwarePartition | BootLayout::Petitboot
+        )
+    }
+}
+
+/// Smallest firmware partition with room for the firmware, two kernels and
+/// their initramfs
+pub const FIRMWARE_PARTITION_MIN: u64 = 256 << 20;
+
+/// Whether the boot ROM of a board booting from a firmware partition can
+/// read `partition`: FAT, the only file system it knows, and large enough.
+/// Such a partition is no EFI system partition, whatever its type.
+pub fn is_firmware_partition(partition: &DkPartition) -> bool {
+    partition
+        .fs_type
+        .as_deref()
+        .is_some_and(|x| matches!(x, "vfat" | "fat" | "fat16" | "fat32"))
+        && partition.size >= FIRMWARE_PARTITION_MIN
+}
+
+/// Split the NUL-separated list of the device tree `compatible` property.
+pub fn parse_compatible(data: &[u8]) -> Vec<String> {
+    data.split(|&b| b == 0)
+        .filter(|x| !x.is_empty())
+        .map(|x| String::from_utf8_lossy(x).into_owned())
+        .collect()
+}
+
+/// Compatible strings of the machine, none if it has no device tree (as
+/// with ACPI).
+pub fn read_compatible() -> Vec<String> {
+    fs::read(DEVICE_TREE_COMPATIBLE)
+        .map(|x| parse_compatible(&x))
+        .unwrap_or_default()
+}
+
+/// The known board matching the most specific of `compatible`.
+pub fn detect_board(compatible: &[String]) -> Option<&'static Board> {
+    compatible.iter().find_map(|c| {
+        BOARDS
+            .iter()
+            .find(|board| board.compatible.contains(&c.as_str()))
+    })
+}
+
+/// The `board` setting for `board`, none for those booting like any other
+/// UEFI machine.
+pub fn board_config(board: &Board) -> Option<BoardConfig> {
+    Some(board.config()).filter(|x| x.layout != BootLayout::Efi)
+}
+
+pub fn find_board(id: &str) -> Option<&'static Board> {
+    BOARDS.iter().find(|x| x.id == id)
+}
+
+/// Boards there are for `arch`.
+pub fn boards_for(arch: &str) -> Vec<&'static Board> {
+    BOARDS
+        .iter()
+        .filter(|x| x.arch.is_empty() || x.arch.contains(&arch))
+        .collect()
+}
+
+/// Whether LoongArch firmware of `bios_version` is old-world, none if the
+/// version does not tell. Loongson firmware follows the standard boot
+/// protocol from version 4 on, as in `Loongson-UDK2018-V4.0.05494-stable`.
+pub fn is_old_world(bios_version: &str) -> Option<bool> {
+    let (_, version) = bios_version.trim().rsplit_once("-V")?;
+    let major = version.split('.').next()?.parse::<u32>().ok()?;
+
+    Some(major < 4)
+}
+
+/// The firmware generation of this LoongArch machine, as a board.
+pub fn detect_loongarch_firmware() -> Option<&'static Board> {
+    let bios_version = fs::read_to_string(DMI_BIOS_VERSION).ok()?;
+    let id = match is_old_world(&bios_version)? {
+        true => "loongarch-ow",
+        false => "uefi",
+    };
+
+    find_board(id)
+}
+
+/// The board for 32-bit UEFI, if that is what started this 64-bit machine.
+pub fn detect_efi_ia32() -> Option<&'static Board> {
+    let size = fs::read_to_string(EFI_PLATFORM_SIZE).ok()?;
+
+    (size.trim() == "32")
+        .then(|| find_board("efi-ia32"))
+        .flatten()
+}
+
+/// The board of this machine, on architectures where how it boots differs
+/// from one machine to another. None when installing on another machine
+/// or for another architecture.
+pub fn detect() -> Option<&'static Board> {
+    if is_foreign_machine() {
+        return None;
+    }
+
+    match get_arch_name()? {
+        "arm64" | "ppc64el" | "ppc64" => detect_board(&read_compatible()),
+        "loongarch64" => detect_loongarch_firmware(),
+        "amd64" => detect_efi_ia32(),
+        _ => None,
+    }
+}
+
+/// How the installed system gets past Secure Boot, which the firmware has
+/// enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecureBoot {
+    /// Through the signed shim, its key enrolled with MokManager on the
+    /// next boot
+    Shim,
+    /// Not at all, so Secure Boot has to be disabled before rebooting
+    Disable,
+}
+
+/// Whether the SecureBoot variable, as read from efivarfs, says Secure Boot
+/// is enabled. The first four bytes are the attributes of the variable.
+pub fn parse_secure_boot(data: &[u8]) -> bool {
+    data.get(4) == Some(&1)
+}
+
+/// Whether the firmware enforces Secure Boot, never so without EFI.
+pub fn secure_boot_enabled() -> bool {
+    fs::read(SECURE_BOOT_VAR).is_ok_and(|x| parse_secure_boot(&x))
+}
+
+/// Whether Secure Boot is enabled on the machine installed on, reached on
+/// `bus`, which tells nothing if the system is for another architecture.
+pub fn host_secure_boot(bus: &Bus) -> bool {
+    if is_foreign_arch() {
+        return false;
+    }
+
+    match bus {
+        Bus::Remote(_) => host_command(bus, &["cat", SECURE_BOOT_VAR])
+            .output()
+            .is_ok_and(|x| x.status.success() && parse_secure_boot(&x.stdout)),
+        _ => secure_boot_enabled(),
+    }
+}
+
+/// How the system on `sqfs` is to boot with Secure Boot `enabled`; none if
+/// it is not enabled. Without a signed shim, the firmware refuses the
+/// bootloader without a word, so Secure Boot has to go.
+pub fn secure_boot_for(enabled: bool, sqfs: &Squashfs) -> Option<SecureBoot> {
+    match (enabled, sqfs.signed_shim) {
+        (false, _) => None,
+        (true, true) => Some(SecureBoot::Shim),
+        (true, false) => Some(SecureBoot::Disable),
+    }
+}
+
+/// ISA extensions of RV64GC, which AOSC OS is built for. Zicsr and
+/// Zifencei are left out, as kernels before 6.4 do not list them.
+pub const RISCV_REQUIRED_EXTENSIONS: &[&str] = &["i", "m", "a", "f", "d", "c"];
+
+/// RISC-V boards loading their SPL from a raw offset on the boot disk,
+/// which dkcli does not write, by device tree compatible string.
+pub const RISCV_SPL_BOARDS: &[(&str, &str)] = &[
+    ("allwinner,sun20i-d1", "Allwinner D1 (Nezha, LicheeRV)"),
+    ("sifive,fu740-c000", "SiFive HiFive Unmatched"),
+    ("sifive,fu540-c000", "SiFive HiFive Unleashed"),
+];
+
+/// What keeps a RISC-V machine from running or booting the system.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RiscvIssue {
+    /// Extensions of [`RISCV_REQUIRED_EXTENSIONS`] the CPU lacks
+    MissingExtensions(Vec<&'static str>),
+    /// Neither EFI nor U-Boot started the machine, so nothing would start
+    /// the bootloader installed
+    NoBootEnvironment,
+    /// The board, by name, needs an SPL written to the disk to boot from it
+    NeedsSpl(&'static str),
+}
+
+/// Extensions in the `isa` line of `/proc/cpuinfo`, single-letter ones
+/// first, as in `rv64imafdc_zicsr_zifencei`.
+pub fn riscv_isa_extensions(cpuinfo: &str) -> Option<Vec<String>> {
+    let isa = cpuinfo.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim() == "isa").then(|| value.trim().to_lowercase())
+    })?;
+    let isa = isa
+        .strip_prefix("rv64")
+        .or_else(|| isa.strip_prefix("rv32"))?;
+
+    let mut parts = isa.split('_');
+    let mut extensions = parts
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(String::from)
+        .collect::<Vec<_>>();
+    extensions.extend(parts.filter(|x| !x.is_empty()).map(String::from));
+
+    Some(extensions)
+}
+
+/// What keeps this machine from running or booting the system, given its
+/// `/proc/cpuinfo`, device tree compatible strings and what booted it.
+pub fn riscv_issues(
+    cpuinfo: &str,
+    compatible: &[String],
+    efi: bool,
+    u_boot: bool,
+) -> Vec<RiscvIssue> {
+    let mut issues = vec![];
+
+    // Nothing to go by without the line, e.g. under emulation
+    if let Some(extensions) = riscv_isa_extensions(cpuinfo) {
+        let missing = RISCV_REQUIRED_EXTENSIONS
+            .iter()
+            .filter(|x| !extensions.iter().any(|e| e == *x))
+            .copied()
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            issues.push(RiscvIssue::MissingExtensions(missing));
+        }
+    }
+
+    if !efi && !u_boot {
+        issues.push(RiscvIssue::NoBootEnvironment);
+    }
+
+    let spl_board = compatible.iter().find_map(|c| {
+        RISCV_SPL_BOARDS
+            .iter()
+            .find(|(x, _)| x == c)
+            .map(|(_, name)| *name)
+    });
+    if let Some(name) = spl_board {
+        issues.push(RiscvIssue::NeedsSpl(name));
+    }
+
+    issues
+}
+
+/// What keeps this RISC-V machine from running or booting the system.
+pub fn check_riscv() -> Vec<RiscvIssue> {
+    riscv_issues(
+        &fs::read_to_string(CPUINFO).unwrap_or_default(),
+        &read_compatible(),
+        Path::new(EFI_DIR).exists(),
+        Path::new(U_BOOT_VERSION).exists(),
+    )
+}
+
+#[test]
+fn test_detect_board() {
+    let compatible = parse_compatible(b"radxa,rock-5b\0rockchip,rk3588\0");
+    assert_eq!(compatible, ["radxa,rock-5b", "rockchip,rk3588"]);
+    assert_eq!(detect_board(&compatible).map(|x| x.id), Some("rk3588"));
+
+    let compatible = parse_compatible(b"raspberrypi,4-model-b\0brcm,bcm2711\0");
+    assert_eq!(
+        detect_board(&compatible).map(|x| x.layout),
+        Some(BootLayout::FirmwarePartition)
+    );
+
+    assert_eq!(detect_board(&parse_compatible(b"qemu,virt\0")), None);
+    assert_eq!(detect_board(&[]), None);
+
+    assert_eq!(
+        is_old_world("Loongson-UDK2018-V2.0.04082-beta8\n"),
+        Some(true)
+    );
+    assert_eq!(
+        is_old_world("Loongson-UDK2018-V4.0.05494-stable"),
+        Some(false)
+    );
+    assert_eq!(is_old_world("Kunlun-A1901-V4.1.3"), Some(false));
+    assert_eq!(is_old_world("1.0"), None);
+    assert!(boards_for("loongarch64")
+        .iter()
+        .all(|x| !x.id.starts_with("rk")));
+    assert_eq!(
+        boards_for("amd64").iter().map(|x| x.id).collect::<Vec<_>>(),
+        ["efi-ia32", "uefi"]
+    );
+
+    let powernv = detect_board(&parse_compatible(b"ibm,powernv\0")).unwrap();
+    assert_eq!(powernv.id, "powernv");
+    assert!(powernv.config().skips_esp());
+    assert!(!find_board("rk3588").unwrap().config().skips_esp());
+
+    let partition = |fs_type: &str, size| DkPartition {
+        path: Some("/dev/mmcblk0p1".into()),
+        parent_path: Some("/dev/mmcblk0".into()),
+        fs_type: Some(fs_type.to_string()),
+        size,
+    };
+    assert!(is_firmware_partition(&partition("vfat", 512 << 20)));
+    assert!(!is_firmware_partition(&partition("vfat", 64 << 20)));
+    assert!(!is_firmware_partition(&partition("ext4", 512 << 20)));
+
+    assert_eq!(
+        serde_json::to_value(find_board("sun50i").unwrap().config()).unwrap(),
+        serde_json::json!({"board": "sun50i", "layout": {"UBoot": {"offset": 8192}}})
+    );
+    assert_eq!(
+        serde_json::to_value(find_board("loongarch-ow").unwrap().config()).unwrap(),
+        serde_json::json!({"board": "loongarch-ow", "layout": "OldWorld"})
+    );
+    assert!(!find_board("efi-ia32").unwrap().config().skips_esp());
+
+    assert!(parse_secure_boot(&[6, 0, 0, 0, 1]));
+    assert!(!parse_secure_boot(&[6, 0, 0, 0, 0]));
+    assert!(!parse_secure_boot(&[]));
+}
+
+#[test]
+fn test_riscv_issues() {
+    let cpuinfo = "processor\t: 0\nhart\t\t: 0\nisa\t\t: rv64imafdch_zicsr_zifencei_zba\n";
+    assert_eq!(
+        riscv_isa_extensions(cpuinfo).unwrap(),
+        ["i", "m", "a", "f", "d", "c", "h", "zicsr", "zifencei", "zba"]
+    );
+    assert_eq!(riscv_issues(cpuinfo, &[], true, false), []);
+
+    assert_eq!(
+        riscv_issues("isa : rv64imac\n", &[], false, true),
+        [RiscvIssue::MissingExtensions(vec!["f", "d"])]
+    );
+    assert_eq!(
+        riscv_issues("", &["allwinner,sun20i-d1".to_string()], false, false),
+        [
+            RiscvIssue::NoBootEnvironment,
+            RiscvIssue::NeedsSpl("Allwinner D1 (Nezha, LicheeRV)")
+        ]
+    );
+}
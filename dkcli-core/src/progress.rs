@@ -0,0 +1,164 @@
+//! Progress of auto-partitioning and of the installation, as the daemon
+//! reports it, and following the installation to its end.
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::time::sleep;
+
+use crate::{
+    client::{is_interrupted, Dbus, DkClient},
+    dbus::DbusMethod,
+    error::{ClientError, DaemonError},
+    host::daemon_log,
+    timings::{StepSpan, StepTimer, StepTimings},
+};
+
+/// Number of steps the daemon reports during installation
+pub const INSTALL_STEPS: u8 = 8;
+
+/// Reply to `get_auto_partition_progress`; `res` holds the system and EFI
+/// partitions made.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "status")]
+pub enum AutoPartitionProgress {
+    Pending,
+    Working,
+    Finish { res: Result<Value, Value> },
+}
+
+/// Reply to `get_progress`: the (1-based) step out of INSTALL_STEPS, its
+/// progress in percent, and throughput in bytes per second where there is
+/// any.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "status")]
+pub enum ProgressStatus {
+    Pending,
+    Working { step: u8, progress: u8, v: usize },
+    Error(Value),
+    Finish,
+}
+
+// How long the progress may stand still before the daemon log is looked
+// at, how often it is looked at then, and how many lines of it
+const STALL_TIME: Duration = Duration::from_secs(30);
+const DAEMON_LOG_INTERVAL: Duration = Duration::from_secs(5);
+const DAEMON_LOG_LINES: usize = 3;
+
+/// Where the installation is, as [`follow`] passes it on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstallProgress {
+    /// The (1-based) step out of INSTALL_STEPS
+    pub step: u8,
+    /// Progress of the step in percent
+    pub progress: u8,
+    /// Throughput in bytes per second, where there is any
+    pub bytes_per_sec: Option<u64>,
+    /// Time left, going by how long the steps took before
+    pub remaining: Option<Duration>,
+}
+
+/// What a frontend shows of the installation while [`follow`] follows it.
+pub trait ProgressObserver {
+    /// The installation is at `progress`, told every time it is asked.
+    fn progress(&mut self, progress: &InstallProgress) -> Result<()>;
+    /// The progress stood still for a while; `log` is what the daemon
+    /// logged lately, told every so often until it moves again.
+    fn stalled(&mut self, log: &[String]);
+    /// Ctrl-C was pressed, and the installation is being canceled.
+    fn canceling(&mut self);
+    /// The installation failed with `e`.
+    fn failed(&mut self, e: &DaemonError);
+    /// The installation is done, and the disks are being synced.
+    fn finished(&mut self);
+}
+
+/// Follow the installation until it finishes, returning the steps that were
+/// followed from the start. After Ctrl-C, the installation is canceled,
+/// failing with [`ClientError::Canceled`].
+pub async fn follow(
+    dk_client: &DkClient,
+    observer: &mut dyn ProgressObserver,
+) -> Result<Vec<StepSpan>> {
+    let mut timings = StepTimings::load();
+    let mut timer = StepTimer::default();
+    let mut steps = vec![];
+
+    let mut last_moved = Instant::now();
+    let mut last_looked: Option<Instant> = None;
+    let mut last = None;
+
+    loop {
+        let progress = match Dbus::run(dk_client, DbusMethod::GetProgress).await {
+            Err(e) if is_interrupted(&e) => {
+                observer.canceling();
+                Dbus::run(dk_client, DbusMethod::CancelInstall).await?;
+                return Err(ClientError::Canceled.into());
+            }
+            res => res?,
+        };
+        let data: ProgressStatus = serde_json::from_value(progress.data)?;
+
+        match data {
+            ProgressStatus::Working { step, progress, v } => {
+                if let Some(span) = timer.update(step, progress) {
+                    timings.record(span.step, span.duration);
+                    debug!("Step {} took {:?}", span.step, span.duration);
+                    steps.push(span);
+                }
+
+                if last != Some((step, progress)) {
+                    last = Some((step, progress));
+                    last_moved = Instant::now();
+                } else if last_moved.elapsed() > STALL_TIME
+                    && last_looked.is_none_or(|x| x.elapsed() > DAEMON_LOG_INTERVAL)
+                {
+                    last_looked = Some(Instant::now());
+                    observer.stalled(&daemon_log(&dk_client.bus, DAEMON_LOG_LINES).await);
+                }
+
+                observer.progress(&InstallProgress {
+                    step,
+                    progress,
+                    bytes_per_sec: (v != 0).then_some(v as u64),
+                    remaining: timings.remaining(step, progress, INSTALL_STEPS),
+                })?;
+            }
+            ProgressStatus::Pending => {
+                continue;
+            }
+            ProgressStatus::Error(e) => {
+                let e = DaemonError::from(e);
+                save_timings(&timings);
+                observer.failed(&e);
+                return Err(e.into());
+            }
+            ProgressStatus::Finish => {
+                if let Some(span) = timer.finish() {
+                    timings.record(span.step, span.duration);
+                    debug!("Step {} took {:?}", span.step, span.duration);
+                    steps.push(span);
+                }
+                save_timings(&timings);
+
+                // So that nothing is lost if the machine is switched off
+                // as soon as it says it is done
+                observer.finished();
+                Dbus::run(dk_client, DbusMethod::SyncDisk).await?;
+
+                return Ok(steps);
+            }
+        }
+
+        sleep(Duration::from_micros(100)).await;
+    }
+}
+
+fn save_timings(timings: &StepTimings) {
+    if let Err(e) = timings.save() {
+        debug!("Failed to save step timings: {e}");
+    }
+}
@@ -0,0 +1,357 @@
+//! The recipe: which variants of AOSC OS there are, and their system
+//! releases for each architecture.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+};
+
+#[cfg(not(feature = "online"))]
+use anyhow::bail;
+use anyhow::Result;
+#[cfg(feature = "online")]
+use reqwest::ClientBuilder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Recipe of the system releases on the live media
+pub const OFFLINE_RECIPE_PATH: &str = "/run/livekit/livemnt/manifest/recipe.json";
+/// Memory below which installing online is out of the question: the system
+/// release is downloaded to memory before it is unpacked
+pub const ONLINE_MIN_MEMORY: u64 = 2 << 30;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Recipe {
+    pub variants: Vec<Variant>,
+    pub mirrors: Value,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Variant {
+    pub name: String,
+    #[serde(rename = "dir-name")]
+    pub dir_name: Option<String>,
+    pub retro: bool,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub squashfs: Vec<Squashfs>,
+}
+
+/// A system release of a variant for one architecture.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Squashfs {
+    pub arch: String,
+    pub date: String,
+    #[serde(rename = "downloadSize")]
+    pub download_size: u64,
+    #[serde(rename = "instSize")]
+    pub inst_size: u64,
+    pub path: String,
+    pub sha256sum: String,
+    pub inodes: u64,
+    /// Whether the release boots with Secure Boot, through a signed shim
+    #[serde(rename = "signedShim", default)]
+    pub signed_shim: bool,
+}
+
+/// Fetch the recipe from the mirror, or read that of the live media.
+pub async fn get_recipe(offline_mode: bool) -> Result<Recipe> {
+    if offline_mode {
+        let f = tokio::fs::read(OFFLINE_RECIPE_PATH).await?;
+        return Ok(serde_json::from_slice(&f)?);
+    }
+
+    fetch_recipe().await
+}
+
+#[cfg(feature = "online")]
+async fn fetch_recipe() -> Result<Recipe> {
+    let client = ClientBuilder::new().user_agent("deploykit").build()?;
+    let resp = client
+        .get("https://releases.aosc.io/manifest/recipe.json")
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(resp.json::<Recipe>().await?)
+}
+
+#[cfg(not(feature = "online"))]
+async fn fetch_recipe() -> Result<Recipe> {
+    bail!("Built without the online feature, so only the recipe of the live media can be read")
+}
+
+/// The variant of `recipe` named `variant`, none if there is no such one.
+pub fn get_variant(recipe: Recipe, variant: &str) -> Option<Variant> {
+    recipe.variants.into_iter().find(|x| x.name == variant)
+}
+
+/// Variants in `recipe` that can be installed on `arch`: those with a
+/// system release for it, other than retro ones and BuildKit.
+pub fn installable_variants(recipe: Recipe, arch: &str) -> Vec<Variant> {
+    recipe
+        .variants
+        .into_iter()
+        .filter(|x| !x.retro && x.name.to_lowercase() != "buildkit")
+        .filter(|x| candidate_sqfs(x, arch).is_some())
+        .collect()
+}
+
+/// Whether `variant` comes with a graphical desktop.
+pub fn is_desktop(variant: &Variant) -> bool {
+    variant.name.to_lowercase() == "desktop"
+}
+
+/// Memory the installed `variant` needs to run well.
+pub fn memory_requirement(variant: &Variant) -> u64 {
+    match is_desktop(variant) {
+        true => 2 << 30,
+        false => 1 << 30,
+    }
+}
+
+/// The memory `variant` needs, if the machine has less `memory` than that.
+/// Never so for another architecture than that of the machine installed on,
+/// whose memory is not the one it is to run with.
+pub fn memory_shortfall(variant: &Variant, memory: Option<u64>) -> Option<u64> {
+    if is_foreign_arch() {
+        return None;
+    }
+
+    let required = memory_requirement(variant);
+    memory.filter(|x| *x < required).map(|_| required)
+}
+
+/// The newest system release of `variant` for `arch`.
+pub fn candidate_sqfs<'a>(variant: &'a Variant, arch: &str) -> Option<&'a Squashfs> {
+    let mut sqfs = variant
+        .squashfs
+        .iter()
+        .filter(|x| x.arch == arch)
+        .collect::<Vec<_>>();
+    sqfs.sort_unstable_by(|a, b| b.date.cmp(&a.date));
+
+    sqfs.first().copied()
+}
+
+/// The recipe has no system release of a variant for the architecture
+/// installed for.
+#[derive(Debug)]
+pub struct NoRelease;
+
+impl fmt::Display for NoRelease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("The recipe has no system release for this architecture")
+    }
+}
+
+impl std::error::Error for NoRelease {}
+
+/// The newest system release of `variant` for the architecture installed
+/// for.
+pub fn release_for(variant: &Variant) -> std::result::Result<&Squashfs, NoRelease> {
+    get_arch_name()
+        .and_then(|arch| candidate_sqfs(variant, arch))
+        .ok_or(NoRelease)
+}
+
+/// Architectures AOSC OS is released for
+pub const ARCHES: &[&str] = &[
+    "amd64",
+    "arm64",
+    "i486",
+    "loongarch64",
+    "loongson3",
+    "powerpc",
+    "ppc64",
+    "ppc64el",
+    "riscv64",
+];
+
+static TARGET_ARCH: OnceLock<&'static str> = OnceLock::new();
+static REMOTE_TARGET: AtomicBool = AtomicBool::new(false);
+static REMOTE_ARCH: OnceLock<&'static str> = OnceLock::new();
+
+/// Install for `arch`, one of [`ARCHES`], rather than for the machine dkcli
+/// runs on. Only the first call counts.
+pub fn set_target_arch(arch: &str) {
+    if let Some(arch) = ARCHES.iter().find(|x| **x == arch) {
+        let _ = TARGET_ARCH.set(arch);
+    }
+}
+
+/// Architecture to install for: that set with [`set_target_arch`], or else
+/// that of this machine.
+pub fn get_arch_name() -> Option<&'static str> {
+    TARGET_ARCH.get().copied().or_else(host_arch_name)
+}
+
+/// Whether the system is installed for another architecture than that of
+/// the machine installed on, which says nothing then about the one it is to
+/// boot on.
+pub fn is_foreign_arch() -> bool {
+    TARGET_ARCH
+        .get()
+        .is_some_and(|x| Some(*x) != daemon_arch_name())
+}
+
+/// Install on another machine than this one, that of a daemon driven over
+/// SSH, of architecture `arch` if known, so that nothing found out about
+/// this one applies.
+pub fn set_remote_target(arch: Option<&'static str>) {
+    REMOTE_TARGET.store(true, Ordering::Relaxed);
+    if let Some(arch) = arch {
+        let _ = REMOTE_ARCH.set(arch);
+    }
+}
+
+/// The architecture of the machine the daemon runs on: the one driven over
+/// SSH, or this one.
+fn daemon_arch_name() -> Option<&'static str> {
+    match REMOTE_TARGET.load(Ordering::Relaxed) {
+        true => REMOTE_ARCH.get().copied(),
+        false => host_arch_name(),
+    }
+}
+
+/// Whether the system is installed on another machine or for another
+/// architecture, in which case probing the hardware here tells nothing
+/// about the machine it is to boot on.
+pub fn is_foreign_machine() -> bool {
+    REMOTE_TARGET.load(Ordering::Relaxed) || is_foreign_arch()
+}
+
+/// The architecture of a machine by what `uname -m` prints there.
+pub fn arch_from_uname(machine: &str) -> Option<&'static str> {
+    match machine.trim() {
+        "x86_64" => Some("amd64"),
+        "i386" | "i486" | "i586" | "i686" => Some("i486"),
+        "ppc" => Some("powerpc"),
+        "ppc64" => Some("ppc64"),
+        "ppc64le" => Some("ppc64el"),
+        "aarch64" => Some("arm64"),
+        "mips64" => Some("loongson3"),
+        "riscv64" => Some("riscv64"),
+        "loongarch64" => Some("loongarch64"),
+        _ => None,
+    }
+}
+
+/// AOSC OS specific architecture mapping for ppc64
+#[cfg(target_arch = "powerpc64")]
+#[inline]
+pub fn host_arch_name() -> Option<&'static str> {
+    let mut endian: libc::c_int = -1;
+    let result;
+    unsafe {
+        result = libc::prctl(libc::PR_GET_ENDIAN, &mut endian as *mut libc::c_int);
+    }
+    if result < 0 {
+        return None;
+    }
+    match endian {
+        libc::PR_ENDIAN_LITTLE | libc::PR_ENDIAN_PPC_LITTLE => Some("ppc64el"),
+        libc::PR_ENDIAN_BIG => Some("ppc64"),
+        _ => None,
+    }
+}
+
+/// AOSC OS specific architecture mapping table
+#[cfg(not(target_arch = "powerpc64"))]
+#[inline]
+pub fn host_arch_name() -> Option<&'static str> {
+    use std::env::consts::ARCH;
+    match ARCH {
+        "x86_64" => Some("amd64"),
+        "x86" => Some("i486"),
+        "powerpc" => Some("powerpc"),
+        "aarch64" => Some("arm64"),
+        "mips64" => Some("loongson3"),
+        "riscv64" => Some("riscv64"),
+        "loongarch64" => Some("loongarch64"),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_recipe_snapshot() {
+    let recipe: Recipe = serde_json::from_str(
+        r#"{
+            "variants": [
+                {
+                    "name": "Base",
+                    "dir-name": "base",
+                    "retro": false,
+                    "description": "Minimal system",
+                    "squashfs": [
+                        {"arch": "amd64", "date": "20250101", "downloadSize": 1024, "instSize": 4096,
+                         "path": "os-amd64/base/aosc-os_base_20250101_amd64.squashfs",
+                         "sha256sum": "aaa", "inodes": 10},
+                        {"arch": "amd64", "date": "20250301", "downloadSize": 2048, "instSize": 8192,
+                         "path": "os-amd64/base/aosc-os_base_20250301_amd64.squashfs",
+                         "sha256sum": "bbb", "inodes": 20},
+                        {"arch": "arm64", "date": "20250401", "downloadSize": 1024, "instSize": 4096,
+                         "path": "os-arm64/base/aosc-os_base_20250401_arm64.squashfs",
+                         "sha256sum": "ccc", "inodes": 10}
+                    ]
+                },
+                {
+                    "name": "Desktop",
+                    "dir-name": null,
+                    "retro": false,
+                    "squashfs": [
+                        {"arch": "arm64", "date": "20250401", "downloadSize": 4096, "instSize": 16384,
+                         "path": "os-arm64/desktop/aosc-os_desktop_20250401_arm64.squashfs",
+                         "sha256sum": "ddd", "inodes": 40}
+                    ]
+                },
+                {
+                    "name": "Server",
+                    "dir-name": "server",
+                    "retro": true,
+                    "squashfs": [
+                        {"arch": "amd64", "date": "20250101", "downloadSize": 1024, "instSize": 4096,
+                         "path": "os-amd64/server/aosc-os_server_20250101_amd64.squashfs",
+                         "sha256sum": "eee", "inodes": 10}
+                    ]
+                },
+                {
+                    "name": "BuildKit",
+                    "dir-name": "buildkit",
+                    "retro": false,
+                    "squashfs": [
+                        {"arch": "amd64", "date": "20250101", "downloadSize": 1024, "instSize": 4096,
+                         "path": "os-amd64/buildkit/aosc-os_buildkit_20250101_amd64.squashfs",
+                         "sha256sum": "fff", "inodes": 10}
+                    ]
+                }
+            ],
+            "mirrors": []
+        }"#,
+    )
+    .unwrap();
+
+    insta::assert_debug_snapshot!(recipe);
+
+    for arch in ["amd64", "arm64", "riscv64"] {
+        let variants = installable_variants(recipe.clone(), arch)
+            .iter()
+            .map(|x| {
+                (
+                    x.name.clone(),
+                    candidate_sqfs(x, arch).unwrap().path.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        insta::assert_json_snapshot!(format!("installable_variants_{arch}"), variants);
+    }
+
+    assert_eq!(memory_requirement(&recipe.variants[0]), 1 << 30);
+    assert_eq!(arch_from_uname("aarch64\n"), Some("arm64"));
+    assert_eq!(arch_from_uname("ppc64le"), Some("ppc64el"));
+    assert_eq!(arch_from_uname("s390x"), None);
+    assert_eq!(memory_requirement(&recipe.variants[1]), 2 << 30);
+}
@@ -0,0 +1,146 @@
+//! How long each installation step took on earlier installations, kept
+//! around to estimate how long the next one is going to take.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+// Older samples fade out, so that a faster mirror or disk shows up quickly
+const MAX_SAMPLES: u32 = 5;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StepTimings {
+    steps: BTreeMap<u8, StepTiming>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct StepTiming {
+    average_secs: f64,
+    samples: u32,
+}
+
+impl StepTimings {
+    /// Timings saved by earlier installations, or none if there are none or
+    /// they cannot be read.
+    pub fn load() -> Self {
+        stats_path()
+            .and_then(|path| Ok(fs::read_to_string(path)?))
+            .and_then(|s| Ok(serde_json::from_str(&s)?))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = stats_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn record(&mut self, step: u8, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        let timing = self.steps.entry(step).or_insert(StepTiming {
+            average_secs: secs,
+            samples: 0,
+        });
+
+        let samples = (timing.samples + 1).min(MAX_SAMPLES);
+        timing.average_secs += (secs - timing.average_secs) / samples as f64;
+        timing.samples = samples;
+    }
+
+    /// Time left until the last step is done, going by past timings, while
+    /// `step` is `progress` percent done. `None` if some step has never been
+    /// timed, or the timings make no sense.
+    pub fn remaining(&self, step: u8, progress: u8, last_step: u8) -> Option<Duration> {
+        let current = self.steps.get(&step)?.average_secs;
+        let current = current * (100 - progress.min(100)) as f64 / 100.0;
+
+        let mut secs = current;
+        for i in (step..=last_step).skip(1) {
+            secs += self.steps.get(&i)?.average_secs;
+        }
+
+        // Saved timings may have been tampered with
+        Duration::try_from_secs_f64(secs).ok()
+    }
+}
+
+/// When a step of an installation started and how long it took.
+#[derive(Debug, Clone, Copy)]
+pub struct StepSpan {
+    pub step: u8,
+    pub start: Instant,
+    pub duration: Duration,
+}
+
+/// Follows the steps of an installation to time them.
+#[derive(Default)]
+pub struct StepTimer {
+    current: Option<(u8, Instant)>,
+    seen: Option<u8>,
+}
+
+impl StepTimer {
+    /// Note that `step` is `progress` percent done. Returns the step that
+    /// was just completed, if it was followed from the start.
+    pub fn update(&mut self, step: u8, progress: u8) -> Option<StepSpan> {
+        if self.seen == Some(step) {
+            return None;
+        }
+
+        let first = self.seen.is_none();
+        self.seen = Some(step);
+
+        let done = self.finish();
+        // Joining an installation halfway through a step
+        if !first || progress == 0 {
+            self.current = Some((step, Instant::now()));
+        }
+
+        done
+    }
+
+    /// The last step, if it was followed from the start.
+    pub fn finish(&mut self) -> Option<StepSpan> {
+        self.current.take().map(|(step, start)| StepSpan {
+            step,
+            start,
+            duration: start.elapsed(),
+        })
+    }
+}
+
+fn stats_path() -> Result<PathBuf> {
+    Ok(crate::state_dir()?.join("step-timings.json"))
+}
+
+#[test]
+fn test_remaining() {
+    let mut timings = StepTimings::default();
+    timings.record(1, Duration::from_secs(10));
+    timings.record(2, Duration::from_secs(100));
+    timings.record(2, Duration::from_secs(200));
+
+    assert_eq!(
+        timings.remaining(1, 50, 2),
+        Some(Duration::from_secs(5 + 150))
+    );
+    assert_eq!(timings.remaining(2, 0, 2), Some(Duration::from_secs(150)));
+    assert_eq!(timings.remaining(1, 0, 3), None);
+
+    // Whatever the daemon reports as the step
+    timings.record(u8::MAX, Duration::from_secs(1));
+    assert_eq!(
+        timings.remaining(u8::MAX, 0, 2),
+        Some(Duration::from_secs(1))
+    );
+}
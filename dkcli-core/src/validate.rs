@@ -0,0 +1,355 @@
+//! Checks of what the user entered, telling what is wrong with it for
+//! frontends to put into words.
+
+use std::{fs, io::Read, net::IpAddr, path::Path};
+
+/// Regular user ID range, as in the default login.defs(5)
+pub const UID_MIN: u32 = 1000;
+pub const UID_MAX: u32 = 60000;
+/// Longest user name login records have room for
+pub const USERNAME_MAX: usize = 32;
+// Largest user icon accepted
+const MAX_AVATAR_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostnameError {
+    Empty,
+    TooLong,
+    StartsWith(char),
+    EndsWith(char),
+    DoubleDot,
+    Illegal(char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsernameError {
+    Empty,
+    TooLong,
+    StartsWith(char),
+    Illegal(char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AvatarError {
+    NotFound,
+    TooLarge,
+    NotImage,
+}
+
+pub fn hostname(input: &str) -> Result<(), HostnameError> {
+    if input.is_empty() {
+        return Err(HostnameError::Empty);
+    }
+
+    if input.len() > 64 {
+        return Err(HostnameError::TooLong);
+    }
+
+    for i in ['-', '.'] {
+        if input.starts_with(i) {
+            return Err(HostnameError::StartsWith(i));
+        }
+    }
+
+    for i in ['-', '.'] {
+        if input.ends_with(i) {
+            return Err(HostnameError::EndsWith(i));
+        }
+    }
+
+    let mut is_dot = false;
+    for c in input.chars() {
+        if c == '.' && is_dot {
+            return Err(HostnameError::DoubleDot);
+        } else if is_dot {
+            is_dot = false;
+        }
+
+        if c == '.' {
+            is_dot = true;
+        }
+
+        if !c.is_ascii_alphanumeric() && c != '-' && c != '.' {
+            return Err(HostnameError::Illegal(c));
+        }
+    }
+
+    Ok(())
+}
+
+/// NTP servers are either IP addresses or host names.
+pub fn ntp_server(input: &str) -> Result<(), HostnameError> {
+    if input.parse::<IpAddr>().is_ok() {
+        return Ok(());
+    }
+
+    hostname(input)
+}
+
+// https://manpages.ubuntu.com/manpages/oracular/en/man5/hostname.5.html
+/// A user name: lowercase letters and digits, starting with a letter, a
+/// subset of what useradd(8) takes.
+pub fn username(input: &str) -> Result<(), UsernameError> {
+    let Some(first) = input.chars().next() else {
+        return Err(UsernameError::Empty);
+    };
+
+    if input.len() > USERNAME_MAX {
+        return Err(UsernameError::TooLong);
+    }
+
+    match input
+        .chars()
+        .find(|c| !c.is_ascii_lowercase() && !c.is_ascii_digit())
+    {
+        Some(c) => Err(UsernameError::Illegal(c)),
+        None if first.is_ascii_digit() => Err(UsernameError::StartsWith(first)),
+        None => Ok(()),
+    }
+}
+
+/// Whether `id` is in the regular user ID range.
+pub fn id(id: u32) -> bool {
+    (UID_MIN..=UID_MAX).contains(&id)
+}
+
+// The full name ends up in the GECOS field of passwd(5), where `:' separates
+// fields, `,' separates GECOS subfields and `=' is reserved by chfn(1).
+/// A full name, failing with the first character not allowed in one.
+pub fn fullname(input: &str) -> Result<(), char> {
+    match input
+        .chars()
+        .find(|c| [':', ',', '='].contains(c) || c.is_control())
+    {
+        Some(c) => Err(c),
+        None => Ok(()),
+    }
+}
+
+/// Trim and collapse whitespace in a full name.
+pub fn normalize_fullname(input: &str) -> String {
+    input.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The user icon is copied into AccountsService as-is, so only accept PNG and
+/// JPEG images of a reasonable size.
+pub fn avatar(path: &Path) -> Result<(), AvatarError> {
+    let Ok(metadata) = fs::metadata(path) else {
+        return Err(AvatarError::NotFound);
+    };
+
+    if !metadata.is_file() {
+        return Err(AvatarError::NotFound);
+    }
+
+    if metadata.len() > MAX_AVATAR_SIZE {
+        return Err(AvatarError::TooLarge);
+    }
+
+    let mut magic = [0u8; 8];
+    let is_image = fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok_and(|_| {
+            magic.starts_with(b"\x89PNG\r\n\x1a\n") || magic.starts_with(&[0xff, 0xd8, 0xff])
+        });
+
+    if !is_image {
+        return Err(AvatarError::NotImage);
+    }
+
+    Ok(())
+}
+
+/// Whether `input` is a SHA-256, SHA-512 or yescrypt hash in the crypt(3)
+/// format.
+pub fn password_hash(input: &str) -> bool {
+    let is_crypt_char = |c: char| c.is_ascii_alphanumeric() || c == '.' || c == '/';
+    let is_crypt_str = |s: &str| !s.is_empty() && s.chars().all(is_crypt_char);
+
+    let fields = input.split('$').collect::<Vec<_>>();
+    match fields.as_slice() {
+        ["", id @ ("5" | "6"), rest @ ..] => {
+            let rest = match rest {
+                [rounds, rest @ ..] if rounds.starts_with("rounds=") => {
+                    if rounds["rounds=".len()..].parse::<u32>().is_err() {
+                        return false;
+                    }
+                    rest
+                }
+                _ => rest,
+            };
+
+            let hash_len = if *id == "5" { 43 } else { 86 };
+            matches!(rest, [salt, hash] if salt.len() <= 16 && is_crypt_str(salt) && hash.len() == hash_len && is_crypt_str(hash))
+        }
+        ["", "y", params, salt, hash] => {
+            is_crypt_str(params) && is_crypt_str(salt) && hash.len() == 43 && is_crypt_str(hash)
+        }
+        _ => false,
+    }
+}
+
+#[test]
+fn test_fullname() {
+    assert_eq!(fullname("Mag Mell"), Ok(()));
+    assert_eq!(fullname("白铭骢"), Ok(()));
+    assert_eq!(fullname("a:b"), Err(':'));
+    assert_eq!(fullname("Bai, Mingcong"), Err(','));
+    assert_eq!(fullname("a=b"), Err('='));
+    assert_eq!(fullname("a\nb"), Err('\n'));
+    assert_eq!(fullname("a\tb"), Err('\t'));
+
+    assert_eq!(normalize_fullname("  Mag   Mell "), "Mag Mell");
+}
+
+#[test]
+fn test_password_hash() {
+    for i in [
+        "$6$saltsalt$qFmFH.bQmmtXzyBY0s9v7Oicd2z4XSIecDzlB5KiA2/jctKu9YterLp8wwnSq.qc.eoxqOmSuNp2xS0ktL3nh/",
+        "$6$rounds=5000$saltsalt$qFmFH.bQmmtXzyBY0s9v7Oicd2z4XSIecDzlB5KiA2/jctKu9YterLp8wwnSq.qc.eoxqOmSuNp2xS0ktL3nh/",
+        "$5$saltsalt$5B8vYYiY.CVt1RlTTf8KbXBH3hsxY/GNooZF9m9wVT7",
+        "$y$j9T$F5Jx5fExrKuPp53xLKQ..1$X3DX6M94c7o.9agCG9G317fhZg9SqC.5i5rd.RhAtQ7",
+    ] {
+        assert!(password_hash(i));
+    }
+
+    for i in [
+        "aosc",
+        "$1$saltsalt$qjXMvbEw8oaL.CzflDugX/",
+        "$6$saltsalt$tooshort",
+        "$6$rounds=abc$saltsalt$qFmFH.bQmmtXzyBY0s9v7Oicd2z4XSIecDzlB5KiA2/jctKu9YterLp8wwnSq.qc.eoxqOmSuNp2xS0ktL3nh/",
+        "$y$j9T$salt$not:valid",
+    ] {
+        assert!(!password_hash(i));
+    }
+}
+
+#[test]
+fn test_id() {
+    assert!(id(1000));
+    assert!(id(60000));
+    assert!(!id(0));
+    assert!(!id(60001));
+}
+
+#[test]
+fn test_hostname() {
+    for i in [
+        "foo",
+        "foo-2e10",
+        "jeffbai-device",
+        "JellyDimension",
+        "AOSC.OS",
+        "a.b.c",
+    ] {
+        assert_eq!(hostname(i), Ok(()));
+    }
+
+    assert_eq!(hostname("invalid_host"), Err(HostnameError::Illegal('_')));
+    assert_eq!(hostname("-invalid"), Err(HostnameError::StartsWith('-')));
+    assert_eq!(hostname("+invalid"), Err(HostnameError::Illegal('+')));
+    assert_eq!(
+        hostname("Jelly_Dimension"),
+        Err(HostnameError::Illegal('_'))
+    );
+    assert_eq!(hostname(&"a".repeat(65)), Err(HostnameError::TooLong));
+    assert_eq!(hostname("a..b"), Err(HostnameError::DoubleDot));
+    assert_eq!(hostname("abc."), Err(HostnameError::EndsWith('.')));
+    assert_eq!(hostname(".abc"), Err(HostnameError::StartsWith('.')));
+    assert_eq!(hostname(".abc."), Err(HostnameError::StartsWith('.')));
+}
+
+#[test]
+fn test_username() {
+    assert_eq!(username("foo"), Ok(()));
+    assert_eq!(username("cth451"), Ok(()));
+    assert_eq!(username("老白"), Err(UsernameError::Illegal('老')));
+    assert_eq!(username("BAIMINGCONG"), Err(UsernameError::Illegal('B')));
+    assert_eq!(username(""), Err(UsernameError::Empty));
+    assert_eq!(username("2077"), Err(UsernameError::StartsWith('2')));
+    assert_eq!(username(&"a".repeat(33)), Err(UsernameError::TooLong));
+}
+
+// What useradd(8) takes with the default NAME_REGEX of login.defs(5),
+// `^[a-z_][a-z0-9_-]*[$]?$`, up to the length of utmp records.
+#[cfg(test)]
+fn useradd_accepts(name: &str) -> bool {
+    let name = name.strip_suffix('$').unwrap_or(name);
+    let mut chars = name.chars();
+
+    name.len() <= USERNAME_MAX
+        && chars
+            .next()
+            .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+}
+
+// What hostnamectl(1) takes as a static host name, as systemd's
+// hostname_is_valid(): dot-separated labels of letters, digits and hyphens.
+#[cfg(test)]
+fn hostnamectl_accepts(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .split('.')
+            .all(|l| !l.is_empty() && l.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+}
+
+#[cfg(test)]
+proptest::proptest! {
+    #[test]
+    fn test_username_agrees_with_useradd(name in "[a-z0-9_.$A-Z-]{0,40}|\\PC{0,8}") {
+        if username(&name).is_ok() {
+            proptest::prop_assert!(useradd_accepts(&name));
+        }
+    }
+
+    #[test]
+    fn test_username_takes_plain_names(name in "[a-z][a-z0-9]{0,31}") {
+        proptest::prop_assert_eq!(username(&name), Ok(()));
+    }
+
+    #[test]
+    fn test_hostname_agrees_with_hostnamectl(name in "[a-zA-Z0-9.-]{0,70}|\\PC{0,8}") {
+        if hostname(&name).is_ok() {
+            proptest::prop_assert!(hostnamectl_accepts(&name));
+        }
+    }
+
+    #[test]
+    fn test_hostname_takes_dns_names(
+        name in "[a-z0-9]([a-z0-9-]{0,10}[a-z0-9])?(\\.[a-z0-9]([a-z0-9-]{0,10}[a-z0-9])?){0,3}"
+    ) {
+        proptest::prop_assert_eq!(hostname(&name), Ok(()));
+    }
+
+    #[test]
+    fn test_fullname_fits_gecos(name in "\\PC{0,20}|[a-z :,=\\n\\t]{0,20}") {
+        if fullname(&name).is_ok() {
+            proptest::prop_assert!(!name.contains([':', ',', '=', '\n']));
+
+            let normalized = normalize_fullname(&name);
+            proptest::prop_assert_eq!(fullname(&normalized), Ok(()));
+            proptest::prop_assert_eq!(normalize_fullname(&normalized), normalized);
+        }
+    }
+
+    #[test]
+    fn test_password_hash_fits_shadow(input in "\\$(5|6|y)\\$[a-zA-Z0-9./:$\\n]{0,100}") {
+        // Anything else would break the fields of shadow(5)
+        if password_hash(&input) {
+            proptest::prop_assert!(!input.contains([':', '\n']));
+        }
+    }
+
+    #[test]
+    fn test_password_hash_takes_sha512(
+        salt in "[a-zA-Z0-9./]{1,16}",
+        hash in "[a-zA-Z0-9./]{86}",
+        rounds in proptest::option::of(1000u32..),
+    ) {
+        let rounds = rounds.map(|x| format!("rounds={x}$")).unwrap_or_default();
+        let input = format!("$6${rounds}{salt}${hash}");
+        proptest::prop_assert!(password_hash(&input));
+    }
+}
@@ -0,0 +1,10 @@
+//! Reply to `list_device`.
+
+#![no_main]
+
+use dkcli_core::config::Device;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = serde_json::from_slice::<Vec<Device>>(data);
+});
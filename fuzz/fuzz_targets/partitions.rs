@@ -0,0 +1,14 @@
+//! Reply to `list_partitions`, and the partitions auto-partitioning made.
+
+#![no_main]
+
+use dkcli_core::{config::DkPartition, progress::AutoPartitionProgress};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = serde_json::from_slice::<Vec<DkPartition>>(data);
+
+    if let Ok(AutoPartitionProgress::Finish { res: Ok(res) }) = serde_json::from_slice(data) {
+        let _ = serde_json::from_value::<(Option<DkPartition>, DkPartition)>(res);
+    }
+});
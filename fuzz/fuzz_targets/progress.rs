@@ -0,0 +1,28 @@
+//! Reply to `get_progress`, and the time estimates made from it.
+
+#![no_main]
+
+use std::time::Duration;
+
+use dkcli_core::{
+    progress::{ProgressStatus, INSTALL_STEPS},
+    timings::{StepTimer, StepTimings},
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(ProgressStatus::Working { step, progress, .. }) = serde_json::from_slice(data) else {
+        return;
+    };
+
+    let mut timings = StepTimings::default();
+    for i in 1..=INSTALL_STEPS {
+        timings.record(i, Duration::from_secs(i as u64));
+    }
+    let _ = timings.remaining(step, progress, INSTALL_STEPS);
+
+    let mut timer = StepTimer::default();
+    if let Some(span) = timer.update(step, progress) {
+        timings.record(span.step, span.duration);
+    }
+});
@@ -0,0 +1,19 @@
+//! The recipe, as fetched from the mirror or read from the live media, and
+//! picking a release out of it.
+
+#![no_main]
+
+use dkcli_core::recipe::{candidate_sqfs, installable_variants, Recipe};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(recipe) = serde_json::from_slice::<Recipe>(data) else {
+        return;
+    };
+
+    for arch in ["amd64", "arm64", "riscv64"] {
+        for variant in installable_variants(recipe.clone(), arch) {
+            let _ = candidate_sqfs(&variant, arch);
+        }
+    }
+});
@@ -16,6 +16,7 @@ invaild-fullname = Invaild full name: { $e }
 invaild-username = Invaild UNIX username: { $e }
 invaild-hostname = Invaild hostname: { $e }
 invaild-locale = Invaild locale: { $s }
+invaild-variant = Unknown variant: { $s }
 invaild-timezone = Invaild timezone: { $s }
 invaild-target-partition = Cannot find target partition or target partition does not have sufficient capacity.
 invaild-efi-partition = The specified EFI System Partition (ESP) cannot be found.
@@ -45,12 +46,11 @@ hostname-illegal-starts-with = The specified hostname starts with invalid charac
 hostname-illegal-ends-with = The specified hostname ends with invalid character(s): { $c }
 hostname-illegal-too-loong = The specified hostname is too long.
 hostname-illegal-double-dot = The specified hostname contains two or more consequent dots (`..'), which is not allowed.
-fullname-illegal = The specified full name contains invalid character: ':'
+fullname-illegal = The specified full name contains invalid character: { $c }
 squashfs-empty = The system release manifest does not contain `squashfs' field and may be corrupted.
 confirm-password = Confirmation
 confirm = Would you like to proceed with AOSC OS installation? If you proceed, YOUR DATA WILL BE CLEARED on the affected partition(s) and storage device(s)!
 confirm-autopart = Would you like to proceed with automatic partition? If you proceed, YOUR DATA WILL BE CLEARED on the selected storage device!
-confirm-prompt = Continue (y/n)
 downloading-recipe = Fetching system release metadata ...
 auto-partition-working = Automatic partitioning in progress, please wait ...
 confirm-password-not-matching = The passwords do not match, please retry.
@@ -59,3 +59,279 @@ password-required = A password is required.
 hostname-required = A valid hostname is required.
 yn-confirm-required = Confirmation required: please type 'Y' to confirm, 'N' to abort.
 installation-aborted = Installation has been aborted.
+keymap = Console keyboard layout
+x11-layout = Graphical (X11) keyboard layout
+invaild-keymap = Invalid console keymap: { $s }
+invaild-x11-layout = Invalid X11 keyboard layout: { $s }
+extra-locales = Additional locales to generate (optional)
+timezone-region = Timezone region
+timezone-city = City
+timezone-search = Search all timezones ...
+geoip-timezone = Would you like to detect your timezone from your IP address? (This will send a request to a GeoIP service)
+detecting-timezone = Detecting timezone ...
+ntp = Would you like to enable network time synchronization (systemd-timesyncd)?
+ntp-servers = NTP servers (space-separated, leave empty to use the defaults)
+invaild-ntp-server = Invalid NTP server: { $e }
+display-language = Installer display language
+dbus-query-failed = Failed to execute query: { $e }
+setxkbmap-failed = Failed to run setxkbmap: { $e }
+advanced-user = Would you like to configure advanced user options (UID/GID)?
+uid = User ID (UID)
+gid = Primary group ID (GID)
+id-out-of-range = The ID must be between { $min } and { $max }.
+invaild-id = Invalid UID/GID: { $e }
+autologin = Enable automatic login for this user?
+locale-preview-help = Each entry shows how dates, times, numbers and currency look in that locale.
+input-method = The selected locale(s) need an input method and fonts to be usable. Would you like to install them?
+avatar = User icon (path to a PNG or JPEG image, optional)
+avatar-not-found = The specified file does not exist.
+avatar-too-large = The specified image is too large (maximum 1 MiB).
+avatar-not-image = The specified file is not a PNG or JPEG image.
+invaild-avatar = Invalid user icon: { $e }
+import-users = Existing Linux installations were found. Would you like to import their users (names and IDs only, not passwords)?
+import-users-skip = Do not import users
+import-users-select = Users to import
+umount-failed = Failed to unmount the existing installation: { $e }
+password-or-hash-required = Exactly one of `password' and `password_hash' must be set in the unattended configuration.
+password-hash-format = The password hash must be a SHA-256 ($5$), SHA-512 ($6$) or yescrypt ($y$) crypt(3) hash.
+invaild-password-hash = Invalid password hash: { $e }
+password-hash-unsupported = Deploykit is too old to set the password from a hash. Give the password itself, or update Deploykit and try again.
+yes = Yes
+no = No
+invalid-value = Invalid value, please retry.
+log = Log
+press-any-key = Press any key to exit.
+step-language = Language
+step-source = System edition
+step-disk = Storage
+step-keyboard = Keyboard
+step-user = User
+step-system = System settings
+step-confirm = Confirmation
+step-install = Installation
+unknown-install-step = Step { $step }
+transfer-status = { $done } / { $total }, { $speed }, { $eta } left
+esc-to-go-back = Press Esc at any question to go back to the previous one.
+summary = The following settings will be applied:
+type-to-confirm = To confirm, type the name of the device: { $name }
+type-to-confirm-mismatch = The input does not match. Type { $name } to confirm, or press Esc to go back.
+device-model = Model
+device-path = Path
+device-size = Size
+device-bus = Bus
+device-removable = Removable
+partition-fs = File system
+plain-go-back = Type < and press Enter at any question to go back to the previous step.
+plain-select = Enter a number or some text to search (default { $default })
+plain-multi-select = Enter numbers separated by spaces, or some text to search (default: { $default })
+plain-no-match = Nothing matches, showing all entries.
+listing-devices = Looking for storage devices ...
+searching-installations = Looking for existing installations ...
+quit-installer = Quit the installer?
+display-language-help = Only changes the language of this installer. The language of the installed system is chosen later.
+quit-installer-help = Nothing has been written to your storage devices yet.
+offline-mode-help = Installs from the system release on this installation media instead of downloading it, so no network connection is needed.
+variant-help = The edition decides which desktop environment and software come preinstalled. More software can be installed later.
+select-device-help = The storage device (hard drive, SSD, USB drive, ...) to install AOSC OS onto.
+auto-partition-help = Answer Yes to erase the whole device and create the needed partitions automatically, or No to pick existing partitions.
+select-system-partition-help = AOSC OS will be installed onto this partition, and all data on it will be erased.
+select-efi-partition-help = The EFI System Partition (ESP) is a small FAT partition holding the bootloaders that your firmware starts. It is shared with other operating systems and will not be erased.
+fullname-help = Your name as shown on the login screen, e.g. Jane Doe.
+username-help = The name used to log in. Lowercase letters, digits, `-' and `_' only.
+password-help = The password for logging in and for administrative tasks (sudo).
+autologin-help = Log in to the graphical desktop without asking for the password on startup.
+avatar-help = Shown next to your name on the login screen. Leave empty to use the default icon.
+advanced-user-help = Only needed to match the IDs of an existing user, e.g. for files on a shared drive.
+uid-help = The numeric ID of the user. Regular users start at 1000.
+gid-help = The numeric ID of the primary group of the user, usually the same as the UID.
+geoip-timezone-help = Sends your IP address to a GeoIP service to guess where you are. Answer No to pick the timezone yourself.
+extra-locales-help = Additional languages and regions to make available to programs. Press Space to select, Enter to confirm.
+hostname-help = The name of this computer on the network, e.g. my-laptop.
+rtc-as-localtime-help = Answer Yes only if you also run Windows on this computer, which keeps the hardware clock in local time. Otherwise, it keeps UTC.
+ntp-help = Keeps the system clock accurate by synchronizing it with time servers on the Internet.
+ntp-servers-help = Host names or IP addresses, e.g. pool.ntp.org.
+swap-size-help = Swap extends the memory with space on the storage device, and is needed for hibernation. Enter 0 to disable it.
+import-users-help = Creates the same users on the new system, so that files shared with the existing installation keep their owners.
+import-users-select-help = Press Space to select, Enter to confirm.
+keymap-help = The keyboard layout used on the text console, e.g. before a graphical desktop starts.
+x11-layout-help = The keyboard layout used in graphical desktops.
+timezone-region-help = Choose a continent or ocean first, then a city in the same timezone as you.
+timezone-help = Type to search, e.g. the name of a city near you.
+help-hint = Press ? for help
+help-hint-text = Press F1 for help
+match-count = { $shown } of { $total }
+list-count = { $total } entries, type to search
+no-variant = The system release manifest has no edition available for this architecture ({ $arch }).
+variant-sizes = { $download } to download, { $installed } installed
+variant-installed-size = { $installed } installed
+notify-finished = AOSC OS has been installed
+notify-finished-body = You may now restart your computer.
+notify-failed = AOSC OS installation failed
+time-remaining = about { $time } remaining
+list-keys = Up/Down to move, Enter to select, type to search
+daemon-reconnecting = Lost connection to the installer daemon ({ $e }), reconnecting ...
+daemon-reconnected = Reconnected to the installer daemon.
+summary-action = What would you like to do?
+summary-action-help = Choose a setting to change it, or start the installation.
+start-install = Start the installation
+edit-answer = Change: { $field }
+starting-daemon = The installer daemon is not running, starting it ...
+daemon-not-running = The installer daemon ({ $unit }) is not running and could not be started. Please check `systemctl status { $unit }' and try again.
+daemon-timeout = The installer daemon did not respond within { $secs } seconds while { $action }. Use --dbus-timeout to wait longer.
+daemon-action-set-config = saving the installation settings
+daemon-action-auto-partition = partitioning the device
+daemon-action-get-progress = checking the progress
+daemon-action-start-install = starting the installation
+daemon-action-list-partitions = listing partitions
+daemon-action-list-devices = listing devices
+daemon-action-swap-size = computing the swap size
+daemon-action-cancel-install = cancelling the installation
+daemon-action-check-disk = checking the device
+daemon-action-reset-config = resetting the installation settings
+daemon-error-disk = Please check that the device is connected and not in use, then try again.
+daemon-error-no-space = The selected partition is too small for this edition. Please choose a larger partition or a smaller edition.
+daemon-error-network = Please check your network connection and try again, or use the offline mode if available.
+daemon-error-config = The installer daemon rejected the settings. Please review them and try again.
+daemon-error-busy = Another installation is in progress. Please wait for it to finish, or cancel it first.
+daemon-skipped-config = The installer daemon is too old for some settings, which will be left at their defaults: { $fields }. Upgrade deploykit to use them.
+auto-partition-pending = Waiting for the installer daemon to start partitioning ...
+auto-partition-failed = Failed to partition { $dev } automatically. The device may have been partially changed, please check it before trying again.
+interrupt-again = Stopping ... Press Ctrl-C again to quit immediately.
+install-canceling = Cancelling the installation ...
+daemon-action-claim = taking control of the installer daemon
+daemon-claimed = Another installer is already in control of the installer daemon: { $owner }
+attach-read-only = Follow the progress of the other installer without changing anything?
+attach-read-only-help = Answer No to quit. Only one installer may configure the installation at a time.
+daemon-action-get-config = reading back the installation settings
+daemon-config = Settings recorded by the installer daemon
+config-differs = { $recorded } (sent: { $sent })
+config-not-recorded = (not recorded)
+config-mismatch-continue = Some settings were not recorded as sent. Install anyway?
+config-mismatch = The installer daemon did not record the settings as sent. Installation has been aborted before making any changes.
+daemon-action-validate-config = checking the installation settings
+config-invalid = The installer daemon found { $count ->
+    [one] a problem
+   *[other] { $count } problems
+} with the settings. Nothing has been changed yet, please fix the following and try again:
+not-authorized = Permission denied while { $action }. Please run dkcli as root, or authenticate when asked.
+daemon-action-connect = connecting
+checking-device = Checking { $dev } ...
+preflight-wrong-table = The partition table of this storage device cannot be used to boot this computer: { $e }
+preflight-action = AOSC OS cannot be installed to { $dev } as it is. How would you like to continue?
+preflight-action-help = Partitioning automatically takes care of the problems above, but erases everything on the device.
+preflight-auto-partition = Erase { $dev } and partition it automatically
+preflight-other-device = Choose another storage device
+running-install-action = What would you like to do?
+running-install-action-help = Only one installation can run at a time.
+running-install-attach = Follow its progress
+running-install-cancel = Cancel it
+running-install-quit = Quit
+leftover-state = The installer daemon still has the following from an earlier run
+leftover-progress = Installation
+leftover-progress-failed = failed: { $e }
+leftover-progress-finished = finished
+reset-leftover-state = Start over from a clean slate?
+reset-leftover-state-help = Answer No to keep these settings, which the new answers will replace.
+leftover-state-reset = Cleared the settings and status left by an earlier run.
+daemon-action-sync = writing data to the storage device
+syncing-disks = Writing data to the storage device, please wait ...
+after-install = What would you like to do now?
+after-install-help = Remove the installation media once the computer has turned off or restarted.
+after-install-reboot = Restart into AOSC OS
+after-install-poweroff = Shut down
+after-install-stay = Stay in the live environment
+log-file-failed = Failed to open the log file { $path }, logging to the terminal only
+report-failed = Failed to write the installation report: { $e }
+collecting-failure-bundle = Collecting information about the failure ...
+failure-bundle = Logs and details about the failure are in { $path }, please attach it when reporting this issue.
+failure-bundle-failed = Failed to collect information about the failure: { $e }
+event-fifo-waiting = Waiting for a frontend to read events from { $path } ...
+event-fifo-not-fifo = { $path } is not a named pipe
+elapsed-time = Time taken
+daemon-code-partition = Failed to partition the storage device.
+daemon-code-format = Failed to format the partition.
+daemon-code-mount = Failed to mount the partition.
+daemon-code-umount = Failed to unmount the partition.
+daemon-code-no-space = There is not enough space on the selected partition.
+daemon-code-not-enough-space = There is not enough space on the selected partition.
+daemon-code-download = Failed to download the system release.
+daemon-code-download-hash-mismatch = The downloaded system release is damaged (checksum mismatch).
+daemon-code-network = A network error occurred.
+daemon-code-invalid-config = The installation settings are invalid.
+daemon-code-busy = The installer daemon is busy with another installation.
+daemon-code-in-progress = Another installation is in progress.
+config-read-failed = Failed to read the configuration file { $path }
+config-parse-failed = Failed to parse the configuration file { $path }
+event-fifo-failed = Failed to open the named pipe { $path }
+stats-prompt = Send anonymous statistics about this installation to AOSC?
+stats-prompt-help = Helps us decide which installer problems to fix first. This is all that is sent: { $stats }
+stats-sending = Sending installation statistics ...
+stats-sent = Thank you for sending installation statistics.
+stats-failed = Failed to send installation statistics: { $e }
+warnings = { $count ->
+    [one] Please note the following before installing:
+   *[other] Please note the following { $count } things before installing:
+}
+warning-smart-failing = The SMART self-assessment of { $dev } reports that it is failing. Back up your data and consider using another storage device.
+warning-small-esp = The EFI system partition is only { $size }, which may be too small for the boot loader.
+warning-esp-other-device = The EFI system partition is on a different storage device than the system partition, so the system will not boot without both.
+warning-almost-full = Only { $left } will be left on the system partition after installing.
+online-unsupported = This build of dkcli only installs the system release on the live media, not one from the mirror.
+checkpoint-found = An earlier run did not finish; it had {$stage}:
+checkpoint-configured = gone through the settings
+checkpoint-config-sent = sent the settings to the installer
+checkpoint-install-started = started the installation
+resume-checkpoint = Pick up where it left off?
+resume-checkpoint-help = Otherwise start over
+checkpoint-password-help = The password is not kept across runs, please enter it again.
+checkpoint-config-held = The installer still holds the settings sent earlier.
+checkpoint-failed = Could not record how far the installation got: {$e}
+hook-failed = Step {$name} failed
+invaild-timezone-suggest = Invaild timezone: { $s }, did you mean { $suggested }?
+username-too-long = The username must be at most { $max } characters long.
+username-illegal-starts-with = The username must not start with { $c }.
+select-board = Board
+select-board-help = Detected: {$board}. The disk is laid out for its firmware to boot from.
+select-board-undetected-help = This board ({$compatible}) is not one dkcli knows. Pick one with the same SoC, or UEFI if its firmware provides it.
+invaild-board = Unknown board: { $s }
+board-unsupported = The installer daemon is too old to install to this board. Update Deploykit and try again.
+select-firmware-partition = Firmware partition
+select-firmware-partition-help = The FAT partition the board boots from. Its boot files are replaced with those of the new system.
+no-firmware-partition = This board boots from a FAT firmware partition of at least {$size}, and the device has none.
+firmware-field-not-set = This board boots from a firmware partition; set firmware_part in the config file.
+invaild-firmware-partition = The firmware partition must be an existing FAT partition of at least {$size}.
+select-loongarch-firmware-help = Could not tell the generation of this machine's firmware. Old-world firmware (before version 4) only boots a bootloader built for it; pick UEFI for new-world firmware.
+loongarch-firmware-unknown = Could not tell whether the firmware of this LoongArch machine is old-world or new-world; installing for new-world firmware. Set board = "loongarch-ow" for old-world firmware.
+riscv-missing-extensions = This CPU lacks ISA extensions AOSC OS is built for ({$extensions}); the installed system will not run on it.
+riscv-no-boot-environment = This machine was started by neither EFI nor U-Boot, and nothing may start the bootloader that is installed.
+riscv-needs-spl = {$board} loads its SPL from a raw offset on the boot disk, which dkcli cannot write. Keep the SPL on another medium (such as SPI flash or an SD card) or write it yourself after installing.
+platform-continue-anyway = Install anyway?
+boot-instructions = To boot
+petitboot-instructions = Reboot and pick AOSC OS on {$partition} in the Petitboot menu. To boot it by default, set it as the autoboot option under System configuration.
+daemon-action-memory = checking the memory size
+low-memory-online = This machine has {$memory} of memory, less than the {$required} it takes to download the system while installing. Only the system on the live media can be installed.
+low-memory-use-offline = Start from the offline installation media, which carries the system with it.
+low-memory-variant = {$variant} needs {$required} of memory to run well; this machine has {$memory}.
+efi-ia32-detected = This machine has 32-bit UEFI firmware, which cannot start the usual 64-bit bootloader. The ia32 EFI bootloader is installed instead.
+secure-boot = Secure Boot
+secure-boot-shim = Enabled, booting through the signed shim
+secure-boot-disable = Enabled, to be disabled before rebooting
+secure-boot-shim-info = Secure Boot is enabled. The system boots through a signed shim, whose key is to be enrolled on the next boot.
+secure-boot-unsigned = Secure Boot is enabled, and this variant ships no signed shim. Disable Secure Boot in the firmware settings before rebooting, or the installed system will not start.
+secure-boot-shim-instructions = On the next boot, MokManager asks to enroll the key of AOSC OS: choose "Enroll MOK", then "Continue" and confirm.
+secure-boot-disable-instructions = Disable Secure Boot in the firmware settings before booting the installed system.
+secure-boot-unsupported = Secure Boot is enabled, and Deploykit is too old to set up shim. Update Deploykit, or disable Secure Boot and start over.
+target-arch-foreign = Installing for {$arch} on a {$host} machine. Checks of this machine are skipped, and setting up the bootloader takes qemu-user emulation for {$arch} registered with binfmt_misc.
+remote-arch-unknown = The other machine is of an architecture AOSC OS is not released for ({$arch}).
+select-board-foreign-help = Installing for another {$arch} machine: pick how it boots.
+gpu-driver = Install the proprietary NVIDIA driver?
+gpu-driver-nvidia-help = This machine has an NVIDIA GPU, which the free driver may not drive well or at all.
+gpu-driver-hybrid-help = This machine has NVIDIA hybrid graphics (Optimus), which often boots to a black screen with the free driver.
+gpu-driver-suggest = This machine has an NVIDIA GPU; set gpu_driver = true in the config file to install its proprietary driver, or false to keep to the free one.
+target-dir = Target directory
+target-dir-info = Installing into {$path}. Nothing is partitioned, and no bootloader is set up.
+target-dir-invalid = {$path} is not an existing directory.
+target-dir-root = {$path} is the running system, which cannot be installed over.
+target-dir-not-empty = {$path} is not empty. Use --force to install into it anyway, overwriting what is there.
+target-dir-not-empty-continue = {$path} is not empty, and what is there may be overwritten. Install into it anyway?
+target-dir-unsupported = Deploykit is too old to install into a directory. Update Deploykit and try again.
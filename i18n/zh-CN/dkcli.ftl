@@ -16,6 +16,7 @@ invaild-fullname = 用户全名无效：{ $e }
 invaild-username = UNIX 用户名无效：{ $e }
 invaild-hostname = 主机名无效：{ $e }
 invaild-locale = 无效系统语言：{ $s }
+invaild-variant = 未知系统版本：{ $s }
 invaild-timezone = 无效时区：{ $s }
 invaild-target-partition = 找不到指定的目标系统分区或目标分区存储空间不足。
 invaild-efi-partition = 找不到指定的 EFI 系统分区 (ESP) 。
@@ -40,14 +41,16 @@ password = 密码
 rtc-as-localtime = 是否使用硬件时钟 (RTC) 作为系统时间？
 swap-size = 虚拟内存文件 (swapfile) 大小 (GiB)
 hostname-illegal = 指定的主机名中包含无效字符：{ $c }
-hostname-illegal-startswith = 指定的主机名以无效字符字符开头：{ $c }
+hostname-illegal-starts-with = 指定的主机名以无效字符开头：{ $c }
+hostname-illegal-ends-with = 指定的主机名以无效字符结尾：{ $c }
+hostname-illegal-too-loong = 指定的主机名过长。
+hostname-illegal-double-dot = 指定的主机名中包含两个或以上连续的点 (`..')，这是不允许的。
 username-illegal = 指定的 UNIX 用户名中包含无效字符：{ $c }
-fullname-illegal = 指定的用户全名中包含无效字符：':'
+fullname-illegal = 指定的用户全名中包含无效字符：{ $c }
 squashfs-empty = 系统发行元数据文件中未包含 `squashfs'，该元数据文件可能已损坏。
 confirm-password = 确认密码
 confirm = 您确定要安装 AOSC OS 吗？若继续，相关分区及存储设备上的数据**将被清空**！
 confirm-autopart = 您确定要自动分区吗？若继续，指定存储设备上的数据**将被清空**！
-confirm-prompt = 是否继续 (y/n)
 downloading-recipe = 正在下载系统发行元数据 ...
 auto-partition-working = 正在进行自动分区，请稍候 ...
 confirm-password-not-matching = 您指定的密码不匹配，请重试。
@@ -56,3 +59,275 @@ hostname-required = 需设置有效主机名。
 username-required = 需设置有效 UNIX 用户名。
 yn-confirm-required = 请确认操作：按 'Y' 确认，按 'N' 中止操作。
 installation-aborted = 已中止安装。
+keymap = 控制台键盘布局
+x11-layout = 图形界面 (X11) 键盘布局
+invaild-keymap = 无效控制台键盘布局：{ $s }
+invaild-x11-layout = 无效 X11 键盘布局：{ $s }
+extra-locales = 需额外生成的语言环境（可选）
+timezone-region = 时区所在地区
+timezone-city = 城市
+timezone-search = 搜索所有时区 ...
+geoip-timezone = 是否根据 IP 地址自动检测时区？（将向 GeoIP 服务发送请求）
+detecting-timezone = 正在检测时区 ...
+ntp = 是否启用网络时间同步 (systemd-timesyncd)？
+ntp-servers = NTP 服务器（以空格分隔，留空则使用默认服务器）
+invaild-ntp-server = 无效 NTP 服务器：{ $e }
+display-language = 安装程序显示语言
+dbus-query-failed = 执行查询失败：{ $e }
+setxkbmap-failed = 无法运行 setxkbmap：{ $e }
+advanced-user = 是否配置高级用户选项 (UID/GID)？
+uid = 用户 ID (UID)
+gid = 主组 ID (GID)
+id-out-of-range = ID 须介于 { $min } 与 { $max } 之间。
+invaild-id = 无效 UID/GID：{ $e }
+autologin = 是否为该用户启用自动登录？
+locale-preview-help = 各条目显示该语言环境下的日期、时间、数字及货币格式。
+input-method = 所选语言环境需要输入法及字体方可正常使用，是否安装？
+avatar = 用户头像（PNG 或 JPEG 图片路径，可选）
+avatar-not-found = 指定的文件不存在。
+avatar-too-large = 指定的图片过大（最大 1 MiB）。
+avatar-not-image = 指定的文件不是 PNG 或 JPEG 图片。
+invaild-avatar = 无效用户头像：{ $e }
+import-users = 探测到已有的 Linux 系统，是否导入其中的用户（仅用户名及 ID，不含密码）？
+import-users-skip = 不导入用户
+import-users-select = 需导入的用户
+umount-failed = 无法卸载已有系统：{ $e }
+password-or-hash-required = 无人值守配置中须且仅须定义 `password' 或 `password_hash' 其中之一。
+password-hash-format = 密码散列须为 SHA-256 ($5$)、SHA-512 ($6$) 或 yescrypt ($y$) 格式的 crypt(3) 散列。
+invaild-password-hash = 无效密码散列：{ $e }
+password-hash-unsupported = Deploykit 版本过旧，无法通过散列设置密码。请直接提供密码，或更新 Deploykit 后重试。
+yes = 是
+no = 否
+invalid-value = 输入值无效，请重试。
+log = 日志
+press-any-key = 按任意键退出。
+step-language = 语言
+step-source = 系统版本
+step-disk = 存储设备
+step-keyboard = 键盘
+step-user = 用户
+step-system = 系统设置
+step-confirm = 确认
+step-install = 安装
+unknown-install-step = 第 { $step } 步
+transfer-status = { $done } / { $total }，{ $speed }，剩余 { $eta }
+esc-to-go-back = 在任意问题中按 Esc 键可返回上一个问题。
+summary = 即将应用以下设置：
+type-to-confirm = 请输入设备名以确认：{ $name }
+type-to-confirm-mismatch = 输入不匹配。请输入 { $name } 以确认，或按 Esc 键返回。
+device-model = 型号
+device-path = 路径
+device-size = 容量
+device-bus = 总线
+device-removable = 可移动
+partition-fs = 文件系统
+plain-go-back = 在任意问题中输入 < 并按回车键可返回上一步。
+plain-select = 请输入编号或要搜索的文本（默认 { $default }）
+plain-multi-select = 请输入以空格分隔的编号，或要搜索的文本（默认：{ $default }）
+plain-no-match = 没有匹配项，显示所有条目。
+listing-devices = 正在查找存储设备 ...
+searching-installations = 正在查找已有的系统 ...
+quit-installer = 要退出安装程序吗？
+display-language-help = 仅更改安装程序本身的语言。所安装系统的语言将在稍后选择。
+quit-installer-help = 目前尚未向存储设备写入任何数据。
+offline-mode-help = 使用安装介质上的系统包进行安装而不必下载，因此无需网络连接。
+variant-help = 版本决定了预装的桌面环境及软件。您可在安装后安装更多软件。
+select-device-help = 用于安装安同 OS 的存储设备（硬盘、固态硬盘、U 盘等）。
+auto-partition-help = 选择“是”将清空整个设备并自动创建所需的分区；选择“否”则从现有分区中选择。
+select-system-partition-help = 安同 OS 将安装到该分区，其中的所有数据都将被清除。
+select-efi-partition-help = EFI 系统分区（ESP）是一个较小的 FAT 分区，存放由固件启动的引导程序。该分区与其他操作系统共用，不会被清除。
+fullname-help = 在登录界面上显示的姓名，如“张三”。
+username-help = 登录时使用的名称，仅可包含小写字母、数字、`-' 及 `_'。
+password-help = 用于登录及执行管理任务（sudo）的密码。
+autologin-help = 启动时无需输入密码即可登录图形桌面。
+avatar-help = 显示在登录界面上的姓名旁。留空则使用默认图标。
+advanced-user-help = 仅在需要与已有用户的 ID 保持一致时使用，如共享磁盘上的文件。
+uid-help = 用户的数字 ID，普通用户从 1000 开始。
+gid-help = 用户主组的数字 ID，通常与 UID 相同。
+geoip-timezone-help = 将您的 IP 地址发送给 GeoIP 服务以推测您的位置。选择“否”则手动选择时区。
+extra-locales-help = 供程序使用的其他语言及地区。按空格键选择，按回车键确认。
+hostname-help = 本计算机在网络上的名称，如 my-laptop。
+rtc-as-localtime-help = 仅当本计算机上还安装有 Windows 时选择“是”，Windows 将硬件时钟设为本地时间；否则硬件时钟使用 UTC。
+ntp-help = 与互联网上的时间服务器同步，使系统时钟保持准确。
+ntp-servers-help = 主机名或 IP 地址，如 pool.ntp.org。
+swap-size-help = 交换文件使用存储设备上的空间扩展内存，休眠功能也需要交换文件。输入 0 可禁用交换文件。
+import-users-help = 在新系统中创建相同的用户，使与已有系统共享的文件保持原有的所有者。
+import-users-select-help = 按空格键选择，按回车键确认。
+keymap-help = 文本控制台（如图形桌面启动前）使用的键盘布局。
+x11-layout-help = 图形桌面中使用的键盘布局。
+timezone-region-help = 先选择大洲或大洋，再选择与您处于同一时区的城市。
+timezone-help = 输入内容以搜索，如您附近城市的名称。
+help-hint = 按 ? 键查看帮助
+help-hint-text = 按 F1 键查看帮助
+match-count = { $total } 项中的 { $shown } 项
+list-count = 共 { $total } 项，输入内容以搜索
+no-variant = 系统发行清单中没有适用于该架构（{ $arch }）的版本。
+variant-sizes = 需下载 { $download }，安装后占用 { $installed }
+variant-installed-size = 安装后占用 { $installed }
+notify-finished = 安同 OS 已安装完成
+notify-finished-body = 您现在可以重启计算机了。
+notify-failed = 安同 OS 安装失败
+time-remaining = 预计剩余 { $time }
+list-keys = 上/下方向键移动，回车键选择，输入内容以搜索
+daemon-reconnecting = 与安装程序守护进程的连接已断开（{ $e }），正在重新连接 ...
+daemon-reconnected = 已重新连接到安装程序守护进程。
+summary-action = 接下来要做什么？
+summary-action-help = 选择一项设置以修改，或开始安装。
+start-install = 开始安装
+edit-answer = 修改：{ $field }
+starting-daemon = 安装程序守护进程未运行，正在启动 ...
+daemon-not-running = 安装程序守护进程（{ $unit }）未运行，且无法启动。请检查 `systemctl status { $unit }' 的输出后重试。
+daemon-timeout = 安装程序守护进程在{ $action }时 { $secs } 秒内未响应。可使用 --dbus-timeout 延长等待时间。
+daemon-action-set-config = 保存安装设置
+daemon-action-auto-partition = 为设备分区
+daemon-action-get-progress = 查询进度
+daemon-action-start-install = 开始安装
+daemon-action-list-partitions = 列出分区
+daemon-action-list-devices = 列出设备
+daemon-action-swap-size = 计算交换空间大小
+daemon-action-cancel-install = 取消安装
+daemon-action-check-disk = 检查设备
+daemon-action-reset-config = 重置安装设置
+daemon-error-disk = 请检查设备是否已连接且未被占用，然后重试。
+daemon-error-no-space = 所选分区空间不足以安装此版本。请选择更大的分区或更小的版本。
+daemon-error-network = 请检查网络连接后重试，或在可用时使用离线模式。
+daemon-error-config = 安装程序守护进程拒绝了这些设置。请检查后重试。
+daemon-error-busy = 另一个安装任务正在进行。请等待其完成，或先取消该任务。
+daemon-skipped-config = 安装程序守护进程版本过旧，不支持部分设置，这些设置将保持默认值：{ $fields }。升级 deploykit 后即可使用。
+auto-partition-pending = 正在等待安装程序守护进程开始分区 ...
+auto-partition-failed = 无法自动为 { $dev } 分区。设备可能已被部分修改，请检查后重试。
+interrupt-again = 正在停止 ... 再次按下 Ctrl-C 可立即退出。
+install-canceling = 正在取消安装 ...
+daemon-action-claim = 获取安装程序守护进程的控制权
+daemon-claimed = 另一个安装程序已在控制安装程序守护进程：{ $owner }
+attach-read-only = 是否以只读方式跟随另一个安装程序的进度？
+attach-read-only-help = 选择“否”以退出。同一时间只能有一个安装程序配置安装。
+daemon-action-get-config = 读取安装设置
+daemon-config = 安装程序守护进程记录的设置
+config-differs = { $recorded }（发送值：{ $sent }）
+config-not-recorded = （未记录）
+config-mismatch-continue = 部分设置的记录值与发送值不一致。仍要安装吗？
+config-mismatch = 安装程序守护进程记录的设置与发送值不一致。安装已在做出任何更改前中止。
+daemon-action-validate-config = 检查安装设置
+config-invalid = 安装程序守护进程发现设置中有 { $count } 个问题。目前尚未做出任何更改，请修正以下问题后重试：
+not-authorized = 在{ $action }时权限被拒绝。请以 root 身份运行 dkcli，或在提示时进行身份验证。
+daemon-action-connect = 连接
+checking-device = 正在检查 { $dev } ...
+preflight-wrong-table = 此存储设备的分区表无法用于引导这台计算机：{ $e }
+preflight-action = 无法直接将安同 OS 安装到 { $dev }。要如何继续？
+preflight-action-help = 自动分区可以解决上述问题，但会清除设备上的所有数据。
+preflight-auto-partition = 清除 { $dev } 并自动分区
+preflight-other-device = 选择其他存储设备
+running-install-action = 要执行什么操作？
+running-install-action-help = 同一时间只能进行一个安装任务。
+running-install-attach = 跟随其进度
+running-install-cancel = 取消该安装任务
+running-install-quit = 退出
+leftover-state = 安装程序守护进程仍保留着先前运行留下的以下内容
+leftover-progress = 安装
+leftover-progress-failed = 失败：{ $e }
+leftover-progress-finished = 已完成
+reset-leftover-state = 是否清除这些内容并重新开始？
+reset-leftover-state-help = 选择“否”以保留这些设置，新的回答将替换它们。
+leftover-state-reset = 已清除先前运行留下的设置和状态。
+daemon-action-sync = 将数据写入存储设备
+syncing-disks = 正在将数据写入存储设备，请稍候 ...
+after-install = 接下来要做什么？
+after-install-help = 请在计算机关闭或重启后移除安装介质。
+after-install-reboot = 重启进入安同 OS
+after-install-poweroff = 关机
+after-install-stay = 留在 Live 环境中
+log-file-failed = 无法打开日志文件 { $path }，日志将仅输出到终端
+report-failed = 无法写入安装报告：{ $e }
+collecting-failure-bundle = 正在收集故障信息 ...
+failure-bundle = 日志及故障详情已保存至 { $path }，报告问题时请附上该文件。
+failure-bundle-failed = 无法收集故障信息：{ $e }
+event-fifo-waiting = 正在等待前端从 { $path } 读取事件 ...
+event-fifo-not-fifo = { $path } 不是命名管道
+elapsed-time = 耗时
+daemon-code-partition = 无法对存储设备进行分区。
+daemon-code-format = 无法格式化分区。
+daemon-code-mount = 无法挂载分区。
+daemon-code-umount = 无法卸载分区。
+daemon-code-no-space = 所选分区空间不足。
+daemon-code-not-enough-space = 所选分区空间不足。
+daemon-code-download = 无法下载系统发行包。
+daemon-code-download-hash-mismatch = 下载的系统发行包已损坏（校验和不匹配）。
+daemon-code-network = 发生网络错误。
+daemon-code-invalid-config = 安装设置无效。
+daemon-code-busy = 安装程序守护进程正忙于另一个安装任务。
+daemon-code-in-progress = 另一个安装任务正在进行。
+config-read-failed = 无法读取配置文件 { $path }
+config-parse-failed = 无法解析配置文件 { $path }
+event-fifo-failed = 无法打开命名管道 { $path }
+stats-prompt = 是否向 AOSC 发送本次安装的匿名统计信息？
+stats-prompt-help = 这将帮助我们决定优先修复哪些安装程序问题。发送的全部内容如下：{ $stats }
+stats-sending = 正在发送安装统计信息 ...
+stats-sent = 感谢您发送安装统计信息。
+stats-failed = 无法发送安装统计信息：{ $e }
+warnings = { $count ->
+   *[other] 安装前请注意以下 { $count } 项：
+}
+warning-smart-failing = { $dev } 的 SMART 自检报告该设备即将损坏。请备份数据并考虑使用其他存储设备。
+warning-small-esp = EFI 系统分区仅有 { $size }，可能不足以容纳引导程序。
+warning-esp-other-device = EFI 系统分区与系统分区位于不同的存储设备上，缺少任一设备都将无法启动系统。
+warning-almost-full = 安装后系统分区将仅剩 { $left } 可用空间。
+online-unsupported = 此版本的 dkcli 仅支持安装 Live 介质中的系统，不支持从镜像源下载安装。
+checkpoint-found = 上次运行未能完成，当时已{$stage}：
+checkpoint-configured = 完成配置
+checkpoint-config-sent = 向安装程序发送配置
+checkpoint-install-started = 开始安装
+resume-checkpoint = 是否从中断处继续？
+resume-checkpoint-help = 否则将重新开始
+checkpoint-password-help = 密码不会被保存，请重新输入。
+checkpoint-config-held = 安装程序仍保留着先前发送的配置。
+checkpoint-failed = 无法记录安装进度：{$e}
+hook-failed = 步骤 {$name} 失败
+invaild-timezone-suggest = 无效时区：{ $s }，是否指的是 { $suggested }？
+username-too-long = 用户名最多只能包含 { $max } 个字符。
+username-illegal-starts-with = 用户名不能以 { $c } 开头。
+select-board = 开发板
+select-board-help = 已检测到：{$board}。将按其固件的引导方式规划磁盘布局。
+select-board-undetected-help = dkcli 无法识别此开发板（{$compatible}）。请选择使用相同 SoC 的开发板；如其固件支持 UEFI，则选择 UEFI。
+invaild-board = 未知开发板：{ $s }
+board-unsupported = 安装程序守护进程版本过旧，无法安装到此开发板。请更新 Deploykit 后重试。
+select-firmware-partition = 固件分区
+select-firmware-partition-help = 开发板从此 FAT 分区引导，其中的引导文件将被替换为新系统的文件。
+no-firmware-partition = 此开发板需要从至少 {$size} 的 FAT 固件分区引导，但该设备上没有这样的分区。
+firmware-field-not-set = 此开发板从固件分区引导，请在配置文件中设置 firmware_part。
+invaild-firmware-partition = 固件分区必须是已有的、至少 {$size} 的 FAT 分区。
+select-loongarch-firmware-help = 无法判断此机器固件的世代。旧世界固件（版本 4 之前）只能引导为其构建的引导程序；新世界固件请选择 UEFI。
+loongarch-firmware-unknown = 无法判断此 LoongArch 机器的固件属于旧世界还是新世界，将按新世界固件安装。如为旧世界固件，请设置 board = "loongarch-ow"。
+riscv-missing-extensions = 此 CPU 缺少 AOSC OS 所需的指令集扩展（{$extensions}），安装后的系统无法在其上运行。
+riscv-no-boot-environment = 此机器既非由 EFI 也非由 U-Boot 启动，安装的引导程序可能无法被启动。
+riscv-needs-spl = {$board} 需从启动盘上的固定偏移处加载 SPL，dkcli 无法写入。请将 SPL 保留在其他介质（如 SPI 闪存或 SD 卡）上，或在安装后自行写入。
+platform-continue-anyway = 仍要安装吗？
+boot-instructions = 启动方式
+petitboot-instructions = 重启后在 Petitboot 菜单中选择 {$partition} 上的 AOSC OS。如需默认启动，请在 System configuration 中将其设为自动启动项。
+daemon-action-memory = 检查内存大小
+low-memory-online = 此机器内存为 {$memory}，不足安装时下载系统所需的 {$required}，只能安装 Live 介质上的系统。
+low-memory-use-offline = 请使用自带系统的离线安装介质启动。
+low-memory-variant = {$variant} 需要 {$required} 内存才能流畅运行，此机器内存为 {$memory}。
+efi-ia32-detected = 此机器使用 32 位 UEFI 固件，无法启动常规的 64 位引导程序，将改为安装 ia32 EFI 引导程序。
+secure-boot = 安全启动
+secure-boot-shim = 已启用，通过已签名的 shim 启动
+secure-boot-disable = 已启用，需在重启前关闭
+secure-boot-shim-info = 安全启动已启用。系统将通过已签名的 shim 启动，其密钥需在下次启动时登记。
+secure-boot-unsigned = 安全启动已启用，但此版本未附带已签名的 shim。请在重启前于固件设置中关闭安全启动，否则安装的系统将无法启动。
+secure-boot-shim-instructions = 下次启动时，MokManager 会要求登记 AOSC OS 的密钥：请选择“Enroll MOK”，再选择“Continue”并确认。
+secure-boot-disable-instructions = 启动安装的系统前，请在固件设置中关闭安全启动。
+secure-boot-unsupported = 安全启动已启用，但 Deploykit 版本过旧，无法配置 shim。请更新 Deploykit，或关闭安全启动后重新开始。
+target-arch-foreign = 正在 {$host} 机器上安装 {$arch} 系统。将跳过对本机的检查，且配置引导程序需要已通过 binfmt_misc 注册的 {$arch} qemu-user 模拟。
+remote-arch-unknown = 另一台机器的架构（{$arch}）不受 AOSC OS 支持。
+select-board-foreign-help = 正在为另一台 {$arch} 机器安装：请选择其启动方式。
+gpu-driver = 是否安装 NVIDIA 专有驱动？
+gpu-driver-nvidia-help = 此机器配有 NVIDIA 显卡，开源驱动可能无法良好驱动甚至无法驱动。
+gpu-driver-hybrid-help = 此机器使用 NVIDIA 混合显卡（Optimus），使用开源驱动时常会启动后黑屏。
+gpu-driver-suggest = 此机器配有 NVIDIA 显卡；可在配置文件中设置 gpu_driver = true 以安装专有驱动，或设为 false 保留开源驱动。
+target-dir = 目标目录
+target-dir-info = 正在安装到 {$path}。将不会进行分区，也不会配置引导程序。
+target-dir-invalid = {$path} 不是已存在的目录。
+target-dir-root = {$path} 是正在运行的系统，无法覆盖安装。
+target-dir-not-empty = {$path} 不为空。如仍要安装至该目录并覆盖其中内容，请使用 --force。
+target-dir-not-empty-continue = {$path} 不为空，其中内容可能被覆盖。是否仍要安装至该目录？
+target-dir-unsupported = Deploykit 版本过旧，无法安装到目录中。请更新 Deploykit 后重试。
@@ -0,0 +1,131 @@
+//! What maintainers ask for about a failed installation, packed into one
+//! file to attach to a bug report.
+
+use std::{
+    fs::{self, OpenOptions},
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+use time::OffsetDateTime;
+
+use dkcli_core::{
+    client::DkClient,
+    config::config_value,
+    dbus::Bus,
+    disk::make_temp_dir,
+    error::DaemonError,
+    host::{run_on_host, DEPLOYKIT_UNIT},
+};
+
+use crate::{redact, LOG_PATH};
+
+// Lines of the daemon's journal to keep
+const JOURNAL_LINES: &str = "2000";
+
+/// Collect the dkcli log, the daemon's journal, the block devices and the
+/// settings sent to the daemon, without passwords, into
+/// `/tmp/dkcli-failure-<timestamp>-<random>.tar.zst`, readable by the user
+/// alone, and return its path.
+pub fn collect(dk_client: &DkClient, error: &DaemonError) -> Result<PathBuf> {
+    let dir = make_temp_dir(&format!(
+        "dkcli-failure-{}-",
+        OffsetDateTime::now_utc().unix_timestamp()
+    ))?;
+
+    let res = fill(&dir, dk_client, error).and_then(|_| {
+        let bundle = dir.with_extension("tar.zst");
+        // Made before tar writes to it, so that it is never readable by
+        // others, and not some file put there in the meantime
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&bundle)
+            .with_context(|| format!("Failed to create {}", bundle.display()))?;
+        pack(&dir, &bundle)?;
+        Ok(bundle)
+    });
+
+    // The bundle is all that is needed
+    let _ = fs::remove_dir_all(&dir);
+
+    res
+}
+
+fn fill(dir: &Path, dk_client: &DkClient, error: &DaemonError) -> Result<()> {
+    // As the daemon put it, for maintainers who may not read the display
+    // language
+    let mut description = redact::text(&error.message).into_owned();
+    if let Some(code) = &error.code {
+        description = format!("[{code}] {description}");
+    }
+    if let Some(context) = &error.context {
+        description = format!("{description}\n{}", redact::text(context));
+    }
+    fs::write(dir.join("error.txt"), description + "\n")?;
+
+    // Logs from before secrets were masked may still hold some
+    if let Some(path) = LOG_PATH.get() {
+        let log = fs::read_to_string(path)?;
+        fs::write(dir.join("dkcli.log"), redact::text(&log).as_ref())?;
+    }
+
+    // Neither is worth giving up the rest for
+    let journal = host_output(
+        &dk_client.bus,
+        &[
+            "journalctl",
+            "--no-pager",
+            "--boot",
+            "--unit",
+            DEPLOYKIT_UNIT,
+            "--lines",
+            JOURNAL_LINES,
+        ],
+    );
+    fs::write(dir.join("journal.txt"), redact::text(&journal).as_ref())?;
+    fs::write(
+        dir.join("lsblk.json"),
+        host_output(&dk_client.bus, &["lsblk", "-J"]),
+    )?;
+
+    let config = dk_client
+        .sent_config()
+        .into_iter()
+        .map(|(field, value)| (field, redact::value(config_value(Value::String(value)))))
+        .collect::<Map<_, _>>();
+    fs::write(
+        dir.join("config.json"),
+        serde_json::to_string_pretty(&config)?,
+    )?;
+
+    Ok(())
+}
+
+/// Output of `args` run on the machine being installed, or why there is
+/// none.
+fn host_output(bus: &Bus, args: &[&str]) -> String {
+    run_on_host(bus, args).unwrap_or_else(|e| format!("{e:#}"))
+}
+
+fn pack(dir: &Path, bundle: &Path) -> Result<()> {
+    let out = Command::new("tar")
+        .arg("--zstd")
+        .arg("-cf")
+        .arg(bundle)
+        .arg("-C")
+        .arg(dir.parent().context("Bundle directory has no parent")?)
+        .arg(dir.file_name().context("Bundle directory has no name")?)
+        .output()
+        .context("Failed to run tar")?;
+
+    if !out.status.success() {
+        bail!("{}", String::from_utf8_lossy(&out.stderr).trim());
+    }
+
+    Ok(())
+}
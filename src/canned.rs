@@ -0,0 +1,213 @@
+//! A [`Prompter`] answering from a list of canned answers instead of a
+//! user, so that tests can go through the wizard without a terminal.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use inquire::{
+    type_aliases::Scorer,
+    validator::{StringValidator, Validation},
+    InquireError, PasswordDisplayMode,
+};
+
+use crate::ui::{error_message, validate_str, InquireResult, Prompter};
+
+/// What the user would have done at a prompt.
+#[derive(Debug, Clone)]
+pub enum Answer {
+    /// Pick the option with this text
+    Select(&'static str),
+    MultiSelect(Vec<&'static str>),
+    /// Type this in, empty for the default
+    Text(&'static str),
+    Confirm(bool),
+    /// Press Esc
+    Back,
+    /// Press Ctrl-C
+    Quit,
+}
+
+/// Shared with the [`Ui`](crate::ui::Ui) it is put into, so that tests can
+/// look at what was asked afterwards.
+#[derive(Clone, Default)]
+pub struct Canned(Arc<Mutex<State>>);
+
+#[derive(Default)]
+struct State {
+    answers: VecDeque<Answer>,
+    // Messages of the prompts shown so far
+    asked: Vec<String>,
+    // Why typed answers were turned down
+    rejected: Vec<String>,
+}
+
+impl Canned {
+    pub fn new(answers: impl IntoIterator<Item = Answer>) -> Self {
+        Self(Arc::new(Mutex::new(State {
+            answers: answers.into_iter().collect(),
+            ..Default::default()
+        })))
+    }
+
+    pub fn asked(&self) -> Vec<String> {
+        self.0.lock().unwrap().asked.clone()
+    }
+
+    pub fn rejected(&self) -> Vec<String> {
+        self.0.lock().unwrap().rejected.clone()
+    }
+
+    /// Whether every answer has been used.
+    pub fn is_done(&self) -> bool {
+        self.0.lock().unwrap().answers.is_empty()
+    }
+
+    fn next(&self, message: &str) -> InquireResult<Answer> {
+        let mut state = self.0.lock().unwrap();
+        state.asked.push(message.to_string());
+
+        match state.answers.pop_front() {
+            Some(Answer::Back) => Err(InquireError::OperationCanceled),
+            Some(Answer::Quit) => Err(InquireError::OperationInterrupted),
+            Some(answer) => Ok(answer),
+            None => panic!("No answer left for {message:?}"),
+        }
+    }
+
+    /// Take typed answers until one passes `validators`, as a user would
+    /// after being told what is wrong.
+    fn next_text(
+        &self,
+        message: &str,
+        default: Option<&str>,
+        validators: &[Box<dyn StringValidator>],
+    ) -> InquireResult<String> {
+        loop {
+            let value = match self.next(message)? {
+                Answer::Text("") => default.unwrap_or_default().to_string(),
+                Answer::Text(value) => value.to_string(),
+                answer => panic!("Expected text for {message:?}, got {answer:?}"),
+            };
+
+            match validate_str(validators, &value).map_err(InquireError::Custom)? {
+                Validation::Valid => return Ok(value),
+                Validation::Invalid(e) => self.0.lock().unwrap().rejected.push(error_message(e)),
+            }
+        }
+    }
+}
+
+fn position(message: &str, options: &[String], value: &str) -> usize {
+    options
+        .iter()
+        .position(|x| x == value)
+        .unwrap_or_else(|| panic!("No {value:?} for {message:?} in {options:?}"))
+}
+
+impl Prompter for Canned {
+    fn select(
+        &self,
+        message: &str,
+        _help: Option<&str>,
+        options: &[String],
+        _cursor: usize,
+        _scorer: Option<Scorer<'_, String>>,
+    ) -> InquireResult<usize> {
+        match self.next(message)? {
+            Answer::Select(value) => Ok(position(message, options, value)),
+            answer => panic!("Expected a selection for {message:?}, got {answer:?}"),
+        }
+    }
+
+    fn multi_select(
+        &self,
+        message: &str,
+        _help: Option<&str>,
+        options: &[String],
+        _default: &[usize],
+        _scorer: Option<Scorer<'_, String>>,
+    ) -> InquireResult<Vec<usize>> {
+        match self.next(message)? {
+            Answer::MultiSelect(values) => Ok(values
+                .into_iter()
+                .map(|x| position(message, options, x))
+                .collect()),
+            answer => panic!("Expected selections for {message:?}, got {answer:?}"),
+        }
+    }
+
+    fn text(
+        &self,
+        message: &str,
+        _help: Option<&str>,
+        default: Option<&str>,
+        validators: &[Box<dyn StringValidator>],
+    ) -> InquireResult<String> {
+        self.next_text(message, default, validators)
+    }
+
+    fn password(
+        &self,
+        message: &str,
+        _help: Option<&str>,
+        _display_mode: PasswordDisplayMode,
+        _confirmation_message: Option<&str>,
+        _confirmation_error_message: Option<&str>,
+        validators: &[Box<dyn StringValidator>],
+    ) -> InquireResult<String> {
+        self.next_text(message, None, validators)
+    }
+
+    fn confirm(
+        &self,
+        message: &str,
+        _help: Option<&str>,
+        default: Option<bool>,
+        _error_message: Option<&str>,
+    ) -> InquireResult<bool> {
+        match self.next(message)? {
+            Answer::Confirm(value) => Ok(value),
+            Answer::Text("") if default.is_some() => Ok(default.unwrap_or_default()),
+            answer => panic!("Expected yes or no for {message:?}, got {answer:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_canned() {
+    use crate::ui::Ui;
+
+    let canned = Canned::new([
+        Answer::MultiSelect(vec!["c", "a"]),
+        Answer::Text("short"),
+        Answer::Text("long enough"),
+        Answer::Back,
+    ]);
+    let ui = Ui::Custom(Box::new(canned.clone()));
+    let options = ["a", "b", "c"].map(|x| x.to_string()).to_vec();
+
+    let selected = ui.multi_select("Pick", options).raw_prompt().unwrap();
+    assert_eq!(selected.iter().map(|x| x.index).collect::<Vec<_>>(), [2, 0]);
+
+    let password = ui
+        .password("Password")
+        .with_validator(|x: &str| {
+            Ok(match x.len() {
+                8.. => Validation::Valid,
+                _ => Validation::Invalid("Too short".into()),
+            })
+        })
+        .prompt()
+        .unwrap();
+    assert_eq!(password, "long enough");
+    assert_eq!(canned.rejected(), ["Too short"]);
+
+    assert!(matches!(
+        ui.confirm("Sure?").prompt(),
+        Err(InquireError::OperationCanceled)
+    ));
+    assert!(canned.is_done());
+    assert_eq!(canned.asked(), ["Pick", "Password", "Password", "Sure?"]);
+}
@@ -0,0 +1,354 @@
+//! Errors in the display language: those of dkcli itself, and those
+//! dkcli-core leaves to the frontend to put into words.
+
+use std::{
+    error::Error,
+    fmt::{self, Debug, Display},
+    io,
+};
+
+use dkcli_core::{
+    answer::AnswerError,
+    error::{normalize_code, ClientError, DaemonError, DaemonErrorKind},
+    recipe::NoRelease,
+};
+use indicatif::HumanBytes;
+use inquire::InquireError;
+
+use crate::{
+    avatar_error, fl, fullname_error, hostname_error, i18n::LANGUAGE_LOADER, redact, username_error,
+};
+
+/// What the user may do about a daemon error of `kind`, if there is
+/// anything.
+pub fn remedy(kind: DaemonErrorKind) -> Option<String> {
+    Some(match kind {
+        DaemonErrorKind::Disk => fl!("daemon-error-disk"),
+        DaemonErrorKind::NoSpace => fl!("daemon-error-no-space"),
+        DaemonErrorKind::Network => fl!("daemon-error-network"),
+        DaemonErrorKind::Config => fl!("daemon-error-config"),
+        DaemonErrorKind::Busy => fl!("daemon-error-busy"),
+        DaemonErrorKind::Other => return None,
+    })
+}
+
+/// A daemon error in the display language.
+pub trait Describe {
+    /// The message in the display language, where there is a translation
+    /// for the error code, or else as the daemon put it.
+    fn localized_message(&self) -> String;
+
+    /// The message in the display language with its context, if any,
+    /// without secrets that may have come back from the settings.
+    fn describe(&self) -> String;
+}
+
+impl Describe for DaemonError {
+    fn localized_message(&self) -> String {
+        let key = self
+            .code
+            .as_deref()
+            .map(|code| format!("daemon-code-{}", normalize_code(code).replace('_', "-")));
+
+        match key {
+            Some(key) if LANGUAGE_LOADER.has(&key) => LANGUAGE_LOADER.get(&key),
+            _ => self.message.clone(),
+        }
+    }
+
+    fn describe(&self) -> String {
+        let message = self.localized_message();
+        let description = match &self.context {
+            Some(context) => format!("{message} ({context})"),
+            None => message,
+        };
+
+        redact::text(&description).into_owned()
+    }
+}
+
+/// What the daemon `method` is for, to tell the user what got stuck.
+pub fn daemon_action(method: &str) -> String {
+    match method {
+        "SetConfig" => fl!("daemon-action-set-config"),
+        "AutoPartition" => fl!("daemon-action-auto-partition"),
+        "GetProgress" | "GetAutoPartitionProgress" => fl!("daemon-action-get-progress"),
+        "StartInstall" => fl!("daemon-action-start-install"),
+        "ListPartitions" => fl!("daemon-action-list-partitions"),
+        "ListDevice" => fl!("daemon-action-list-devices"),
+        "GetRecommendSwapSize" => fl!("daemon-action-swap-size"),
+        "GetMemory" => fl!("daemon-action-memory"),
+        "CancelInstall" => fl!("daemon-action-cancel-install"),
+        "DiskIsRightCombo" | "GetAllEspPartitions" | "IsLvmDevice" | "IsEFI" => {
+            fl!("daemon-action-check-disk")
+        }
+        "ResetConfig" | "ResetProgressStatus" => fl!("daemon-action-reset-config"),
+        "Claim" => fl!("daemon-action-claim"),
+        "GetConfig" => fl!("daemon-action-get-config"),
+        "ValidateConfig" => fl!("daemon-action-validate-config"),
+        "SyncDisk" | "SyncAndReboot" => fl!("daemon-action-sync"),
+        _ => fl!("daemon-action-connect"),
+    }
+}
+
+fn client_error(e: &ClientError) -> String {
+    match e {
+        ClientError::Interrupted => InquireError::OperationInterrupted.to_string(),
+        ClientError::Timeout { method, secs } => {
+            let secs = *secs;
+            fl!(
+                "daemon-timeout",
+                secs = secs,
+                action = daemon_action(method)
+            )
+        }
+        ClientError::NotAuthorized { method, .. } => {
+            fl!("not-authorized", action = daemon_action(method))
+        }
+        ClientError::NotRunning { unit, .. } => fl!("daemon-not-running", unit = unit.to_string()),
+        ClientError::BadReply("IsEFI") => fl!("direct-efi-error"),
+        ClientError::BadReply("IsLvmDevice") => fl!("direct-lvm-error"),
+        ClientError::BadReply(_) => e.to_string(),
+        ClientError::Canceled => fl!("install-is-canceled"),
+        ClientError::Unsupported("board") => fl!("board-unsupported"),
+        ClientError::Unsupported("secure_boot") => fl!("secure-boot-unsupported"),
+        ClientError::Unsupported("target_dir") => fl!("target-dir-unsupported"),
+        ClientError::Unsupported("password_hash") => fl!("password-hash-unsupported"),
+        ClientError::Unsupported(_) => e.to_string(),
+    }
+}
+
+fn answer_error(e: &AnswerError) -> String {
+    match e {
+        AnswerError::Variant(name) => fl!("invaild-variant", s = name.as_str()),
+        AnswerError::Board(id) => fl!("invaild-board", s = id.as_str()),
+        AnswerError::EfiFieldNotSet => fl!("efi-field-not-set"),
+        AnswerError::FirmwareFieldNotSet => fl!("firmware-field-not-set"),
+        AnswerError::Fullname(c) => fl!("invaild-fullname", e = fullname_error(*c)),
+        AnswerError::Username(e) => fl!("invaild-username", e = username_error(*e)),
+        AnswerError::Hostname(e) => fl!("invaild-hostname", e = hostname_error(*e)),
+        AnswerError::Locale(locale) => fl!("invaild-locale", s = locale.as_str()),
+        AnswerError::Timezone {
+            given,
+            suggested: Some(suggested),
+        } => fl!(
+            "invaild-timezone-suggest",
+            s = given.as_str(),
+            suggested = suggested.as_str()
+        ),
+        AnswerError::Timezone { given, .. } => fl!("invaild-timezone", s = given.as_str()),
+        AnswerError::Avatar(e) => fl!("invaild-avatar", e = avatar_error(*e)),
+        AnswerError::PasswordHash => {
+            fl!("invaild-password-hash", e = fl!("password-hash-format"))
+        }
+        AnswerError::PasswordOrHash => fl!("password-or-hash-required"),
+        AnswerError::Id(_) => fl!(
+            "invaild-id",
+            e = fl!(
+                "id-out-of-range",
+                min = dkcli_core::validate::UID_MIN,
+                max = dkcli_core::validate::UID_MAX
+            )
+        ),
+        AnswerError::NtpServer(e) => fl!("invaild-ntp-server", e = hostname_error(*e)),
+        AnswerError::Keymap(keymap) => fl!("invaild-keymap", s = keymap.as_str()),
+        AnswerError::X11Layout(layout) => fl!("invaild-x11-layout", s = layout.as_str()),
+        AnswerError::TargetPartition => fl!("invaild-target-partition"),
+        AnswerError::EfiPartition => fl!("invaild-efi-partition"),
+        AnswerError::FirmwarePartition => fl!(
+            "invaild-firmware-partition",
+            size = HumanBytes(dkcli_core::platform::FIRMWARE_PARTITION_MIN).to_string()
+        ),
+    }
+}
+
+/// `cause` in the display language, where it is one of dkcli-core that
+/// only says what went wrong.
+fn localize(cause: &(dyn Error + 'static)) -> String {
+    if let Some(e) = cause.downcast_ref::<DaemonError>() {
+        let mut message = fl!("dbus-query-failed", e = e.describe());
+        if let Some(remedy) = remedy(e.kind) {
+            message = format!("{message}\n{remedy}");
+        }
+        return message;
+    }
+    if let Some(e) = cause.downcast_ref::<ClientError>() {
+        return client_error(e);
+    }
+    if let Some(e) = cause.downcast_ref::<AnswerError>() {
+        return answer_error(e);
+    }
+    if cause.is::<NoRelease>() {
+        return fl!("squashfs-empty");
+    }
+
+    cause.to_string()
+}
+
+/// An error put into words in the display language, cause by cause, to be
+/// shown as anyhow would: `{}` for the error alone, `{:#}` with its causes
+/// on one line and `{:?}` with them listed.
+pub struct Localized<'a>(pub &'a anyhow::Error);
+
+impl Display for Localized<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut causes = self.0.chain().map(localize);
+        if let Some(first) = causes.next() {
+            f.write_str(&first)?;
+        }
+        if f.alternate() {
+            for cause in causes {
+                write!(f, ": {cause}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Debug for Localized<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let causes = self.0.chain().map(localize).collect::<Vec<_>>();
+        f.write_str(&causes[0])?;
+        if causes.len() > 1 {
+            f.write_str("\n\nCaused by:")?;
+            for (i, cause) in causes[1..].iter().enumerate() {
+                let cause = cause.replace('\n', "\n       ");
+                write!(f, "\n    {i}: {cause}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A failure of dkcli itself, with a code that stays the same across
+/// versions and languages.
+#[derive(Debug)]
+pub struct Failure {
+    pub code: &'static str,
+    message: String,
+}
+
+impl Failure {
+    pub fn new(code: &'static str, message: String) -> Self {
+        Self { code, message }
+    }
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// A stable, snake_case code for what `e` is about, for provisioning
+/// wrappers to tell failures apart by.
+pub fn failure_code(e: &anyhow::Error) -> String {
+    for cause in e.chain() {
+        if let Some(failure) = cause.downcast_ref::<Failure>() {
+            return failure.code.to_string();
+        }
+        if let Some(e) = cause.downcast_ref::<DaemonError>() {
+            return match &e.code {
+                Some(code) => normalize_code(code),
+                None => "daemon".to_string(),
+            };
+        }
+        if let Some(code) = cause
+            .downcast_ref::<ClientError>()
+            .and_then(ClientError::code)
+        {
+            return code.to_string();
+        }
+        if cause.is::<AnswerError>() || cause.is::<NoRelease>() {
+            return "config_file".to_string();
+        }
+        if cause.downcast_ref::<InquireError>().is_some() {
+            return "prompt".to_string();
+        }
+        if cause.downcast_ref::<zbus::Error>().is_some() {
+            return "dbus".to_string();
+        }
+        if cause.downcast_ref::<toml::de::Error>().is_some() {
+            return "config_file".to_string();
+        }
+        if cause.downcast_ref::<io::Error>().is_some() {
+            return "io".to_string();
+        }
+    }
+
+    "other".to_string()
+}
+
+#[test]
+fn test_daemon_error() {
+    use serde_json::json;
+
+    let e = DaemonError::from(json!({"t": "Mount", "message": "mount failed", "data": null}));
+    assert_eq!(e.describe(), fl!("daemon-code-mount"));
+
+    let e = DaemonError::from(json!({"code": "made-up", "message": "made up"}));
+    assert_eq!(e.describe(), "made up");
+
+    let errors = DaemonError::list(json!([
+        {"code": "config", "message": "hostname is not set"},
+        "swap file is too large",
+    ]));
+    assert_eq!(errors[1].describe(), "swap file is too large");
+
+    let e = DaemonError::from(json!({
+        "code": "config",
+        "message": "invalid user",
+        "context": {"username": "aosc", "password": "anthon"},
+    }));
+    assert_eq!(
+        e.describe(),
+        r#"invalid user ({"password":"********","username":"aosc"})"#
+    );
+
+    // Shown with its remedy, under whatever it happened in
+    let e = anyhow::Error::from(e).context("Failed to install");
+    assert_eq!(
+        format!("{:#}", Localized(&e)),
+        format!(
+            "Failed to install: {}\n{}",
+            fl!(
+                "dbus-query-failed",
+                e = r#"invalid user ({"password":"********","username":"aosc"})"#
+            ),
+            fl!("daemon-error-config")
+        )
+    );
+    assert_eq!(format!("{}", Localized(&e)), "Failed to install");
+}
+
+#[test]
+fn test_failure_code() {
+    use anyhow::Context;
+    use serde_json::json;
+
+    let e = anyhow::Error::from(DaemonError::from(json!({
+        "code": "Download-Hash-Mismatch",
+        "message": "checksum mismatch",
+    })));
+    assert_eq!(failure_code(&e), "download_hash_mismatch");
+
+    let e = anyhow::Error::from(Failure::new("canceled", "Canceled".to_string()));
+    assert_eq!(failure_code(&e.context("While installing")), "canceled");
+
+    let e = Err::<(), _>(io::Error::from(io::ErrorKind::NotFound))
+        .context("Failed to read config.toml")
+        .unwrap_err();
+    assert_eq!(failure_code(&e), "io");
+    assert_eq!(failure_code(&anyhow::anyhow!("Something")), "other");
+
+    let e = anyhow::Error::from(ClientError::Unsupported("board"));
+    assert_eq!(failure_code(&e), "board_unsupported");
+    assert_eq!(Localized(&e).to_string(), fl!("board-unsupported"));
+    let e = anyhow::Error::from(AnswerError::Variant("Foo".to_string()));
+    assert_eq!(failure_code(&e), "config_file");
+    assert_eq!(Localized(&e).to_string(), fl!("invaild-variant", s = "Foo"));
+}
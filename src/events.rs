@@ -0,0 +1,122 @@
+//! Progress events for frontends that drive dkcli rather than talk to the
+//! daemon themselves (`--event-fifo`), one JSON object per line.
+
+use std::{
+    ffi::CString,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    os::unix::{ffi::OsStrExt, fs::FileTypeExt},
+    path::Path,
+    sync::Mutex,
+};
+
+use anyhow::{bail, Context, Result};
+use log::debug;
+use serde::Serialize;
+
+use crate::{fl, tui::Step};
+
+static SINK: Mutex<Option<File>> = Mutex::new(None);
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// The wizard moved on to another step
+    Step {
+        step: Step,
+    },
+    /// The installation is at `progress` percent of step `step` out of
+    /// `steps`
+    Progress {
+        step: u8,
+        steps: u8,
+        name: &'a str,
+        progress: u8,
+        bytes_per_sec: Option<u64>,
+    },
+    Finished,
+    Failed {
+        code: Option<&'a str>,
+        message: &'a str,
+    },
+    Canceled,
+}
+
+/// Send events to the FIFO at `path`, made if there is nothing there,
+/// waiting for the frontend to open it for reading.
+pub fn open(path: &Path) -> Result<()> {
+    match path.metadata() {
+        Ok(metadata) if !metadata.file_type().is_fifo() => {
+            bail!(
+                "{}",
+                fl!("event-fifo-not-fifo", path = path.display().to_string())
+            )
+        }
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let c_path = CString::new(path.as_os_str().as_bytes())?;
+            if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+                return Err(io::Error::last_os_error())
+                    .with_context(|| fl!("event-fifo-failed", path = path.display().to_string()));
+            }
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    let file = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .with_context(|| fl!("event-fifo-failed", path = path.display().to_string()))?;
+    *SINK.lock().unwrap() = Some(file);
+
+    Ok(())
+}
+
+pub fn emit(event: Event) {
+    let mut sink = SINK.lock().unwrap();
+    let Some(file) = sink.as_mut() else {
+        return;
+    };
+
+    let res = serde_json::to_string(&event)
+        .map_err(io::Error::from)
+        .and_then(|line| writeln!(file, "{line}"));
+
+    // The frontend went away, which is no reason to stop installing
+    if let Err(e) = res {
+        debug!("Not sending events any more: {e}");
+        *sink = None;
+    }
+}
+
+#[test]
+fn test_event_json() {
+    use serde_json::json;
+
+    assert_eq!(
+        serde_json::to_value(Event::Step { step: Step::Disk }).unwrap(),
+        json!({"event": "step", "step": "disk"})
+    );
+    assert_eq!(
+        serde_json::to_value(Event::Progress {
+            step: 2,
+            steps: 8,
+            name: "Downloading",
+            progress: 50,
+            bytes_per_sec: Some(1024),
+        })
+        .unwrap(),
+        json!({
+            "event": "progress",
+            "step": 2,
+            "steps": 8,
+            "name": "Downloading",
+            "progress": 50,
+            "bytes_per_sec": 1024,
+        })
+    );
+    assert_eq!(
+        serde_json::to_value(Event::Finished).unwrap(),
+        json!({"event": "finished"})
+    );
+}
@@ -0,0 +1,165 @@
+//! Extra steps for distributions derived from AOSC OS, run at fixed points
+//! of the installation, so that asking for a license to be accepted or some
+//! branding questions takes no changes to the rest of dkcli.
+
+use anyhow::{Context, Result};
+use dkcli_core::config::InstallConfig;
+
+use crate::{fl, ui::Ui};
+
+/// What a hook has at hand when it runs.
+#[cfg_attr(not(test), allow(dead_code))]
+pub struct HookContext<'a> {
+    pub ui: &'a Ui,
+    /// Installing from an answer file, with nobody around to answer
+    /// questions
+    pub unattended: bool,
+}
+
+/// Steps of a derivative distribution. Each does nothing unless
+/// implemented.
+///
+/// Errors stop the installation; those of a prompt left with Esc or
+/// Ctrl-C act as they do in the rest of the wizard.
+pub trait Hook: Send + Sync {
+    /// Shown when the hook fails.
+    fn name(&self) -> &str;
+
+    /// Before the disk to install to is first chosen, but not again when
+    /// the wizard goes back to that step.
+    fn pre_partition(&self, _cx: &HookContext) -> Result<()> {
+        Ok(())
+    }
+
+    /// Once the daemon holds the settings and before it starts installing.
+    fn pre_install(&self, _cx: &HookContext, _config: &InstallConfig) -> Result<()> {
+        Ok(())
+    }
+
+    /// After a successful installation, before offering to reboot. Failing
+    /// here is only warned about, as the system is installed by then.
+    fn post_install(&self, _cx: &HookContext, _config: &InstallConfig) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Hooks of the distribution dkcli is built for, in the order they run.
+/// Derivatives add theirs here.
+fn distro_hooks() -> Vec<Box<dyn Hook>> {
+    vec![]
+}
+
+/// Hooks run at each point, in the order registered.
+pub struct Hooks(Vec<Box<dyn Hook>>);
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Self(distro_hooks())
+    }
+}
+
+impl Hooks {
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn register(&mut self, hook: impl Hook + 'static) {
+        self.0.push(Box::new(hook));
+    }
+
+    pub fn pre_partition(&self, cx: &HookContext) -> Result<()> {
+        for hook in &self.0 {
+            hook.pre_partition(cx)
+                .with_context(|| fl!("hook-failed", name = hook.name()))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn pre_install(&self, cx: &HookContext, config: &InstallConfig) -> Result<()> {
+        for hook in &self.0 {
+            hook.pre_install(cx, config)
+                .with_context(|| fl!("hook-failed", name = hook.name()))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn post_install(&self, cx: &HookContext, config: &InstallConfig) -> Result<()> {
+        for hook in &self.0 {
+            hook.post_install(cx, config)
+                .with_context(|| fl!("hook-failed", name = hook.name()))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_hooks() {
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::bail;
+
+    use crate::canned::{Answer, Canned};
+
+    struct License;
+
+    impl Hook for License {
+        fn name(&self) -> &str {
+            "license"
+        }
+
+        fn pre_partition(&self, cx: &HookContext) -> Result<()> {
+            if !cx.unattended && !cx.ui.confirm("Accept the license?").prompt()? {
+                bail!("License not accepted");
+            }
+            Ok(())
+        }
+    }
+
+    struct Record(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Hook for Record {
+        fn name(&self) -> &str {
+            "record"
+        }
+
+        fn pre_partition(&self, _: &HookContext) -> Result<()> {
+            self.0.lock().unwrap().push("pre_partition");
+            Ok(())
+        }
+
+        fn post_install(&self, _: &HookContext, _: &InstallConfig) -> Result<()> {
+            self.0.lock().unwrap().push("post_install");
+            Ok(())
+        }
+    }
+
+    let calls = Arc::new(Mutex::new(vec![]));
+    let mut hooks = Hooks(vec![]);
+    hooks.register(License);
+    hooks.register(Record(calls.clone()));
+
+    let canned = Canned::new([Answer::Confirm(true), Answer::Confirm(false)]);
+    let ui = Ui::Custom(Box::new(canned.clone()));
+    let cx = HookContext {
+        ui: &ui,
+        unattended: false,
+    };
+
+    hooks.pre_partition(&cx).unwrap();
+    // Later hooks do not run once one fails
+    let e = hooks.pre_partition(&cx).unwrap_err();
+    assert_eq!(e.root_cause().to_string(), "License not accepted");
+    assert!(canned.is_done());
+
+    let unattended = HookContext {
+        ui: &ui,
+        unattended: true,
+    };
+    hooks.pre_partition(&unattended).unwrap();
+    let config = crate::mock_install_config();
+    hooks.post_install(&unattended, &config).unwrap();
+    assert_eq!(
+        *calls.lock().unwrap(),
+        ["pre_partition", "pre_partition", "post_install"]
+    );
+}
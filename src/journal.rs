@@ -0,0 +1,248 @@
+//! Logging straight to the systemd journal, for unattended installations
+//! run as a systemd unit, with priorities and fields to search by instead
+//! of colored lines.
+
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, Write},
+    mem,
+    os::{
+        fd::{AsRawFd, FromRawFd, RawFd},
+        unix::{fs::MetadataExt, net::UnixDatagram},
+    },
+    ptr,
+};
+
+use log::{kv::Key, LevelFilter, Log, Metadata, Record};
+use simplelog::{Config, SharedLogger};
+
+use crate::logging;
+
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Whether stderr goes to the journal, as it does for a systemd unit.
+pub fn is_connected() -> bool {
+    // Set by systemd to the device and inode of the stream it connected,
+    // which stays in the environment of processes that redirect stderr
+    let Some(stream) = env::var_os("JOURNAL_STREAM") else {
+        return false;
+    };
+    let Ok(stderr) = fs::metadata("/proc/self/fd/2") else {
+        return false;
+    };
+
+    stream.to_string_lossy() == format!("{}:{}", stderr.dev(), stderr.ino())
+}
+
+pub struct JournalLogger {
+    level: LevelFilter,
+    ignore: &'static [&'static str],
+    socket: UnixDatagram,
+}
+
+impl JournalLogger {
+    /// A logger sending records down to `level` to the journal, except
+    /// those from modules starting with any of `ignore`, if it can be
+    /// reached.
+    pub fn new(level: LevelFilter, ignore: &'static [&'static str]) -> Option<Box<Self>> {
+        let socket = UnixDatagram::unbound().ok()?;
+        socket.connect(JOURNAL_SOCKET).ok()?;
+
+        Some(Box::new(Self {
+            level,
+            ignore,
+            socket,
+        }))
+    }
+}
+
+impl Log for JournalLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+            && !self
+                .ignore
+                .iter()
+                .any(|module| metadata.target().starts_with(module))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let entry = entry(record);
+        let res = match self.socket.send(&entry) {
+            // Larger than a datagram may be, as with long daemon errors
+            Err(e) if matches!(e.raw_os_error(), Some(libc::EMSGSIZE | libc::ENOBUFS)) => {
+                send_sealed(&self.socket, &entry)
+            }
+            res => res.map(drop),
+        };
+
+        // The message at least, if the journal cannot take it
+        if res.is_err() {
+            eprintln!("{}", record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+impl SharedLogger for JournalLogger {
+    fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    fn config(&self) -> Option<&Config> {
+        None
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
+
+/// A journal entry for `record`, in the native protocol of
+/// systemd-journald.service(8).
+fn entry(record: &Record) -> Vec<u8> {
+    // syslog(3) priorities
+    let priority = match record.level() {
+        log::Level::Error => 3,
+        log::Level::Warn => 4,
+        log::Level::Info => 6,
+        log::Level::Debug | log::Level::Trace => 7,
+    };
+
+    let mut entry = vec![];
+    field(&mut entry, "MESSAGE", &record.args().to_string());
+    field(&mut entry, "PRIORITY", &priority.to_string());
+    field(&mut entry, "SYSLOG_IDENTIFIER", "dkcli");
+    field(&mut entry, "CODE_MODULE", record.target());
+    if let Some(step) = logging::step() {
+        field(&mut entry, "STEP", &step.to_string());
+    }
+    if let Some(device) = logging::device() {
+        field(&mut entry, "DEVICE", &device);
+    }
+    if let Some(method) = record.key_values().get(Key::from_str("method")) {
+        field(&mut entry, "DBUS_METHOD", &method.to_string());
+    }
+
+    entry
+}
+
+/// Send `entry` to the journal on `socket` in a sealed memfd, as
+/// systemd-journald.service(8) takes entries too large for a datagram.
+fn send_sealed(socket: &UnixDatagram, entry: &[u8]) -> io::Result<()> {
+    // SAFETY: the name is NUL-terminated, and the descriptor returned, if
+    // any, is new and owned by nothing else.
+    let mut memfd = unsafe {
+        let fd = libc::memfd_create(
+            c"dkcli-journal".as_ptr(),
+            libc::MFD_ALLOW_SEALING | libc::MFD_CLOEXEC,
+        );
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        File::from_raw_fd(fd)
+    };
+    memfd.write_all(entry)?;
+
+    // journald only takes memfds that cannot change under it
+    let seals = libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL;
+    // SAFETY: the descriptor is open for as long as `memfd` is.
+    if unsafe { libc::fcntl(memfd.as_raw_fd(), libc::F_ADD_SEALS, seals) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    send_fd(socket, memfd.as_raw_fd())
+}
+
+/// Send `fd` on `socket` with SCM_RIGHTS, and nothing else.
+fn send_fd(socket: &UnixDatagram, fd: RawFd) -> io::Result<()> {
+    // Aligned for cmsghdr, and room enough for one descriptor
+    let mut control = [0u64; 4];
+
+    // SAFETY: `msg` is zeroed, which is valid for msghdr, and its control
+    // buffer is large enough for the one header written through the
+    // pointers the CMSG macros give, all of which live until sendmsg
+    // returns.
+    unsafe {
+        let mut msg = mem::zeroed::<libc::msghdr>();
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = libc::CMSG_SPACE(mem::size_of::<RawFd>() as u32) as _;
+        debug_assert!(msg.msg_controllen as usize <= mem::size_of_val(&control));
+
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<RawFd>() as u32) as _;
+        ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast::<RawFd>(), fd);
+
+        if libc::sendmsg(socket.as_raw_fd(), &msg, 0) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+fn field(entry: &mut Vec<u8>, name: &str, value: &str) {
+    if value.contains('\n') {
+        // Values with newlines go with their length instead
+        let _ = writeln!(entry, "{name}");
+        entry.extend((value.len() as u64).to_le_bytes());
+        entry.extend(value.as_bytes());
+        entry.push(b'\n');
+    } else {
+        let _ = writeln!(entry, "{name}={value}");
+    }
+}
+
+#[test]
+fn test_journal_entry() {
+    let record = Record::builder()
+        .level(log::Level::Warn)
+        .target("dkcli")
+        .args(format_args!("two\nlines"))
+        .build();
+    let entry = entry(&record);
+
+    let mut expected = b"MESSAGE\n".to_vec();
+    expected.extend(9u64.to_le_bytes());
+    expected.extend(b"two\nlines\n");
+    expected.extend(b"PRIORITY=4\nSYSLOG_IDENTIFIER=dkcli\nCODE_MODULE=dkcli\n");
+    assert_eq!(entry, expected);
+}
+
+#[test]
+fn test_send_sealed() {
+    use std::os::unix::fs::FileExt;
+
+    let (tx, rx) = UnixDatagram::pair().unwrap();
+    let entry = vec![b'x'; 1 << 20];
+    send_sealed(&tx, &entry).unwrap();
+
+    // SAFETY: as in send_fd, receiving instead.
+    let fd = unsafe {
+        let mut control = [0u64; 4];
+        let mut msg = mem::zeroed::<libc::msghdr>();
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = mem::size_of_val(&control) as _;
+        assert!(libc::recvmsg(rx.as_raw_fd(), &mut msg, 0) >= 0);
+
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        assert_eq!((*cmsg).cmsg_type, libc::SCM_RIGHTS);
+        ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast::<RawFd>())
+    };
+    let memfd = unsafe { File::from_raw_fd(fd) };
+
+    let seals = unsafe { libc::fcntl(fd, libc::F_GET_SEALS) };
+    assert_ne!(seals & libc::F_SEAL_WRITE, 0);
+
+    // The offset is shared with the sender, journald reads it from the start
+    let mut received = vec![0; entry.len()];
+    memfd.read_exact_at(&mut received, 0).unwrap();
+    assert!(received == entry);
+}
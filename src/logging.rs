@@ -0,0 +1,288 @@
+//! Log records as JSON lines (`--log-format json`), for provisioning
+//! systems that collect what dkcli prints instead of a person reading it,
+//! and per-module log levels (`--log-filter`).
+
+use std::{
+    io::{self, Write},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Mutex,
+    },
+};
+
+use log::{kv::Key, LevelFilter, Log, Metadata, Record};
+use serde_json::{json, Value};
+use simplelog::{Config, SharedLogger};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+// Installation step being worked on, 0 before the installation starts
+static STEP: AtomicU8 = AtomicU8::new(0);
+// Partition being installed to, once it is known
+static DEVICE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Tag later records with installation step `step`.
+pub fn set_step(step: u8) {
+    STEP.store(step, Ordering::Relaxed);
+}
+
+pub fn step() -> Option<u8> {
+    Some(STEP.load(Ordering::Relaxed)).filter(|&x| x != 0)
+}
+
+/// Tag later records with the partition being installed to.
+pub fn set_device(device: String) {
+    *DEVICE.lock().unwrap() = Some(device);
+}
+
+pub fn device() -> Option<String> {
+    DEVICE.lock().unwrap().clone()
+}
+
+pub struct JsonLogger {
+    level: LevelFilter,
+    ignore: &'static [&'static str],
+}
+
+impl JsonLogger {
+    /// A logger printing records down to `level` to stderr, except those
+    /// from modules starting with any of `ignore`.
+    pub fn new(level: LevelFilter, ignore: &'static [&'static str]) -> Box<Self> {
+        Box::new(Self { level, ignore })
+    }
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+            && !self
+                .ignore
+                .iter()
+                .any(|module| metadata.target().starts_with(module))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = to_json(
+            record,
+            OffsetDateTime::now_utc(),
+            STEP.load(Ordering::Relaxed),
+        );
+        // Nowhere left to report a failure to write a log record
+        let _ = writeln!(io::stderr().lock(), "{line}");
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+impl SharedLogger for JsonLogger {
+    fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    fn config(&self) -> Option<&Config> {
+        None
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
+
+/// Log levels by module, as in `zbus=warn,reqwest=info,dkcli=debug`. A
+/// bare level applies to modules not named, a bare module name to
+/// everything from it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    default: Option<LevelFilter>,
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl FromStr for LogFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = Self::default();
+
+        for directive in s.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+            match directive.split_once('=') {
+                Some((module, level)) => {
+                    let level = level
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("Unknown log level in {directive}"))?;
+                    filter.modules.push((module.trim().to_string(), level));
+                }
+                None => match directive.parse() {
+                    Ok(level) => filter.default = Some(level),
+                    Err(_) => filter
+                        .modules
+                        .push((directive.to_string(), LevelFilter::Trace)),
+                },
+            }
+        }
+
+        Ok(filter)
+    }
+}
+
+impl LogFilter {
+    /// The level for records from `target`, the most specific module
+    /// directive that matches, if any, or else `default`.
+    pub fn level(&self, target: &str, default: LevelFilter) -> LevelFilter {
+        self.modules
+            .iter()
+            .filter(|(module, _)| {
+                target
+                    .strip_prefix(module.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.default.unwrap_or(default), |&(_, level)| level)
+    }
+
+    /// The most verbose level of any module.
+    pub fn max_level(&self, default: LevelFilter) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|&(_, level)| level)
+            .chain([self.default.unwrap_or(default)])
+            .max()
+            .unwrap_or(default)
+    }
+}
+
+/// Passes records on to another logger, which should let through those down
+/// to `filter.max_level(default)`, if the filter lets them through.
+pub struct Filtered {
+    inner: Box<dyn SharedLogger>,
+    filter: LogFilter,
+    default: LevelFilter,
+}
+
+impl Filtered {
+    pub fn new(inner: Box<dyn SharedLogger>, filter: LogFilter, default: LevelFilter) -> Box<Self> {
+        Box::new(Self {
+            inner,
+            filter,
+            default,
+        })
+    }
+}
+
+impl Log for Filtered {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level(metadata.target(), self.default)
+            && self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+impl SharedLogger for Filtered {
+    fn level(&self) -> LevelFilter {
+        self.filter.max_level(self.default)
+    }
+
+    fn config(&self) -> Option<&Config> {
+        self.inner.config()
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        self
+    }
+}
+
+fn to_json(record: &Record, time: OffsetDateTime, step: u8) -> Value {
+    let method = record
+        .key_values()
+        .get(Key::from_str("method"))
+        .map(|v| v.to_string());
+
+    let mut json = json!({
+        "timestamp": time.format(&Rfc3339).ok(),
+        "level": record.level().as_str(),
+        "step": (step != 0).then_some(step),
+        "message": record.args().to_string(),
+        "method": method,
+    });
+
+    // Only fatal errors have a failure code
+    if let Some(code) = record.key_values().get(Key::from_str("code")) {
+        json["code"] = Value::String(code.to_string());
+    }
+
+    json
+}
+
+#[test]
+fn test_json_record() {
+    use log::Level;
+
+    let time = OffsetDateTime::UNIX_EPOCH;
+    let kvs = [("method", "GetProgress")];
+    let record = Record::builder()
+        .level(Level::Warn)
+        .args(format_args!("Reconnecting"))
+        .key_values(&kvs)
+        .build();
+    assert_eq!(
+        to_json(&record, time, 3),
+        json!({
+            "timestamp": "1970-01-01T00:00:00Z",
+            "level": "WARN",
+            "step": 3,
+            "message": "Reconnecting",
+            "method": "GetProgress",
+        })
+    );
+
+    let record = Record::builder()
+        .level(Level::Info)
+        .args(format_args!("Hello"))
+        .build();
+    let json = to_json(&record, time, 0);
+    assert_eq!(json["step"], Value::Null);
+    assert_eq!(json["method"], Value::Null);
+}
+
+#[test]
+fn test_log_filter() {
+    let filter = "zbus=warn, reqwest=info,dkcli=debug,dkcli::tui=off"
+        .parse::<LogFilter>()
+        .unwrap();
+    assert_eq!(
+        filter.level("zbus::connection", LevelFilter::Info),
+        LevelFilter::Warn
+    );
+    assert_eq!(filter.level("dkcli", LevelFilter::Info), LevelFilter::Debug);
+    assert_eq!(
+        filter.level("dkcli::tui", LevelFilter::Info),
+        LevelFilter::Off
+    );
+    assert_eq!(filter.level("dkclix", LevelFilter::Info), LevelFilter::Info);
+    assert_eq!(filter.max_level(LevelFilter::Info), LevelFilter::Debug);
+
+    let filter = "warn,dkcli".parse::<LogFilter>().unwrap();
+    assert_eq!(filter.level("hyper", LevelFilter::Info), LevelFilter::Warn);
+    assert_eq!(
+        filter.level("dkcli::mock", LevelFilter::Info),
+        LevelFilter::Trace
+    );
+
+    assert_eq!("".parse::<LogFilter>(), Ok(LogFilter::default()));
+    assert!("zbus=loud".parse::<LogFilter>().is_err());
+}
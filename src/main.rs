@@ -1,240 +1,317 @@
+mod bundle;
+#[cfg(test)]
+mod canned;
+mod error;
+mod events;
+mod hooks;
 mod i18n;
-mod parser;
+mod journal;
+mod logging;
+#[cfg(any(test, feature = "mock"))]
+mod mock;
+mod notify;
+mod plain;
+mod polkit;
+mod preflight;
+mod redact;
+mod report;
+#[cfg(feature = "online")]
+mod stats;
+mod tui;
+mod ui;
+mod wizard;
 
 use std::{
     error::Error,
+    ffi::{CStr, CString},
     fmt::Debug,
-    fs,
+    fs::{self, File, OpenOptions},
+    future::Future,
+    os::unix::fs::MetadataExt,
     path::{Path, PathBuf},
-    process::exit,
-    sync::Arc,
-    time::Duration,
+    process::{exit, Command},
+    sync::{atomic::Ordering, Arc, LazyLock, OnceLock},
+    time::{Duration, Instant},
 };
 
 use anyhow::{bail, Context, Result};
-use clap::Parser;
-use i18n::LANGUAGE_LOADER;
-use i18n_embed::DesktopLanguageRequester;
-use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
-use inquire::{
-    required,
-    validator::{ErrorMessage, Validation},
-    Confirm, CustomType, Password, PasswordDisplayMode, Select, Text,
+use clap::{builder::PossibleValuesParser, Parser, Subcommand, ValueEnum};
+use comfy_table::{
+    presets::{ASCII_FULL_CONDENSED, UTF8_FULL_CONDENSED},
+    Attribute, Cell, CellAlignment, Color, ContentArrangement, Table,
+};
+use dkcli_core::{
+    answer,
+    checkpoint::{Checkpoint, Stage},
+    client::{
+        self, config_held, get_devices, get_memory, get_partitions, set_board, set_config,
+        validate_config, Dbus, DkClient, Frontend, Notice, RecordedConfig,
+    },
+    config::{
+        input_method_packages, Device, DkPartition, InstallConfig, Keyboard, LocaleConfig,
+        NtpConfig, UserConfig, UserPassword,
+    },
+    dbus::{Bus, DbusFailure, DbusMethod},
+    disk,
+    error::{ClientError, DaemonError, DaemonErrorKind},
+    gpu::{classify, gpu_driver_packages, list_gpus, Graphics},
+    host::{
+        self, host_command, host_has_offline_recipe, host_keymaps, host_recipe, host_xkb_layouts,
+        run_on_host,
+    },
+    parser::{
+        default_locale_for_timezone, list_locales, list_zoneinfo, locale_for_language_code, Locale,
+        PasswdEntry, Zone,
+    },
+    platform::{
+        board_config, boards_for, check_riscv, detect_board, detect_efi_ia32,
+        detect_loongarch_firmware, find_board, host_secure_boot, read_compatible, secure_boot_for,
+        BoardConfig, BootLayout, RiscvIssue, SecureBoot,
+    },
+    progress::{
+        self, AutoPartitionProgress, InstallProgress, ProgressObserver, ProgressStatus,
+        INSTALL_STEPS,
+    },
+    recipe::{
+        arch_from_uname, get_arch_name, host_arch_name, installable_variants, is_desktop,
+        is_foreign_arch, is_foreign_machine, memory_shortfall, release_for, set_remote_target,
+        set_target_arch, Squashfs, Variant, ARCHES, ONLINE_MIN_MEMORY,
+    },
+    timings::StepSpan,
+    validate::{
+        self, normalize_fullname, AvatarError, HostnameError, UsernameError, UID_MAX, UID_MIN,
+    },
 };
-use log::{debug, info, LevelFilter};
-use parser::list_zoneinfo;
+use error::{failure_code, Describe, Failure, Localized};
+use events::Event;
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use hooks::{HookContext, Hooks};
+use i18n::LANGUAGE_LOADER;
+use i18n_embed::{DesktopLanguageRequester, LanguageLoader, Localizer};
+use indicatif::{HumanBytes, HumanDuration, ProgressBar, ProgressStyle};
+use inquire::{required, validator::Validation, InquireError, PasswordDisplayMode};
+use journal::JournalLogger;
+use log::{debug, error, info, warn, LevelFilter};
+use logging::{Filtered, JsonLogger, LogFilter};
+use preflight::Preflight;
+use report::Report;
+#[cfg(feature = "online")]
 use reqwest::ClientBuilder;
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use simplelog::{ColorChoice, ConfigBuilder, TermLogger, TerminalMode};
-use tokio::{runtime::Runtime, time::sleep};
-use zbus::{proxy, Connection, Result as zResult};
-
-const LOCALE_LIST: &str = include_str!("../lang_select.json");
-const OFFLINE_RECIPE_PATH: &str = "/run/livekit/livemnt/manifest/recipe.json";
+use simplelog::{
+    ColorChoice, CombinedLogger, ConfigBuilder, SharedLogger, TermLogger, TerminalMode, WriteLogger,
+};
+#[cfg(feature = "online")]
+use stats::Stats;
+use time::OffsetDateTime;
+use tokio::{runtime::Handle, signal, time::sleep};
+use tui::{LogBuffer, Step, Tui};
+use ui::{Ui, MULTI_PROGRESS};
+use wizard::{Asked, Input, Questions, State, Wizard};
+
+#[cfg(feature = "online")]
+const GEOIP_TIMEZONE_URL: &str = "https://ipapi.co/timezone";
+const LOG_DIR: &str = "/var/log/dkcli";
+// Modules too chatty to log
+const LOG_IGNORE: &[&str] = &["i18n_embed"];
+const GIB: u64 = 1024 * 1024 * 1024;
+// Smallest EFI system partition not worth a warning
+const MIN_ESP_SIZE: u64 = 100 * 1024 * 1024;
+// Space left on the system partition after installing below which to warn
+const MIN_SPACE_LEFT: u64 = 5 * GIB;
+
+// Log file of this run, once it is open
+static LOG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+static FUZZY_MATCHER: LazyLock<SkimMatcherV2> =
+    LazyLock::new(|| SkimMatcherV2::default().ignore_case());
 
 #[derive(Debug, Parser)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Commands>,
     /// Set install config path
     #[clap(short, long)]
     config: Option<PathBuf>,
+    /// Use the full-screen terminal interface
+    #[clap(long, conflicts_with = "plain")]
+    tui: bool,
+    /// Use numbered menus and plain progress lines, for screen readers
+    #[clap(long)]
+    plain: bool,
+    /// How to show passwords as they are typed
+    #[clap(long, value_enum, default_value_t = PasswordDisplay::Masked)]
+    password_display: PasswordDisplay,
+    /// Only print ASCII characters, for dumb terminals and serial console logs
+    #[clap(long)]
+    ascii: bool,
+    /// Message bus of the Deploykit daemon: system, session, or
+    /// address:<D-Bus address> for a private bus
+    #[clap(long, default_value = "system")]
+    bus: Bus,
+    /// Drive the daemon of another machine booted into the live
+    /// environment, reaching its system bus over SSH
+    #[clap(long, value_name = "[USER@]HOST", conflicts_with = "bus")]
+    remote: Option<String>,
+    /// Give up on a daemon call after this many seconds, 0 to wait forever
+    #[clap(long, value_name = "SECONDS", default_value_t = 60)]
+    dbus_timeout: u64,
+    /// How to print log messages
+    #[clap(long, value_enum, default_value_t = LogFormat::Human)]
+    log_format: LogFormat,
+    /// Log levels by module, e.g. zbus=warn,reqwest=info,dkcli=debug; a
+    /// bare level applies to all other modules
+    #[clap(long, value_name = "DIRECTIVES")]
+    log_filter: Option<LogFilter>,
+    /// Write progress events as JSON lines to this named pipe, made if
+    /// missing, for a frontend to show
+    #[clap(long, value_name = "PATH")]
+    event_fifo: Option<PathBuf>,
+    /// Install for this architecture rather than that of this machine, e.g.
+    /// an arm64 system to an SD card from an amd64 machine. Checks of this
+    /// machine are skipped
+    #[clap(long, value_name = "ARCH", value_parser = PossibleValuesParser::new(ARCHES))]
+    target_arch: Option<String>,
+    /// Install into this existing directory, e.g. a mounted file system or
+    /// the root of a container, rather than a partition; nothing is
+    /// partitioned and no bootloader is set up
+    #[clap(long, value_name = "PATH")]
+    target_dir: Option<PathBuf>,
+    /// Install into a --target-dir that is not empty without asking,
+    /// overwriting what is there
+    #[clap(long, requires = "target_dir")]
+    force: bool,
+    /// Talk to a built-in stand-in for the daemon, which pretends to
+    /// install in seconds, replying as scripted in a JSON file if given
+    #[cfg(feature = "mock")]
+    #[clap(long, value_name = "SCRIPT", num_args = 0..=1, conflicts_with_all = ["bus", "remote"])]
+    mock: Option<Option<PathBuf>>,
 }
 
-struct InstallConfig {
-    offline_install: bool,
-    variant: Variant,
-    fullname: Option<String>,
-    user: String,
-    password: String,
-    hostname: String,
-    timezone: String,
-    rtc_as_localtime: bool,
-    target_part: DkPartition,
-    efi_disk: Option<DkPartition>,
-    locale: String,
-    swapfile_size: f64,
+impl Args {
+    fn mock(&self) -> bool {
+        #[cfg(feature = "mock")]
+        return self.mock.is_some();
+        #[cfg(not(feature = "mock"))]
+        return false;
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct UserConfig {
-    offline_install: bool,
-    variant: String,
-    fullname: Option<String>,
-    user: String,
-    password: String,
-    hostname: String,
-    timezone: String,
-    rtc_as_localtime: bool,
-    target_part: String,
-    efi_disk: Option<String>,
-    locale: String,
-    swapfile_size: Option<f64>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    /// Colored text, for people
+    Human,
+    /// One JSON object per line, with the time, level, installation step,
+    /// message and daemon method, for provisioning systems
+    Json,
 }
 
-#[derive(Debug, Deserialize)]
-struct Dbus {
-    result: DbusResult,
-    data: Value,
-}
-
-#[derive(Debug, Deserialize, PartialEq, Eq)]
-enum DbusResult {
-    Ok,
-    Error,
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(tag = "status")]
-enum AutoPartitionProgress {
-    Pending,
-    Working,
-    Finish { res: Result<Value, Value> },
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(tag = "status")]
-enum ProgressStatus {
-    Pending,
-    Working { step: u8, progress: u8, v: usize },
-    Error(Value),
-    Finish,
-}
-
-#[derive(Debug, Deserialize, Serialize, Clone)]
-struct Recipe {
-    variants: Vec<Variant>,
-    mirrors: Value,
-}
-
-#[derive(Debug, Deserialize, Serialize, Clone)]
-struct Variant {
-    name: String,
-    #[serde(rename = "dir-name")]
-    dir_name: Option<String>,
-    retro: bool,
-    squashfs: Vec<Squashfs>,
-}
-
-#[derive(Debug, Deserialize)]
-struct Device {
-    model: String,
-    path: String,
-    size: u64,
-}
-
-#[derive(Debug, Deserialize, Serialize, Clone)]
-struct Squashfs {
-    arch: String,
-    date: String,
-    #[serde(rename = "downloadSize")]
-    download_size: u64,
-    #[serde(rename = "instSize")]
-    inst_size: u64,
-    path: String,
-    sha256sum: String,
-    inodes: u64,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct DkPartition {
-    path: Option<PathBuf>,
-    parent_path: Option<PathBuf>,
-    fs_type: Option<String>,
-    size: u64,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct Locale {
-    lang_english: String,
-    locale: String,
-    lang: String,
-    text: String,
-    data: String,
-}
-
-#[proxy(
-    interface = "io.aosc.Deploykit1",
-    default_service = "io.aosc.Deploykit",
-    default_path = "/io/aosc/Deploykit"
-)]
-trait Deploykit {
-    async fn set_config(&self, field: &str, value: &str) -> zResult<String>;
-    async fn get_config(&self, field: &str) -> zResult<String>;
-    async fn get_progress(&self) -> zResult<String>;
-    async fn reset_config(&self) -> zResult<String>;
-    async fn get_list_devices(&self) -> zResult<String>;
-    async fn auto_partition(&self, dev: &str) -> zResult<String>;
-    async fn start_install(&self) -> zResult<String>;
-    async fn get_auto_partition_progress(&self) -> zResult<String>;
-    async fn get_list_partitions(&self, dev: &str) -> zResult<String>;
-    async fn get_recommend_swap_size(&self) -> zResult<String>;
-    async fn get_memory(&self) -> zResult<String>;
-    async fn find_esp_partition(&self, dev: &str) -> zResult<String>;
-    async fn cancel_install(&self) -> zResult<String>;
-    async fn disk_is_right_combo(&self, dev: &str) -> zResult<String>;
-    async fn ping(&self) -> zResult<String>;
-    async fn get_all_esp_partitions(&self) -> zResult<String>;
-    async fn reset_progress_status(&self) -> zResult<String>;
-    async fn sync_disk(&self) -> zResult<String>;
-    async fn sync_and_reboot(&self) -> zResult<String>;
-    async fn is_lvm_device(&self, dev: &str) -> zResult<String>;
-    async fn is_efi(&self) -> zResult<String>;
-}
-
-impl Dbus {
-    async fn run(proxy: &DeploykitProxy<'_>, method: DbusMethod<'_>) -> Result<Self> {
-        let s = match method {
-            DbusMethod::SetConfig(field, value) => proxy.set_config(field, value).await?,
-            DbusMethod::AutoPartition(p) => proxy.auto_partition(p).await?,
-            DbusMethod::GetProgress => proxy.get_progress().await?,
-            DbusMethod::StartInstall => proxy.start_install().await?,
-            DbusMethod::GetAutoPartitionProgress => proxy.get_auto_partition_progress().await?,
-            DbusMethod::ListPartitions(dev) => proxy.get_list_partitions(dev).await?,
-            DbusMethod::ListDevice => proxy.get_list_devices().await?,
-            DbusMethod::GetRecommendSwapSize => proxy.get_recommend_swap_size().await?,
-            DbusMethod::CancelInstall => proxy.cancel_install().await?,
-            DbusMethod::DiskIsRightCombo(dev) => proxy.disk_is_right_combo(dev).await?,
-            DbusMethod::GetAllEspPartitions => proxy.get_all_esp_partitions().await?,
-            DbusMethod::IsLvmDevice(dev) => proxy.is_lvm_device(dev).await?,
-            DbusMethod::IsEFI => proxy.is_efi().await?,
-        };
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum PasswordDisplay {
+    /// Show nothing
+    Hidden,
+    /// Show an asterisk for each character
+    Masked,
+    /// Show the password itself
+    Full,
+}
 
-        let res = Self::try_from(s)?;
-        Ok(res)
+impl From<PasswordDisplay> for PasswordDisplayMode {
+    fn from(value: PasswordDisplay) -> Self {
+        match value {
+            PasswordDisplay::Hidden => PasswordDisplayMode::Hidden,
+            PasswordDisplay::Masked => PasswordDisplayMode::Masked,
+            PasswordDisplay::Full => PasswordDisplayMode::Full,
+        }
     }
 }
 
-#[derive(Debug)]
-enum DbusMethod<'a> {
-    SetConfig(&'a str, &'a str),
-    AutoPartition(&'a str),
-    GetProgress,
-    StartInstall,
-    GetAutoPartitionProgress,
-    ListPartitions(&'a str),
-    ListDevice,
-    GetRecommendSwapSize,
-    CancelInstall,
-    DiskIsRightCombo(&'a str),
-    GetAllEspPartitions,
-    IsLvmDevice(&'a str),
-    IsEFI,
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// List storage devices and their partitions
+    ListDevices,
 }
 
-impl TryFrom<String> for Dbus {
-    type Error = anyhow::Error;
+/// Settings the user already picked for the live session, used as prompt
+/// defaults.
+#[derive(Debug, Default, PartialEq)]
+struct LiveDefaults {
+    locale: Option<String>,
+    keymap: Option<String>,
+    x11_layout: Option<String>,
+    timezone: Option<String>,
+}
+
+impl LiveDefaults {
+    /// Those of the live session on the machine being installed.
+    fn detect(bus: &Bus) -> Self {
+        let mut defaults = host_command(bus, &["localectl", "status"])
+            .output()
+            .ok()
+            .filter(|x| x.status.success())
+            .map(|x| Self::from_localectl(&String::from_utf8_lossy(&x.stdout)))
+            .unwrap_or_default();
+
+        defaults.timezone = host_command(
+            bus,
+            &["timedatectl", "show", "--property=Timezone", "--value"],
+        )
+        .output()
+        .ok()
+        .filter(|x| x.status.success())
+        .map(|x| String::from_utf8_lossy(&x.stdout).trim().to_string())
+        .filter(|x| !x.is_empty());
+
+        debug!("Live session defaults: {defaults:?}");
+
+        defaults
+    }
+
+    fn from_localectl(status: &str) -> Self {
+        let mut defaults = Self::default();
+
+        for line in status.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
 
-    fn try_from(value: String) -> std::prelude::v1::Result<Self, <Dbus as TryFrom<String>>::Error> {
-        let res = serde_json::from_str::<Dbus>(&value)?;
+            let value = value.trim();
+            if value.is_empty() || value == "n/a" || value == "(unset)" {
+                continue;
+            }
 
-        match res.result {
-            DbusResult::Ok => Ok(res),
-            DbusResult::Error => bail!("Failed to execute query: {:#?}", res.data),
+            match key.trim() {
+                "System Locale" => {
+                    defaults.locale = value
+                        .split_whitespace()
+                        .find_map(|x| x.strip_prefix("LANG="))
+                        .map(|x| x.to_string());
+                }
+                "VC Keymap" => defaults.keymap = Some(value.to_string()),
+                "X11 Layout" => defaults.x11_layout = Some(value.to_string()),
+                _ => {}
+            }
         }
+
+        defaults
     }
 }
 
-fn main() -> Result<()> {
+/// Open today's log file, which keeps everything down to debug messages,
+/// for looking into a failed installation once the terminal is gone.
+fn open_log_file() -> Result<(PathBuf, File)> {
+    let today = OffsetDateTime::now_local()
+        .unwrap_or_else(|_| OffsetDateTime::now_utc())
+        .date();
+    let path = Path::new(LOG_DIR).join(format!("install-{today}.log"));
+    let file = fs::create_dir_all(LOG_DIR)
+        .and_then(|_| OpenOptions::new().create(true).append(true).open(&path))
+        .with_context(|| fl!("log-file-failed", path = path.display().to_string()))?;
+
+    Ok((path, file))
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
     let localizer = crate::i18n::localizer();
     let requested_languages = DesktopLanguageRequester::requested_languages();
 
@@ -248,886 +325,4326 @@ fn main() -> Result<()> {
     LANGUAGE_LOADER.set_use_isolating(false);
 
     let args = Args::parse();
+    ui::ASCII.store(args.ascii, Ordering::Relaxed);
+    if let Some(arch) = &args.target_arch {
+        set_target_arch(arch);
+    }
 
-    TermLogger::init(
-        LevelFilter::Info,
-        ConfigBuilder::default()
-            .add_filter_ignore_str("i18n_embed")
-            .build(),
-        TerminalMode::Stderr,
-        ColorChoice::Auto,
-    )?;
+    let log_config = ConfigBuilder::default()
+        .add_filter_ignore_str(LOG_IGNORE[0])
+        .set_time_offset_to_local()
+        .unwrap_or_else(|b| b)
+        .build();
+
+    let log_file = open_log_file();
+    let mut loggers: Vec<Box<dyn SharedLogger>> = vec![];
+    if let Ok((_, file)) = &log_file {
+        loggers.push(WriteLogger::new(
+            LevelFilter::Debug,
+            log_config.clone(),
+            file.try_clone()?,
+        ));
+    }
+
+    // What is shown goes by --log-filter; the log file keeps everything
+    let filter = args.log_filter.clone().unwrap_or_default();
+    let level = filter.max_level(LevelFilter::Info);
+    let shown = |logger| Filtered::new(logger, filter.clone(), LevelFilter::Info);
+
+    let ui = if args.tui && args.command.is_none() {
+        let logs = LogBuffer::default();
+        loggers.push(shown(WriteLogger::new(level, log_config, logs.clone())));
+        init_logger(loggers)?;
+        Ui::Tui(Tui::new(logs)?)
+    } else {
+        // Escape codes and timestamps are of no use in the journal
+        let journal = match args.log_format {
+            LogFormat::Human if journal::is_connected() => JournalLogger::new(level, LOG_IGNORE),
+            _ => None,
+        };
+
+        loggers.push(shown(match (args.log_format, journal) {
+            (_, Some(journal)) => journal,
+            (LogFormat::Human, None) => TermLogger::new(
+                level,
+                log_config,
+                TerminalMode::Stderr,
+                if args.plain {
+                    ColorChoice::Never
+                } else {
+                    ColorChoice::Auto
+                },
+            ),
+            (LogFormat::Json, None) => JsonLogger::new(level, LOG_IGNORE),
+        }));
+        init_logger(loggers)?;
+
+        if args.plain {
+            Ui::Plain
+        } else {
+            Ui::Inquire
+        }
+    };
+
+    match log_file {
+        Ok((path, _)) => {
+            debug!(
+                "dkcli {} logging to {}",
+                env!("CARGO_PKG_VERSION"),
+                path.display()
+            );
+            let _ = LOG_PATH.set(path);
+        }
+        Err(e) => warn!("{e:#}"),
+    }
 
-    let rt = tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()?;
-
-    let dk_client = rt.block_on(create_dbus_client())?;
-    let dk_client = Arc::new(dk_client);
-    let dc = dk_client.clone();
-
-    ctrlc::set_handler(move || {
-        info!("{}", fl!("install-is-canceled"));
-        let rt = tokio::runtime::Builder::new_multi_thread()
-            .enable_all()
-            .build()
-            .unwrap();
-        rt.block_on(Dbus::run(&dc, DbusMethod::CancelInstall))
-            .unwrap();
+    // The wizard blocks on its prompts, so it gets a thread of its own and
+    // hands daemon calls back to this runtime. The localizer only wraps
+    // LANGUAGE_LOADER, but can't be sent there.
+    let log_format = args.log_format;
+    let rt = Handle::current();
+    let res = tokio::task::spawn_blocking(move || run(args, ui, &*i18n::localizer(), rt)).await?;
+    if let Err(e) = res {
+        let code = failure_code(&e);
+        match log_format {
+            // Otherwise the error would be the one line that is not JSON
+            LogFormat::Json => error!(code = code.as_str(); "{:#}", Localized(&e)),
+            // As returning the error would print it, but without secrets
+            // the daemon or a configuration file may have added to it
+            LogFormat::Human => {
+                eprintln!("Error: {}", redact::text(&format!("{:?}", Localized(&e))));
+                // For provisioning wrappers, which should not have to make
+                // sense of the above
+                let detail = redact::text(&format!("{:#}", Localized(&e))).replace('\n', " ");
+                eprintln!("DKCLI_ERROR code={code} detail={detail}");
+            }
+        }
         exit(1);
-    })
-    .expect("Failed to set ctrlc handler");
+    }
 
-    let progress = rt.block_on(Dbus::run(&dk_client, DbusMethod::GetProgress))?;
-    let data: ProgressStatus = serde_json::from_value(progress.data)?;
+    Ok(())
+}
 
-    if let ProgressStatus::Working { .. } = data {
-        info!("{}", fl!("another-install-is-running"));
-        rt.block_on(get_progress(&dk_client))?;
-        return Ok(());
+/// Install `loggers`, with secrets masked in everything they write.
+fn init_logger(loggers: Vec<Box<dyn SharedLogger>>) -> Result<()> {
+    let logger = redact::Logger(CombinedLogger::new(loggers));
+    log::set_max_level(logger.level());
+    log::set_boxed_logger(Box::new(logger))?;
+
+    Ok(())
+}
+
+fn run(args: Args, ui: Ui, localizer: &dyn Localizer, rt: Handle) -> Result<()> {
+    // Before anything else is asked or said, so that it is in that language
+    let interactive = args.config.is_none() && args.command.is_none();
+    if interactive {
+        match select_display_language(&ui, localizer) {
+            // Esc keeps the language of the live session
+            Err(e) if matches!(e.downcast_ref(), Some(InquireError::OperationCanceled)) => {}
+            res => res?,
+        }
     }
 
-    let config = if let Some(config_path) = args.config {
+    if let Some(path) = &args.event_fifo {
         info!(
+            "{}",
+            fl!("event-fifo-waiting", path = path.display().to_string())
+        );
+        events::open(path)?;
+    }
+
+    if is_foreign_arch() && args.remote.is_none() {
+        warn!(
             "{}",
             fl!(
-                "install-from-config",
-                path = config_path.display().to_string()
+                "target-arch-foreign",
+                arch = get_arch_name().unwrap_or_default(),
+                host = host_arch_name().unwrap_or("unknown")
             )
         );
-        let f = fs::read_to_string(config_path)?;
-        let config: UserConfig = toml::from_str(&f)?;
-        from_config(&rt, config, &dk_client)?
-    } else {
-        inquire(&rt, &dk_client)?
+    }
+
+    let (target_dir, target_dir_in_use) = match &args.target_dir {
+        // Otherwise the directory is on the machine of the daemon
+        Some(dir) if !args.mock() && args.remote.is_none() => {
+            let dir = check_target_dir(dir)?;
+            let in_use = !dir_is_empty(&dir)?;
+            if in_use && args.config.is_some() && !args.force {
+                return Err(Failure::new(
+                    "target_dir",
+                    fl!("target-dir-not-empty", path = dir.display().to_string()),
+                )
+                .into());
+            }
+            (Some(dir), in_use && !args.force)
+        }
+        dir => (dir.clone(), false),
     };
+    if let Some(dir) = &target_dir {
+        info!(
+            "{}",
+            fl!("target-dir-info", path = dir.display().to_string())
+        );
+    }
 
-    rt.block_on(set_config(&dk_client, &config))?;
-    rt.block_on(Dbus::run(&dk_client, DbusMethod::StartInstall))?;
-    rt.block_on(get_progress(&dk_client))?;
+    let bus = match &args.remote {
+        Some(host) => Bus::Remote(host.clone()),
+        None => args.bus.clone(),
+    };
+    if let Bus::Remote(_) = &bus {
+        let arch = remote_arch(&bus);
+        set_remote_target(arch.as_ref().ok().copied());
+        // Unless given, that of the other machine rather than this one
+        if args.target_arch.is_none() {
+            set_target_arch(arch?);
+        }
+    }
+    // Held until the end, for the agent to answer while dkcli runs
+    let _agent = match bus {
+        Bus::System if !matches!(ui, Ui::Tui(_)) && !args.mock() => polkit::Agent::spawn(),
+        _ => None,
+    };
 
-    Ok(())
-}
+    let dk_client = rt.block_on(connect(&args, bus))?;
+    let cancel = dk_client.cancel.clone();
+    let hooks = Hooks::default();
+    let hook_cx = HookContext {
+        ui: &ui,
+        unattended: args.config.is_some(),
+    };
 
-async fn get_progress(dk_client: &DeploykitProxy<'_>) -> Result<()> {
-    let style = ProgressStyle::with_template(
-        "{prefix:.bold}   [{wide_bar:.cyan/blue}] {percent}% {spinner:.green}",
-    )?
-    .progress_chars("#>-");
+    // Prompts see Ctrl-C as a key press; this is for when the terminal is
+    // not in raw mode, e.g. while waiting on the daemon. Pending and later
+    // daemon calls give up, and the main flow winds down from there.
+    rt.spawn(async move {
+        while signal::ctrl_c().await.is_ok() {
+            if cancel.is_cancelled() {
+                exit(130);
+            }
+            cancel.cancel();
+            info!("{}", fl!("interrupt-again"));
+        }
+    });
 
-    let pb = ProgressBar::new(100).with_style(style);
+    if let Some(Commands::ListDevices) = args.command {
+        return rt.block_on(list_devices(&dk_client));
+    }
 
-    let steps = [
-        fl!("formatting-partition"),
-        fl!("downloading-system-release"),
-        fl!("unpacking-system-release"),
-        fl!("generating-fstab"),
-        fl!("generating-initramfs"),
-        fl!("installing-bootloader"),
-        fl!("generating-ssh-key"),
-        fl!("finalizing-installation"),
-    ];
+    let progress = rt.block_on(Dbus::run(&dk_client, DbusMethod::GetProgress))?;
+    let data: ProgressStatus = serde_json::from_value(progress.data)?;
 
-    loop {
-        let progress = Dbus::run(dk_client, DbusMethod::GetProgress).await?;
-        let data: ProgressStatus = serde_json::from_value(progress.data)?;
+    // Starting another installation on top would leave the daemon in a mess
+    if let ProgressStatus::Working { .. } = data {
+        if args.config.is_some() {
+            return Err(Failure::new("busy", fl!("another-install-is-running")).into());
+        }
 
-        match data {
-            ProgressStatus::Working { step, progress, .. } => {
-                pb.set_prefix(format!(
-                    "({}/{}) {}",
-                    step,
-                    steps.len(),
-                    steps[step.saturating_sub(1) as usize]
-                ));
-                pb.set_position(progress as u64);
-            }
-            ProgressStatus::Pending => {
-                continue;
+        warn!("{}", fl!("another-install-is-running"));
+        let action = ui
+            .select(
+                &fl!("running-install-action"),
+                vec![
+                    fl!("running-install-attach"),
+                    fl!("running-install-cancel"),
+                    fl!("running-install-quit"),
+                ],
+            )
+            .with_help_message(&fl!("running-install-action-help"))
+            .raw_prompt();
+
+        // Possibly one started by a dkcli that crashed since
+        match action.map(|x| x.index) {
+            Ok(0) => {
+                rt.block_on(get_progress(&ui, &dk_client, None))?;
+                show_finished(&ui, &[])?;
+                clear_checkpoint();
             }
-            ProgressStatus::Error(e) => {
-                bail!("{e}");
+            Ok(1) => {
+                rt.block_on(Dbus::run(&dk_client, DbusMethod::CancelInstall))?;
+                info!("{}", fl!("install-is-canceled"));
+                clear_checkpoint();
             }
+            Ok(_) | Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => {}
+            Err(e) => return Err(e.into()),
+        }
+        return Ok(());
+    }
+
+    if let Err(e) = rt.block_on(dk_client.claim()) {
+        let busy = e
+            .downcast_ref::<DaemonError>()
+            .filter(|e| e.kind == DaemonErrorKind::Busy);
+
+        let Some(busy) = busy.filter(|_| args.config.is_none()) else {
+            return Err(e);
+        };
+
+        warn!("{}", fl!("daemon-claimed", owner = busy.message.as_str()));
+        let attach = ui
+            .confirm(&fl!("attach-read-only"))
+            .with_help_message(&fl!("attach-read-only-help"))
+            .with_default(true)
+            .prompt();
+
+        // Waits for the other installer to start, then follows it
+        if attach? {
+            rt.block_on(get_progress(&ui, &dk_client, None))?;
+            show_finished(&ui, &[])?;
+        }
+        return Ok(());
+    }
+
+    // Only interactive installations are picked up again; an answer file
+    // can simply be used again, and the mock daemon remembers nothing
+    let checkpoints = args.config.is_none() && !args.mock();
+    let checkpoint = match checkpoints {
+        true => resume_checkpoint(&ui, args.password_display.into())?,
+        false => None,
+    };
+    // From then on, the settings the daemon holds are those of the checkpoint
+    let resumed = checkpoint
+        .as_ref()
+        .is_some_and(|x| x.stage >= Stage::ConfigSent);
+
+    if resumed {
+        match data {
+            // Finished with no dkcli around to follow it
             ProgressStatus::Finish => {
-                pb.finish_and_clear();
-                info!("{}", fl!("finished"));
+                show_finished(&ui, &[])?;
+                clear_checkpoint();
                 return Ok(());
             }
+            ProgressStatus::Error(_) => {
+                rt.block_on(Dbus::run(&dk_client, DbusMethod::ResetProgressStatus))?;
+            }
+            ProgressStatus::Pending | ProgressStatus::Working { .. } => {}
+        }
+    } else {
+        // Left behind by an installer that did not get to finish or clean up
+        let leftovers = rt.block_on(leftover_state(&dk_client, &data))?;
+        if !leftovers.is_empty() {
+            ui.summary(&fl!("leftover-state"), &leftovers);
+
+            let reset = args.config.is_some()
+                || ui
+                    .confirm(&fl!("reset-leftover-state"))
+                    .with_help_message(&fl!("reset-leftover-state-help"))
+                    .with_default(true)
+                    .prompt()?;
+
+            if reset {
+                rt.block_on(Dbus::run(&dk_client, DbusMethod::ResetConfig))?;
+                rt.block_on(Dbus::run(&dk_client, DbusMethod::ResetProgressStatus))?;
+                info!("{}", fl!("leftover-state-reset"));
+            }
         }
+    }
 
-        sleep(Duration::from_micros(100)).await;
+    // Left to a warning with an answer file, which should not stall. Nothing
+    // is booted from a directory
+    if checkpoint.is_none() && target_dir.is_none() && !check_platform(&ui, args.config.is_some())?
+    {
+        info!("{}", fl!("installation-aborted"));
+        return Ok(());
     }
-}
 
-fn from_config(
-    runtime: &Runtime,
-    config: UserConfig,
-    dk_client: &DeploykitProxy<'_>,
-) -> Result<InstallConfig> {
-    let recipe = runtime.block_on(get_recipe(config.offline_install))?;
-    let variant = get_variant(recipe, &config.variant);
-    let cand = candidate_sqfs(&variant)?;
+    let memory = rt.block_on(get_memory(&dk_client));
+    if checkpoint.is_none() && memory.is_some_and(|x| x < ONLINE_MIN_MEMORY) {
+        warn!("{}", low_memory_online(memory.unwrap_or_default()));
+    }
 
-    let devices = runtime
-        .block_on(get_devices(dk_client))?
-        .into_iter()
-        .filter(|x| {
-            if config.offline_install {
-                x.size as f64 > cand.inst_size as f64 * 1.25
-            } else {
-                x.size > cand.inst_size + cand.download_size
+    let config = if let Some(checkpoint) = &checkpoint {
+        checkpoint.config.clone()
+    } else if let Some(config_path) = &args.config {
+        info!(
+            "{}",
+            fl!(
+                "install-from-config",
+                path = config_path.display().to_string()
+            )
+        );
+        let path = config_path.display().to_string();
+        let f = fs::read_to_string(config_path)
+            .with_context(|| fl!("config-read-failed", path = path.as_str()))?;
+        let config: UserConfig =
+            toml::from_str(&f).with_context(|| fl!("config-parse-failed", path = path.as_str()))?;
+        hooks.pre_partition(&hook_cx)?;
+        from_config(&ui, &rt, config, &dk_client, memory, target_dir)?
+    } else {
+        let config = inquire(
+            &ui,
+            localizer,
+            &rt,
+            &dk_client,
+            &hooks,
+            args.password_display.into(),
+            Answers {
+                memory,
+                target_dir,
+                target_dir_in_use,
+                local_disks: !args.mock() && args.remote.is_none(),
+                language_chosen: interactive,
+                ..Default::default()
+            },
+        )?;
+
+        match config {
+            Some(config) => config,
+            None => {
+                // Drop anything the daemon was told before quitting
+                rt.block_on(Dbus::run(&dk_client, DbusMethod::ResetConfig))?;
+                info!("{}", fl!("installation-aborted"));
+                return Ok(());
             }
-        })
-        .collect::<Vec<_>>();
+        }
+    };
 
-    let mut target_part = None;
-    let mut efi_disk = None;
+    if let Some(path) = target_path(&config).or(config.target_dir.as_ref()) {
+        logging::set_device(path.display().to_string());
+    }
 
-    let is_efi = runtime
-        .block_on(Dbus::run(dk_client, DbusMethod::IsEFI))?
-        .data
-        .as_bool()
-        .context(fl!("direct-efi-error"))?;
-
-    for d in devices {
-        let partitions = runtime.block_on(get_partitions(dk_client, &d.path))?;
-        if let Some(v) = partitions.iter().find(|x| {
-            x.path
-                .as_ref()
-                .is_some_and(|x| x.display().to_string() == config.target_part)
-        }) {
-            target_part = Some(v.to_owned());
-        }
-
-        if is_efi {
-            if config.efi_disk.is_none() {
-                bail!("{}", fl!("efi-field-not-set"));
-            }
-            if let Some(v) = partitions.iter().find(|x| {
-                x.path
-                    .as_ref()
-                    .is_some_and(|x| x.display().to_string() == *config.efi_disk.as_ref().unwrap())
-            }) {
-                efi_disk = Some(v.to_owned());
-            }
-        }
+    if checkpoints && !resumed {
+        save_checkpoint(Stage::Configured, &config, &dk_client);
     }
 
-    if let Some(fullname) = &config.fullname {
-        if let Ok(Validation::Invalid(e)) = vaildation_fullname(fullname) {
-            if let ErrorMessage::Custom(s) = e {
-                bail!("{}", fl!("invaild-fullname", e = s));
-            } else {
-                unreachable!()
+    // Sent before dkcli was interrupted, unless the daemon lost them since
+    let sent_config = checkpoint
+        .as_ref()
+        .filter(|_| resumed)
+        .map(|x| x.sent_config.as_slice());
+    let held = match sent_config {
+        Some(sent) => rt.block_on(config_held(&dk_client, sent))?,
+        None => false,
+    };
+
+    if held {
+        info!("{}", fl!("checkpoint-config-held"));
+        dk_client.restore_config(sent_config.unwrap_or_default());
+    } else {
+        // What is left of them would get mixed up with the settings sent now
+        if resumed {
+            rt.block_on(Dbus::run(&dk_client, DbusMethod::ResetConfig))?;
+        }
+
+        if let Err(e) = rt.block_on(set_config(&dk_client, &config)) {
+            if !is_interrupted(&e) {
+                return Err(e);
             }
+            rt.block_on(Dbus::run(&dk_client, DbusMethod::ResetConfig))?;
+            clear_checkpoint();
+            info!("{}", fl!("installation-aborted"));
+            return Ok(());
         }
     }
 
-    if let Ok(Validation::Invalid(e)) = validate_username(&config.user) {
-        if let ErrorMessage::Custom(s) = e {
-            bail!("{}", fl!("invaild-username", e = s));
-        } else {
-            unreachable!()
+    let problems = rt.block_on(validate_config(&dk_client))?;
+    if !problems.is_empty() {
+        rt.block_on(Dbus::run(&dk_client, DbusMethod::ResetConfig))?;
+        clear_checkpoint();
+        let list = problems
+            .iter()
+            .map(|x| format!("  - {}", x.describe()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let message = format!("{}\n{list}", fl!("config-invalid", count = problems.len()));
+        return Err(Failure::new("invalid_config", message).into());
+    }
+
+    if !rt.block_on(check_config(&ui, &dk_client))? {
+        let proceed = args.config.is_none()
+            && ui
+                .confirm(&fl!("config-mismatch-continue"))
+                .with_default(false)
+                .prompt()?;
+
+        if !proceed {
+            rt.block_on(Dbus::run(&dk_client, DbusMethod::ResetConfig))?;
+            clear_checkpoint();
+            return Err(Failure::new("config_mismatch", fl!("config-mismatch")).into());
         }
     }
 
-    if let Ok(Validation::Invalid(e)) = validate_hostname(&config.hostname) {
-        if let ErrorMessage::Custom(s) = e {
-            bail!("{}", fl!("invaild-hostname", e = s));
-        } else {
-            unreachable!()
+    if let Err(e) = hooks.pre_install(&hook_cx, &config) {
+        rt.block_on(Dbus::run(&dk_client, DbusMethod::ResetConfig))?;
+        clear_checkpoint();
+        if !is_interrupted(&e) && !is_canceled(&e) {
+            return Err(e);
         }
+        info!("{}", fl!("installation-aborted"));
+        return Ok(());
     }
 
-    let locales = locales()?;
-    let timezones = list_zoneinfo()?;
+    if checkpoints {
+        save_checkpoint(Stage::ConfigSent, &config, &dk_client);
+    }
+    rt.block_on(Dbus::run(&dk_client, DbusMethod::StartInstall))?;
+    if checkpoints {
+        save_checkpoint(Stage::InstallStarted, &config, &dk_client);
+    }
+    let start = Instant::now();
+    // Never asked for in unattended or mock installations
+    #[cfg(feature = "online")]
+    let ask_stats = args.config.is_none() && !args.mock();
+    let steps = match rt.block_on(get_progress(
+        &ui,
+        &dk_client,
+        release_for(&config.variant).ok(),
+    )) {
+        Ok(steps) => steps,
+        Err(e) => {
+            // Kept otherwise, to try again
+            if failure_code(&e) == "canceled" {
+                clear_checkpoint();
+            }
 
-    if locales.iter().all(|x| x.data != config.locale) {
-        bail!("{}", fl!("invaild-locale", s = config.locale));
+            // Not worth asking someone who just pressed Ctrl-C
+            #[cfg(feature = "online")]
+            if ask_stats && failure_code(&e) != "canceled" {
+                let code = failure_code(&e);
+                let stats = Stats::new(&config.variant.name, start.elapsed(), Some(code));
+                // Not at the cost of why the installation failed
+                if let Err(stats_e) = offer_stats(&ui, &rt, &stats) {
+                    warn!("{}", fl!("stats-failed", e = format!("{stats_e:#}")));
+                }
+            }
+            return Err(e);
+        }
+    };
+    let elapsed = start.elapsed();
+
+    // The target is only at hand on this machine
+    if !args.mock() && args.remote.is_none() {
+        let report = Report::new(&config, &steps, start, elapsed);
+        let res = match (&config.target_dir, target_path(&config)) {
+            (Some(dir), _) => report.write_into(dir),
+            (None, Some(part)) => report.write(&dk_client, part),
+            (None, None) => Ok(()),
+        };
+        if let Err(e) = res {
+            warn!("{}", fl!("report-failed", e = format!("{e:#}")));
+        }
     }
 
-    if timezones.iter().all(|x| x != &config.timezone) {
-        bail!("{}", fl!("invaild-timezone", s = config.timezone));
+    show_finished(&ui, &finish_summary(&config, &steps, elapsed))?;
+    clear_checkpoint();
+
+    if let Err(e) = hooks.post_install(&hook_cx, &config) {
+        warn!("{e:#}");
     }
 
-    if target_part.is_none() {
-        bail!("{}", fl!("invaild-target-partition"));
+    #[cfg(feature = "online")]
+    if ask_stats {
+        offer_stats(&ui, &rt, &Stats::new(&config.variant.name, elapsed, None))?;
     }
 
-    if efi_disk.is_none() && is_efi {
-        bail!("{}", fl!("invaild-efi-partition"));
+    if args.config.is_none() {
+        after_install(&ui, &rt, &dk_client, args.mock())?;
     }
 
-    Ok(InstallConfig {
-        offline_install: config.offline_install,
-        variant,
-        fullname: config.fullname,
-        user: config.user,
-        password: config.password,
-        hostname: config.hostname,
-        timezone: config.timezone,
-        rtc_as_localtime: config.rtc_as_localtime,
-        target_part: target_part.unwrap(),
-        efi_disk,
-        locale: config.locale,
-        swapfile_size: config.swapfile_size.unwrap_or(0.0),
-    })
+    Ok(())
 }
 
-fn inquire(runtime: &Runtime, dk_client: &DeploykitProxy<'_>) -> Result<InstallConfig> {
-    let is_offline_install = if Path::new(OFFLINE_RECIPE_PATH).exists() {
-        Confirm::new(&fl!("offline-mode"))
-            .with_default(true)
-            .prompt()?
-    } else {
-        false
+/// Offer to pick up where an earlier run left off, if it did not get to
+/// finish.
+fn resume_checkpoint(ui: &Ui, password_display: PasswordDisplayMode) -> Result<Option<Checkpoint>> {
+    let Some(mut checkpoint) = Checkpoint::load() else {
+        return Ok(None);
     };
 
-    let recipe = runtime.block_on(get_recipe(is_offline_install))?;
-    let variant = Select::new(
-        &fl!("variant"),
-        recipe
-            .variants
-            .iter()
-            .filter(|x| !x.retro && x.name.to_lowercase() != "buildkit")
-            .map(|x| x.name.to_string())
-            .collect::<Vec<_>>(),
-    )
-    .prompt()?;
+    let stage = match checkpoint.stage {
+        Stage::Configured => fl!("checkpoint-configured"),
+        Stage::ConfigSent => fl!("checkpoint-config-sent"),
+        Stage::InstallStarted => fl!("checkpoint-install-started"),
+    };
+    ui.summary(
+        &fl!("checkpoint-found", stage = stage),
+        &install_summary(&checkpoint.config),
+    );
 
-    let variant = get_variant(recipe, &variant);
+    let resume = ui
+        .confirm(&fl!("resume-checkpoint"))
+        .with_help_message(&fl!("resume-checkpoint-help"))
+        .with_default(true)
+        .prompt()?;
 
-    let cand = candidate_sqfs(&variant)?;
+    if !resume {
+        clear_checkpoint();
+        return Ok(None);
+    }
 
-    let devices = runtime
-        .block_on(get_devices(dk_client))?
-        .into_iter()
-        .filter(|x| {
-            if is_offline_install {
-                x.size as f64 > cand.inst_size as f64 * 1.25
-            } else {
-                x.size > cand.inst_size + cand.download_size
-            }
-        })
-        .collect::<Vec<_>>();
+    // Never saved along with the rest
+    let password = ask_password(ui, password_display, &fl!("checkpoint-password-help"))?;
+    checkpoint.set_password(UserPassword::Plain(password))?;
 
-    info!("{}", fl!("list-of-device"));
+    Ok(Some(checkpoint))
+}
 
-    for i in &devices {
-        info!("{} {} ({})", i.model, i.path, HumanBytes(i.size));
-    }
+/// Note that the installation got to `stage`, for a later run to pick up
+/// from there should this one crash. Failing to is not worth more than a
+/// warning.
+fn save_checkpoint(stage: Stage, config: &InstallConfig, dk_client: &DkClient) {
+    let checkpoint = Checkpoint {
+        stage,
+        config: config.clone(),
+        sent_config: dk_client.sent_config(),
+    };
 
-    if devices.is_empty() {
-        bail!("{}", fl!("no-device-to-install"));
+    if let Err(e) = checkpoint.save() {
+        warn!("{}", fl!("checkpoint-failed", e = format!("{e:#}")));
     }
+}
 
-    let device = Select::new(
-        &fl!("select-device"),
-        devices
-            .iter()
-            .map(|x| x.path.to_string())
-            .collect::<Vec<_>>(),
-    )
-    .prompt()?;
-
-    info!("{}", fl!("confirm-autopart"));
+fn clear_checkpoint() {
+    if let Err(e) = Checkpoint::clear() {
+        warn!("{}", fl!("checkpoint-failed", e = format!("{e:#}")));
+    }
+}
 
-    let auto_partition = Confirm::new(&fl!("auto-partiton"))
-        .with_error_message(&fl!("yn-confirm-required"))
-        .prompt()?;
+/// Tell that the installation is done, with what was installed where as
+/// `(label, value)` rows, if known.
+fn show_finished(ui: &Ui, rows: &[(String, String)]) -> Result<()> {
+    match ui {
+        Ui::Tui(tui) => {
+            let mut message = fl!("finished");
+            if !rows.is_empty() {
+                message.push('\n');
+            }
+            for (label, value) in rows {
+                message.push_str(&format!("\n{label}: {value}"));
+            }
+            tui.finish(&message)?;
+        }
+        _ if rows.is_empty() => info!("{}", fl!("finished")),
+        _ => ui.summary(&fl!("finished"), rows),
+    }
 
-    let (partition, efi) = if auto_partition {
-        runtime.block_on(Dbus::run(dk_client, DbusMethod::AutoPartition(&device)))?;
-        runtime.block_on(get_auto_partition_progress(dk_client))?
-    } else {
-        let disk_is_right_combo =
-            runtime.block_on(Dbus::run(dk_client, DbusMethod::DiskIsRightCombo(&device)));
+    Ok(())
+}
 
-        if let Err(e) = disk_is_right_combo {
-            bail!("{e}");
+/// What was installed where, and how long it took in all and by step, as
+/// `(label, value)` rows.
+fn finish_summary(
+    config: &InstallConfig,
+    steps: &[StepSpan],
+    elapsed: Duration,
+) -> Vec<(String, String)> {
+    let mut rows = vec![(fl!("variant"), config.variant.name.clone())];
+
+    if let Some(part) = &config.target_part {
+        if let Some(disk) = &part.parent_path {
+            rows.push((fl!("select-device"), disk.display().to_string()));
+        }
+        if let Some(path) = &part.path {
+            rows.push((fl!("select-system-partition"), path.display().to_string()));
         }
+    }
+    if let Some(dir) = &config.target_dir {
+        rows.push((fl!("target-dir"), dir.display().to_string()));
+    }
 
-        let partitions = runtime.block_on(get_partitions(dk_client, &device))?;
+    rows.push((fl!("hostname"), config.hostname.clone()));
+    rows.push((fl!("username"), config.user.clone()));
+    rows.push((fl!("locale"), config.locale.default.clone()));
+    rows.push((fl!("elapsed-time"), HumanDuration(elapsed).to_string()));
+    // Tells apart a slow mirror from a slow disk
+    for span in steps {
+        rows.push((
+            format!("  {}", install_step_name(span.step)),
+            HumanDuration(span.duration).to_string(),
+        ));
+    }
 
-        let install_parts_list = partitions
-            .iter()
-            .filter(|x| {
-                if is_offline_install {
-                    x.size as f64 > cand.inst_size as f64 * 1.25
-                } else {
-                    x.size > cand.inst_size + cand.download_size
-                }
-            })
-            .collect::<Vec<_>>();
+    for instructions in boot_instructions(config) {
+        rows.push((fl!("boot-instructions"), instructions));
+    }
 
-        if install_parts_list.is_empty() {
-            bail!("{}", fl!("no-partition-to-install"));
-        }
+    rows
+}
 
-        let is_efi = runtime
-            .block_on(Dbus::run(dk_client, DbusMethod::IsEFI))?
-            .data
-            .as_bool()
-            .context(fl!("direct-efi-error"))?;
+/// Device of the partition installed to, if any.
+fn target_path(config: &InstallConfig) -> Option<&PathBuf> {
+    config.target_part.as_ref().and_then(|x| x.path.as_ref())
+}
 
-        debug!("Device is{}EFI", if is_efi { " " } else { " not " });
+/// How to boot the installed system, for machines where rebooting is not
+/// enough.
+fn boot_instructions(config: &InstallConfig) -> Vec<String> {
+    let mut instructions = vec![];
+
+    // Petitboot boots whatever it finds first unless told otherwise
+    if config
+        .board
+        .as_ref()
+        .is_some_and(|x| x.layout == BootLayout::Petitboot)
+    {
+        instructions.push(fl!(
+            "petitboot-instructions",
+            partition = target_path(config)
+                .map(|x| x.display().to_string())
+                .unwrap_or_default()
+        ));
+    }
 
-        let is_lvm_device = runtime
-            .block_on(Dbus::run(dk_client, DbusMethod::IsLvmDevice(&device)))?
-            .data
-            .as_bool()
-            .context(fl!("direct-lvm-error"))?;
+    match config.secure_boot {
+        Some(SecureBoot::Shim) => instructions.push(fl!("secure-boot-shim-instructions")),
+        Some(SecureBoot::Disable) => instructions.push(fl!("secure-boot-disable-instructions")),
+        None => {}
+    }
 
-        if is_lvm_device {
-            bail!("{}", fl!("unsupport-lvm-device"));
-        }
+    instructions
+}
 
-        let partition = Select::new(
-            &fl!("select-system-partition"),
-            install_parts_list
-                .iter()
-                .filter_map(|x| x.path.as_ref().map(|x| x.to_string_lossy().to_string()))
-                .collect::<Vec<_>>(),
-        )
-        .prompt()?;
+/// Ask whether to send `stats`, showing all there is in them, and send them
+/// if so. Failing to is not worth more than a warning.
+#[cfg(feature = "online")]
+fn offer_stats(ui: &Ui, runtime: &Handle, stats: &Stats) -> Result<()> {
+    let send = ui
+        .confirm(&fl!("stats-prompt"))
+        .with_help_message(&fl!(
+            "stats-prompt-help",
+            stats = serde_json::to_string(stats)?
+        ))
+        .with_default(false)
+        .prompt();
 
-        let partition = get_partition(&partitions, &partition);
+    match send {
+        Ok(true) => {}
+        Ok(false) | Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => {
+            return Ok(())
+        }
+        Err(e) => return Err(e.into()),
+    }
 
-        let mut efi = None;
+    let pb = ui.spinner(fl!("stats-sending"));
+    let res = runtime.block_on(stats.submit());
+    pb.finish_and_clear();
 
-        if is_efi {
-            let efi_parts = runtime
-                .block_on(Dbus::run(dk_client, DbusMethod::GetAllEspPartitions))?
-                .data;
+    match res {
+        Ok(()) => info!("{}", fl!("stats-sent")),
+        Err(e) => warn!("{}", fl!("stats-failed", e = format!("{e:#}"))),
+    }
 
-            let efi_parts: Vec<DkPartition> = serde_json::from_value(efi_parts)?;
+    Ok(())
+}
 
-            if efi_parts.is_empty() {
-                bail!("{}", fl!("no-efi-partition"));
+/// Offer to restart or shut down into the installed system. `mock` keeps
+/// this machine running when trying out the interface.
+fn after_install(ui: &Ui, runtime: &Handle, dk_client: &DkClient, mock: bool) -> Result<()> {
+    let action = ui
+        .select(
+            &fl!("after-install"),
+            vec![
+                fl!("after-install-reboot"),
+                fl!("after-install-poweroff"),
+                fl!("after-install-stay"),
+            ],
+        )
+        .with_help_message(&fl!("after-install-help"))
+        .raw_prompt();
+
+    match action.map(|x| x.index) {
+        Ok(0) => {
+            let res = runtime.block_on(Dbus::run(dk_client, DbusMethod::SyncAndReboot));
+            // The daemon may well go down before it gets to answer
+            let rebooting = |e: &anyhow::Error| {
+                e.downcast_ref::<zbus::Error>()
+                    .is_some_and(|e| DbusFailure::of(e) == DbusFailure::Disconnected)
+            };
+            match res {
+                Err(e) if !rebooting(&e) => return Err(e),
+                _ => {}
             }
+        }
+        Ok(1) if mock => debug!("Not powering off with the mock daemon"),
+        Ok(1) => {
+            // Disks were synced as the installation finished
+            runtime.block_on(host::power_off(&dk_client.bus))?;
+        }
+        Ok(_) | Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => {}
+        Err(e) => return Err(e.into()),
+    }
 
-            let efi_part = Select::new(
-                &fl!("select-efi-partition"),
-                efi_parts
-                    .iter()
-                    .filter_map(|x| x.path.as_ref().map(|x| x.to_string_lossy().to_string()))
-                    .collect::<Vec<_>>(),
-            )
-            .prompt()?;
-
-            let efi_part = get_partition(&partitions, &efi_part);
+    Ok(())
+}
 
-            efi = Some(efi_part);
+/// Tells the user what goes on in dkcli-core through the log, as with
+/// everything else that is not asked.
+struct Cli;
+
+impl Frontend for Cli {
+    fn notice(&self, notice: Notice<'_>) {
+        match notice {
+            Notice::StartingDaemon => info!("{}", fl!("starting-daemon")),
+            Notice::Reconnecting { method, error } => warn!(
+                method = method;
+                "{}", fl!("daemon-reconnecting", e = error.to_string())
+            ),
+            Notice::Reconnected => info!("{}", fl!("daemon-reconnected")),
+            Notice::SkippedConfig(fields) => warn!(
+                "{}",
+                fl!("daemon-skipped-config", fields = fields.join(", "))
+            ),
+            Notice::LoongarchFirmwareUnknown => warn!("{}", fl!("loongarch-firmware-unknown")),
+            Notice::EfiIa32 => warn!("{}", fl!("efi-ia32-detected")),
+            Notice::GpuDriverSuggested => warn!("{}", fl!("gpu-driver-suggest")),
+            Notice::LowMemory {
+                variant,
+                memory,
+                required,
+            } => warn!(
+                "{}",
+                fl!(
+                    "low-memory-variant",
+                    variant = variant.name.as_str(),
+                    memory = HumanBytes(memory).to_string(),
+                    required = HumanBytes(required).to_string()
+                )
+            ),
+            Notice::SecureBoot(SecureBoot::Shim) => info!("{}", fl!("secure-boot-shim-info")),
+            // Otherwise the firmware refuses the bootloader without a word
+            Notice::SecureBoot(SecureBoot::Disable) => warn!("{}", fl!("secure-boot-unsigned")),
+            Notice::UmountFailed(e) => warn!("{}", fl!("umount-failed", e = e)),
         }
+    }
+}
 
-        (partition, efi)
-    };
+/// Connect to the daemon, or to the mock one if asked to.
+async fn connect(args: &Args, bus: Bus) -> Result<DkClient> {
+    #[cfg(feature = "mock")]
+    if let Some(script) = &args.mock {
+        let mock = mock::Mock::new(script.as_deref())?;
+        return DkClient::new(bus, Arc::new(mock), args.dbus_timeout, Arc::new(Cli)).await;
+    }
 
-    let fullname = Text::new(&fl!("fullname"))
-        .with_validator(vaildation_fullname)
-        .prompt()?;
-
-    let default_username = get_default_username(&fullname);
-
-    let username = Text::new(&fl!("username"))
-        .with_validator(required!(fl!("username-required")))
-        .with_validator(validate_username)
-        .with_default(&default_username)
-        .prompt()?;
-
-    let password = Password::new(&fl!("password"))
-        .with_validator(required!(fl!("password-required")))
-        .with_display_mode(PasswordDisplayMode::Masked)
-        .with_custom_confirmation_message(&fl!("confirm-password"))
-        .with_custom_confirmation_error_message(&fl!("confirm-password-not-matching"))
-        .prompt()?;
-
-    let timezones = list_zoneinfo()?;
-
-    let timezone = Select::new(&fl!("timezone"), timezones).prompt()?;
-
-    let locales = locales()?;
-
-    let locale = Select::new(
-        &fl!("locale"),
-        locales.iter().map(|x| x.text.clone()).collect::<Vec<_>>(),
-    )
-    .prompt()?;
+    DkClient::connect(bus, args.dbus_timeout, Arc::new(Cli)).await
+}
 
-    let locale = locales.iter().find(|x| x.text == locale).unwrap();
+/// Follow the installation until it finishes, returning the steps that were
+/// followed from the start. `sqfs` is the system release being installed, if
+/// known, used to show transfer sizes.
+async fn get_progress(
+    ui: &Ui,
+    dk_client: &DkClient,
+    sqfs: Option<&Squashfs>,
+) -> Result<Vec<StepSpan>> {
+    ui.step(Step::Install);
+
+    // Serial consoles are too narrow to fit the bar next to the step name
+    let template = if ui::terminal_width() < 100 {
+        "{prefix:.bold} {wide_msg:.bold}\n[{wide_bar:.cyan/blue}] {percent:>3}% {spinner:.green}"
+    } else {
+        "{prefix:.bold} {msg:.bold}   [{wide_bar:.cyan/blue}] {percent}% {spinner:.green}"
+    };
+    let style = ui::with_ascii_ticks(ProgressStyle::with_template(template)?.progress_chars("#>-"));
 
-    let hostname = Text::new(&fl!("hostname"))
-        .with_validator(required!(fl!("hostname-required")))
-        .with_validator(validate_hostname)
-        .prompt()?;
+    let pb = match ui {
+        Ui::Inquire => MULTI_PROGRESS.add(ProgressBar::new(100).with_style(style)),
+        Ui::Plain | Ui::Tui(_) | Ui::Custom(_) => ProgressBar::hidden(),
+    };
 
-    let rtc_as_localtime = Confirm::new(&fl!("rtc-as-localtime"))
-        .with_default(false)
-        .prompt()?;
+    // Estimated time left, shown above the progress bar
+    let eta_pb = match ui {
+        Ui::Inquire => MULTI_PROGRESS.insert_before(
+            &pb,
+            ProgressBar::new_spinner().with_style(ProgressStyle::with_template("{msg}")?),
+        ),
+        Ui::Plain | Ui::Tui(_) | Ui::Custom(_) => ProgressBar::hidden(),
+    };
 
-    let mut recommend_swap_file_size = runtime
-        .block_on(Dbus::run(dk_client, DbusMethod::GetRecommendSwapSize))?
-        .data
-        .as_f64()
-        .unwrap_or(0.0);
+    // What the daemon logged lately, shown below the progress bar while
+    // the installation seems stuck
+    let log_pb = match ui {
+        Ui::Inquire => MULTI_PROGRESS.insert_after(
+            &pb,
+            ProgressBar::new_spinner().with_style(ProgressStyle::with_template("{msg:.dim}")?),
+        ),
+        Ui::Plain | Ui::Tui(_) | Ui::Custom(_) => ProgressBar::hidden(),
+    };
 
-    if recommend_swap_file_size > 32.0 * 1024.0 * 1024.0 * 1024.0 {
-        recommend_swap_file_size = 32.0 * 1024.0 * 1024.0 * 1024.0;
+    let mut view = InstallView {
+        ui,
+        dk_client,
+        sqfs,
+        pb,
+        eta_pb,
+        log_pb,
+        daemon_log: vec![],
+        last_printed: None,
+        last_title: None,
+        spinner: None,
+        failed: false,
+    };
+    let res = progress::follow(dk_client, &mut view).await;
+    view.clear();
+    if let Some(spinner) = &view.spinner {
+        spinner.finish_and_clear();
     }
 
-    if is_offline_install {
-        let size = recommend_swap_file_size + cand.inst_size as f64 * 1.25;
-        if (partition.size as f64) < size {
-            recommend_swap_file_size =
-                (recommend_swap_file_size - (partition.size as f64 - size)) / 1.25;
+    match res {
+        Ok(steps) => {
+            events::emit(Event::Finished);
+            notify::notify(&fl!("notify-finished"), &fl!("notify-finished-body"), false).await;
+            ui.set_title(&fl!("notify-finished"));
+            ui.bell();
+            Ok(steps)
         }
-    } else {
-        let size = recommend_swap_file_size + cand.inst_size as f64 + cand.download_size as f64;
-        if (partition.size as f64) < size {
-            recommend_swap_file_size =
-                (recommend_swap_file_size - (partition.size as f64 - size)) / 1.25;
+        Err(e) if matches!(e.downcast_ref(), Some(ClientError::Canceled)) => {
+            events::emit(Event::Canceled);
+            ui.set_title(&fl!("install-is-canceled"));
+            Err(Failure::new("canceled", fl!("install-is-canceled")).into())
+        }
+        Err(e) => {
+            if let Some(e) = e.downcast_ref::<DaemonError>().filter(|_| view.failed) {
+                notify::notify(&fl!("notify-failed"), &e.message, true).await;
+                ui.set_title(&fl!("notify-failed"));
+                ui.bell();
+            }
+            Err(e)
         }
     }
+}
 
-    let swap_size = CustomType::<f64>::new(&fl!("swap-size"))
-        .with_default(
-            format!("{:.2}", recommend_swap_file_size / 1024.0 / 1024.0 / 1024.0)
-                .parse::<f64>()
-                .unwrap(),
-        )
-        .prompt()?;
-
-    info!("{}", fl!("confirm"));
-
-    let confirm = Confirm::new(&fl!("confirm-prompt"))
-        .with_error_message(&fl!("yn-confirm-required"))
-        .prompt()?;
+/// The installation as shown while it goes on: a progress bar with the time
+/// left, or a line every tenth of a step in plain mode.
+struct InstallView<'a> {
+    ui: &'a Ui,
+    dk_client: &'a DkClient,
+    sqfs: Option<&'a Squashfs>,
+    pb: ProgressBar,
+    eta_pb: ProgressBar,
+    log_pb: ProgressBar,
+    daemon_log: Vec<String>,
+    // Last step and tenth of progress printed in plain mode
+    last_printed: Option<(u8, u8)>,
+    last_title: Option<(u8, u8)>,
+    // Shown while the disks are synced
+    spinner: Option<ProgressBar>,
+    failed: bool,
+}
 
-    if !confirm {
-        bail!("{}", fl!("installation-aborted"));
-    }
-
-    Ok(InstallConfig {
-        offline_install: is_offline_install,
-        variant,
-        fullname: Some(fullname),
-        user: username,
-        password,
-        hostname,
-        timezone,
-        rtc_as_localtime,
-        target_part: partition,
-        efi_disk: efi,
-        locale: locale.data.clone(),
-        swapfile_size: swap_size,
-    })
+impl InstallView<'_> {
+    fn clear(&self) {
+        self.pb.finish_and_clear();
+        self.eta_pb.finish_and_clear();
+        self.log_pb.finish_and_clear();
+    }
 }
 
-fn get_default_username(fullname: &str) -> String {
-    let mut default_username = String::new();
-    let mut not_a_number = false;
+impl ProgressObserver for InstallView<'_> {
+    fn progress(&mut self, progress: &InstallProgress) -> Result<()> {
+        let &InstallProgress {
+            step,
+            progress,
+            bytes_per_sec,
+            remaining,
+        } = progress;
+        let ui = self.ui;
+
+        logging::set_step(step);
+        let prefix = format!("({step}/{INSTALL_STEPS})");
+        let v = bytes_per_sec.unwrap_or_default() as usize;
+        let name = match transfer_status(step, progress, v, self.sqfs) {
+            Some(transfer) => format!("{}  {transfer}", install_step_name(step)),
+            None => install_step_name(step),
+        };
 
-    for c in fullname.chars() {
-        if c.is_ascii_digit() && !not_a_number {
-            continue;
+        let eta = remaining.map(|x| fl!("time-remaining", time = HumanDuration(x).to_string()));
+        let name = match &eta {
+            Some(eta) if !matches!(ui, Ui::Inquire) => format!("{name}  ({eta})"),
+            _ => name,
+        };
+        self.eta_pb.set_message(eta.unwrap_or_default());
+
+        if self.last_title != Some((step, progress)) {
+            self.last_title = Some((step, progress));
+            self.log_pb.set_message("");
+            events::emit(Event::Progress {
+                step,
+                steps: INSTALL_STEPS,
+                name: &install_step_name(step),
+                progress,
+                bytes_per_sec,
+            });
+            ui.set_title(&format!("{prefix} {} {progress}%", install_step_name(step)));
         }
 
-        if !c.is_ascii_alphabetic() && !c.is_ascii_digit() {
-            continue;
+        match ui {
+            Ui::Inquire => {
+                self.pb.set_prefix(prefix);
+                self.pb.set_message(name);
+                self.pb.set_position(progress as u64);
+            }
+            Ui::Plain | Ui::Custom(_) => {
+                if self.last_printed != Some((step, progress / 10)) {
+                    self.last_printed = Some((step, progress / 10));
+                    eprintln!("{prefix} {name}: {progress}%");
+                }
+            }
+            Ui::Tui(tui) => {
+                tui.progress(&format!("{prefix} {name}"), progress as u64)?;
+
+                // The terminal is in raw mode, so Ctrl-C arrives as a key press
+                if tui.poll_interrupted()? {
+                    self.dk_client.cancel.cancel();
+                }
+            }
         }
 
-        default_username.push(c.to_ascii_lowercase());
-        not_a_number = true;
+        Ok(())
     }
 
-    default_username
-}
-
-fn locales() -> Result<Vec<Locale>> {
-    let locales: Vec<Locale> = serde_json::from_str(LOCALE_LIST)?;
-
-    Ok(locales)
-}
+    fn stalled(&mut self, log: &[String]) {
+        match self.ui {
+            Ui::Inquire => self.log_pb.set_message(
+                log.iter()
+                    .map(|x| format!("  {x}"))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            Ui::Plain | Ui::Tui(_) | Ui::Custom(_) => {
+                for line in log.iter().filter(|x| !self.daemon_log.contains(x)) {
+                    self.ui.println(&format!("  {line}"));
+                }
+            }
+        }
+        self.daemon_log = log.to_vec();
+    }
 
-fn validate_hostname(input: &str) -> std::result::Result<Validation, Box<dyn Error + Send + Sync>> {
-    if input.len() > 64 {
-        return Ok(Validation::Invalid(
-            fl!("hostname-illegal-too-loong").into(),
-        ));
+    fn canceling(&mut self) {
+        self.clear();
+        info!("{}", fl!("install-canceling"));
     }
 
-    for i in ['-', '.'] {
-        if input.starts_with(i) {
-            return Ok(Validation::Invalid(
-                fl!("hostname-illegal-starts-with", c = i.to_string()).into(),
-            ));
+    fn failed(&mut self, e: &DaemonError) {
+        self.failed = true;
+        events::emit(Event::Failed {
+            code: e.code.as_deref(),
+            message: &e.describe(),
+        });
+        self.clear();
+
+        let spinner = self.ui.spinner(fl!("collecting-failure-bundle"));
+        let bundle = bundle::collect(self.dk_client, e);
+        spinner.finish_and_clear();
+        match bundle {
+            Ok(path) => info!(
+                "{}",
+                fl!("failure-bundle", path = path.display().to_string())
+            ),
+            Err(e) => warn!(
+                "{}",
+                fl!("failure-bundle-failed", e = format!("{:#}", Localized(&e)))
+            ),
         }
     }
 
-    for i in ['-', '.'] {
-        if input.ends_with(i) {
-            return Ok(Validation::Invalid(
-                fl!("hostname-illegal-ends-with", c = i.to_string()).into(),
-            ));
-        }
+    fn finished(&mut self) {
+        self.clear();
+        self.spinner = Some(self.ui.spinner(fl!("syncing-disks")));
     }
+}
 
-    let mut is_dot = false;
-    for c in input.chars() {
-        if c == '.' && is_dot {
-            return Ok(Validation::Invalid(
-                fl!("hostname-illegal-double-dot").into(),
-            ));
-        } else if is_dot {
-            is_dot = false;
-        }
+/// The architecture of the machine driven with --remote.
+fn remote_arch(bus: &Bus) -> Result<&'static str> {
+    let machine = run_on_host(bus, &["uname", "-m"])?;
 
-        if c == '.' {
-            is_dot = true;
-        }
+    arch_from_uname(&machine).ok_or_else(|| {
+        Failure::new(
+            "remote_arch",
+            fl!("remote-arch-unknown", arch = machine.trim().to_string()),
+        )
+        .into()
+    })
+}
 
-        if !c.is_ascii_alphanumeric() && c != '-' && c != '.' {
-            return Ok(Validation::Invalid(
-                fl!("hostname-illegal", c = c.to_string()).into(),
-            ));
-        }
+/// Human-readable name of a (1-based) installation step reported by the
+/// daemon.
+fn install_step_name(step: u8) -> String {
+    match step {
+        1 => fl!("formatting-partition"),
+        2 => fl!("downloading-system-release"),
+        3 => fl!("unpacking-system-release"),
+        4 => fl!("generating-fstab"),
+        5 => fl!("generating-initramfs"),
+        6 => fl!("installing-bootloader"),
+        7 => fl!("generating-ssh-key"),
+        8 => fl!("finalizing-installation"),
+        _ => fl!("unknown-install-step", step = step),
     }
-
-    Ok(Validation::Valid)
 }
 
-// https://manpages.ubuntu.com/manpages/oracular/en/man5/hostname.5.html
-fn validate_username(input: &str) -> std::result::Result<Validation, Box<dyn Error + Send + Sync>> {
-    for i in input.chars() {
-        if !i.is_ascii_lowercase() && !i.is_ascii_alphanumeric() {
-            return Ok(Validation::Invalid(
-                fl!("username-illegal", c = i.to_string()).into(),
-            ));
-        }
+/// Bytes transferred, throughput and ETA for the download and unpack steps,
+/// where the daemon reports its throughput (in bytes per second) as `v`.
+fn transfer_status(step: u8, progress: u8, v: usize, sqfs: Option<&Squashfs>) -> Option<String> {
+    if v == 0 {
+        return None;
     }
 
-    Ok(Validation::Valid)
+    let speed = format!("{}/s", HumanBytes(v as u64));
+
+    let size = match (step, sqfs) {
+        (2, Some(sqfs)) => sqfs.download_size,
+        (3, Some(sqfs)) => sqfs.inst_size,
+        (2 | 3, None) => return Some(speed),
+        _ => return None,
+    };
+
+    let done = size * progress.min(100) as u64 / 100;
+    let eta = Duration::from_secs((size - done) / v as u64);
+
+    Some(fl!(
+        "transfer-status",
+        done = HumanBytes(done).to_string(),
+        total = HumanBytes(size).to_string(),
+        speed = speed,
+        eta = HumanDuration(eta).to_string()
+    ))
 }
 
-fn vaildation_fullname(
-    input: &str,
-) -> std::result::Result<Validation, Box<dyn Error + Send + Sync>> {
-    if input.contains(":") {
-        return Ok(Validation::Invalid(fl!("fullname-illegal").into()));
+/// The installation the answer file `config` asks for, checked by
+/// dkcli-core against the recipe, the machine and the daemon.
+fn from_config(
+    ui: &Ui,
+    runtime: &Handle,
+    config: UserConfig,
+    dk_client: &DkClient,
+    memory: Option<u64>,
+    target_dir: Option<PathBuf>,
+) -> Result<InstallConfig> {
+    check_online(config.offline_install)?;
+    if !config.offline_install {
+        check_online_memory(memory)?;
     }
 
-    Ok(Validation::Valid)
+    let recipe = block_on_with_spinner(
+        ui,
+        runtime,
+        fl!("downloading-recipe"),
+        host_recipe(&dk_client.bus, config.offline_install),
+    )?;
+
+    let import_users = match &config.import_users_from {
+        Some(part) => disk::read_existing_users(dk_client, part, &disk::probe_fs_type(part)?)?,
+        None => vec![],
+    };
+
+    block_on_with_spinner(
+        ui,
+        runtime,
+        fl!("listing-devices"),
+        answer::from_config(dk_client, recipe, config, import_users, memory, target_dir),
+    )
 }
 
-fn get_partition(partitions: &[DkPartition], partition: &str) -> DkPartition {
-    let partition = partitions
+/// Ask for the language of the installer itself, among the languages dkcli
+/// has translations for.
+fn select_display_language(ui: &Ui, localizer: &dyn Localizer) -> Result<()> {
+    let mut languages = localizer.available_languages()?;
+    languages.sort_unstable_by_key(|x| x.to_string());
+
+    let locales = list_locales()?;
+    let ids = languages.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+
+    // Default to the language of the live session
+    let cursor = ["LC_ALL", "LC_MESSAGES", "LANG"]
         .iter()
-        .find(|x| {
-            x.path
-                .as_ref()
-                .map(|x| x.to_string_lossy() == partition)
-                .unwrap_or(false)
+        .filter_map(|x| std::env::var(x).ok())
+        .find(|x| !x.is_empty())
+        .and_then(|x| session_language(&x, &ids, &locales))
+        .or_else(|| {
+            let current = LANGUAGE_LOADER.current_language();
+            languages.iter().position(|x| *x == current)
         })
-        .unwrap()
-        .to_owned();
+        .unwrap_or(0);
+
+    let names = languages
+        .iter()
+        .map(|lang| {
+            locale_for_language_code(&locales, &lang.to_string())
+                .map(|x| {
+                    if x.text == x.lang_english {
+                        x.text.clone()
+                    } else {
+                        format!("{} ({})", x.text, x.lang_english)
+                    }
+                })
+                .unwrap_or_else(|| lang.to_string())
+        })
+        .collect::<Vec<_>>();
+
+    let lang = ui
+        .select(&fl!("display-language"), names)
+        .with_help_message(&fl!("display-language-help"))
+        .with_starting_cursor(cursor)
+        .raw_prompt()?;
 
-    partition
+    localizer.select(&[languages[lang.index].clone()])?;
+
+    Ok(())
 }
 
-fn get_variant(recipe: Recipe, variant: &str) -> Variant {
-    let variant = recipe
-        .variants
-        .iter()
-        .find(|x| x.name == variant)
-        .unwrap()
-        .to_owned();
+/// Index among `ids` (language identifiers like `zh-CN`) of the language of
+/// the POSIX locale `posix` (like `zh_CN.UTF-8`), going by the locales dkcli
+/// offers, or else by the language alone.
+fn session_language(posix: &str, ids: &[String], locales: &[Locale]) -> Option<usize> {
+    let language = posix.split(['_', '.', '@']).next().unwrap_or_default();
+
+    locale_for_language_code(locales, posix)
+        .and_then(|x| ids.iter().position(|id| *id == x.id))
+        .or_else(|| {
+            ids.iter()
+                .position(|id| id.split('-').next() == Some(language))
+        })
+}
 
-    variant
+/// Answers given in the interactive wizard so far. They are kept when going
+/// back to an earlier step, and offered as defaults when asked again.
+#[derive(Default, Clone)]
+struct Answers {
+    offline_install: bool,
+    variant: Option<Variant>,
+    // Of the machine, as the daemon told at startup
+    memory: Option<u64>,
+    // Install the NVIDIA driver, kept apart from the input method packages
+    // asked for later
+    gpu_driver: Option<bool>,
+    secure_boot: Option<SecureBoot>,
+    device: Option<String>,
+    // Partition the device automatically, as asked before anything else
+    auto_partition: bool,
+    // Of the device, unless partitioned since
+    preflight: Option<Preflight>,
+    // Partition the device automatically after all, it being unusable as
+    // it is
+    repartition: bool,
+    partition: Option<DkPartition>,
+    // Given on the command line, in place of the disk step
+    target_dir: Option<PathBuf>,
+    // Whether there are files in target_dir to overwrite, to ask first
+    target_dir_in_use: bool,
+    efi: Option<DkPartition>,
+    keyboard: Option<Keyboard>,
+    // Installation to carry users over from, and its users
+    import_from: Option<(PathBuf, Vec<PasswdEntry>)>,
+    // Including one of the same name as the new user, which is recreated
+    // as that rather than imported
+    import_users: Vec<PasswdEntry>,
+    fullname: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    autologin: bool,
+    avatar: Option<PathBuf>,
+    // Pick the user and group IDs
+    advanced_user: bool,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    timezone: Option<String>,
+    locale: Option<LocaleConfig>,
+    // Install the input methods of the locales, once asked
+    input_method: Option<bool>,
+    extra_packages: Vec<String>,
+    hostname: Option<String>,
+    rtc_as_localtime: bool,
+    ntp: Option<NtpConfig>,
+    swapfile_size: Option<f64>,
+    board: Option<BoardConfig>,
+    firmware: Option<DkPartition>,
+    // Whether SMART says the chosen device is failing
+    device_failing: bool,
+    // Whether the devices the daemon lists are those of this machine, to be
+    // mounted here
+    local_disks: bool,
+    // Whether the language of the installer was asked for at startup
+    language_chosen: bool,
+    // Whether the pre-partition hooks were run
+    pre_partition_run: bool,
+    // Whether the question being asked was gone back to with Esc, for those
+    // asked only once otherwise
+    going_back: bool,
 }
 
-async fn get_auto_partition_progress(
-    proxy: &DeploykitProxy<'_>,
-) -> Result<(DkPartition, Option<DkPartition>)> {
-    let pb = ProgressBar::new_spinner();
-    loop {
-        let progress = Dbus::run(proxy, DbusMethod::GetAutoPartitionProgress).await?;
-        let data: AutoPartitionProgress = serde_json::from_value(progress.data)?;
+/// Run the interactive wizard, skipping the steps `answers` already has the
+/// answers to. Returns `None` if the user chose to quit.
+fn inquire(
+    ui: &Ui,
+    localizer: &dyn Localizer,
+    runtime: &Handle,
+    dk_client: &DkClient,
+    hooks: &Hooks,
+    password_display: PasswordDisplayMode,
+    mut answers: Answers,
+) -> Result<Option<InstallConfig>> {
+    let live_defaults = LiveDefaults::detect(&dk_client.bus);
 
-        match data {
-            AutoPartitionProgress::Finish { ref res } => match res {
-                Err(v) => {
-                    pb.finish_and_clear();
-                    bail!("{v}");
-                }
-                Ok(value) => {
-                    pb.finish_and_clear();
-                    let (efi, p): (Option<DkPartition>, DkPartition) =
-                        serde_json::from_value(value.clone())?;
-                    return Ok((p, efi));
-                }
-            },
-            AutoPartitionProgress::Working => {
-                pb.set_message(fl!("auto-partition-working"));
-            }
-            _ => {
-                debug!("Progress: {:?}", data);
+    let steps = [
+        Step::Language,
+        Step::Source,
+        Step::Disk,
+        Step::Keyboard,
+        Step::User,
+        Step::System,
+        Step::Confirm,
+    ];
+
+    let mut wizard = Wizard::new(&steps, |step| answers.answered(step));
+
+    match ui {
+        Ui::Plain => info!("{}", fl!("plain-go-back")),
+        _ => info!("{}", fl!("esc-to-go-back")),
+    }
+
+    loop {
+        let step = match wizard.state() {
+            State::Ask(step) => step,
+            State::ConfirmQuit => {
+                let input = if confirm_quit(ui)? {
+                    Input::Quit
+                } else {
+                    Input::Back
+                };
+                wizard.transition(input);
+                continue;
             }
-        }
+            State::Done => break,
+            State::Quit => return Ok(None),
+        };
+        ui.step(step);
+
+        let questions = wizard.questions();
+        let res = match step {
+            Step::Language => select_display_language(ui, localizer),
+            Step::Source => inquire_source(ui, runtime, dk_client, questions, &mut answers),
+            // Once, however often the step is gone back to
+            Step::Disk if !answers.pre_partition_run => hooks
+                .pre_partition(&HookContext {
+                    ui,
+                    unattended: false,
+                })
+                .and_then(|()| {
+                    answers.pre_partition_run = true;
+                    inquire_disk(ui, runtime, dk_client, questions, &mut answers)
+                }),
+            Step::Disk => inquire_disk(ui, runtime, dk_client, questions, &mut answers),
+            Step::Keyboard => inquire_keyboard(ui, &dk_client.bus, &live_defaults, &mut answers),
+            Step::User => inquire_user(
+                ui,
+                runtime,
+                dk_client,
+                password_display,
+                questions,
+                &mut answers,
+            ),
+            Step::System => inquire_system(
+                ui,
+                runtime,
+                dk_client,
+                &live_defaults,
+                questions,
+                &mut answers,
+            ),
+            Step::Confirm => inquire_confirm(ui, runtime, dk_client, &live_defaults, &mut answers),
+            Step::Install => unreachable!(),
+        };
 
-        sleep(Duration::from_millis(100)).await;
+        // Esc on the first question of a step goes back to the previous
+        // step, or offers to quit on the first
+        let input = match res {
+            Ok(()) => Input::Answered,
+            Err(e) if is_canceled(&e) => Input::Back,
+            Err(e) if is_interrupted(&e) => Input::Quit,
+            Err(e) => return Err(e),
+        };
+        wizard.transition(input);
     }
+
+    Ok(Some(answers.to_install_config()))
 }
 
-async fn create_dbus_client() -> Result<DeploykitProxy<'static>> {
-    let conn = Connection::system().await?;
-    let client = DeploykitProxy::new(&conn).await?;
+/// Ask whether to leave the installer. Esc here means staying.
+fn confirm_quit(ui: &Ui) -> Result<bool> {
+    let res = ui
+        .confirm(&fl!("quit-installer"))
+        .with_help_message(&fl!("quit-installer-help"))
+        .with_default(false)
+        .prompt();
 
-    Ok(client)
+    match res {
+        Ok(quit) => Ok(quit),
+        Err(InquireError::OperationCanceled) => Ok(false),
+        Err(InquireError::OperationInterrupted) => Ok(true),
+        Err(e) => Err(e.into()),
+    }
 }
 
-async fn get_recipe(offline_mode: bool) -> Result<Recipe> {
-    let recipe = if !offline_mode {
-        info!("{}", fl!("downloading-recipe"));
-        let client = ClientBuilder::new().user_agent("deploykit").build()?;
-        let resp = client
-            .get("https://releases.aosc.io/manifest/recipe.json")
-            .send()
-            .await?
-            .error_for_status()?;
+impl Answers {
+    /// Whether everything `step` asks for is known already. The language is
+    /// asked unless chosen before the wizard started, and the confirmation
+    /// always is.
+    fn answered(&self, step: Step) -> bool {
+        match step {
+            Step::Source => self.variant.is_some(),
+            Step::Disk => self.partition.is_some() || self.target_dir.is_some(),
+            Step::Keyboard => self.keyboard.is_some(),
+            Step::User => self.username.is_some() && self.password.is_some(),
+            Step::System => {
+                self.timezone.is_some()
+                    && self.locale.is_some()
+                    && self.hostname.is_some()
+                    && self.swapfile_size.is_some()
+            }
+            Step::Language => self.language_chosen,
+            Step::Confirm | Step::Install => false,
+        }
+    }
 
-        resp.json::<Recipe>().await?
-    } else {
-        let f = tokio::fs::read(OFFLINE_RECIPE_PATH).await?;
-        serde_json::from_slice(&f)?
-    };
+    /// The imported user of the same name as the new user, recreated as it
+    /// rather than imported.
+    fn existing_user(&self) -> Option<&PasswdEntry> {
+        self.import_users
+            .iter()
+            .find(|x| Some(&x.username) == self.username.as_ref())
+    }
 
-    Ok(recipe)
+    /// Only valid once every step before the confirmation has been answered.
+    fn to_install_config(&self) -> InstallConfig {
+        InstallConfig {
+            offline_install: self.offline_install,
+            variant: self.variant.clone().unwrap(),
+            fullname: self.fullname.clone(),
+            user: self.username.clone().unwrap(),
+            uid: self.uid,
+            gid: self.gid,
+            avatar: self.avatar.clone(),
+            import_users: self
+                .import_users
+                .iter()
+                .filter(|x| Some(&x.username) != self.username.as_ref())
+                .cloned()
+                .collect(),
+            password: UserPassword::Plain(self.password.clone().unwrap()),
+            autologin: self.autologin,
+            hostname: self.hostname.clone().unwrap(),
+            timezone: self.timezone.clone().unwrap(),
+            rtc_as_localtime: self.rtc_as_localtime,
+            ntp: self.ntp.clone(),
+            target_part: self.partition.clone(),
+            target_dir: self.target_dir.clone(),
+            efi_disk: self.efi.clone(),
+            locale: self.locale.clone().unwrap(),
+            swapfile_size: self.swapfile_size.unwrap(),
+            keyboard: self.keyboard.clone(),
+            extra_packages: self
+                .extra_packages
+                .iter()
+                .cloned()
+                .chain(gpu_driver_packages(self.gpu_driver.unwrap_or(false)))
+                .collect(),
+            board: self.board.clone(),
+            firmware_part: self.firmware.clone(),
+            secure_boot: self.secure_boot,
+        }
+    }
 }
 
-async fn get_devices(dk_client: &DeploykitProxy<'_>) -> Result<Vec<Device>> {
-    let devices = Dbus::run(dk_client, DbusMethod::ListDevice).await?;
-    let devices: Vec<Device> = serde_json::from_value(devices.data)?;
+/// Whether `e` comes from the user pressing Esc in a prompt.
+fn is_canceled(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<InquireError>(),
+        Some(InquireError::OperationCanceled)
+    )
+}
 
-    Ok(devices)
+/// Whether `e` comes from the user pressing Ctrl-C in a prompt, or closing
+/// the input.
+fn is_interrupted(e: &anyhow::Error) -> bool {
+    client::is_interrupted(e)
+        || matches!(
+            e.downcast_ref::<InquireError>(),
+            Some(InquireError::OperationInterrupted)
+        )
 }
 
-async fn get_partitions(dk_client: &DeploykitProxy<'_>, device: &str) -> Result<Vec<DkPartition>> {
-    let partitions = Dbus::run(dk_client, DbusMethod::ListPartitions(device)).await?;
-    let partitions = serde_json::from_value(partitions.data)?;
+/// One question of a step, asked of the user into the answers.
+type Question<'a> = dyn Fn(&mut Answers) -> Result<Asked> + 'a;
+
+/// Ask `asks` in turn, each one question of a step. Esc goes back to the
+/// question answered before, and out of the step from the first.
+fn ask_questions(
+    questions: &mut Questions,
+    answers: &mut Answers,
+    asks: &[&Question],
+) -> Result<()> {
+    let mut n = questions.start();
+    answers.going_back = false;
+
+    while let Some(ask) = asks.get(n) {
+        let back = match ask(answers) {
+            Ok(Asked::Yes) => {
+                questions.answered(n);
+                n += 1;
+                false
+            }
+            // Nothing to go back to there, so on to the one before
+            Ok(Asked::No) if answers.going_back => true,
+            Ok(Asked::No) => {
+                n += 1;
+                false
+            }
+            Ok(Asked::Again(again)) => {
+                n = questions.back_to(again);
+                false
+            }
+            Ok(Asked::Done) => break,
+            Err(e) if is_canceled(&e) => true,
+            Err(e) => return Err(e),
+        };
+
+        if back {
+            n = match questions.back() {
+                Some(previous) => previous,
+                // Esc on the first question leaves the step
+                None => return Err(InquireError::OperationCanceled.into()),
+            };
+        }
+        answers.going_back = back;
+    }
 
-    Ok(partitions)
+    Ok(())
 }
 
-async fn set_config(proxy: &DeploykitProxy<'_>, config: &InstallConfig) -> Result<()> {
-    let variant = &config.variant;
-    let sqfs = candidate_sqfs(variant)?;
-    let url = format!("https://releases.aosc.io/{}", sqfs.path);
+fn inquire_source(
+    ui: &Ui,
+    runtime: &Handle,
+    dk_client: &DkClient,
+    questions: &mut Questions,
+    answers: &mut Answers,
+) -> Result<()> {
+    let has_offline = host_has_offline_recipe(&dk_client.bus);
+    check_online(has_offline)?;
+    if !has_offline {
+        check_online_memory(answers.memory)?;
+    }
 
-    if !config.offline_install {
-        let download_value = serde_json::json!({
-            "Http": {
-                "url": url,
-                "hash": sqfs.sha256sum,
-            }
-        });
+    ask_questions(
+        questions,
+        answers,
+        &[
+            &|answers| ask_offline_mode(ui, has_offline, answers),
+            &|answers| ask_variant(ui, runtime, dk_client, answers),
+            &|answers| ask_gpu_driver(ui, answers),
+        ],
+    )
+}
 
-        Dbus::run(
-            proxy,
-            DbusMethod::SetConfig("download", &download_value.to_string()),
-        )
-        .await?;
-    } else {
-        let variant = config.variant.dir_name.as_ref().unwrap();
+/// Ask whether to install the release on the live media, if there is one
+/// and the release could be downloaded instead.
+fn ask_offline_mode(ui: &Ui, has_offline: bool, answers: &mut Answers) -> Result<Asked> {
+    // Warned about at startup already
+    let low_memory = answers.memory.is_some_and(|x| x < ONLINE_MIN_MEMORY);
+    if !cfg!(feature = "online") || low_memory || !has_offline {
+        answers.offline_install = has_offline;
+        return Ok(Asked::No);
+    }
 
-        let download_value = serde_json::json!({
-            "Dir": format!("/run/livekit/sysroots/{}", variant)
-        });
+    answers.offline_install = ui
+        .confirm(&fl!("offline-mode"))
+        .with_help_message(&fl!("offline-mode-help"))
+        .with_default(answers.variant.is_none() || answers.offline_install)
+        .prompt()?;
 
-        Dbus::run(
-            proxy,
-            DbusMethod::SetConfig("download", &download_value.to_string()),
-        )
-        .await?;
-    };
+    Ok(Asked::Yes)
+}
 
-    Dbus::run(proxy, DbusMethod::SetConfig("locale", &config.locale)).await?;
+fn ask_variant(
+    ui: &Ui,
+    runtime: &Handle,
+    dk_client: &DkClient,
+    answers: &mut Answers,
+) -> Result<Asked> {
+    let recipe = block_on_with_spinner(
+        ui,
+        runtime,
+        fl!("downloading-recipe"),
+        host_recipe(&dk_client.bus, answers.offline_install),
+    )?;
+    // Editions without a release for this architecture cannot be installed
+    let mut variants = match get_arch_name() {
+        Some(arch) => installable_variants(recipe, arch),
+        None => vec![],
+    };
 
-    let json = serde_json::json! {{
-        "username": &config.user,
-        "password": &config.password,
-        "full_name": &config.fullname,
-    }};
+    if variants.is_empty() {
+        bail!(
+            "{}",
+            fl!("no-variant", arch = get_arch_name().unwrap_or("unknown"))
+        );
+    }
 
-    Dbus::run(proxy, DbusMethod::SetConfig("user", &json.to_string())).await?;
+    let cursor = answers
+        .variant
+        .as_ref()
+        .and_then(|v| variants.iter().position(|x| x.name == v.name))
+        .unwrap_or(0);
 
-    Dbus::run(proxy, DbusMethod::SetConfig("timezone", &config.timezone)).await?;
+    let variant = ui
+        .select(
+            &fl!("variant"),
+            variants
+                .iter()
+                .map(|x| variant_entry(x, answers.offline_install))
+                .collect(),
+        )
+        .with_help_message(&fl!("variant-help"))
+        .with_starting_cursor(cursor)
+        .raw_prompt()?;
+
+    let variant = variants.swap_remove(variant.index);
+    warn_variant_memory(&variant, answers.memory);
+    answers.secure_boot = release_for(&variant)
+        .ok()
+        .filter(|_| answers.target_dir.is_none())
+        .and_then(|x| secure_boot_for(host_secure_boot(&dk_client.bus), x));
+    if let Some(secure_boot) = answers.secure_boot {
+        Cli.notice(Notice::SecureBoot(secure_boot));
+    }
+    answers.variant = Some(variant);
 
-    Dbus::run(proxy, DbusMethod::SetConfig("hostname", &config.hostname)).await?;
-    Dbus::run(
-        proxy,
-        DbusMethod::SetConfig("rtc_as_localtime", &(config.rtc_as_localtime).to_string()),
-    )
-    .await?;
+    Ok(Asked::Yes)
+}
 
-    let swap_config = if config.swapfile_size == 0.0 {
-        "\"Disable\"".to_string()
-    } else {
-        serde_json::json!({
-            "Custom": (config.swapfile_size * 1024.0 * 1024.0 * 1024.0) as u64
-        })
-        .to_string()
+/// On desktops with an NVIDIA GPU, ask whether to install its driver.
+fn ask_gpu_driver(ui: &Ui, answers: &mut Answers) -> Result<Asked> {
+    let graphics = match answers.variant.as_ref() {
+        Some(variant) if is_desktop(variant) && !is_foreign_machine() => classify(&list_gpus()),
+        _ => Graphics::Other,
+    };
+    let help = match graphics {
+        Graphics::Nvidia => fl!("gpu-driver-nvidia-help"),
+        Graphics::Hybrid => fl!("gpu-driver-hybrid-help"),
+        Graphics::Other => {
+            answers.gpu_driver = Some(false);
+            return Ok(Asked::No);
+        }
     };
 
-    Dbus::run(proxy, DbusMethod::SetConfig("swapfile", &swap_config)).await?;
+    answers.gpu_driver = Some(
+        ui.confirm(&fl!("gpu-driver"))
+            .with_help_message(&help)
+            .with_default(answers.gpu_driver.unwrap_or(true))
+            .prompt()?,
+    );
 
-    let part_config = serde_json::to_string(&config.target_part)?;
+    Ok(Asked::Yes)
+}
 
-    Dbus::run(
-        proxy,
-        DbusMethod::SetConfig("target_partition", &part_config),
+fn low_memory_online(memory: u64) -> String {
+    fl!(
+        "low-memory-online",
+        memory = HumanBytes(memory).to_string(),
+        required = HumanBytes(ONLINE_MIN_MEMORY).to_string()
     )
-    .await?;
+}
 
-    if let Some(efi) = &config.efi_disk {
-        let part_config = serde_json::to_string(&efi)?;
-        Dbus::run(proxy, DbusMethod::SetConfig("efi_partition", &part_config)).await?;
+/// Installing online takes the download to fit in `memory`.
+fn check_online_memory(memory: Option<u64>) -> Result<()> {
+    match memory {
+        Some(memory) if memory < ONLINE_MIN_MEMORY => Err(Failure::new(
+            "low_memory",
+            format!(
+                "{}\n{}",
+                low_memory_online(memory),
+                fl!("low-memory-use-offline")
+            ),
+        )
+        .into()),
+        _ => Ok(()),
     }
+}
 
-    Ok(())
+fn warn_variant_memory(variant: &Variant, memory: Option<u64>) {
+    if let Some(required) = memory_shortfall(variant, memory) {
+        Cli.notice(Notice::LowMemory {
+            variant,
+            memory: memory.unwrap_or_default(),
+            required,
+        });
+    }
 }
 
-fn candidate_sqfs(variant: &Variant) -> Result<&Squashfs> {
-    let mut sqfs = variant
-        .squashfs
-        .iter()
-        .filter(|x| get_arch_name().map(|arch| arch == x.arch).unwrap_or(false))
-        .collect::<Vec<_>>();
-    sqfs.sort_unstable_by(|a, b| b.date.cmp(&a.date));
-    let sqfs = sqfs.first().context(fl!("squashfs-empty"))?;
+/// The directory given with --target-dir, made absolute, if it is one.
+fn check_target_dir(dir: &Path) -> Result<PathBuf> {
+    let canonical = match fs::canonicalize(dir) {
+        Ok(dir) if dir.is_dir() => dir,
+        _ => {
+            return Err(Failure::new(
+                "target_dir",
+                fl!("target-dir-invalid", path = dir.display().to_string()),
+            )
+            .into())
+        }
+    };
+
+    // Unpacking over the running system would wreck it; a bind mount of it
+    // elsewhere is just as much the running system
+    let is_root = |root: fs::Metadata| {
+        canonical
+            .metadata()
+            .is_ok_and(|x| x.dev() == root.dev() && x.ino() == root.ino())
+    };
+    if canonical == Path::new("/") || fs::metadata("/").is_ok_and(is_root) {
+        return Err(Failure::new(
+            "target_dir",
+            fl!("target-dir-root", path = dir.display().to_string()),
+        )
+        .into());
+    }
 
-    Ok(sqfs)
+    Ok(canonical)
 }
 
-// AOSC OS specific architecture mapping for ppc64
-#[cfg(target_arch = "powerpc64")]
-#[inline]
-pub(crate) fn get_arch_name() -> Option<&'static str> {
-    let mut endian: libc::c_int = -1;
-    let result;
-    unsafe {
-        result = libc::prctl(libc::PR_GET_ENDIAN, &mut endian as *mut libc::c_int);
-    }
-    if result < 0 {
-        return None;
-    }
-    match endian {
-        libc::PR_ENDIAN_LITTLE | libc::PR_ENDIAN_PPC_LITTLE => Some("ppc64el"),
-        libc::PR_ENDIAN_BIG => Some("ppc64"),
-        _ => None,
+/// Whether `dir` has nothing in it but what a new file system starts with.
+fn dir_is_empty(dir: &Path) -> Result<bool> {
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        if entry?.file_name() != "lost+found" {
+            return Ok(false);
+        }
     }
+
+    Ok(true)
 }
 
-/// AOSC OS specific architecture mapping table
-#[cfg(not(target_arch = "powerpc64"))]
-#[inline]
-pub(crate) fn get_arch_name() -> Option<&'static str> {
-    use std::env::consts::ARCH;
-    match ARCH {
-        "x86_64" => Some("amd64"),
-        "x86" => Some("i486"),
-        "powerpc" => Some("powerpc"),
-        "aarch64" => Some("arm64"),
-        "mips64" => Some("loongson3"),
-        "riscv64" => Some("riscv64"),
-        "loongarch64" => Some("loongarch64"),
-        _ => None,
+/// Builds without the `online` feature only install the release on the live
+/// media.
+fn check_online(offline_install: bool) -> Result<()> {
+    if !offline_install && !cfg!(feature = "online") {
+        return Err(Failure::new("online_unsupported", fl!("online-unsupported")).into());
     }
+
+    Ok(())
 }
 
-#[test]
-fn test_username() {
-    let full_name = "Mag Mell";
-    let name = get_default_username(&full_name);
-    assert_eq!("magmell", name);
+/// Select entry for `variant`: name, description and what the installation
+/// takes. Only for variants that have a release for this architecture.
+fn variant_entry(variant: &Variant, offline: bool) -> String {
+    let Ok(sqfs) = release_for(variant) else {
+        return variant.name.clone();
+    };
 
-    let full_name = "123test";
-    let name = get_default_username(&full_name);
-    assert_eq!("test", name);
+    let installed = HumanBytes(sqfs.inst_size).to_string();
+    let sizes = if offline {
+        fl!("variant-installed-size", installed = installed)
+    } else {
+        fl!(
+            "variant-sizes",
+            download = HumanBytes(sqfs.download_size).to_string(),
+            installed = installed
+        )
+    };
+
+    match &variant.description {
+        Some(description) => format!("{} - {description} ({sizes})", variant.name),
+        None => format!("{} ({sizes})", variant.name),
+    }
+}
+
+// Position of ask_device among the questions of the disk step
+const DEVICE_QUESTION: usize = 1;
+
+fn inquire_disk(
+    ui: &Ui,
+    runtime: &Handle,
+    dk_client: &DkClient,
+    questions: &mut Questions,
+    answers: &mut Answers,
+) -> Result<()> {
+    let cand = release_for(answers.variant.as_ref().unwrap())?;
+    // Room needed for the installed system, and for the download unless
+    // installing from the live media
+    let min_size = if answers.offline_install {
+        (cand.inst_size as f64 * 1.25) as u64
+    } else {
+        cand.inst_size + cand.download_size
+    };
+
+    let devices =
+        block_on_with_spinner(ui, runtime, fl!("listing-devices"), get_devices(dk_client))?
+            .into_iter()
+            .filter(|x| x.size > min_size)
+            .collect::<Vec<_>>();
+
+    if devices.is_empty() {
+        bail!("{}", fl!("no-device-to-install"));
+    }
+
+    let preflight = |answers: &mut Answers| -> Result<Preflight> {
+        if let Some(preflight) = &answers.preflight {
+            return Ok(preflight.clone());
+        }
+
+        let device = answers.device.clone().unwrap_or_default();
+        let preflight = block_on_with_spinner(
+            ui,
+            runtime,
+            fl!("checking-device", dev = device.as_str()),
+            preflight::run(dk_client, &device, min_size, answers.board.as_ref()),
+        )?;
+        answers.preflight = Some(preflight.clone());
+
+        Ok(preflight)
+    };
+
+    ask_questions(
+        questions,
+        answers,
+        &[
+            &|answers| ask_board(ui, answers),
+            &|answers| ask_device(ui, &devices, answers),
+            &|answers| ask_auto_partition(ui, answers),
+            &|answers| match answers.auto_partition {
+                true => Ok(Asked::No),
+                false => ask_preflight_action(ui, &preflight(answers)?, answers),
+            },
+            &|answers| partition_automatically(ui, runtime, dk_client, answers),
+            &|answers| ask_system_partition(ui, &preflight(answers)?, answers),
+            &|answers| ask_efi_partition(ui, &preflight(answers)?, answers),
+            &|answers| ask_firmware_partition(ui, &preflight(answers)?, answers),
+        ],
+    )?;
+
+    answers.device_failing = disk::smart_failing(
+        &dk_client.bus,
+        answers.device.as_deref().unwrap_or_default(),
+    );
+
+    Ok(())
+}
+
+fn ask_device(ui: &Ui, devices: &[Device], answers: &mut Answers) -> Result<Asked> {
+    ui.println(&fl!("list-of-device"));
+    ui.table(device_table(devices));
+
+    let cursor = answers
+        .device
+        .as_ref()
+        .and_then(|d| devices.iter().position(|x| x.path == *d))
+        .unwrap_or(0);
+
+    let device = ui
+        .select(
+            &fl!("select-device"),
+            devices
+                .iter()
+                .map(|x| x.path.to_string())
+                .collect::<Vec<_>>(),
+        )
+        .with_help_message(&fl!("select-device-help"))
+        .with_starting_cursor(cursor)
+        .prompt()?;
+
+    answers.device = Some(device);
+    // Checked again, if only in case it was partitioned since
+    answers.preflight = None;
+
+    Ok(Asked::Yes)
+}
+
+fn ask_auto_partition(ui: &Ui, answers: &mut Answers) -> Result<Asked> {
+    answers.auto_partition = ui
+        .confirm(&fl!("auto-partiton"))
+        .with_help_message(&fl!("auto-partition-help"))
+        .with_error_message(&fl!("yn-confirm-required"))
+        .prompt()?;
+
+    Ok(Asked::Yes)
+}
+
+/// If the device cannot be installed to as it is, say why and ask whether
+/// to partition it automatically after all, or to pick another one.
+fn ask_preflight_action(ui: &Ui, preflight: &Preflight, answers: &mut Answers) -> Result<Asked> {
+    answers.repartition = false;
+    if preflight.findings.is_empty() {
+        return Ok(Asked::No);
+    }
+
+    for finding in &preflight.findings {
+        warn!("{}", finding.message());
+    }
+
+    let device = answers.device.as_deref().unwrap_or_default();
+    let action = ui
+        .select(
+            &fl!("preflight-action", dev = device),
+            vec![
+                fl!("preflight-auto-partition", dev = device),
+                fl!("preflight-other-device"),
+            ],
+        )
+        .with_help_message(&fl!("preflight-action-help"))
+        .raw_prompt()?;
+
+    if action.index == 0 {
+        answers.repartition = true;
+        return Ok(Asked::Yes);
+    }
+
+    Ok(Asked::Again(DEVICE_QUESTION))
+}
+
+fn ask_system_partition(ui: &Ui, preflight: &Preflight, answers: &mut Answers) -> Result<Asked> {
+    let install_parts = &preflight.partitions;
+    let cursor = previous_cursor(install_parts, answers.partition.as_ref());
+
+    let partition = ui
+        .select(
+            &fl!("select-system-partition"),
+            partition_entries(install_parts),
+        )
+        .with_help_message(&fl!("select-system-partition-help"))
+        .with_starting_cursor(cursor)
+        .raw_prompt()?;
+
+    answers.partition = Some(install_parts[partition.index].clone());
+
+    Ok(Asked::Yes)
+}
+
+fn ask_efi_partition(ui: &Ui, preflight: &Preflight, answers: &mut Answers) -> Result<Asked> {
+    let Some(efi_parts) = &preflight.esps else {
+        answers.efi = None;
+        return Ok(Asked::No);
+    };
+
+    let cursor = previous_cursor(efi_parts, answers.efi.as_ref());
+
+    let efi_part = ui
+        .select(&fl!("select-efi-partition"), partition_entries(efi_parts))
+        .with_help_message(&fl!("select-efi-partition-help"))
+        .with_starting_cursor(cursor)
+        .raw_prompt()?;
+
+    answers.efi = Some(efi_parts[efi_part.index].clone());
+
+    Ok(Asked::Yes)
+}
+
+/// The firmware partition of boards booting from one.
+fn ask_firmware_partition(ui: &Ui, preflight: &Preflight, answers: &mut Answers) -> Result<Asked> {
+    let Some(firmware_parts) = &preflight.firmware_parts else {
+        answers.firmware = None;
+        return Ok(Asked::No);
+    };
+
+    let cursor = previous_cursor(firmware_parts, answers.firmware.as_ref());
+
+    let firmware_part = ui
+        .select(
+            &fl!("select-firmware-partition"),
+            partition_entries(firmware_parts),
+        )
+        .with_help_message(&fl!("select-firmware-partition-help"))
+        .with_starting_cursor(cursor)
+        .raw_prompt()?;
+
+    answers.firmware = Some(firmware_parts[firmware_part.index].clone());
+
+    Ok(Asked::Yes)
+}
+
+/// On arm64 machines with a device tree, ask which single-board computer
+/// this is, suggesting the one detected, for the daemon to lay the disk out
+/// the way its firmware boots from. On LoongArch, ask only if the firmware
+/// generation cannot be told; OpenPOWER machines are only detected. When
+/// installing for another architecture, ask whenever there is a choice.
+fn ask_board(ui: &Ui, answers: &mut Answers) -> Result<Asked> {
+    let arch = get_arch_name().unwrap_or_default();
+    let (detected, undetected_help) = match arch {
+        // Nothing here tells about the machine installed on
+        _ if is_foreign_machine() => {
+            if boards_for(arch).len() < 2 {
+                answers.board = None;
+                return Ok(Asked::No);
+            }
+            (None, fl!("select-board-foreign-help", arch = arch))
+        }
+        "arm64" => {
+            let compatible = read_compatible();
+            if compatible.is_empty() {
+                answers.board = None;
+                return Ok(Asked::No);
+            }
+            let help = fl!(
+                "select-board-undetected-help",
+                compatible = compatible[0].as_str()
+            );
+            (detect_board(&compatible), help)
+        }
+        // Nothing to ask, what the firmware is being known for sure
+        "amd64" => {
+            answers.board = detect_efi_ia32().and_then(board_config);
+            if answers.board.is_some() {
+                warn!("{}", fl!("efi-ia32-detected"));
+            }
+            return Ok(Asked::No);
+        }
+        // Nothing to ask, OPAL firmware being all there is to boot them
+        "ppc64el" | "ppc64" => {
+            answers.board = detect_board(&read_compatible()).and_then(board_config);
+            return Ok(Asked::No);
+        }
+        "loongarch64" => {
+            if let Some(board) = detect_loongarch_firmware() {
+                answers.board = board_config(board);
+                return Ok(Asked::No);
+            }
+            (None, fl!("select-loongarch-firmware-help"))
+        }
+        _ => {
+            answers.board = None;
+            return Ok(Asked::No);
+        }
+    };
+
+    let boards = boards_for(arch);
+    let previous = answers.board.as_ref().map(|x| x.board.as_str());
+    let cursor = previous
+        .or(detected.map(|x| x.id))
+        .or(Some("uefi"))
+        .and_then(|id| boards.iter().position(|x| x.id == id))
+        .unwrap_or(0);
+
+    let help = match detected {
+        Some(board) => fl!("select-board-help", board = board.name),
+        None => undetected_help,
+    };
+
+    let board = ui
+        .select(
+            &fl!("select-board"),
+            boards.iter().map(|x| x.name.to_string()).collect(),
+        )
+        .with_help_message(&help)
+        .with_starting_cursor(cursor)
+        .raw_prompt()?;
+
+    answers.board = board_config(boards[board.index]);
+
+    Ok(Asked::Yes)
+}
+
+/// Warn about what keeps this machine from running or booting the system,
+/// and ask whether to go on regardless unless `unattended`.
+fn check_platform(ui: &Ui, unattended: bool) -> Result<bool> {
+    if get_arch_name() != Some("riscv64") || is_foreign_machine() {
+        return Ok(true);
+    }
+
+    let issues = check_riscv();
+    for issue in &issues {
+        let message = match issue {
+            RiscvIssue::MissingExtensions(missing) => {
+                fl!("riscv-missing-extensions", extensions = missing.join(", "))
+            }
+            RiscvIssue::NoBootEnvironment => fl!("riscv-no-boot-environment"),
+            RiscvIssue::NeedsSpl(board) => fl!("riscv-needs-spl", board = board.to_string()),
+        };
+        warn!("{message}");
+    }
+
+    if issues.is_empty() || unattended {
+        return Ok(true);
+    }
+
+    let go_on = ui
+        .confirm(&fl!("platform-continue-anyway"))
+        .with_default(false)
+        .prompt();
+
+    match go_on {
+        Ok(go_on) => Ok(go_on),
+        Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// If asked to, erase the device and have the daemon partition it, once
+/// the user confirms, which leaves nothing to ask about partitions.
+fn partition_automatically(
+    ui: &Ui,
+    runtime: &Handle,
+    dk_client: &DkClient,
+    answers: &mut Answers,
+) -> Result<Asked> {
+    if !answers.auto_partition && !answers.repartition {
+        return Ok(Asked::No);
+    }
+
+    let device = answers.device.clone().unwrap_or_default();
+    info!("{}", fl!("confirm-autopart"));
+    confirm_destructive(ui, Path::new(&device))?;
+
+    // For the daemon to make room for the boot firmware of the board
+    if let Some(board) = &answers.board {
+        runtime.block_on(set_board(dk_client, board))?;
+    }
+
+    runtime.block_on(Dbus::run(dk_client, DbusMethod::AutoPartition(&device)))?;
+    let (partition, boot) = runtime
+        .block_on(get_auto_partition_progress(ui, dk_client))
+        .with_context(|| fl!("auto-partition-failed", dev = device.as_str()))?;
+
+    // The EFI system partition, or the firmware partition of boards booting
+    // from one
+    let firmware = answers
+        .board
+        .as_ref()
+        .is_some_and(|x| x.has_firmware_partition());
+    answers.partition = Some(partition);
+    (answers.efi, answers.firmware) = match firmware {
+        true => (None, boot),
+        false => (boot, None),
+    };
+    answers.preflight = None;
+
+    Ok(Asked::Done)
+}
+
+/// Things about the installation worth a second look, though none of them
+/// stops it.
+fn install_warnings(config: &InstallConfig, device_failing: bool) -> Vec<String> {
+    let mut warnings = vec![];
+    let target = config.target_part.as_ref();
+
+    if device_failing {
+        let device = target
+            .and_then(|x| x.parent_path.as_ref())
+            .map(|x| x.display().to_string())
+            .unwrap_or_default();
+        warnings.push(fl!("warning-smart-failing", dev = device));
+    }
+
+    if let Some(efi) = &config.efi_disk {
+        if efi.size < MIN_ESP_SIZE {
+            warnings.push(fl!(
+                "warning-small-esp",
+                size = HumanBytes(efi.size).to_string()
+            ));
+        }
+        if efi.parent_path.is_some()
+            && efi.parent_path.as_ref() != target.and_then(|x| x.parent_path.as_ref())
+        {
+            warnings.push(fl!("warning-esp-other-device"));
+        }
+    }
+
+    // How much room a directory has is not known
+    if let (Some(target), Ok(sqfs)) = (target, release_for(&config.variant)) {
+        let swap = (config.swapfile_size * GIB as f64) as u64;
+        let left = target.size.saturating_sub(sqfs.inst_size + swap);
+        if left < MIN_SPACE_LEFT {
+            warnings.push(fl!(
+                "warning-almost-full",
+                left = HumanBytes(left).to_string()
+            ));
+        }
+    }
+
+    warnings
+}
+
+/// What the installation is going to do, as `(label, value)` rows.
+fn install_summary(config: &InstallConfig) -> Vec<(String, String)> {
+    let partition = |p: &DkPartition| {
+        let path = p
+            .path
+            .as_ref()
+            .map(|x| x.display().to_string())
+            .unwrap_or_default();
+        match &p.fs_type {
+            Some(fs) => format!("{path} ({}, {fs})", HumanBytes(p.size)),
+            None => format!("{path} ({})", HumanBytes(p.size)),
+        }
+    };
+
+    let mut rows = vec![(fl!("variant"), config.variant.name.clone())];
+
+    if let Some(target) = &config.target_part {
+        if let Some(disk) = &target.parent_path {
+            rows.push((fl!("select-device"), disk.display().to_string()));
+        }
+
+        rows.push((fl!("select-system-partition"), partition(target)));
+    }
+
+    if let Some(dir) = &config.target_dir {
+        rows.push((fl!("target-dir"), dir.display().to_string()));
+    }
+
+    if let Some(efi) = &config.efi_disk {
+        rows.push((fl!("select-efi-partition"), partition(efi)));
+    }
+
+    if let Some(firmware) = &config.firmware_part {
+        rows.push((fl!("select-firmware-partition"), partition(firmware)));
+    }
+
+    if let Some(board) = &config.board {
+        rows.push((
+            fl!("select-board"),
+            find_board(&board.board).map_or(board.board.clone(), |x| x.name.to_string()),
+        ));
+    }
+
+    if let Some(secure_boot) = config.secure_boot {
+        rows.push((
+            fl!("secure-boot"),
+            match secure_boot {
+                SecureBoot::Shim => fl!("secure-boot-shim"),
+                SecureBoot::Disable => fl!("secure-boot-disable"),
+            },
+        ));
+    }
+
+    rows.push((
+        fl!("username"),
+        match config.fullname.as_deref().filter(|x| !x.is_empty()) {
+            Some(fullname) => format!("{} ({fullname})", config.user),
+            None => config.user.clone(),
+        },
+    ));
+    rows.push((fl!("hostname"), config.hostname.clone()));
+    rows.push((fl!("locale"), config.locale.generate.join(", ")));
+    rows.push((fl!("timezone"), config.timezone.clone()));
+
+    if let Some(keyboard) = &config.keyboard {
+        rows.push((
+            fl!("keymap"),
+            format!("{} / {}", keyboard.console, keyboard.x11),
+        ));
+    }
+
+    rows.push((fl!("swap-size"), format!("{:.2}", config.swapfile_size)));
+
+    rows
+}
+
+/// Position of a previously selected partition among `paths`, if any.
+fn previous_cursor(partitions: &[DkPartition], previous: Option<&DkPartition>) -> usize {
+    previous
+        .and_then(|p| p.path.as_ref())
+        .and_then(|p| partitions.iter().position(|x| x.path.as_ref() == Some(p)))
+        .unwrap_or(0)
+}
+
+/// Select entries for `partitions`: path, size and file system, lined up in
+/// columns.
+fn partition_entries(partitions: &[DkPartition]) -> Vec<String> {
+    let rows = partitions
+        .iter()
+        .map(|x| {
+            (
+                x.path
+                    .as_ref()
+                    .map(|x| x.display().to_string())
+                    .unwrap_or_default(),
+                HumanBytes(x.size).to_string(),
+                x.fs_type.as_deref().unwrap_or("-"),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let path_width = rows.iter().map(|x| x.0.len()).max().unwrap_or(0);
+    let size_width = rows.iter().map(|x| x.1.len()).max().unwrap_or(0);
+
+    rows.into_iter()
+        .map(|(path, size, fs)| format!("{path:<path_width$}  {size:>size_width$}  {fs}"))
+        .collect()
+}
+
+fn inquire_keyboard(
+    ui: &Ui,
+    bus: &Bus,
+    live_defaults: &LiveDefaults,
+    answers: &mut Answers,
+) -> Result<()> {
+    let keyboard = select_keyboard(
+        ui,
+        bus,
+        answers
+            .keyboard
+            .as_ref()
+            .map(|x| x.console.as_str())
+            .or(live_defaults.keymap.as_deref()),
+        answers
+            .keyboard
+            .as_ref()
+            .map(|x| x.x11.as_str())
+            .or(live_defaults.x11_layout.as_deref()),
+    )?;
+    // Not this machine's layout to change otherwise
+    if !matches!(bus, Bus::Remote(_)) {
+        apply_keyboard_layout(&keyboard);
+    }
+
+    answers.keyboard = Some(keyboard);
+
+    Ok(())
+}
+
+/// Ask for the password of the new user, twice.
+fn ask_password(ui: &Ui, password_display: PasswordDisplayMode, help: &str) -> Result<String> {
+    let password = ui
+        .password(&fl!("password"))
+        .with_help_message(help)
+        .with_validator(required!(fl!("password-required")))
+        .with_display_mode(password_display)
+        .with_custom_confirmation_message(&fl!("confirm-password"))
+        .with_custom_confirmation_error_message(&fl!("confirm-password-not-matching"))
+        .prompt()?;
+
+    Ok(password)
+}
+
+fn inquire_user(
+    ui: &Ui,
+    runtime: &Handle,
+    dk_client: &DkClient,
+    password_display: PasswordDisplayMode,
+    questions: &mut Questions,
+    answers: &mut Answers,
+) -> Result<()> {
+    ask_questions(
+        questions,
+        answers,
+        &[
+            &|answers| ask_import_install(ui, runtime, dk_client, answers),
+            &|answers| ask_import_users(ui, answers),
+            &|answers| ask_fullname(ui, answers),
+            &|answers| ask_username(ui, answers),
+            &|answers| {
+                answers.password = Some(ask_password(ui, password_display, &fl!("password-help"))?);
+                Ok(Asked::Yes)
+            },
+            &|answers| ask_autologin(ui, answers),
+            &|answers| ask_avatar(ui, answers),
+            &|answers| ask_advanced_user(ui, answers),
+            &|answers| ask_uid(ui, answers),
+            &|answers| ask_gid(ui, answers),
+        ],
+    )
+}
+
+fn ask_fullname(ui: &Ui, answers: &mut Answers) -> Result<Asked> {
+    let fullname = ui
+        .text(&fl!("fullname"))
+        .with_help_message(&fl!("fullname-help"))
+        .with_default(answers.fullname.as_deref().unwrap_or_default())
+        .with_validator(vaildation_fullname)
+        .prompt()?;
+
+    answers.fullname = Some(normalize_fullname(&fullname));
+
+    Ok(Asked::Yes)
+}
+
+/// Ask for the UNIX username, suggesting one made of the full name.
+fn ask_username(ui: &Ui, answers: &mut Answers) -> Result<Asked> {
+    let default_username = answers
+        .username
+        .clone()
+        .unwrap_or_else(|| get_default_username(answers.fullname.as_deref().unwrap_or_default()));
+
+    let username = ui
+        .text(&fl!("username"))
+        .with_help_message(&fl!("username-help"))
+        .with_validator(required!(fl!("username-required")))
+        .with_validator(validate_username)
+        .with_default(&default_username)
+        .prompt()?;
+
+    answers.username = Some(username);
+
+    Ok(Asked::Yes)
+}
+
+fn ask_autologin(ui: &Ui, answers: &mut Answers) -> Result<Asked> {
+    answers.autologin = ui
+        .confirm(&fl!("autologin"))
+        .with_help_message(&fl!("autologin-help"))
+        .with_default(answers.autologin)
+        .prompt()?;
+
+    Ok(Asked::Yes)
+}
+
+fn ask_avatar(ui: &Ui, answers: &mut Answers) -> Result<Asked> {
+    let previous_avatar = answers
+        .avatar
+        .as_ref()
+        .map(|x| x.display().to_string())
+        .unwrap_or_default();
+
+    let avatar = ui
+        .text(&fl!("avatar"))
+        .with_help_message(&fl!("avatar-help"))
+        .with_default(&previous_avatar)
+        .with_validator(|input: &str| match input.trim() {
+            "" => Ok(Validation::Valid),
+            path => validate_avatar(path),
+        })
+        .prompt()?;
+
+    answers.avatar = Some(avatar.trim())
+        .filter(|x| !x.is_empty())
+        .map(PathBuf::from);
+
+    Ok(Asked::Yes)
+}
+
+/// Ask whether to pick the user and group IDs, which otherwise are those of
+/// the imported user of the same name, if any.
+fn ask_advanced_user(ui: &Ui, answers: &mut Answers) -> Result<Asked> {
+    answers.advanced_user = ui
+        .confirm(&fl!("advanced-user"))
+        .with_help_message(&fl!("advanced-user-help"))
+        .with_default(answers.advanced_user)
+        .prompt()?;
+
+    if !answers.advanced_user {
+        (answers.uid, answers.gid) = answers
+            .existing_user()
+            .map_or((None, None), |x| (Some(x.uid), Some(x.gid)));
+    }
+
+    Ok(Asked::Yes)
+}
+
+fn ask_uid(ui: &Ui, answers: &mut Answers) -> Result<Asked> {
+    if !answers.advanced_user {
+        return Ok(Asked::No);
+    }
+
+    let uid = ui
+        .custom_type::<u32>(&fl!("uid"))
+        .with_help_message(&fl!("uid-help"))
+        .with_default(
+            answers
+                .existing_user()
+                .map(|x| x.uid)
+                .or(answers.uid)
+                .unwrap_or(UID_MIN),
+        )
+        .with_validator(validate_id)
+        .prompt()?;
+
+    answers.uid = Some(uid);
+
+    Ok(Asked::Yes)
+}
+
+fn ask_gid(ui: &Ui, answers: &mut Answers) -> Result<Asked> {
+    if !answers.advanced_user {
+        return Ok(Asked::No);
+    }
+
+    let gid = ui
+        .custom_type::<u32>(&fl!("gid"))
+        .with_help_message(&fl!("gid-help"))
+        .with_default(
+            answers
+                .existing_user()
+                .map(|x| x.gid)
+                .or(answers.gid)
+                .or(answers.uid)
+                .unwrap_or(UID_MIN),
+        )
+        .with_validator(validate_id)
+        .prompt()?;
+
+    answers.gid = Some(gid);
+
+    Ok(Asked::Yes)
+}
+
+fn inquire_system(
+    ui: &Ui,
+    runtime: &Handle,
+    dk_client: &DkClient,
+    live_defaults: &LiveDefaults,
+    questions: &mut Questions,
+    answers: &mut Answers,
+) -> Result<()> {
+    ask_questions(
+        questions,
+        answers,
+        &[
+            &|answers| ask_geoip_timezone(ui, runtime, answers),
+            &|answers| ask_timezone(ui, live_defaults, answers),
+            &|answers| ask_locale(ui, live_defaults, answers),
+            &|answers| ask_extra_locales(ui, answers),
+            &|answers| ask_input_method(ui, answers),
+            &|answers| ask_hostname(ui, answers),
+            &|answers| ask_rtc(ui, answers),
+            &|answers| ask_ntp(ui, answers),
+            &|answers| ask_ntp_servers(ui, answers),
+            // A swap file has no place in a container or a system set up
+            // elsewhere
+            &|answers| match answers.target_dir {
+                Some(_) => {
+                    answers.swapfile_size = Some(0.0);
+                    Ok(Asked::No)
+                }
+                None => ask_swap(ui, runtime, dk_client, answers),
+            },
+        ],
+    )
+}
+
+fn ask_timezone(ui: &Ui, live_defaults: &LiveDefaults, answers: &mut Answers) -> Result<Asked> {
+    let timezones = list_zoneinfo(false)?;
+
+    let suggested_timezone = answers.timezone.clone().or(live_defaults
+        .timezone
+        .clone()
+        .filter(|x| timezones.iter().any(|tz| tz.name() == *x)));
+
+    let timezone = select_timezone(ui, timezones, suggested_timezone.as_deref())?;
+
+    answers.timezone = Some(timezone);
+
+    Ok(Asked::Yes)
+}
+
+/// Offer to look up the time zone by IP address, the first time one is
+/// asked for when installing online.
+#[cfg(feature = "online")]
+fn ask_geoip_timezone(ui: &Ui, runtime: &Handle, answers: &mut Answers) -> Result<Asked> {
+    if answers.offline_install || (answers.timezone.is_some() && !answers.going_back) {
+        return Ok(Asked::No);
+    }
+
+    let detect = ui
+        .confirm(&fl!("geoip-timezone"))
+        .with_help_message(&fl!("geoip-timezone-help"))
+        .with_default(false)
+        .prompt()?;
+
+    if detect {
+        let timezones = list_zoneinfo(false)?;
+        let detected =
+            block_on_with_spinner(ui, runtime, fl!("detecting-timezone"), get_geoip_timezone())
+                .filter(|x| timezones.iter().any(|tz| tz.name() == *x));
+        if detected.is_some() {
+            answers.timezone = detected;
+        }
+    }
+
+    Ok(Asked::Yes)
+}
+
+#[cfg(not(feature = "online"))]
+fn ask_geoip_timezone(_: &Ui, _: &Handle, _: &mut Answers) -> Result<Asked> {
+    Ok(Asked::No)
+}
+
+/// Ask for the system locale, keeping the locales to generate besides it.
+fn ask_locale(ui: &Ui, live_defaults: &LiveDefaults, answers: &mut Answers) -> Result<Asked> {
+    let locales = list_locales()?;
+
+    let position = |l: &str| locales.iter().position(|x| x.data == l);
+    // Failing the live session, the language of where the chosen timezone is
+    let cursor = answers
+        .locale
+        .as_ref()
+        .and_then(|x| position(&x.default))
+        .or_else(|| live_defaults.locale.as_deref().and_then(position))
+        .or_else(|| {
+            let timezone = answers.timezone.as_deref()?;
+            position(&default_locale_for_timezone(&locales, timezone)?.data)
+        })
+        .unwrap_or(0);
+
+    let locale = ui
+        .select(
+            &fl!("locale"),
+            locales
+                .iter()
+                .map(|x| match locale_preview(&x.data) {
+                    Some(preview) => format!("{}  ({preview})", x.text),
+                    None => x.text.clone(),
+                })
+                .collect::<Vec<_>>(),
+        )
+        .with_help_message(&fl!("locale-preview-help"))
+        .with_starting_cursor(cursor)
+        .with_scorer(&|input, _, _, idx| locale_score(input, &locales[idx]))
+        .raw_prompt()?;
+
+    let previous = answers
+        .locale
+        .take()
+        .map(|x| x.generate)
+        .unwrap_or_default();
+    answers.locale = Some(LocaleConfig::new(
+        locales[locale.index].data.clone(),
+        previous,
+    ));
+
+    Ok(Asked::Yes)
+}
+
+/// Ask for additional locales to generate.
+fn ask_extra_locales(ui: &Ui, answers: &mut Answers) -> Result<Asked> {
+    let locales = list_locales()?;
+    let Some(locale) = &answers.locale else {
+        return Ok(Asked::No);
+    };
+
+    let others = locales
+        .iter()
+        .filter(|x| x.data != locale.default)
+        .collect::<Vec<_>>();
+
+    let previous_extra = others
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| locale.generate.contains(&x.data))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    let extra_locales = ui
+        .multi_select(
+            &fl!("extra-locales"),
+            others.iter().map(|x| x.text.clone()).collect::<Vec<_>>(),
+        )
+        .with_help_message(&fl!("extra-locales-help"))
+        .with_default(&previous_extra)
+        .with_scorer(&|input, _, _, idx| locale_score(input, others[idx]))
+        .raw_prompt()?
+        .into_iter()
+        .map(|x| others[x.index].data.clone())
+        .collect::<Vec<_>>();
+
+    answers.locale = Some(LocaleConfig::new(locale.default.clone(), extra_locales));
+
+    Ok(Asked::Yes)
+}
+
+/// Ask whether to install the input methods the locales need, if any.
+fn ask_input_method(ui: &Ui, answers: &mut Answers) -> Result<Asked> {
+    let input_method = answers
+        .locale
+        .as_ref()
+        .map(|x| input_method_packages(&x.generate))
+        .unwrap_or_default();
+
+    if input_method.is_empty() {
+        answers.extra_packages = vec![];
+        return Ok(Asked::No);
+    }
+
+    let install = ui
+        .confirm(&fl!("input-method"))
+        .with_default(answers.input_method.unwrap_or(true))
+        .with_help_message(&input_method.join(" "))
+        .prompt()?;
+
+    answers.input_method = Some(install);
+    answers.extra_packages = match install {
+        true => input_method,
+        false => vec![],
+    };
+
+    Ok(Asked::Yes)
+}
+
+fn ask_hostname(ui: &Ui, answers: &mut Answers) -> Result<Asked> {
+    let hostname = ui
+        .text(&fl!("hostname"))
+        .with_help_message(&fl!("hostname-help"))
+        .with_default(answers.hostname.as_deref().unwrap_or_default())
+        .with_validator(required!(fl!("hostname-required")))
+        .with_validator(validate_hostname)
+        .prompt()?;
+
+    answers.hostname = Some(hostname);
+
+    Ok(Asked::Yes)
+}
+
+/// Ask whether to keep the hardware clock in local time.
+fn ask_rtc(ui: &Ui, answers: &mut Answers) -> Result<Asked> {
+    answers.rtc_as_localtime = ui
+        .confirm(&fl!("rtc-as-localtime"))
+        .with_help_message(&fl!("rtc-as-localtime-help"))
+        .with_default(answers.rtc_as_localtime)
+        .prompt()?;
+
+    Ok(Asked::Yes)
+}
+
+/// Ask whether to synchronize the clock over the network.
+fn ask_ntp(ui: &Ui, answers: &mut Answers) -> Result<Asked> {
+    let enable = ui
+        .confirm(&fl!("ntp"))
+        .with_help_message(&fl!("ntp-help"))
+        .with_default(answers.ntp.as_ref().is_none_or(|x| x.enable))
+        .prompt()?;
+
+    let servers = answers
+        .ntp
+        .take()
+        .filter(|_| enable)
+        .map(|x| x.servers)
+        .unwrap_or_default();
+    answers.ntp = Some(NtpConfig { enable, servers });
+
+    Ok(Asked::Yes)
+}
+
+/// Ask which NTP servers to use, if any besides the default ones.
+fn ask_ntp_servers(ui: &Ui, answers: &mut Answers) -> Result<Asked> {
+    let Some(ntp) = answers.ntp.as_mut().filter(|x| x.enable) else {
+        return Ok(Asked::No);
+    };
+
+    ntp.servers = ui
+        .text(&fl!("ntp-servers"))
+        .with_help_message(&fl!("ntp-servers-help"))
+        .with_default(&ntp.servers.join(" "))
+        .with_validator(|input: &str| {
+            for i in input.split_whitespace() {
+                if let Validation::Invalid(e) = validate_ntp_server(i)? {
+                    return Ok(Validation::Invalid(e));
+                }
+            }
+
+            Ok(Validation::Valid)
+        })
+        .prompt()?
+        .split_whitespace()
+        .map(|x| x.to_string())
+        .collect();
+
+    Ok(Asked::Yes)
+}
+
+fn ask_swap(
+    ui: &Ui,
+    runtime: &Handle,
+    dk_client: &DkClient,
+    answers: &mut Answers,
+) -> Result<Asked> {
+    let cand = release_for(answers.variant.as_ref().unwrap())?;
+    let partition = answers.partition.as_ref().unwrap();
+
+    let mut recommend_swap_file_size = runtime
+        .block_on(Dbus::run(dk_client, DbusMethod::GetRecommendSwapSize))?
+        .data
+        .as_f64()
+        .unwrap_or(0.0);
+
+    if recommend_swap_file_size > 32.0 * 1024.0 * 1024.0 * 1024.0 {
+        recommend_swap_file_size = 32.0 * 1024.0 * 1024.0 * 1024.0;
+    }
+
+    if answers.offline_install {
+        let size = recommend_swap_file_size + cand.inst_size as f64 * 1.25;
+        if (partition.size as f64) < size {
+            recommend_swap_file_size =
+                (recommend_swap_file_size - (partition.size as f64 - size)) / 1.25;
+        }
+    } else {
+        let size = recommend_swap_file_size + cand.inst_size as f64 + cand.download_size as f64;
+        if (partition.size as f64) < size {
+            recommend_swap_file_size =
+                (recommend_swap_file_size - (partition.size as f64 - size)) / 1.25;
+        }
+    }
+
+    let swap_size = ui
+        .custom_type::<f64>(&fl!("swap-size"))
+        .with_help_message(&fl!("swap-size-help"))
+        .with_default(answers.swapfile_size.unwrap_or_else(|| {
+            format!("{:.2}", recommend_swap_file_size / 1024.0 / 1024.0 / 1024.0)
+                .parse::<f64>()
+                .unwrap()
+        }))
+        .prompt()?;
+
+    answers.swapfile_size = Some(swap_size);
+
+    Ok(Asked::Yes)
+}
+
+/// Answers that can be changed from the summary, each asked again on its
+/// own.
+#[derive(Debug, Clone, Copy)]
+enum Edit {
+    Source,
+    Disk,
+    User,
+    Hostname,
+    Locale,
+    Timezone,
+    Keyboard,
+    Swap,
+}
+
+impl Edit {
+    const ALL: [Edit; 8] = [
+        Edit::Source,
+        Edit::Disk,
+        Edit::User,
+        Edit::Hostname,
+        Edit::Locale,
+        Edit::Timezone,
+        Edit::Keyboard,
+        Edit::Swap,
+    ];
+
+    fn label(self) -> String {
+        match self {
+            Edit::Source => fl!("variant"),
+            Edit::Disk => fl!("step-disk"),
+            Edit::User => fl!("username"),
+            Edit::Hostname => fl!("hostname"),
+            Edit::Locale => fl!("locale"),
+            Edit::Timezone => fl!("timezone"),
+            Edit::Keyboard => fl!("step-keyboard"),
+            Edit::Swap => fl!("swap-size"),
+        }
+    }
+}
+
+/// Show what is about to be done, and either start or change some answer
+/// and show it again.
+fn inquire_confirm(
+    ui: &Ui,
+    runtime: &Handle,
+    dk_client: &DkClient,
+    live_defaults: &LiveDefaults,
+    answers: &mut Answers,
+) -> Result<()> {
+    loop {
+        let config = answers.to_install_config();
+        ui.summary(&fl!("summary"), &install_summary(&config));
+        let warnings = install_warnings(&config, answers.device_failing);
+        if !warnings.is_empty() {
+            ui.println(&fl!("warnings", count = warnings.len()));
+            for warning in &warnings {
+                ui.println(&format!("  - {warning}"));
+            }
+            ui.println("");
+        }
+
+        // There is no disk to pick or swap file to size for a directory
+        let edits = Edit::ALL
+            .into_iter()
+            .filter(|x| answers.target_dir.is_none() || !matches!(x, Edit::Disk | Edit::Swap))
+            .collect::<Vec<_>>();
+
+        let mut options = vec![fl!("start-install")];
+        options.extend(edits.iter().map(|x| fl!("edit-answer", field = x.label())));
+
+        let choice = ui
+            .select(&fl!("summary-action"), options)
+            .with_help_message(&fl!("summary-action-help"))
+            .raw_prompt()?;
+
+        let res = match choice.index.checked_sub(1).map(|i| edits[i]) {
+            None => {
+                info!("{}", fl!("confirm"));
+                match (
+                    &config.target_dir,
+                    config.target_part.as_ref().and_then(|x| x.path.as_ref()),
+                ) {
+                    (Some(dir), _) if answers.target_dir_in_use => confirm_target_dir(ui, dir),
+                    (None, Some(path)) => confirm_destructive(ui, path).map(|_| true),
+                    _ => Ok(true),
+                }
+            }
+            Some(edit) => {
+                // Put back on Esc, rather than left half changed
+                let before = answers.clone();
+                let res = edit_answer(ui, runtime, dk_client, live_defaults, answers, edit);
+                if res.as_ref().is_err_and(is_canceled) {
+                    *answers = before;
+                }
+                res.map(|_| false)
+            }
+        };
+
+        match res {
+            Ok(true) => return Ok(()),
+            Ok(false) => {}
+            // Esc returns to the summary, leaving the answer as it was
+            Err(e) if is_canceled(&e) => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn edit_answer(
+    ui: &Ui,
+    runtime: &Handle,
+    dk_client: &DkClient,
+    live_defaults: &LiveDefaults,
+    answers: &mut Answers,
+    edit: Edit,
+) -> Result<()> {
+    // Asked on their own, Esc on the first going back to the summary
+    let questions = &mut Questions::default();
+    match edit {
+        // How much space is needed depends on the edition
+        Edit::Source => {
+            inquire_source(ui, runtime, dk_client, questions, answers)?;
+            match answers.target_dir {
+                Some(_) => Ok(()),
+                None => inquire_disk(ui, runtime, dk_client, &mut Questions::default(), answers),
+            }
+        }
+        Edit::Disk => inquire_disk(ui, runtime, dk_client, questions, answers),
+        Edit::User => {
+            // The user keeps the IDs of the imported user it was recreated
+            // as, which is not imported under another name then
+            let username = answers.username.clone();
+            answers
+                .import_users
+                .retain(|x| Some(&x.username) != username.as_ref());
+            ask_questions(
+                questions,
+                answers,
+                &[&|answers| ask_fullname(ui, answers), &|answers| {
+                    ask_username(ui, answers)
+                }],
+            )
+        }
+        Edit::Hostname => ask_hostname(ui, answers).map(drop),
+        Edit::Locale => ask_questions(
+            questions,
+            answers,
+            &[
+                &|answers| ask_locale(ui, live_defaults, answers),
+                &|answers| ask_extra_locales(ui, answers),
+                &|answers| ask_input_method(ui, answers),
+            ],
+        ),
+        Edit::Timezone => ask_questions(
+            questions,
+            answers,
+            &[
+                &|answers| ask_geoip_timezone(ui, runtime, answers),
+                &|answers| ask_timezone(ui, live_defaults, answers),
+            ],
+        ),
+        Edit::Keyboard => inquire_keyboard(ui, &dk_client.bus, live_defaults, answers),
+        Edit::Swap => ask_swap(ui, runtime, dk_client, answers).map(drop),
+    }
+}
+
+/// Make the user type the name of the device about to be wiped (e.g. `sda`
+/// for `/dev/sda`), rather than just answering y/n.
+fn confirm_destructive(ui: &Ui, device: &Path) -> Result<()> {
+    let name = device
+        .file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_else(|| device.display().to_string());
+
+    let expected = name.clone();
+    ui.text(&fl!("type-to-confirm", name = name))
+        .with_validator(move |input: &str| {
+            if input.trim() == expected {
+                Ok(Validation::Valid)
+            } else {
+                Ok(Validation::Invalid(
+                    fl!("type-to-confirm-mismatch", name = expected.clone()).into(),
+                ))
+            }
+        })
+        .prompt()?;
+
+    Ok(())
+}
+
+/// Ask before installing into a directory that has files in it, which may
+/// be overwritten. Returns whether to go ahead.
+fn confirm_target_dir(ui: &Ui, dir: &Path) -> Result<bool> {
+    let proceed = ui
+        .confirm(&fl!(
+            "target-dir-not-empty-continue",
+            path = dir.display().to_string()
+        ))
+        .with_default(false)
+        .prompt()?;
+
+    Ok(proceed)
+}
+
+/// Look for existing Linux installations and offer to carry over their
+/// regular users (names and IDs only), so that file ownership on a preserved
+/// /home stays consistent. This asks which installation to carry them over
+/// from.
+fn ask_import_install(
+    ui: &Ui,
+    runtime: &Handle,
+    dk_client: &DkClient,
+    answers: &mut Answers,
+) -> Result<Asked> {
+    answers.import_from = None;
+    // The devices the daemon lists are not those of this machine otherwise
+    if !answers.local_disks {
+        return Ok(Asked::No);
+    }
+
+    let installs = block_on_with_spinner(
+        ui,
+        runtime,
+        fl!("searching-installations"),
+        disk::find_installs(dk_client),
+    )?;
+
+    if installs.is_empty() {
+        return Ok(Asked::No);
+    }
+
+    let skip = fl!("import-users-skip");
+    let mut options = installs
+        .iter()
+        .map(|(path, _)| path.display().to_string())
+        .collect::<Vec<_>>();
+    options.push(skip);
+
+    let install = ui
+        .select(&fl!("import-users"), options)
+        .with_help_message(&fl!("import-users-help"))
+        .raw_prompt()?;
+    answers.import_from = installs.into_iter().nth(install.index);
+
+    Ok(Asked::Yes)
+}
+
+/// Ask which users of the installation chosen to carry over, all of them
+/// unless picked before.
+fn ask_import_users(ui: &Ui, answers: &mut Answers) -> Result<Asked> {
+    let Some((_, users)) = &answers.import_from else {
+        answers.import_users = vec![];
+        return Ok(Asked::No);
+    };
+
+    let previous = (0..users.len())
+        .filter(|i| answers.import_users.contains(&users[*i]))
+        .collect::<Vec<_>>();
+    let default = match previous.is_empty() {
+        true => (0..users.len()).collect(),
+        false => previous,
+    };
+
+    let selected = ui
+        .multi_select(
+            &fl!("import-users-select"),
+            users
+                .iter()
+                .map(|x| format!("{} (UID {}, GID {})", x.username, x.uid, x.gid))
+                .collect::<Vec<_>>(),
+        )
+        .with_help_message(&fl!("import-users-select-help"))
+        .with_default(&default)
+        .raw_prompt()?
+        .into_iter()
+        .map(|x| users[x.index].clone())
+        .collect();
+
+    answers.import_users = selected;
+
+    Ok(Asked::Yes)
+}
+
+/// Pick the console and X11 layouts of those the machine being installed
+/// has, starting at the given ones (or `us`).
+fn select_keyboard(
+    ui: &Ui,
+    bus: &Bus,
+    keymap: Option<&str>,
+    x11_layout: Option<&str>,
+) -> Result<Keyboard> {
+    let keymaps = host_keymaps(bus)?;
+    let keymap = keymap.unwrap_or("us");
+    let cursor = keymaps.iter().position(|x| x == keymap).unwrap_or(0);
+
+    let console = ui
+        .select(&fl!("keymap"), keymaps)
+        .with_help_message(&fl!("keymap-help"))
+        .with_starting_cursor(cursor)
+        .prompt()?;
+
+    let layouts = host_xkb_layouts(bus)?;
+    let layout = x11_layout.unwrap_or("us");
+    let cursor = layouts.iter().position(|(x, _)| x == layout).unwrap_or(0);
+
+    let x11 = ui
+        .select(
+            &fl!("x11-layout"),
+            layouts
+                .iter()
+                .map(|(name, desc)| format!("{name} - {desc}"))
+                .collect::<Vec<_>>(),
+        )
+        .with_help_message(&fl!("x11-layout-help"))
+        .with_starting_cursor(cursor)
+        .raw_prompt()?;
+
+    let x11 = layouts[x11.index].0.clone();
+
+    Ok(Keyboard { console, x11 })
+}
+
+/// Switch the live session to the selected layout, so that the following
+/// prompts (passwords in particular) are typed with it.
+fn apply_keyboard_layout(keyboard: &Keyboard) {
+    match Command::new("loadkeys").arg(&keyboard.console).output() {
+        Ok(out) if out.status.success() => {}
+        Ok(out) => debug!(
+            "loadkeys failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ),
+        Err(e) => debug!("Failed to run loadkeys: {e}"),
+    }
+
+    if std::env::var_os("DISPLAY").is_some() {
+        if let Err(e) = Command::new("setxkbmap").arg(&keyboard.x11).status() {
+            warn!("{}", fl!("setxkbmap-failed", e = e.to_string()));
+        }
+    }
+}
+
+/// Pick a timezone in two steps (region, then city), with an escape hatch
+/// to a searchable flat list of all timezones. `suggested` is pre-selected
+/// in both steps when given.
+fn select_timezone(ui: &Ui, timezones: Vec<Zone>, suggested: Option<&str>) -> Result<String> {
+    let search = fl!("timezone-search");
+    let suggested = suggested.and_then(Zone::parse);
+
+    let mut regions = timezones
+        .iter()
+        .map(|x| x.region.clone())
+        .collect::<Vec<_>>();
+    regions.sort_unstable();
+    regions.dedup();
+
+    let cursor = suggested
+        .as_ref()
+        .or(timezones.first())
+        .and_then(|zone| regions.iter().position(|x| *x == zone.region))
+        .unwrap_or(0);
+
+    regions.push(search.clone());
+
+    let region = ui
+        .select(&fl!("timezone-region"), regions)
+        .with_help_message(&fl!("timezone-region-help"))
+        .with_starting_cursor(cursor)
+        .prompt()?;
+
+    if region == search {
+        let timezone = ui
+            .select(
+                &fl!("timezone"),
+                timezones.iter().map(|x| x.name()).collect(),
+            )
+            .with_help_message(&fl!("timezone-help"))
+            .with_scorer(&|input, tz, _, _| timezone_score(input, tz))
+            .prompt()?;
+
+        return Ok(timezone);
+    }
+
+    let preferred = suggested
+        .filter(|x| x.region == region)
+        .or_else(|| timezones.iter().find(|x| x.region == region).cloned());
+
+    let mut cities = timezones
+        .into_iter()
+        .filter(|x| x.region == region)
+        .collect::<Vec<_>>();
+    cities.sort_unstable();
+
+    let cursor = preferred
+        .and_then(|p| cities.iter().position(|x| *x == p))
+        .unwrap_or(0);
+
+    let city = ui
+        .select(
+            &fl!("timezone-city"),
+            cities
+                .iter()
+                .map(|x| x.city.replace('_', " "))
+                .collect::<Vec<_>>(),
+        )
+        .with_help_message(&fl!("timezone-help"))
+        .with_starting_cursor(cursor)
+        .with_scorer(&|input, _, _, idx| timezone_score(input, &cities[idx].name()))
+        .raw_prompt()?;
+
+    Ok(cities[city.index].name())
+}
+
+fn get_default_username(fullname: &str) -> String {
+    let mut default_username = String::new();
+    let mut not_a_number = false;
+
+    // Transliterate first, so that e.g. CJK names still yield a suggestion
+    for c in deunicode::deunicode(fullname).chars() {
+        if c.is_ascii_digit() && !not_a_number {
+            continue;
+        }
+
+        if !c.is_ascii_alphabetic() && !c.is_ascii_digit() {
+            continue;
+        }
+
+        default_username.push(c.to_ascii_lowercase());
+        not_a_number = true;
+    }
+
+    default_username
+}
+
+/// Format the current date and time, a sample number and the currency symbol
+/// the way `locale` would, or `None` if the locale is not available on the
+/// live system.
+fn locale_preview(locale: &str) -> Option<String> {
+    let name = CString::new(locale).ok()?;
+
+    // SAFETY: `loc` is checked for NULL and freed before returning; strings
+    // returned by nl_langinfo_l are copied before the locale is freed.
+    unsafe {
+        let loc = libc::newlocale(libc::LC_ALL_MASK, name.as_ptr(), std::ptr::null_mut());
+        if loc.is_null() {
+            return None;
+        }
+
+        let langinfo = |item| {
+            CStr::from_ptr(libc::nl_langinfo_l(item, loc))
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        let radix = langinfo(libc::RADIXCHAR);
+        let thousands = langinfo(libc::THOUSEP);
+        let currency = langinfo(libc::CRNCYSTR);
+
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm = std::mem::zeroed();
+        libc::localtime_r(&now, &mut tm);
+
+        let mut buf = [0u8; 128];
+        let len = libc::strftime_l(
+            buf.as_mut_ptr() as *mut libc::c_char,
+            buf.len(),
+            c"%x %X".as_ptr(),
+            &tm,
+            loc,
+        );
+
+        libc::freelocale(loc);
+
+        let datetime = String::from_utf8_lossy(&buf[..len]);
+        let number = format!("{}{radix}50", group_digits("1234567", &thousands));
+        // The first character of CRNCYSTR tells where the symbol goes
+        let currency = currency.get(1..).unwrap_or_default();
+
+        let sep = if ui::ascii() { "|" } else { "·" };
+        Some(format!("{datetime} {sep} {number} {sep} {currency}"))
+    }
+}
+
+fn group_digits(digits: &str, separator: &str) -> String {
+    let mut res = String::new();
+
+    for (i, c) in digits.chars().enumerate() {
+        if i != 0 && (digits.len() - i).is_multiple_of(3) {
+            res.push_str(separator);
+        }
+        res.push(c);
+    }
+
+    res
+}
+
+/// Best fuzzy match score of `input` against any of the given candidates.
+fn fuzzy_score<'a>(input: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<i64> {
+    candidates
+        .into_iter()
+        .filter_map(|x| FUZZY_MATCHER.fuzzy_match(x, input))
+        .max()
+}
+
+/// Match a locale by its native name, English name or code (`zh-CN`,
+/// `zh_CN.UTF-8`), ranking an exact code match above everything else.
+fn locale_score(input: &str, locale: &Locale) -> Option<i64> {
+    let score = fuzzy_score(
+        input,
+        [
+            locale.text.as_str(),
+            locale.lang_english.as_str(),
+            locale.id.as_str(),
+            locale.data.as_str(),
+        ],
+    )?;
+
+    let code = input.replace('-', "_");
+    let is_code = locale.id.replace('-', "_").eq_ignore_ascii_case(&code)
+        || locale
+            .data
+            .split('.')
+            .next()
+            .is_some_and(|x| x.eq_ignore_ascii_case(&code));
+
+    Some(if is_code { score + 1000 } else { score })
+}
+
+/// Match a timezone by its full name or city, treating underscores as spaces
+/// (so `new york` finds `America/New_York`).
+fn timezone_score(input: &str, tz: &str) -> Option<i64> {
+    let readable = tz.replace('_', " ");
+    let city = readable.rsplit('/').next().unwrap_or(&readable);
+
+    fuzzy_score(input, [tz, readable.as_str(), city])
+}
+
+fn validate_hostname(input: &str) -> std::result::Result<Validation, Box<dyn Error + Send + Sync>> {
+    Ok(match validate::hostname(input) {
+        Ok(()) => Validation::Valid,
+        Err(e) => Validation::Invalid(hostname_error(e).into()),
+    })
+}
+
+fn hostname_error(e: HostnameError) -> String {
+    match e {
+        HostnameError::Empty => fl!("hostname-required"),
+        HostnameError::TooLong => fl!("hostname-illegal-too-loong"),
+        HostnameError::StartsWith(c) => fl!("hostname-illegal-starts-with", c = c.to_string()),
+        HostnameError::EndsWith(c) => fl!("hostname-illegal-ends-with", c = c.to_string()),
+        HostnameError::DoubleDot => fl!("hostname-illegal-double-dot"),
+        HostnameError::Illegal(c) => fl!("hostname-illegal", c = c.to_string()),
+    }
+}
+
+fn validate_ntp_server(
+    input: &str,
+) -> std::result::Result<Validation, Box<dyn Error + Send + Sync>> {
+    Ok(match validate::ntp_server(input) {
+        Ok(()) => Validation::Valid,
+        Err(e) => Validation::Invalid(hostname_error(e).into()),
+    })
+}
+
+fn validate_username(input: &str) -> std::result::Result<Validation, Box<dyn Error + Send + Sync>> {
+    Ok(match validate::username(input) {
+        Ok(()) => Validation::Valid,
+        Err(e) => Validation::Invalid(username_error(e).into()),
+    })
+}
+
+fn username_error(e: UsernameError) -> String {
+    match e {
+        UsernameError::Empty => fl!("username-required"),
+        UsernameError::TooLong => fl!("username-too-long", max = validate::USERNAME_MAX),
+        UsernameError::StartsWith(c) => fl!("username-illegal-starts-with", c = c.to_string()),
+        UsernameError::Illegal(c) => fl!("username-illegal", c = c.to_string()),
+    }
+}
+
+fn validate_id(input: &u32) -> std::result::Result<Validation, Box<dyn Error + Send + Sync>> {
+    if !validate::id(*input) {
+        return Ok(Validation::Invalid(
+            fl!("id-out-of-range", min = UID_MIN, max = UID_MAX).into(),
+        ));
+    }
+
+    Ok(Validation::Valid)
+}
+
+fn validate_avatar(input: &str) -> std::result::Result<Validation, Box<dyn Error + Send + Sync>> {
+    Ok(match validate::avatar(Path::new(input)) {
+        Ok(()) => Validation::Valid,
+        Err(e) => Validation::Invalid(avatar_error(e).into()),
+    })
+}
+
+fn avatar_error(e: AvatarError) -> String {
+    match e {
+        AvatarError::NotFound => fl!("avatar-not-found"),
+        AvatarError::TooLarge => fl!("avatar-too-large"),
+        AvatarError::NotImage => fl!("avatar-not-image"),
+    }
+}
+
+fn vaildation_fullname(
+    input: &str,
+) -> std::result::Result<Validation, Box<dyn Error + Send + Sync>> {
+    Ok(match validate::fullname(input) {
+        Ok(()) => Validation::Valid,
+        Err(c) => Validation::Invalid(fullname_error(c).into()),
+    })
+}
+
+fn fullname_error(c: char) -> String {
+    fl!("fullname-illegal", c = format!("{c:?}"))
+}
+
+async fn get_auto_partition_progress(
+    ui: &Ui,
+    dk_client: &DkClient,
+) -> Result<(DkPartition, Option<DkPartition>)> {
+    let mut pb = ui.spinner(fl!("auto-partition-pending"));
+    let mut working = false;
+
+    let res = loop {
+        let data = match Dbus::run(dk_client, DbusMethod::GetAutoPartitionProgress)
+            .await
+            .and_then(|x| Ok(serde_json::from_value::<AutoPartitionProgress>(x.data)?))
+        {
+            Ok(data) => data,
+            Err(e) => break Err(e),
+        };
+
+        match data {
+            AutoPartitionProgress::Finish { res: Err(e) } => {
+                break Err(DaemonError::from(e).into());
+            }
+            AutoPartitionProgress::Finish { res: Ok(value) } => {
+                break serde_json::from_value::<(Option<DkPartition>, DkPartition)>(value)
+                    .map(|(efi, p)| (p, efi))
+                    .map_err(|e| e.into());
+            }
+            // Told apart so that a daemon stuck waiting for the device
+            // does not look like partitioning taking long
+            AutoPartitionProgress::Working if !working => {
+                working = true;
+                pb.finish_and_clear();
+                pb = ui.spinner(fl!("auto-partition-working"));
+            }
+            AutoPartitionProgress::Working | AutoPartitionProgress::Pending => {}
+        }
+
+        sleep(Duration::from_millis(100)).await;
+    };
+
+    pb.finish_and_clear();
+    res
+}
+
+/// Run `fut` to completion behind a spinner showing `message`.
+fn block_on_with_spinner<T>(
+    ui: &Ui,
+    runtime: &Handle,
+    message: String,
+    fut: impl Future<Output = T>,
+) -> T {
+    let pb = ui.spinner(message);
+    let res = runtime.block_on(fut);
+    pb.finish_and_clear();
+
+    res
+}
+
+#[cfg(feature = "online")]
+async fn get_geoip_timezone() -> Option<String> {
+    let resp = async {
+        let client = ClientBuilder::new()
+            .user_agent("deploykit")
+            .timeout(Duration::from_secs(5))
+            .build()?;
+
+        client
+            .get(GEOIP_TIMEZONE_URL)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await
+    }
+    .await;
+
+    match resp {
+        Ok(tz) => Some(tz.trim().to_string()),
+        Err(e) => {
+            debug!("Failed to detect timezone: {e}");
+            None
+        }
+    }
+}
+
+/// Print all storage devices and their partitions, for `dkcli list-devices`.
+async fn list_devices(dk_client: &DkClient) -> Result<()> {
+    let devices = get_devices(dk_client).await?;
+
+    println!("{}", device_table(&devices));
+
+    for device in &devices {
+        let partitions = get_partitions(dk_client, &device.path).await?;
+        if !partitions.is_empty() {
+            println!("\n{}", partition_table(&partitions));
+        }
+    }
+
+    Ok(())
+}
+
+fn table_header(table: &mut Table, labels: Vec<String>) {
+    table
+        .load_preset(if ui::ascii() {
+            ASCII_FULL_CONDENSED
+        } else {
+            UTF8_FULL_CONDENSED
+        })
+        // Wrap long models instead of overflowing narrow terminals
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(
+            labels
+                .into_iter()
+                .map(|x| Cell::new(x).add_attribute(Attribute::Bold)),
+        );
+}
+
+fn device_table(devices: &[Device]) -> Table {
+    let mut table = Table::new();
+    table_header(
+        &mut table,
+        vec![
+            fl!("device-model"),
+            fl!("device-path"),
+            fl!("device-size"),
+            fl!("device-bus"),
+            fl!("device-removable"),
+        ],
+    );
+
+    for device in devices {
+        let removable = disk::is_removable(&device.path);
+
+        table.add_row(vec![
+            Cell::new(&device.model),
+            Cell::new(&device.path).fg(Color::Cyan),
+            Cell::new(HumanBytes(device.size)).set_alignment(CellAlignment::Right),
+            Cell::new(disk::bus(&device.path).unwrap_or("-")),
+            if removable {
+                Cell::new(fl!("yes")).fg(Color::Yellow)
+            } else {
+                Cell::new(fl!("no"))
+            },
+        ]);
+    }
+
+    table
+}
+
+fn partition_table(partitions: &[DkPartition]) -> Table {
+    let mut table = Table::new();
+    table_header(
+        &mut table,
+        vec![fl!("device-path"), fl!("device-size"), fl!("partition-fs")],
+    );
+
+    for part in partitions {
+        table.add_row(vec![
+            Cell::new(
+                part.path
+                    .as_ref()
+                    .map(|x| x.display().to_string())
+                    .unwrap_or_default(),
+            )
+            .fg(Color::Cyan),
+            Cell::new(HumanBytes(part.size)).set_alignment(CellAlignment::Right),
+            Cell::new(part.fs_type.as_deref().unwrap_or("-")),
+        ]);
+    }
+
+    table
+}
+
+/// Settings and installation status a fresh daemon would not have, as
+/// `(label, value)` rows.
+async fn leftover_state(
+    dk_client: &DkClient,
+    progress: &ProgressStatus,
+) -> Result<Vec<(String, String)>> {
+    let mut rows = vec![];
+
+    match progress {
+        ProgressStatus::Error(e) => rows.push((
+            fl!("leftover-progress"),
+            fl!(
+                "leftover-progress-failed",
+                e = DaemonError::from(e.clone()).describe()
+            ),
+        )),
+        ProgressStatus::Finish => {
+            rows.push((fl!("leftover-progress"), fl!("leftover-progress-finished")))
+        }
+        ProgressStatus::Pending | ProgressStatus::Working { .. } => {}
+    }
+
+    for (field, value) in client::leftover_config(dk_client).await? {
+        rows.push((field.to_string(), redact::value(value).to_string()));
+    }
+
+    Ok(rows)
+}
+
+/// Read back every setting sent to the daemon and show what it recorded,
+/// marking those that differ from what was sent. Returns whether all of
+/// them match.
+async fn check_config(ui: &Ui, dk_client: &DkClient) -> Result<bool> {
+    let settings = client::read_back_config(dk_client).await?;
+    let matches = settings.iter().all(RecordedConfig::matches);
+
+    let rows = settings
+        .into_iter()
+        .map(|x| {
+            let value = match x.recorded {
+                Some(recorded) if recorded == x.sent => redact::value(recorded).to_string(),
+                Some(recorded) => fl!(
+                    "config-differs",
+                    recorded = redact::value(recorded).to_string(),
+                    sent = redact::value(x.sent).to_string()
+                ),
+                None => fl!("config-not-recorded"),
+            };
+
+            (x.field, value)
+        })
+        .collect::<Vec<_>>();
+
+    ui.summary(&fl!("daemon-config"), &rows);
+
+    Ok(matches)
+}
+
+#[test]
+fn test_username() {
+    let full_name = "Mag Mell";
+    let name = get_default_username(full_name);
+    assert_eq!("magmell", name);
+
+    let full_name = "123test";
+    let name = get_default_username(full_name);
+    assert_eq!("test", name);
 
     let full_name = "123 45";
-    let name = get_default_username(&full_name);
+    let name = get_default_username(full_name);
     assert_eq!("", name);
 
     let full_name = "cth451";
-    let name = get_default_username(&full_name);
+    let name = get_default_username(full_name);
     assert_eq!("cth451", name);
+
+    let full_name = "白铭骢";
+    let name = get_default_username(full_name);
+    assert_eq!("baimingcong", name);
+
+    let full_name = "Zoë Ångström";
+    let name = get_default_username(full_name);
+    assert_eq!("zoeangstrom", name);
 }
 
 #[test]
-fn test_hostname_validation() {
-    assert_eq!(validate_hostname("foo").unwrap(), Validation::Valid);
-    assert_eq!(validate_hostname("foo-2e10").unwrap(), Validation::Valid);
+fn test_install_step_name() {
+    assert_eq!(install_step_name(1), fl!("formatting-partition"));
     assert_eq!(
-        validate_hostname("jeffbai-device").unwrap(),
-        Validation::Valid
+        install_step_name(INSTALL_STEPS),
+        fl!("finalizing-installation")
     );
-    assert!(matches!(
-        validate_hostname("invalid_host").unwrap(),
-        Validation::Invalid(..)
-    ));
-    assert!(matches!(
-        validate_hostname("-invalid").unwrap(),
-        Validation::Invalid(..)
-    ));
-    assert!(matches!(
-        validate_hostname("+invalid").unwrap(),
-        Validation::Invalid(..)
-    ));
-    assert!(matches!(
-        validate_hostname("JellyDimension").unwrap(),
-        Validation::Valid
-    ));
-    assert!(matches!(
-        validate_hostname("Jelly_Dimension").unwrap(),
-        Validation::Invalid(..)
-    ));
-    assert_eq!(validate_hostname("AOSC.OS").unwrap(), Validation::Valid);
-    assert!(matches!(
-        validate_hostname(&"a".repeat(65)).unwrap(),
-        Validation::Invalid(..)
-    ));
-    assert!(matches!(
-        validate_hostname("a..b").unwrap(),
-        Validation::Invalid(..)
-    ));
-    assert!(matches!(
-        validate_hostname("abc.").unwrap(),
-        Validation::Invalid(..)
-    ));
-    assert!(matches!(
-        validate_hostname(".abc").unwrap(),
-        Validation::Invalid(..)
-    ));
-    assert!(matches!(
-        validate_hostname("a.b.c").unwrap(),
-        Validation::Valid
-    ));
-    assert!(matches!(
-        validate_hostname(".abc.").unwrap(),
-        Validation::Invalid(..)
-    ));
+    // Steps from a newer daemon must not panic
+    assert_eq!(install_step_name(0), fl!("unknown-install-step", step = 0));
+    assert_eq!(install_step_name(9), fl!("unknown-install-step", step = 9));
 }
 
 #[test]
-fn test_username_validation() {
-    assert_eq!(validate_username("foo").unwrap(), Validation::Valid);
-    assert_eq!(validate_username("cth451").unwrap(), Validation::Valid);
-    assert!(matches!(
-        validate_username("老白").unwrap(),
-        Validation::Invalid(..)
-    ));
-    assert!(matches!(
-        validate_username("BAIMINGCONG").unwrap(),
-        Validation::Invalid(..)
-    ));
+fn test_transfer_status() {
+    let sqfs = Squashfs {
+        arch: "amd64".to_string(),
+        date: "20250101".to_string(),
+        download_size: 1000 * 1024 * 1024,
+        inst_size: 4000 * 1024 * 1024,
+        path: String::new(),
+        sha256sum: String::new(),
+        inodes: 0,
+        signed_shim: false,
+    };
+
+    assert_eq!(
+        transfer_status(2, 50, 10 * 1024 * 1024, Some(&sqfs)),
+        Some(fl!(
+            "transfer-status",
+            done = "500.00 MiB",
+            total = "1000.00 MiB",
+            speed = "10.00 MiB/s",
+            eta = "50 seconds"
+        ))
+    );
+    assert_eq!(
+        transfer_status(3, 0, 1024, None),
+        Some("1.00 KiB/s".to_string())
+    );
+    assert_eq!(transfer_status(2, 50, 0, Some(&sqfs)), None);
+    assert_eq!(transfer_status(5, 50, 1024, Some(&sqfs)), None);
+}
+
+#[test]
+fn test_locale_score() {
+    let locales = list_locales().unwrap();
+    let best = |input| {
+        locales
+            .iter()
+            .max_by_key(|x| locale_score(input, x))
+            .unwrap()
+            .data
+            .as_str()
+    };
+
+    assert_eq!(best("zh-CN"), "zh_CN.UTF-8");
+    assert_eq!(best("zh_tw"), "zh_TW.UTF-8");
+    assert_eq!(best("Albanian"), "sq_AL.UTF-8");
+    assert!(locale_score("xyzzy", &locales[0]).is_none());
+}
+
+#[test]
+fn test_locale_preview() {
+    assert_eq!(group_digits("1234567", ","), "1,234,567");
+    assert_eq!(group_digits("123", ","), "123");
+    assert!(locale_preview("C").is_some());
+    assert!(locale_preview("xx_XX.UTF-8").is_none());
+}
+
+#[test]
+fn test_timezone_score() {
+    assert!(timezone_score("new york", "America/New_York").is_some());
+    assert!(timezone_score("shanghai", "Asia/Shanghai").is_some());
+    assert!(timezone_score("shanghai", "Europe/Paris").is_none());
+}
+
+#[test]
+fn test_live_defaults() {
+    let status = "   System Locale: LANG=zh_CN.UTF-8\n       VC Keymap: de\n      X11 Layout: de\n       X11 Model: pc105\n";
+    assert_eq!(
+        LiveDefaults::from_localectl(status),
+        LiveDefaults {
+            locale: Some("zh_CN.UTF-8".to_string()),
+            keymap: Some("de".to_string()),
+            x11_layout: Some("de".to_string()),
+            timezone: None,
+        }
+    );
+
+    let status =
+        "   System Locale: LANG=C.UTF-8\n       VC Keymap: (unset)\n      X11 Layout: n/a\n";
+    let defaults = LiveDefaults::from_localectl(status);
+    assert_eq!(defaults.locale.as_deref(), Some("C.UTF-8"));
+    assert_eq!(defaults.keymap, None);
+    assert_eq!(defaults.x11_layout, None);
+}
+
+#[test]
+fn test_partition_entries() {
+    let partitions = vec![
+        DkPartition {
+            path: Some(PathBuf::from("/dev/nvme0n1p1")),
+            parent_path: Some(PathBuf::from("/dev/nvme0n1")),
+            fs_type: Some("vfat".to_string()),
+            size: 512 * 1024 * 1024,
+        },
+        DkPartition {
+            path: Some(PathBuf::from("/dev/sda2")),
+            parent_path: Some(PathBuf::from("/dev/sda")),
+            fs_type: None,
+            size: 500 * 1024 * 1024 * 1024,
+        },
+    ];
+
+    assert_eq!(
+        partition_entries(&partitions),
+        vec![
+            "/dev/nvme0n1p1  512.00 MiB  vfat",
+            "/dev/sda2       500.00 GiB  -",
+        ]
+    );
+}
+
+#[test]
+fn test_variant_entry() {
+    let mut variant = Variant {
+        name: "Base".to_string(),
+        dir_name: None,
+        retro: false,
+        description: Some("Minimal system".to_string()),
+        squashfs: vec![Squashfs {
+            arch: get_arch_name().unwrap().to_string(),
+            date: "20250101".to_string(),
+            download_size: 1024 * 1024 * 1024,
+            inst_size: 3 * 1024 * 1024 * 1024,
+            path: String::new(),
+            sha256sum: String::new(),
+            inodes: 0,
+            signed_shim: false,
+        }],
+    };
+
+    assert_eq!(
+        variant_entry(&variant, true),
+        format!(
+            "Base - Minimal system ({})",
+            fl!("variant-installed-size", installed = "3.00 GiB")
+        )
+    );
+
+    variant.squashfs[0].arch = "unknown".to_string();
+    assert_eq!(variant_entry(&variant, false), "Base");
+}
+
+#[test]
+fn test_session_language() {
+    let locales = list_locales().unwrap();
+    let ids = vec!["en-US".to_string(), "zh-CN".to_string()];
+
+    assert_eq!(session_language("zh_CN.UTF-8", &ids, &locales), Some(1));
+    assert_eq!(session_language("en_US.UTF-8", &ids, &locales), Some(0));
+    assert_eq!(session_language("en_GB.UTF-8", &ids, &locales), Some(0));
+    assert_eq!(session_language("C.UTF-8", &ids, &locales), None);
+}
+
+#[test]
+fn test_locale_list_snapshot() {
+    let locales = list_locales()
+        .unwrap()
+        .iter()
+        .map(|x| format!("{} {} {} ({})", x.id, x.data, x.lang_english, x.text))
+        .collect::<Vec<_>>();
+
+    insta::assert_snapshot!(locales.join("\n"));
+}
+
+/// A client of a mock daemon replying to the methods in `script` as told
+/// there, for going through the unattended installation without one.
+#[cfg(test)]
+async fn mock_client(script: serde_json::Value) -> (Arc<mock::Mock>, DkClient) {
+    let mock = Arc::new(mock::Mock::scripted(script));
+    let client = DkClient::new(Bus::Session, mock.clone(), 0, Arc::new(()))
+        .await
+        .unwrap();
+
+    (mock, client)
+}
+
+#[cfg(test)]
+fn mock_install_config() -> InstallConfig {
+    let partition = |path: &str, fs_type: &str, size| DkPartition {
+        path: Some(path.into()),
+        parent_path: Some("/dev/sda".into()),
+        fs_type: Some(fs_type.to_string()),
+        size,
+    };
+
+    InstallConfig {
+        offline_install: false,
+        variant: Variant {
+            name: "Base".to_string(),
+            dir_name: None,
+            retro: false,
+            description: None,
+            squashfs: vec![Squashfs {
+                arch: get_arch_name().unwrap().to_string(),
+                date: "20250101".to_string(),
+                download_size: 1024,
+                inst_size: 4096,
+                path: "os-amd64/base.squashfs".to_string(),
+                sha256sum: "abc".to_string(),
+                inodes: 0,
+                signed_shim: false,
+            }],
+        },
+        fullname: Some("AOSC User".to_string()),
+        user: "aosc".to_string(),
+        uid: None,
+        gid: None,
+        avatar: None,
+        import_users: vec![],
+        password: UserPassword::Plain("anthon".to_string()),
+        autologin: true,
+        hostname: "aosc-pc".to_string(),
+        timezone: "Asia/Shanghai".to_string(),
+        rtc_as_localtime: false,
+        ntp: Some(NtpConfig {
+            enable: true,
+            servers: vec![],
+        }),
+        target_part: Some(partition("/dev/sda2", "ext4", 64 * GIB)),
+        target_dir: None,
+        efi_disk: Some(partition("/dev/sda1", "vfat", GIB / 2)),
+        locale: LocaleConfig::new("zh_CN.UTF-8".to_string(), vec!["en_US.UTF-8".to_string()]),
+        swapfile_size: 0.0,
+        keyboard: None,
+        extra_packages: vec![],
+        board: None,
+        firmware_part: None,
+        secure_boot: None,
+    }
+}
+
+#[tokio::test]
+async fn test_unattended_calls() {
+    use serde_json::json;
+
+    let (mock, client) = mock_client(json!({})).await;
+    let config = mock_install_config();
+
+    async {
+        client.claim().await?;
+        set_config(&client, &config).await?;
+        assert!(validate_config(&client).await?.is_empty());
+        Dbus::run(&client, DbusMethod::StartInstall).await
+    }
+    .await
+    .unwrap();
+
+    assert_eq!(
+        mock.calls(),
+        [
+            "GetVersion",
+            "Claim",
+            "SetConfig(download)",
+            "SetConfig(locale)",
+            "SetConfig(user)",
+            "SetConfig(autologin)",
+            "SetConfig(timezone)",
+            "SetConfig(hostname)",
+            "SetConfig(rtc_as_localtime)",
+            "SetConfig(ntp)",
+            "SetConfig(swapfile)",
+            "SetConfig(target_partition)",
+            "SetConfig(efi_partition)",
+            "ValidateConfig",
+            "StartInstall",
+        ]
+    );
+
+    let sent = client
+        .sent_config()
+        .into_iter()
+        .map(|(field, value)| {
+            (
+                field,
+                dkcli_core::config::config_value(serde_json::Value::String(value)),
+            )
+        })
+        .collect::<Vec<_>>();
+    let partition = |path, fs_type, size| json!({"path": path, "parent_path": "/dev/sda", "fs_type": fs_type, "size": size});
+    assert_eq!(
+        sent,
+        [
+            (
+                "download".to_string(),
+                json!({"Http": {
+                    "url": "https://releases.aosc.io/os-amd64/base.squashfs",
+                    "hash": "abc",
+                }})
+            ),
+            (
+                "locale".to_string(),
+                json!({"default": "zh_CN.UTF-8", "generate": ["zh_CN.UTF-8", "en_US.UTF-8"]})
+            ),
+            (
+                "user".to_string(),
+                json!({"username": "aosc", "full_name": "AOSC User", "password": "anthon"})
+            ),
+            ("autologin".to_string(), json!(true)),
+            ("timezone".to_string(), json!("Asia/Shanghai")),
+            ("hostname".to_string(), json!("aosc-pc")),
+            ("rtc_as_localtime".to_string(), json!(false)),
+            ("ntp".to_string(), json!({"enable": true, "servers": []})),
+            ("swapfile".to_string(), json!("Disable")),
+            (
+                "target_partition".to_string(),
+                partition("/dev/sda2", "ext4", 64 * GIB)
+            ),
+            (
+                "efi_partition".to_string(),
+                partition("/dev/sda1", "vfat", GIB / 2)
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_old_daemon_calls() {
+    use serde_json::json;
+
+    // Daemons from before get_version answer it with an empty object here
+    let (mock, client) = mock_client(json!({"GetVersion": {"result": "Ok", "data": {}}})).await;
+
+    async {
+        client.claim().await?;
+        set_config(&client, &mock_install_config()).await?;
+        assert!(validate_config(&client).await?.is_empty());
+        Ok::<_, anyhow::Error>(())
+    }
+    .await
+    .unwrap();
+
+    // No claiming, validation or newer settings
+    assert_eq!(
+        mock.calls(),
+        [
+            "GetVersion",
+            "SetConfig(download)",
+            "SetConfig(locale)",
+            "SetConfig(user)",
+            "SetConfig(timezone)",
+            "SetConfig(hostname)",
+            "SetConfig(rtc_as_localtime)",
+            "SetConfig(swapfile)",
+            "SetConfig(target_partition)",
+            "SetConfig(efi_partition)",
+        ]
+    );
+    assert!(client
+        .sent_config()
+        .contains(&("locale".to_string(), "zh_CN.UTF-8".to_string())));
+}
+
+#[tokio::test]
+async fn test_rejected_config_calls() {
+    use serde_json::json;
+
+    let (mock, client) = mock_client(json!({
+            "SetConfig": {
+                "result": "Error",
+                "data": {"code": "config", "message": "no such mirror"},
+            },
+    }))
+    .await;
+
+    let e = set_config(&client, &mock_install_config())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        e.downcast_ref::<DaemonError>().map(|x| x.kind),
+        Some(DaemonErrorKind::Config)
+    );
+
+    // Nothing after the first rejected setting, which is not remembered
+    // for a restarted daemon either
+    assert_eq!(mock.calls(), ["GetVersion", "SetConfig(download)"]);
+    assert!(client.sent_config().is_empty());
+}
+
+#[tokio::test]
+async fn test_memory_checks() {
+    use serde_json::json;
+
+    let (_, client) = mock_client(json!({})).await;
+    assert_eq!(get_memory(&client).await, Some(8 << 30));
+    check_online_memory(get_memory(&client).await).unwrap();
+
+    let (_, client) = mock_client(json!({
+        "GetMemory": {"result": "Ok", "data": 1u64 << 30},
+    }))
+    .await;
+    let e = check_online_memory(get_memory(&client).await).unwrap_err();
+    assert_eq!(failure_code(&e), "low_memory");
+
+    // Daemons that cannot tell are not held against
+    let (_, client) = mock_client(json!({
+        "GetMemory": {"result": "Error", "data": {"code": "memory", "message": "no meminfo"}},
+    }))
+    .await;
+    assert_eq!(get_memory(&client).await, None);
+    check_online_memory(None).unwrap();
+}
+
+#[tokio::test]
+async fn test_secure_boot() {
+    use serde_json::json;
+
+    let mut config = mock_install_config();
+    let mut sqfs = config.variant.squashfs[0].clone();
+    assert_eq!(secure_boot_for(false, &sqfs), None);
+    assert_eq!(secure_boot_for(true, &sqfs), Some(SecureBoot::Disable));
+    sqfs.signed_shim = true;
+    assert_eq!(secure_boot_for(true, &sqfs), Some(SecureBoot::Shim));
+
+    // Nothing for the daemon to do when the user disables it
+    config.secure_boot = Some(SecureBoot::Disable);
+    let (mock, client) = mock_client(json!({})).await;
+    set_config(&client, &config).await.unwrap();
+    assert!(!mock.calls().contains(&"SetConfig(secure_boot)".to_string()));
+    assert_eq!(
+        boot_instructions(&config),
+        [fl!("secure-boot-disable-instructions")]
+    );
+
+    config.secure_boot = Some(SecureBoot::Shim);
+    let (mock, client) = mock_client(json!({})).await;
+    set_config(&client, &config).await.unwrap();
+    assert_eq!(mock.calls().last().unwrap(), "SetConfig(secure_boot)");
+    assert_eq!(client.sent_config().last().unwrap().1, r#""shim""#);
+
+    let (_, client) = mock_client(json!({
+        "GetVersion": {"result": "Ok", "data": {"version": "0.9", "api_level": dkcli_core::dbus::API_LEVEL_BOARD}},
+    }))
+    .await;
+    let e = set_config(&client, &config).await.unwrap_err();
+    assert_eq!(failure_code(&e), "secure_boot_unsupported");
+}
+
+#[tokio::test]
+async fn test_target_dir() {
+    use serde_json::json;
+
+    let mut config = mock_install_config();
+    config.target_part = None;
+    config.efi_disk = None;
+    config.target_dir = Some("/mnt/sysroot".into());
+
+    let (mock, client) = mock_client(json!({})).await;
+    set_config(&client, &config).await.unwrap();
+    assert!(!mock
+        .calls()
+        .contains(&"SetConfig(target_partition)".to_string()));
+    assert_eq!(mock.calls().last().unwrap(), "SetConfig(target_dir)");
+    assert_eq!(client.sent_config().last().unwrap().1, r#""/mnt/sysroot""#);
+
+    let rows = install_summary(&config);
+    assert!(rows.contains(&(fl!("target-dir"), "/mnt/sysroot".to_string())));
+    assert!(install_warnings(&config, false).is_empty());
+
+    let (_, client) = mock_client(json!({
+        "GetVersion": {"result": "Ok", "data": {"version": "0.9", "api_level": dkcli_core::dbus::API_LEVEL_SECURE_BOOT}},
+    }))
+    .await;
+    let e = set_config(&client, &config).await.unwrap_err();
+    assert_eq!(failure_code(&e), "target_dir_unsupported");
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    assert_eq!(
+        check_target_dir(root).unwrap(),
+        root.canonicalize().unwrap()
+    );
+    let e = check_target_dir(&root.join("Cargo.toml")).unwrap_err();
+    assert_eq!(failure_code(&e), "target_dir");
+}
+
+#[tokio::test]
+async fn test_old_daemon_user() {
+    use serde_json::json;
+
+    let mut config = mock_install_config();
+    config.uid = Some(1001);
+    config.avatar = Some("/usr/share/pixmaps/faces/cat.png".into());
+    let old = json!({
+        "GetVersion": {"result": "Ok", "data": {"version": "0.8", "api_level": 0}},
+    });
+
+    // The IDs and avatar are left out, as a daemon this old does not know them
+    let (_, client) = mock_client(old.clone()).await;
+    set_config(&client, &config).await.unwrap();
+    let sent = client.sent_config();
+    let user = &sent.iter().find(|(k, _)| k == "user").unwrap().1;
+    assert_eq!(
+        user,
+        r#"{"username":"aosc","full_name":"AOSC User","password":"anthon"}"#
+    );
+    assert_eq!(
+        dkcli_core::config::skipped_config(&config),
+        ["extra_locales", "autologin", "ntp", "uid", "avatar"]
+    );
+
+    config.password = UserPassword::Hashed("$6$salt$hash".to_string());
+    let (mock, client) = mock_client(old).await;
+    let e = set_config(&client, &config).await.unwrap_err();
+    assert_eq!(failure_code(&e), "password_hash_unsupported");
+    assert_eq!(mock.calls(), ["GetVersion"]);
+    assert_eq!(Localized(&e).to_string(), fl!("password-hash-unsupported"));
+}
+
+#[tokio::test]
+async fn test_board_calls() {
+    use serde_json::json;
+
+    let mut config = mock_install_config();
+    config.board = find_board("rk3588").and_then(board_config);
+
+    let (mock, client) = mock_client(json!({})).await;
+    set_config(&client, &config).await.unwrap();
+    assert_eq!(mock.calls().last().unwrap(), "SetConfig(board)");
+    assert_eq!(
+        client.sent_config().last().unwrap().1,
+        r#"{"board":"rk3588","layout":{"UBoot":{"offset":32768}}}"#
+    );
+
+    // Nothing is sent to a daemon that cannot install for the board
+    let (mock, client) = mock_client(json!({
+        "GetVersion": {"result": "Ok", "data": {"version": "0.9", "api_level": dkcli_core::dbus::API_LEVEL_VALIDATE}},
+    }))
+    .await;
+    let e = set_config(&client, &config).await.unwrap_err();
+    assert_eq!(failure_code(&e), "board_unsupported");
+    assert_eq!(mock.calls(), ["GetVersion"]);
+
+    assert_eq!(find_board("uefi").and_then(board_config), None);
+
+    // Petitboot needs telling what to boot, which the summary explains
+    config.board = find_board("powernv").and_then(board_config);
+    let rows = finish_summary(&config, &[], Duration::ZERO);
+    assert_eq!(rows.last().unwrap().0, fl!("boot-instructions"));
+    config.board = None;
+    assert!(boot_instructions(&config).is_empty());
+
+    // The firmware partition goes with the board
+    config.board = find_board("rpi4").and_then(board_config);
+    config.firmware_part = Some(DkPartition {
+        path: Some("/dev/mmcblk0p1".into()),
+        parent_path: Some("/dev/mmcblk0".into()),
+        fs_type: Some("vfat".to_string()),
+        size: 512 << 20,
+    });
+    let (mock, client) = mock_client(json!({})).await;
+    set_config(&client, &config).await.unwrap();
+    assert_eq!(
+        mock.calls().last_chunk::<2>().unwrap(),
+        &["SetConfig(board)", "SetConfig(firmware_partition)"]
+    );
+}
+
+#[test]
+fn test_canned_prompts() {
+    use canned::{Answer, Canned};
+
+    let canned = Canned::new([
+        Answer::Text("  Jane   Doe "),
+        // Turned down, then the suggestion is taken
+        Answer::Text("Jane"),
+        Answer::Text(""),
+        Answer::Confirm(false),
+        Answer::Text(""),
+        Answer::Text("pool.ntp.org -ntp"),
+        Answer::Text("pool.ntp.org 10.0.0.1"),
+        Answer::Select("America"),
+        Answer::Select("New York"),
+        Answer::Back,
+        Answer::Quit,
+    ]);
+    let ui = Ui::Custom(Box::new(canned.clone()));
+
+    let mut answers = Answers::default();
+    ask_questions(
+        &mut Questions::default(),
+        &mut answers,
+        &[
+            &|answers| ask_fullname(&ui, answers),
+            &|answers| ask_username(&ui, answers),
+            &|answers| ask_rtc(&ui, answers),
+            &|answers| ask_ntp(&ui, answers),
+            &|answers| ask_ntp_servers(&ui, answers),
+        ],
+    )
+    .unwrap();
+    assert_eq!(answers.fullname.as_deref(), Some("Jane Doe"));
+    assert_eq!(answers.username.as_deref(), Some("janedoe"));
+    assert!(!answers.rtc_as_localtime);
+    let ntp = answers.ntp.unwrap();
+    assert!(ntp.enable);
+    assert_eq!(ntp.servers, ["pool.ntp.org", "10.0.0.1"]);
+
+    let timezones = ["Asia/Shanghai", "America/Los_Angeles", "America/New_York"]
+        .map(|x| Zone::parse(x).unwrap())
+        .to_vec();
+    assert_eq!(
+        select_timezone(&ui, timezones, None).unwrap(),
+        "America/New_York"
+    );
+
+    // Esc stays, Ctrl-C quits
+    assert!(!confirm_quit(&ui).unwrap());
+    assert!(confirm_quit(&ui).unwrap());
+    assert!(canned.is_done());
+
+    assert_eq!(
+        canned.asked(),
+        [
+            fl!("fullname"),
+            fl!("username"),
+            fl!("username"),
+            fl!("rtc-as-localtime"),
+            fl!("ntp"),
+            fl!("ntp-servers"),
+            fl!("ntp-servers"),
+            fl!("timezone-region"),
+            fl!("timezone-city"),
+            fl!("quit-installer"),
+            fl!("quit-installer"),
+        ]
+    );
+    assert_eq!(
+        canned.rejected(),
+        [
+            fl!("username-illegal", c = "J"),
+            fl!("hostname-illegal-starts-with", c = "-"),
+        ]
+    );
+}
+
+#[test]
+fn test_question_back() {
+    use canned::{Answer, Canned};
+
+    let canned = Canned::new([
+        Answer::Text("Jane"),
+        Answer::Back,
+        Answer::Text("John Doe"),
+        Answer::Text(""),
+        Answer::Confirm(false),
+        // Back past the NTP servers, not asked without NTP
+        Answer::Back,
+        Answer::Confirm(true),
+        Answer::Text("pool.ntp.org"),
+        Answer::Text("aosc"),
+        // Out of the step from its first question
+        Answer::Back,
+    ]);
+    let ui = Ui::Custom(Box::new(canned.clone()));
+
+    let mut answers = Answers::default();
+    let asks: [&Question; 5] = [
+        &|answers| ask_fullname(&ui, answers),
+        &|answers| ask_username(&ui, answers),
+        &|answers| ask_ntp(&ui, answers),
+        &|answers| ask_ntp_servers(&ui, answers),
+        &|answers| ask_hostname(&ui, answers),
+    ];
+    ask_questions(&mut Questions::default(), &mut answers, &asks).unwrap();
+    assert_eq!(answers.username.as_deref(), Some("johndoe"));
+    assert_eq!(answers.hostname.as_deref(), Some("aosc"));
+    assert_eq!(answers.ntp.as_ref().unwrap().servers, ["pool.ntp.org"]);
+
+    let e = ask_questions(&mut Questions::default(), &mut answers, &asks[4..]).unwrap_err();
+    assert!(is_canceled(&e));
+    assert!(canned.is_done());
+
+    assert_eq!(
+        canned.asked(),
+        [
+            fl!("fullname"),
+            fl!("username"),
+            fl!("fullname"),
+            fl!("username"),
+            fl!("ntp"),
+            fl!("hostname"),
+            fl!("ntp"),
+            fl!("ntp-servers"),
+            fl!("hostname"),
+            fl!("hostname"),
+        ]
+    );
 }
@@ -0,0 +1,231 @@
+//! A stand-in for the Deploykit daemon, answering from memory with a made-up
+//! disk and an installation that only takes a few seconds, so that the
+//! wizard and the progress display can be tried out without root or a
+//! daemon. Selected with `--mock`.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use dkcli_core::{
+    dbus::{BoxFuture, DbusMethod, DeploykitBackend, API_LEVEL_TARGET_DIR},
+    progress::INSTALL_STEPS,
+};
+use serde_json::{json, Value};
+use zbus::Result as zResult;
+
+const GIB: u64 = 1024 * 1024 * 1024;
+// How long each installation step and auto-partitioning take
+const STEP_TIME: Duration = Duration::from_secs(2);
+const AUTO_PARTITION_TIME: Duration = Duration::from_secs(2);
+const DOWNLOAD_SPEED: u64 = 50 * 1024 * 1024;
+
+#[derive(Default)]
+pub struct Mock {
+    // Replies to use instead of the made-up ones, by method name
+    script: HashMap<String, Value>,
+    state: Mutex<State>,
+    // Methods called so far, with the setting for SetConfig, for tests to
+    // check the order of
+    #[cfg(test)]
+    calls: Mutex<Vec<String>>,
+}
+
+#[derive(Default)]
+struct State {
+    config: HashMap<String, String>,
+    auto_partition: Option<Instant>,
+    install: Option<Instant>,
+}
+
+impl Mock {
+    /// A mock daemon, replying to the methods named in the JSON object at
+    /// `script`, if any, with the reply found there, e.g.
+    /// `{"StartInstall": {"result": "Error", "data": "No space left"}}`.
+    #[cfg_attr(not(feature = "mock"), allow(dead_code))]
+    pub fn new(script: Option<&Path>) -> Result<Self> {
+        let script = match script {
+            Some(path) => {
+                let s = fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                serde_json::from_str(&s)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?
+            }
+            None => HashMap::new(),
+        };
+
+        Ok(Self {
+            script,
+            ..Default::default()
+        })
+    }
+
+    /// A mock daemon replying to the methods in `script` as told there.
+    #[cfg(test)]
+    pub fn scripted(script: Value) -> Self {
+        Self {
+            script: serde_json::from_value(script).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(test)]
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn reply(&self, method: DbusMethod<'_>) -> Value {
+        if let Some(reply) = self.script.get(method.name()) {
+            return reply.clone();
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let data = match method {
+            DbusMethod::SetConfig(field, value) => {
+                state.config.insert(field.to_string(), value.to_string());
+                Value::Null
+            }
+            DbusMethod::GetConfig(field) => match state.config.get(field) {
+                Some(value) => json!(value),
+                None => return error(format!("{field} is not set")),
+            },
+            DbusMethod::ResetConfig => {
+                state.config.clear();
+                Value::Null
+            }
+            DbusMethod::ListDevice => json!([{
+                "model": "Mock Disk",
+                "path": "/dev/mock0",
+                "size": 128 * GIB,
+            }]),
+            DbusMethod::ListPartitions(_) => json!([esp(), system_partition()]),
+            DbusMethod::GetAllEspPartitions => json!([esp()]),
+            DbusMethod::AutoPartition(_) => {
+                state.auto_partition = Some(Instant::now());
+                Value::Null
+            }
+            DbusMethod::GetAutoPartitionProgress => match state.auto_partition {
+                None => json!({"status": "Pending"}),
+                Some(start) if start.elapsed() < AUTO_PARTITION_TIME => {
+                    json!({"status": "Working"})
+                }
+                Some(_) => json!({
+                    "status": "Finish",
+                    "res": {"Ok": [esp(), system_partition()]},
+                }),
+            },
+            DbusMethod::StartInstall => {
+                state.install = Some(Instant::now());
+                Value::Null
+            }
+            DbusMethod::GetProgress => match state.install {
+                None => json!({"status": "Pending"}),
+                Some(start) => progress(start.elapsed()),
+            },
+            DbusMethod::CancelInstall | DbusMethod::ResetProgressStatus => {
+                state.install = None;
+                Value::Null
+            }
+            DbusMethod::GetRecommendSwapSize => json!(4 * GIB),
+            DbusMethod::GetMemory => json!(8 * GIB),
+            DbusMethod::DiskIsRightCombo(_)
+            | DbusMethod::Claim(_)
+            | DbusMethod::SyncDisk
+            | DbusMethod::SyncAndReboot => Value::Null,
+            DbusMethod::IsLvmDevice(_) => json!(false),
+            DbusMethod::IsEFI => json!(true),
+            DbusMethod::ValidateConfig => json!([]),
+            DbusMethod::GetVersion => json!({
+                "version": "mock",
+                "api_level": API_LEVEL_TARGET_DIR,
+            }),
+        };
+
+        json!({"result": "Ok", "data": data})
+    }
+}
+
+impl DeploykitBackend for Mock {
+    fn call<'a>(&'a self, method: DbusMethod<'a>) -> BoxFuture<'a, zResult<String>> {
+        #[cfg(test)]
+        self.calls.lock().unwrap().push(match method {
+            DbusMethod::SetConfig(field, _) => format!("SetConfig({field})"),
+            method => method.name().to_string(),
+        });
+
+        Box::pin(async move { Ok(self.reply(method).to_string()) })
+    }
+}
+
+fn error(message: String) -> Value {
+    json!({"result": "Error", "data": {"message": message}})
+}
+
+fn esp() -> Value {
+    json!({
+        "path": "/dev/mock0p1",
+        "parent_path": "/dev/mock0",
+        "fs_type": "vfat",
+        "size": GIB / 2,
+    })
+}
+
+fn system_partition() -> Value {
+    json!({
+        "path": "/dev/mock0p2",
+        "parent_path": "/dev/mock0",
+        "fs_type": "ext4",
+        "size": 127 * GIB,
+    })
+}
+
+/// Progress of an installation started `elapsed` ago.
+fn progress(elapsed: Duration) -> Value {
+    let step = (elapsed.as_millis() / STEP_TIME.as_millis()) as u8 + 1;
+    if step > INSTALL_STEPS {
+        return json!({"status": "Finish"});
+    }
+
+    let progress =
+        (elapsed.as_millis() % STEP_TIME.as_millis() * 100 / STEP_TIME.as_millis()) as u8;
+    // The download and unpacking steps report a speed
+    let v = if matches!(step, 2 | 3) {
+        DOWNLOAD_SPEED
+    } else {
+        0
+    };
+
+    json!({"status": "Working", "step": step, "progress": progress, "v": v})
+}
+
+#[test]
+fn test_mock_progress() {
+    assert_eq!(
+        progress(Duration::ZERO),
+        json!({"status": "Working", "step": 1, "progress": 0, "v": 0})
+    );
+    assert_eq!(
+        progress(STEP_TIME + STEP_TIME / 2),
+        json!({"status": "Working", "step": 2, "progress": 50, "v": DOWNLOAD_SPEED})
+    );
+    assert_eq!(
+        progress(STEP_TIME * INSTALL_STEPS as u32),
+        json!({"status": "Finish"})
+    );
+
+    let mock = Mock::default();
+    assert_eq!(
+        mock.reply(DbusMethod::GetProgress)["data"]["status"],
+        "Pending"
+    );
+    mock.reply(DbusMethod::SetConfig("hostname", "aosc"));
+    assert_eq!(
+        mock.reply(DbusMethod::GetConfig("hostname"))["data"],
+        "aosc"
+    );
+}
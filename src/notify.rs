@@ -0,0 +1,65 @@
+//! Desktop notifications, so that users who left the installer running in a
+//! live desktop session learn when it is done.
+
+use std::{collections::HashMap, env};
+
+use log::debug;
+use zbus::{proxy, zvariant::Value, Connection, Result as zResult};
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, &Value<'_>>,
+        expire_timeout: i32,
+    ) -> zResult<u32>;
+}
+
+/// Send a notification if running in a graphical session. `failed` raises
+/// its urgency and picks an error sound. Failures are only logged, as there
+/// may well be nobody listening.
+pub async fn notify(summary: &str, body: &str, failed: bool) {
+    if env::var_os("WAYLAND_DISPLAY").is_none() && env::var_os("DISPLAY").is_none() {
+        return;
+    }
+
+    if let Err(e) = send(summary, body, failed).await {
+        debug!("Failed to send a desktop notification: {e}");
+    }
+}
+
+async fn send(summary: &str, body: &str, failed: bool) -> zResult<()> {
+    let conn = Connection::session().await?;
+    let proxy = NotificationsProxy::new(&conn).await?;
+
+    // Urgency 1 is normal, 2 critical. Sound names are from the
+    // freedesktop sound theme; servers without sound support ignore them.
+    let (icon, urgency, sound) = if failed {
+        ("dialog-error", Value::U8(2), Value::from("dialog-error"))
+    } else {
+        (
+            "system-software-install",
+            Value::U8(1),
+            Value::from("complete"),
+        )
+    };
+
+    let hints = HashMap::from([("urgency", &urgency), ("sound-name", &sound)]);
+
+    proxy
+        .notify("dkcli", 0, icon, summary, body, &[], hints, -1)
+        .await?;
+
+    Ok(())
+}
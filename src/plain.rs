@@ -0,0 +1,313 @@
+//! Line-based prompts for `--plain`: numbered menus and plain questions, no
+//! cursor movement or redraws, so that screen readers (brltty, speakup) can
+//! follow along.
+
+use std::io::{self, Write};
+
+use inquire::{
+    type_aliases::Scorer,
+    validator::{StringValidator, Validation},
+    CustomUserError, InquireError, PasswordDisplayMode,
+};
+
+use crate::{
+    fl,
+    ui::{error_message, filter_options, read_password, validate_str, InquireResult, Prompter},
+};
+
+// Typed instead of Esc, which line input cannot see
+const BACK: &str = "<";
+
+fn select(
+    message: &str,
+    help: Option<&str>,
+    options: &[String],
+    cursor: usize,
+    scorer: Option<Scorer<'_, String>>,
+) -> InquireResult<usize> {
+    let mut shown = (0..options.len()).collect::<Vec<_>>();
+
+    loop {
+        print_menu(message, help, options, &shown);
+        let input = ask(&fl!("plain-select", default = (cursor + 1).to_string()))?;
+
+        if input.is_empty() {
+            return Ok(cursor);
+        }
+
+        if let Some(i) = parse_number(&input, options.len()) {
+            return Ok(i);
+        }
+
+        // Anything else narrows the menu down
+        shown = filter_options(options, &input, scorer);
+        match shown.as_slice() {
+            [i] => {
+                eprintln!("{}", options[*i]);
+                return Ok(*i);
+            }
+            [] => {
+                eprintln!("{}", fl!("plain-no-match"));
+                shown = (0..options.len()).collect();
+            }
+            _ => print_match_count(shown.len(), options.len()),
+        }
+    }
+}
+
+fn multi_select(
+    message: &str,
+    help: Option<&str>,
+    options: &[String],
+    default: &[usize],
+    scorer: Option<Scorer<'_, String>>,
+) -> InquireResult<Vec<usize>> {
+    let mut shown = (0..options.len()).collect::<Vec<_>>();
+
+    loop {
+        print_menu(message, help, options, &shown);
+
+        let default = default
+            .iter()
+            .map(|i| (i + 1).to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let input = ask(&fl!("plain-multi-select", default = default.clone()))?;
+
+        let input = if input.is_empty() { default } else { input };
+        let numbers = input
+            .split([' ', ','])
+            .filter(|x| !x.is_empty())
+            .map(|x| parse_number(x, options.len()))
+            .collect::<Option<Vec<_>>>();
+
+        match numbers {
+            Some(numbers) => return Ok(numbers),
+            None => {
+                shown = filter_options(options, &input, scorer);
+                if shown.is_empty() {
+                    eprintln!("{}", fl!("plain-no-match"));
+                    shown = (0..options.len()).collect();
+                } else {
+                    print_match_count(shown.len(), options.len());
+                }
+            }
+        }
+    }
+}
+
+fn text(
+    message: &str,
+    help: Option<&str>,
+    default: Option<&str>,
+    mask: Option<PasswordDisplayMode>,
+    validate: &dyn Fn(&str) -> Result<Validation, CustomUserError>,
+) -> InquireResult<String> {
+    if let Some(help) = help {
+        eprintln!("{help}");
+    }
+
+    loop {
+        let prompt = match default {
+            Some(default) if !default.is_empty() => format!("{message} [{default}]"),
+            _ => message.to_string(),
+        };
+
+        let input = match mask {
+            None | Some(PasswordDisplayMode::Full) => ask(&prompt)?,
+            // Masking needs raw input, so hide the input entirely
+            Some(_) => ask_hidden(&prompt)?,
+        };
+
+        let value = match default {
+            Some(default) if input.is_empty() => default.to_string(),
+            _ => input,
+        };
+
+        match validate(&value).map_err(InquireError::Custom)? {
+            Validation::Valid => return Ok(value),
+            Validation::Invalid(e) => eprintln!("{}", error_message(e)),
+        }
+    }
+}
+
+fn confirm(
+    message: &str,
+    help: Option<&str>,
+    default: Option<bool>,
+    error_message: Option<&str>,
+) -> InquireResult<bool> {
+    if let Some(help) = help {
+        eprintln!("{help}");
+    }
+
+    let hint = match default {
+        Some(true) => "(Y/n)",
+        Some(false) => "(y/N)",
+        None => "(y/n)",
+    };
+
+    loop {
+        match ask(&format!("{message} {hint}"))?.to_lowercase().as_str() {
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            "" if default.is_some() => return Ok(default.unwrap_or_default()),
+            _ => eprintln!(
+                "{}",
+                error_message
+                    .map(|x| x.to_string())
+                    .unwrap_or_else(|| fl!("yn-confirm-required"))
+            ),
+        }
+    }
+}
+
+/// Line-based prompts on standard input.
+pub struct Plain;
+
+impl Prompter for Plain {
+    fn select(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        options: &[String],
+        cursor: usize,
+        scorer: Option<Scorer<'_, String>>,
+    ) -> InquireResult<usize> {
+        select(message, help, options, cursor, scorer)
+    }
+
+    fn multi_select(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        options: &[String],
+        default: &[usize],
+        scorer: Option<Scorer<'_, String>>,
+    ) -> InquireResult<Vec<usize>> {
+        multi_select(message, help, options, default, scorer)
+    }
+
+    fn text(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        default: Option<&str>,
+        validators: &[Box<dyn StringValidator>],
+    ) -> InquireResult<String> {
+        text(message, help, default, None, &|x| {
+            validate_str(validators, x)
+        })
+    }
+
+    fn password(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        display_mode: PasswordDisplayMode,
+        confirmation_message: Option<&str>,
+        confirmation_error_message: Option<&str>,
+        validators: &[Box<dyn StringValidator>],
+    ) -> InquireResult<String> {
+        read_password(
+            message,
+            confirmation_message,
+            confirmation_error_message,
+            validators,
+            |message, validate| text(message, help, None, Some(display_mode), validate),
+            |error| eprintln!("{error}"),
+        )
+    }
+
+    fn confirm(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        default: Option<bool>,
+        error_message: Option<&str>,
+    ) -> InquireResult<bool> {
+        confirm(message, help, default, error_message)
+    }
+}
+
+fn print_menu(message: &str, help: Option<&str>, options: &[String], shown: &[usize]) {
+    eprintln!("{message}");
+    if let Some(help) = help {
+        eprintln!("{help}");
+    }
+
+    for i in shown {
+        eprintln!("{}. {}", i + 1, options[*i]);
+    }
+}
+
+fn print_match_count(shown: usize, total: usize) {
+    eprintln!("{}", fl!("match-count", shown = shown, total = total));
+}
+
+/// Print `prompt` and read a trimmed line. `<` goes back, end of input quits.
+fn ask(prompt: &str) -> InquireResult<String> {
+    eprint!("{prompt}: ");
+    io::stderr().flush()?;
+
+    read_line()
+}
+
+fn ask_hidden(prompt: &str) -> InquireResult<String> {
+    eprint!("{prompt}: ");
+    io::stderr().flush()?;
+
+    let fd = libc::STDIN_FILENO;
+
+    // SAFETY: `term` is fully initialized by tcgetattr before use, and the
+    // original settings are restored before returning.
+    unsafe {
+        let mut term = std::mem::zeroed::<libc::termios>();
+        if libc::tcgetattr(fd, &mut term) != 0 {
+            return read_line();
+        }
+
+        let orig = term;
+        term.c_lflag &= !libc::ECHO;
+        libc::tcsetattr(fd, libc::TCSANOW, &term);
+
+        let res = read_line();
+
+        libc::tcsetattr(fd, libc::TCSANOW, &orig);
+        eprintln!();
+
+        res
+    }
+}
+
+fn read_line() -> InquireResult<String> {
+    let mut line = String::new();
+    if io::stdin().read_line(&mut line)? == 0 {
+        return Err(InquireError::OperationInterrupted);
+    }
+
+    let line = line.trim();
+    if line == BACK {
+        return Err(InquireError::OperationCanceled);
+    }
+
+    Ok(line.to_string())
+}
+
+/// 1-based menu number to index.
+fn parse_number(input: &str, len: usize) -> Option<usize> {
+    input
+        .parse::<usize>()
+        .ok()
+        .filter(|x| (1..=len).contains(x))
+        .map(|x| x - 1)
+}
+
+#[test]
+fn test_parse_number() {
+    assert_eq!(parse_number("1", 3), Some(0));
+    assert_eq!(parse_number("3", 3), Some(2));
+    assert_eq!(parse_number("0", 3), None);
+    assert_eq!(parse_number("4", 3), None);
+    assert_eq!(parse_number("sda", 3), None);
+}
@@ -0,0 +1,42 @@
+//! A text authentication agent for polkit, so that an unprivileged user of
+//! the live environment can authorize what the daemon is asked to do.
+
+use std::{
+    io::{self, IsTerminal},
+    process::{Child, Command},
+};
+
+use log::debug;
+
+/// `pkttyagent` running for this process, killed on drop.
+pub struct Agent(Child);
+
+impl Agent {
+    /// Start `pkttyagent` to ask for passwords on the terminal. Nothing to
+    /// do as root, without a terminal to ask on, or if the desktop session
+    /// has an agent already, which `--fallback` leaves in charge.
+    pub fn spawn() -> Option<Self> {
+        if unsafe { libc::geteuid() } == 0 || !io::stdin().is_terminal() {
+            return None;
+        }
+
+        let res = Command::new("pkttyagent")
+            .args(["--fallback", "--process", &std::process::id().to_string()])
+            .spawn();
+
+        match res {
+            Ok(child) => Some(Self(child)),
+            Err(e) => {
+                debug!("Failed to start pkttyagent: {e}");
+                None
+            }
+        }
+    }
+}
+
+impl Drop for Agent {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
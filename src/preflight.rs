@@ -0,0 +1,148 @@
+//! Checks of the device chosen for installing to an existing partition,
+//! made before asking for partitions, so that a device that cannot be used
+//! as it is comes with something to do about it.
+
+use anyhow::{Context, Result};
+use dkcli_core::{
+    client::{get_partitions, Dbus, DkClient},
+    config::DkPartition,
+    dbus::DbusMethod,
+    error::DaemonError,
+    platform::{is_firmware_partition, BoardConfig, FIRMWARE_PARTITION_MIN},
+};
+use indicatif::HumanBytes;
+use log::debug;
+
+use crate::{error::Describe, fl};
+
+/// A reason the device cannot be installed to as it is. Partitioning it
+/// automatically takes care of all of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    /// The partition table does not suit the firmware, e.g. MBR with EFI
+    WrongTable(String),
+    /// The device is an LVM physical volume
+    Lvm,
+    /// No partition is large enough
+    NoRoom,
+    /// The firmware is EFI but there is no EFI system partition
+    NoEsp,
+    /// The board boots from a FAT firmware partition and there is none
+    NoFirmwarePartition,
+}
+
+impl Finding {
+    pub fn message(&self) -> String {
+        match self {
+            Finding::WrongTable(e) => fl!("preflight-wrong-table", e = e.as_str()),
+            Finding::Lvm => fl!("unsupport-lvm-device"),
+            Finding::NoRoom => fl!("no-partition-to-install"),
+            Finding::NoEsp => fl!("no-efi-partition"),
+            Finding::NoFirmwarePartition => fl!(
+                "no-firmware-partition",
+                size = HumanBytes(FIRMWARE_PARTITION_MIN).to_string()
+            ),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Preflight {
+    /// Partitions large enough to install to
+    pub partitions: Vec<DkPartition>,
+    /// EFI system partitions, if the firmware is EFI
+    pub esps: Option<Vec<DkPartition>>,
+    /// Firmware partitions, if the board boots from one instead
+    pub firmware_parts: Option<Vec<DkPartition>>,
+    pub findings: Vec<Finding>,
+}
+
+/// Check whether the system can be installed to an existing partition of
+/// `device` that is larger than `min_size`, and whether it has the
+/// partition `board` boots from.
+pub async fn run(
+    dk_client: &DkClient,
+    device: &str,
+    min_size: u64,
+    board: Option<&BoardConfig>,
+) -> Result<Preflight> {
+    let mut findings = vec![];
+
+    // The daemon tells a partition table it cannot use apart with an error
+    match Dbus::run(dk_client, DbusMethod::DiskIsRightCombo(device)).await {
+        Ok(_) => {}
+        Err(e) => match e.downcast::<DaemonError>() {
+            Ok(e) => findings.push(Finding::WrongTable(e.describe())),
+            Err(e) => return Err(e),
+        },
+    }
+
+    let is_lvm = Dbus::run(dk_client, DbusMethod::IsLvmDevice(device))
+        .await?
+        .data
+        .as_bool()
+        .context(fl!("direct-lvm-error"))?;
+    if is_lvm {
+        findings.push(Finding::Lvm);
+    }
+
+    let all_partitions = get_partitions(dk_client, device).await?;
+    let partitions = all_partitions
+        .iter()
+        .filter(|x| x.size > min_size && x.path.is_some())
+        .cloned()
+        .collect::<Vec<_>>();
+    if partitions.is_empty() {
+        findings.push(Finding::NoRoom);
+    }
+
+    // The firmware partition is all the board boots from, whether or not
+    // its firmware also offers EFI
+    if board.is_some_and(|x| x.has_firmware_partition()) {
+        let firmware_parts = all_partitions
+            .into_iter()
+            .filter(|x| x.path.is_some() && is_firmware_partition(x))
+            .collect::<Vec<_>>();
+        if firmware_parts.is_empty() {
+            findings.push(Finding::NoFirmwarePartition);
+        }
+
+        return Ok(Preflight {
+            partitions,
+            esps: None,
+            firmware_parts: Some(firmware_parts),
+            findings,
+        });
+    }
+
+    let is_efi = !board.is_some_and(|x| x.skips_esp())
+        && Dbus::run(dk_client, DbusMethod::IsEFI)
+            .await?
+            .data
+            .as_bool()
+            .context(fl!("direct-efi-error"))?;
+    debug!("Device is{}EFI", if is_efi { " " } else { " not " });
+
+    let esps = if is_efi {
+        let data = Dbus::run(dk_client, DbusMethod::GetAllEspPartitions)
+            .await?
+            .data;
+        let esps = serde_json::from_value::<Vec<DkPartition>>(data)?
+            .into_iter()
+            .filter(|x| x.path.is_some())
+            .collect::<Vec<_>>();
+        if esps.is_empty() {
+            findings.push(Finding::NoEsp);
+        }
+        Some(esps)
+    } else {
+        None
+    };
+
+    Ok(Preflight {
+        partitions,
+        esps,
+        firmware_parts: None,
+        findings,
+    })
+}
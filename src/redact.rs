@@ -0,0 +1,209 @@
+//! Masking of passwords and passphrases, wherever they may turn up in what
+//! is logged or shown: settings sent to the daemon, errors it reports back,
+//! or a configuration file quoted by a parse error.
+
+use std::borrow::Cow;
+
+use log::{LevelFilter, Log, Metadata, Record};
+use serde_json::Value;
+use simplelog::{Config, SharedLogger};
+
+const MASK: &str = "********";
+// Fields holding secrets, going by (part of) their name
+const SECRET_KEYS: &[&str] = &["password", "passphrase"];
+
+fn is_secret(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    SECRET_KEYS.iter().any(|x| key.contains(x))
+}
+
+/// Mask secrets in a JSON value, however deep they are.
+pub fn value(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| {
+                    let v = if is_secret(&k) && !v.is_null() {
+                        Value::String(MASK.to_string())
+                    } else {
+                        self::value(v)
+                    };
+                    (k, v)
+                })
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(self::value).collect()),
+        // Settings are sent as JSON in a string
+        Value::String(s) => match text(&s) {
+            Cow::Borrowed(_) => Value::String(s),
+            Cow::Owned(s) => Value::String(s),
+        },
+        v => v,
+    }
+}
+
+/// Mask secrets in free text, where they look like `"password": "..."`
+/// (also with escaped quotes, as JSON in JSON), `password = "..."` or
+/// `password=...`.
+pub fn text(s: &str) -> Cow<'_, str> {
+    let lower = s.to_ascii_lowercase();
+    let bytes = s.as_bytes();
+    let mut out = String::new();
+    // End of what has been copied to out
+    let mut copied = 0;
+    let mut pos = 0;
+
+    while let Some((start, key)) = SECRET_KEYS
+        .iter()
+        .filter_map(|key| Some((lower[pos..].find(key)? + pos, *key)))
+        .min()
+    {
+        pos = start + key.len();
+        let Some((value_start, value_end)) = secret_value(bytes, pos) else {
+            continue;
+        };
+
+        out.push_str(&s[copied..value_start]);
+        out.push_str(MASK);
+        copied = value_end;
+        pos = value_end;
+    }
+
+    if copied == 0 {
+        return Cow::Borrowed(s);
+    }
+
+    out.push_str(&s[copied..]);
+    Cow::Owned(out)
+}
+
+/// Where the value of the key ending at `pos` starts and ends, if it is
+/// followed by one.
+fn secret_value(s: &[u8], mut pos: usize) -> Option<(usize, usize)> {
+    let skip = |pos: &mut usize, pred: fn(u8) -> bool| {
+        while s.get(*pos).copied().is_some_and(pred) {
+            *pos += 1;
+        }
+    };
+
+    // The rest of the key, e.g. password_hash
+    skip(&mut pos, |c| {
+        c.is_ascii_alphanumeric() || c == b'_' || c == b'-'
+    });
+    // Closing quote of the key, escaped or not
+    skip(&mut pos, |c| c == b'\\' || c == b'"' || c == b'\'');
+    skip(&mut pos, |c| c == b' ');
+    if !matches!(s.get(pos), Some(b':' | b'=')) {
+        return None;
+    }
+    pos += 1;
+    skip(&mut pos, |c| c == b' ');
+
+    let start = pos;
+    match s.get(pos)? {
+        b'"' | b'\'' => {
+            let quote = s[pos];
+            let start = pos + 1;
+            let mut end = start;
+            while end < s.len() && s[end] != quote {
+                end += if s[end] == b'\\' { 2 } else { 1 };
+            }
+            Some((start, end.min(s.len())))
+        }
+        // JSON in a JSON string
+        b'\\' if s.get(pos + 1) == Some(&b'"') => {
+            let start = pos + 2;
+            let end = s[start..]
+                .windows(2)
+                .position(|x| x == b"\\\"")
+                .map_or(s.len(), |x| x + start);
+            Some((start, end))
+        }
+        _ => {
+            skip(&mut pos, |c| {
+                !c.is_ascii_whitespace() && !matches!(c, b',' | b'}' | b']' | b'&' | b';')
+            });
+            let value = &s[start..pos];
+            (pos > start && value != b"null").then_some((start, pos))
+        }
+    }
+}
+
+/// Passes log records on to another logger with secrets masked.
+pub struct Logger(pub Box<dyn SharedLogger>);
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.0.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.0.enabled(record.metadata()) {
+            return;
+        }
+
+        let message = record.args().to_string();
+        self.0.log(
+            &record
+                .to_builder()
+                .args(format_args!("{}", text(&message)))
+                .build(),
+        );
+    }
+
+    fn flush(&self) {
+        self.0.flush();
+    }
+}
+
+impl SharedLogger for Logger {
+    fn level(&self) -> LevelFilter {
+        self.0.level()
+    }
+
+    fn config(&self) -> Option<&Config> {
+        self.0.config()
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        self
+    }
+}
+
+#[test]
+fn test_redact() {
+    use serde_json::json;
+
+    assert_eq!(
+        value(json!({"username": "aosc", "password": "anthon"})),
+        json!({"username": "aosc", "password": "********"})
+    );
+    assert_eq!(
+        value(json!([{"Passphrase": "x", "hash": null, "password_hash": null}])),
+        json!([{"Passphrase": "********", "hash": null, "password_hash": null}])
+    );
+    assert_eq!(
+        value(json!(r#"{"username":"aosc","password":"anthon"}"#)),
+        json!(r#"{"username":"aosc","password":"********"}"#)
+    );
+
+    assert_eq!(text("nothing to see"), "nothing to see");
+    assert_eq!(
+        text(r#"{"password": "a\"b", "user": "aosc"}"#),
+        r#"{"password": "********", "user": "aosc"}"#
+    );
+    assert_eq!(
+        text(r#"set_config("user", "{\"username\":\"aosc\",\"password\":\"anthon\"}")"#),
+        r#"set_config("user", "{\"username\":\"aosc\",\"password\":\"********\"}")"#
+    );
+    assert_eq!(
+        text("password = \"anthon\"\nhostname = \"aosc\""),
+        "password = \"********\"\nhostname = \"aosc\""
+    );
+    assert_eq!(
+        text("PASSWORD=anthon, user=aosc"),
+        "PASSWORD=********, user=aosc"
+    );
+    assert_eq!(text("the password is wrong"), "the password is wrong");
+    assert_eq!(text(r#"{"password": null}"#), r#"{"password": null}"#);
+}
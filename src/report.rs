@@ -0,0 +1,103 @@
+//! A record of how the system was installed, left in the installed system
+//! for whoever has to look into it later.
+
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use dkcli_core::{client::DkClient, disk::with_mounted, recipe::release_for, timings::StepSpan};
+
+use crate::{install_step_name, InstallConfig};
+
+// Where the report goes, relative to the root of the installed system
+const REPORT_PATH: &str = "var/log/installer/report.json";
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    dkcli_version: &'static str,
+    variant: String,
+    squashfs: Option<SquashfsReport>,
+    target_partition: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_dir: Option<String>,
+    efi_partition: Option<String>,
+    /// Steps that were followed from the start
+    steps: Vec<StepReport>,
+    total_secs: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct SquashfsReport {
+    path: String,
+    date: String,
+    sha256sum: String,
+}
+
+#[derive(Debug, Serialize)]
+struct StepReport {
+    step: u8,
+    name: String,
+    /// Seconds into the installation when the step started
+    start_secs: f64,
+    secs: f64,
+}
+
+impl Report {
+    /// The report of installing `config`, started at `start`.
+    pub fn new(
+        config: &InstallConfig,
+        steps: &[StepSpan],
+        start: Instant,
+        total: Duration,
+    ) -> Self {
+        let path_of = |x: &crate::DkPartition| x.path.as_ref().map(|x| x.display().to_string());
+
+        Self {
+            dkcli_version: env!("CARGO_PKG_VERSION"),
+            variant: config.variant.name.clone(),
+            squashfs: release_for(&config.variant).ok().map(|x| SquashfsReport {
+                path: x.path.clone(),
+                date: x.date.clone(),
+                sha256sum: x.sha256sum.clone(),
+            }),
+            target_partition: config.target_part.as_ref().and_then(path_of),
+            target_dir: config.target_dir.as_ref().map(|x| x.display().to_string()),
+            efi_partition: config.efi_disk.as_ref().and_then(path_of),
+            steps: steps
+                .iter()
+                .map(|span| StepReport {
+                    step: span.step,
+                    name: install_step_name(span.step),
+                    start_secs: span.start.saturating_duration_since(start).as_secs_f64(),
+                    secs: span.duration.as_secs_f64(),
+                })
+                .collect(),
+            total_secs: total.as_secs_f64(),
+        }
+    }
+
+    /// Write the report into the system installed to `part`.
+    pub fn write(&self, dk_client: &DkClient, part: &Path) -> Result<()> {
+        with_mounted(dk_client, part, "rw", |root| self.write_into(root))??;
+
+        Ok(())
+    }
+
+    /// Write the report into the system installed at `root`.
+    pub fn write_into(&self, root: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+
+        let path = root.join(REPORT_PATH);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, json)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+//! Anonymous installation statistics, sent only when the user agrees to,
+//! to tell which failures are worth fixing first. Nothing that identifies
+//! the user or the machine is included: no hostname, user name or address.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use reqwest::ClientBuilder;
+use serde::Serialize;
+
+const STATS_URL: &str = "https://releases.aosc.io/stats/dkcli";
+
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    dkcli_version: &'static str,
+    variant: String,
+    arch: Option<&'static str>,
+    duration_secs: u64,
+    /// `success`, or the failure code
+    result: String,
+}
+
+impl Stats {
+    pub fn new(variant: &str, duration: Duration, failure: Option<String>) -> Self {
+        Self {
+            dkcli_version: env!("CARGO_PKG_VERSION"),
+            variant: variant.to_string(),
+            arch: crate::get_arch_name(),
+            duration_secs: duration.as_secs(),
+            result: failure.unwrap_or_else(|| "success".to_string()),
+        }
+    }
+
+    pub async fn submit(&self) -> Result<()> {
+        let client = ClientBuilder::new()
+            .user_agent("deploykit")
+            .timeout(Duration::from_secs(10))
+            .build()?;
+
+        client
+            .post(STATS_URL)
+            .json(self)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_stats_json() {
+    let stats = Stats::new(
+        "Base",
+        Duration::from_secs(90),
+        Some("no_space".to_string()),
+    );
+    let json = serde_json::to_value(&stats).unwrap();
+
+    assert_eq!(json["variant"], "Base");
+    assert_eq!(json["duration_secs"], 90);
+    assert_eq!(json["result"], "no_space");
+    // Only these, so that nothing else slips in unnoticed
+    assert_eq!(json.as_object().unwrap().len(), 5);
+}
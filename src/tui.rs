@@ -0,0 +1,808 @@
+//! The full-screen interface (`--tui`), built on ratatui, with the wizard
+//! steps, the page being answered and the log side by side.
+
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use inquire::{
+    type_aliases::Scorer,
+    validator::{StringValidator, Validation},
+    CustomUserError, InquireError, PasswordDisplayMode,
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    symbols::border,
+    text::{Line, Span},
+    widgets::{Block, Gauge, List, ListItem, ListState, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+
+use serde::Serialize;
+
+use crate::{
+    fl,
+    ui::{self, error_message, filter_options, InquireResult, Prompter},
+};
+
+const SIDEBAR_WIDTH: u16 = 24;
+const LOG_HEIGHT: u16 = 8;
+const LOG_CAPACITY: usize = 1000;
+
+/// Wizard steps, as listed in the sidebar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    Language,
+    Source,
+    Disk,
+    Keyboard,
+    User,
+    System,
+    Confirm,
+    Install,
+}
+
+impl Step {
+    const ALL: [Step; 8] = [
+        Step::Language,
+        Step::Source,
+        Step::Disk,
+        Step::Keyboard,
+        Step::User,
+        Step::System,
+        Step::Confirm,
+        Step::Install,
+    ];
+
+    fn name(self) -> String {
+        match self {
+            Step::Language => fl!("step-language"),
+            Step::Source => fl!("step-source"),
+            Step::Disk => fl!("step-disk"),
+            Step::Keyboard => fl!("step-keyboard"),
+            Step::User => fl!("step-user"),
+            Step::System => fl!("step-system"),
+            Step::Confirm => fl!("step-confirm"),
+            Step::Install => fl!("step-install"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct LogBufferInner {
+    lines: Vec<String>,
+    pending: String,
+}
+
+/// Log sink for the full-screen interface, where log lines can't be written
+/// to the terminal directly.
+#[derive(Clone, Default)]
+pub struct LogBuffer(Arc<Mutex<LogBufferInner>>);
+
+impl LogBuffer {
+    fn tail(&self, n: usize) -> Vec<String> {
+        let inner = self.0.lock().unwrap();
+        let start = inner.lines.len().saturating_sub(n);
+
+        inner.lines[start..].to_vec()
+    }
+
+    fn take(&self) -> Vec<String> {
+        std::mem::take(&mut self.0.lock().unwrap().lines)
+    }
+}
+
+impl Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut inner = self.0.lock().unwrap();
+        inner.pending.push_str(&String::from_utf8_lossy(buf));
+
+        while let Some(pos) = inner.pending.find('\n') {
+            let line = inner.pending.drain(..=pos).collect::<String>();
+            inner.lines.push(line.trim_end().to_string());
+        }
+
+        let len = inner.lines.len();
+        if len > LOG_CAPACITY {
+            inner.lines.drain(..len - LOG_CAPACITY);
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct State {
+    step: Step,
+    answers: Vec<(String, String)>,
+    notice: Option<String>,
+}
+
+/// Full-screen interface: a sidebar of wizard steps, the current page with
+/// the answers given so far, and a live log pane.
+pub struct Tui {
+    terminal: RefCell<DefaultTerminal>,
+    state: RefCell<State>,
+    logs: LogBuffer,
+}
+
+impl Tui {
+    pub fn new(logs: LogBuffer) -> io::Result<Self> {
+        let terminal = ratatui::try_init()?;
+
+        Ok(Self {
+            terminal: RefCell::new(terminal),
+            state: RefCell::new(State {
+                step: Step::Language,
+                answers: vec![],
+                notice: None,
+            }),
+            logs,
+        })
+    }
+
+    pub fn set_step(&self, step: Step) {
+        let mut state = self.state.borrow_mut();
+        if state.step != step {
+            state.step = step;
+            state.answers.clear();
+            state.notice = None;
+        }
+    }
+
+    /// Forget the answers shown on the current page.
+    pub fn clear(&self) {
+        let mut state = self.state.borrow_mut();
+        state.answers.clear();
+        state.notice = None;
+    }
+
+    /// Record an answer to show on the current page.
+    pub fn answer(&self, label: &str, value: &str) {
+        let mut state = self.state.borrow_mut();
+        state.answers.push((label.to_string(), value.to_string()));
+        state.notice = None;
+    }
+
+    /// Show a message above the next prompt.
+    pub fn notice(&self, message: &str) {
+        self.state.borrow_mut().notice = Some(message.to_string());
+    }
+
+    pub fn status(&self, message: &str) {
+        let _ = self.draw(|f, area| {
+            f.render_widget(
+                Paragraph::new(message.to_string()).wrap(Wrap { trim: false }),
+                area,
+            );
+        });
+    }
+
+    pub fn progress(&self, prefix: &str, position: u64) -> io::Result<()> {
+        self.draw(|f, area| {
+            let [title, gauge] =
+                Layout::vertical([Constraint::Length(2), Constraint::Length(1)]).areas(area);
+            f.render_widget(Paragraph::new(prefix.to_string()).bold(), title);
+            f.render_widget(
+                Gauge::default()
+                    .gauge_style(Style::new().fg(Color::Cyan))
+                    .percent(position.min(100) as u16),
+                gauge,
+            );
+        })
+    }
+
+    /// Show a final message and wait for a key press.
+    pub fn finish(&self, message: &str) -> io::Result<()> {
+        self.draw(|f, area| {
+            let [text, hint] =
+                Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+            f.render_widget(
+                Paragraph::new(message.to_string()).wrap(Wrap { trim: false }),
+                text,
+            );
+            f.render_widget(Paragraph::new(fl!("press-any-key")).dim(), hint);
+        })?;
+
+        loop {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Whether Ctrl-C has been pressed since the last call, without blocking.
+    pub fn poll_interrupted(&self) -> io::Result<bool> {
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()? {
+                if is_interrupt(&key) {
+                    return Ok(true);
+                }
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn read_select(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        options: &[String],
+        cursor: usize,
+        scorer: Option<Scorer<'_, String>>,
+    ) -> InquireResult<usize> {
+        let mut filter = String::new();
+        let mut list = ListState::default().with_selected(Some(cursor));
+        let mut help = Help::new(help);
+
+        loop {
+            let visible = filter_options(options, &filter, scorer);
+            clamp_selection(&mut list, visible.len());
+
+            self.draw(|f, area| {
+                let items = visible
+                    .iter()
+                    .map(|i| ListItem::new(options[*i].as_str()))
+                    .collect::<Vec<_>>();
+                let filter = filter_line(&filter, visible.len(), options.len());
+                render_list(f, area, message, &help, filter, items, &mut list);
+            })?;
+
+            let key = self.read_key()?;
+            match key.code {
+                KeyCode::Enter => {
+                    if let Some(i) = list.selected().and_then(|x| visible.get(x)) {
+                        return Ok(*i);
+                    }
+                }
+                KeyCode::Char('?') | KeyCode::F(1) => help.toggle(),
+                KeyCode::Char(c) => {
+                    filter.push(c);
+                    list.select_first();
+                }
+                code => handle_list_key(code, &mut list, &mut filter)?,
+            }
+        }
+    }
+
+    fn read_multi_select(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        options: &[String],
+        default: &[usize],
+        scorer: Option<Scorer<'_, String>>,
+    ) -> InquireResult<Vec<usize>> {
+        let mut filter = String::new();
+        let mut list = ListState::default().with_selected(Some(0));
+        let mut help = Help::new(help);
+        let mut checked = (0..options.len())
+            .map(|i| default.contains(&i))
+            .collect::<Vec<_>>();
+
+        loop {
+            let visible = filter_options(options, &filter, scorer);
+            clamp_selection(&mut list, visible.len());
+
+            self.draw(|f, area| {
+                let items = visible
+                    .iter()
+                    .map(|i| {
+                        let mark = if checked[*i] { "[x]" } else { "[ ]" };
+                        ListItem::new(format!("{mark} {}", options[*i]))
+                    })
+                    .collect::<Vec<_>>();
+                let filter = filter_line(&filter, visible.len(), options.len());
+                render_list(f, area, message, &help, filter, items, &mut list);
+            })?;
+
+            let key = self.read_key()?;
+            match key.code {
+                KeyCode::Enter => {
+                    return Ok((0..options.len()).filter(|i| checked[*i]).collect());
+                }
+                KeyCode::Char(' ') => {
+                    if let Some(i) = list.selected().and_then(|x| visible.get(x)) {
+                        checked[*i] = !checked[*i];
+                    }
+                }
+                KeyCode::Char('?') | KeyCode::F(1) => help.toggle(),
+                KeyCode::Char(c) => {
+                    filter.push(c);
+                    list.select_first();
+                }
+                code => handle_list_key(code, &mut list, &mut filter)?,
+            }
+        }
+    }
+
+    /// Read a line of text, masked according to `mask` if given, until it
+    /// passes `validate`.
+    fn read_text(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        default: Option<&str>,
+        mask: Option<PasswordDisplayMode>,
+        validate: &dyn Fn(&str) -> Result<Validation, CustomUserError>,
+    ) -> InquireResult<String> {
+        let mut input = String::new();
+        let mut error = None;
+        let mut help = Help::new(help);
+
+        loop {
+            let shown = match mask {
+                None | Some(PasswordDisplayMode::Full) => input.clone(),
+                Some(PasswordDisplayMode::Masked) => "*".repeat(input.chars().count()),
+                Some(PasswordDisplayMode::Hidden) => String::new(),
+            };
+
+            self.draw(|f, area| {
+                let [title, field, error_area, help_area] = Layout::vertical([
+                    Constraint::Length(1),
+                    Constraint::Length(1),
+                    Constraint::Length(1),
+                    Constraint::Length(help.height(area.width)),
+                ])
+                .areas(area);
+
+                f.render_widget(Paragraph::new(message.to_string()).bold(), title);
+
+                let line = match default {
+                    Some(default) if input.is_empty() => {
+                        Line::from(vec![Span::raw("> "), Span::raw(default.to_string()).dim()])
+                    }
+                    _ => Line::from(format!("> {shown}")),
+                };
+                f.render_widget(Paragraph::new(line), field);
+                f.set_cursor_position((field.x + 2 + shown.chars().count() as u16, field.y));
+
+                if let Some(error) = &error {
+                    f.render_widget(
+                        Paragraph::new(format!("# {error}")).fg(Color::Red),
+                        error_area,
+                    );
+                }
+
+                help.render(f, help_area, fl!("help-hint-text"));
+            })?;
+
+            let key = self.read_key()?;
+            match key.code {
+                KeyCode::Enter => {
+                    let value = match default {
+                        Some(default) if input.is_empty() => default,
+                        _ => input.as_str(),
+                    };
+
+                    match validate(value).map_err(InquireError::Custom)? {
+                        Validation::Valid => return Ok(value.to_string()),
+                        Validation::Invalid(e) => error = Some(error_message(e)),
+                    }
+                }
+                // `?` may be part of the input
+                KeyCode::F(1) => help.toggle(),
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Esc => return Err(InquireError::OperationCanceled),
+                _ => {}
+            }
+        }
+    }
+
+    fn read_confirm(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        default: Option<bool>,
+        error_message: Option<&str>,
+    ) -> InquireResult<bool> {
+        let mut error = None;
+        let mut help = Help::new(help);
+        let hint = match default {
+            Some(true) => "(Y/n)",
+            Some(false) => "(y/N)",
+            None => "(y/n)",
+        };
+
+        loop {
+            self.draw(|f, area| {
+                let [title, error_area, help_area] = Layout::vertical([
+                    Constraint::Min(1),
+                    Constraint::Length(1),
+                    Constraint::Length(help.height(area.width)),
+                ])
+                .areas(area);
+
+                f.render_widget(
+                    Paragraph::new(Line::from(vec![
+                        Span::raw(message.to_string()).bold(),
+                        Span::raw(format!(" {hint}")).dim(),
+                    ]))
+                    .wrap(Wrap { trim: false }),
+                    title,
+                );
+
+                if let Some(error) = &error {
+                    f.render_widget(
+                        Paragraph::new(format!("# {error}")).fg(Color::Red),
+                        error_area,
+                    );
+                }
+
+                help.render(f, help_area, fl!("help-hint"));
+            })?;
+
+            let key = self.read_key()?;
+            match key.code {
+                KeyCode::Char('y' | 'Y') => return Ok(true),
+                KeyCode::Char('n' | 'N') => return Ok(false),
+                KeyCode::Char('?') | KeyCode::F(1) => help.toggle(),
+                KeyCode::Enter if default.is_some() => return Ok(default.unwrap_or_default()),
+                KeyCode::Esc => return Err(InquireError::OperationCanceled),
+                KeyCode::Null => {}
+                _ => {
+                    error = Some(
+                        error_message
+                            .map(|x| x.to_string())
+                            .unwrap_or_else(|| fl!("yn-confirm-required")),
+                    )
+                }
+            }
+        }
+    }
+
+    fn read_key(&self) -> InquireResult<KeyEvent> {
+        loop {
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    if is_interrupt(&key) {
+                        return Err(InquireError::OperationInterrupted);
+                    }
+
+                    return Ok(key);
+                }
+                // Let the caller redraw
+                Event::Resize(..) => return Ok(KeyEvent::from(KeyCode::Null)),
+                _ => {}
+            }
+        }
+    }
+
+    fn draw(&self, body: impl FnOnce(&mut Frame, Rect)) -> io::Result<()> {
+        let state = self.state.borrow();
+
+        self.terminal.borrow_mut().draw(|f| {
+            let [sidebar, main] =
+                Layout::horizontal([Constraint::Length(SIDEBAR_WIDTH), Constraint::Min(0)])
+                    .areas(f.area());
+            let [page, log] =
+                Layout::vertical([Constraint::Min(0), Constraint::Length(LOG_HEIGHT)]).areas(main);
+
+            let steps = Step::ALL
+                .iter()
+                .map(|step| {
+                    let (mark, style) = if *step == state.step {
+                        (">", Style::new().fg(Color::Cyan).bold())
+                    } else if *step < state.step {
+                        ("*", Style::new().fg(Color::Green))
+                    } else {
+                        (" ", Style::new().dim())
+                    };
+                    ListItem::new(format!("{mark} {}", step.name())).style(style)
+                })
+                .collect::<Vec<_>>();
+            f.render_widget(
+                List::new(steps).block(bordered().title(" AOSC OS ")),
+                sidebar,
+            );
+
+            let block = bordered().title(format!(" {} ", state.step.name()));
+            let inner = block.inner(page);
+            f.render_widget(block, page);
+
+            // Leave room for at least a few lines of the prompt itself
+            let answers_height = (state.answers.len() as u16).min(inner.height.saturating_sub(6));
+            let notice_height = if state.notice.is_some() { 1 } else { 0 };
+            let [answers, notice, body_area] = Layout::vertical([
+                Constraint::Length(answers_height + (answers_height > 0) as u16),
+                Constraint::Length(notice_height),
+                Constraint::Min(0),
+            ])
+            .areas(inner);
+
+            let lines = state
+                .answers
+                .iter()
+                .skip(state.answers.len() - answers_height as usize)
+                .map(|(label, value)| {
+                    Line::from(vec![
+                        Span::raw(format!("{label}: ")).dim(),
+                        Span::raw(value.as_str()).fg(Color::Cyan),
+                    ])
+                })
+                .collect::<Vec<_>>();
+            f.render_widget(Paragraph::new(lines), answers);
+
+            if let Some(message) = &state.notice {
+                f.render_widget(Paragraph::new(message.as_str()).fg(Color::Yellow), notice);
+            }
+
+            body(f, body_area);
+
+            let lines = self
+                .logs
+                .tail(log.height.saturating_sub(2) as usize)
+                .into_iter()
+                .map(Line::from)
+                .collect::<Vec<_>>();
+            f.render_widget(
+                Paragraph::new(lines).block(bordered().title(format!(" {} ", fl!("log")))),
+                log,
+            );
+        })?;
+
+        Ok(())
+    }
+}
+
+impl Prompter for Tui {
+    fn select(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        options: &[String],
+        cursor: usize,
+        scorer: Option<Scorer<'_, String>>,
+    ) -> InquireResult<usize> {
+        let index = self.read_select(message, help, options, cursor, scorer)?;
+        self.answer(message, &options[index]);
+
+        Ok(index)
+    }
+
+    fn multi_select(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        options: &[String],
+        default: &[usize],
+        scorer: Option<Scorer<'_, String>>,
+    ) -> InquireResult<Vec<usize>> {
+        let selected = self.read_multi_select(message, help, options, default, scorer)?;
+        self.answer(
+            message,
+            &selected
+                .iter()
+                .map(|i| options[*i].as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+
+        Ok(selected)
+    }
+
+    fn text(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        default: Option<&str>,
+        validators: &[Box<dyn StringValidator>],
+    ) -> InquireResult<String> {
+        let value = self.read_text(message, help, default, None, &|x| {
+            ui::validate_str(validators, x)
+        })?;
+        self.answer(message, &value);
+
+        Ok(value)
+    }
+
+    fn password(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        display_mode: PasswordDisplayMode,
+        confirmation_message: Option<&str>,
+        confirmation_error_message: Option<&str>,
+        validators: &[Box<dyn StringValidator>],
+    ) -> InquireResult<String> {
+        let password = ui::read_password(
+            message,
+            confirmation_message,
+            confirmation_error_message,
+            validators,
+            |message, validate| self.read_text(message, help, None, Some(display_mode), validate),
+            |error| self.notice(error),
+        )?;
+        self.answer(message, "********");
+
+        Ok(password)
+    }
+
+    fn confirm(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        default: Option<bool>,
+        error_message: Option<&str>,
+    ) -> InquireResult<bool> {
+        let value = self.read_confirm(message, help, default, error_message)?;
+        self.answer(message, &if value { fl!("yes") } else { fl!("no") });
+
+        Ok(value)
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        ratatui::restore();
+
+        // Keep the log visible once the full-screen interface is gone
+        for line in self.logs.take() {
+            eprintln!("{line}");
+        }
+    }
+}
+
+/// A bordered block, drawn with `+`, `-` and `|` in ASCII mode.
+fn bordered() -> Block<'static> {
+    let block = Block::bordered();
+    if ui::ascii() {
+        block.border_set(ASCII_BORDER)
+    } else {
+        block
+    }
+}
+
+const ASCII_BORDER: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+fn is_interrupt(key: &KeyEvent) -> bool {
+    key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c')
+}
+
+fn clamp_selection(list: &mut ListState, len: usize) {
+    if len == 0 {
+        list.select(None);
+    } else {
+        list.select(Some(list.selected().unwrap_or(0).min(len - 1)));
+    }
+}
+
+fn handle_list_key(code: KeyCode, list: &mut ListState, filter: &mut String) -> InquireResult<()> {
+    match code {
+        KeyCode::Up => list.select_previous(),
+        KeyCode::Down => list.select_next(),
+        KeyCode::PageUp => list.scroll_up_by(10),
+        KeyCode::PageDown => list.scroll_down_by(10),
+        KeyCode::Home => list.select_first(),
+        KeyCode::End => list.select_last(),
+        KeyCode::Backspace => {
+            filter.pop();
+            list.select_first();
+        }
+        KeyCode::Esc => return Err(InquireError::OperationCanceled),
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// The filter being typed, and how many of the options match it.
+fn filter_line(filter: &str, shown: usize, total: usize) -> Line<'static> {
+    Line::from(vec![
+        Span::raw(format!("> {filter}")),
+        Span::raw(format!(
+            "  ({})",
+            fl!("match-count", shown = shown, total = total)
+        )),
+    ])
+}
+
+fn render_list(
+    f: &mut Frame,
+    area: Rect,
+    message: &str,
+    help: &Help,
+    filter: Line,
+    items: Vec<ListItem>,
+    list: &mut ListState,
+) {
+    let [title, filter_area, list_area, help_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(help.height(area.width)),
+    ])
+    .areas(area);
+
+    f.render_widget(Paragraph::new(message.to_string()).bold(), title);
+    f.render_widget(Paragraph::new(filter).dim(), filter_area);
+    f.render_stateful_widget(
+        List::new(items)
+            .highlight_style(Style::new().fg(Color::Cyan).bold())
+            .highlight_symbol("> "),
+        list_area,
+        list,
+    );
+
+    help.render(f, help_area, fl!("help-hint"));
+}
+
+/// Help text of a prompt, collapsed to one line until expanded with `?`
+/// (or F1 where `?` is valid input).
+struct Help<'a> {
+    text: Option<&'a str>,
+    expanded: bool,
+}
+
+impl<'a> Help<'a> {
+    fn new(text: Option<&'a str>) -> Self {
+        Self {
+            text,
+            expanded: false,
+        }
+    }
+
+    fn toggle(&mut self) {
+        self.expanded = !self.expanded;
+    }
+
+    /// Lines needed: one when collapsed, all of the text wrapped when
+    /// expanded.
+    fn height(&self, width: u16) -> u16 {
+        match self.text {
+            None => 0,
+            Some(help) if self.expanded => {
+                let width = width.max(1) as usize;
+                help.lines()
+                    .map(|x| Line::raw(x).width().div_ceil(width).max(1))
+                    .sum::<usize>() as u16
+                    // Word wrapping leaves some lines short
+                    + 1
+            }
+            Some(_) => 1,
+        }
+    }
+
+    /// Show the text if it fits on one line or has been expanded, and
+    /// otherwise `hint` on how to expand it.
+    fn render(&self, f: &mut Frame, area: Rect, hint: String) {
+        let Some(help) = self.text else {
+            return;
+        };
+
+        let text = format!("[{help}]");
+        let paragraph = if self.expanded {
+            Paragraph::new(help.to_string()).wrap(Wrap { trim: true })
+        } else if Line::raw(text.as_str()).width() <= area.width as usize {
+            Paragraph::new(text)
+        } else {
+            Paragraph::new(format!("[{hint}]"))
+        };
+
+        f.render_widget(paragraph.dim(), area);
+    }
+}
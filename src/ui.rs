@@ -0,0 +1,809 @@
+//! The prompts the wizard asks through, whichever way they are drawn, and
+//! the progress bars and helpers they share.
+
+use std::{
+    cmp::Reverse,
+    fmt::Display,
+    io::{self, IsTerminal},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        LazyLock,
+    },
+    time::Duration,
+};
+
+use comfy_table::{presets::NOTHING, Table};
+use fuzzy_matcher::FuzzyMatcher;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use inquire::{
+    list_option::ListOption,
+    type_aliases::Scorer,
+    validator::{CustomTypeValidator, ErrorMessage, StringValidator, Validation},
+    Confirm, CustomUserError, InquireError, MultiSelect, Password, PasswordDisplayMode, Select,
+    Text,
+};
+use ratatui::crossterm::{
+    execute,
+    terminal::{self, SetTitle},
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{
+    events::{self, Event},
+    fl, plain,
+    tui::{Step, Tui},
+    FUZZY_MATCHER,
+};
+
+pub type InquireResult<T> = Result<T, InquireError>;
+
+/// All progress bars and spinners, so that they are drawn together.
+pub static MULTI_PROGRESS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+
+/// Whether to stick to ASCII in spinners, tables, borders and the like.
+pub static ASCII: AtomicBool = AtomicBool::new(false);
+
+pub fn ascii() -> bool {
+    ASCII.load(Ordering::Relaxed)
+}
+
+/// `style` with a spinner made of ASCII characters in ASCII mode.
+pub fn with_ascii_ticks(style: ProgressStyle) -> ProgressStyle {
+    if ascii() {
+        style.tick_chars("-\\|/ ")
+    } else {
+        style
+    }
+}
+
+/// Where the wizard asks its questions: inquire prompts on the current
+/// terminal, line-based prompts for screen readers, or the full-screen
+/// interface.
+pub enum Ui {
+    Inquire,
+    Plain,
+    Tui(Tui),
+    /// Questions asked through some other [`Prompter`], such as canned
+    /// answers in tests, with output printed as in plain mode
+    #[cfg_attr(not(test), allow(dead_code))]
+    Custom(Box<dyn Prompter + Send>),
+}
+
+impl Ui {
+    /// Move on to another wizard step. Only the full-screen interface shows
+    /// steps; prompts stay linear otherwise.
+    pub fn step(&self, step: Step) {
+        events::emit(Event::Step { step });
+        if let Ui::Tui(tui) = self {
+            tui.set_step(step);
+        }
+    }
+
+    /// Print some text that isn't a prompt. The full-screen interface sends
+    /// it to the log pane.
+    pub fn println(&self, text: &str) {
+        match self {
+            Ui::Inquire | Ui::Plain | Ui::Custom(_) => eprintln!("{text}"),
+            Ui::Tui(_) => {
+                for line in text.lines() {
+                    log::info!("{line}");
+                }
+            }
+        }
+    }
+
+    /// Print a table, without borders in plain mode so that screen readers
+    /// don't read out the box drawing.
+    pub fn table(&self, mut table: Table) {
+        if let Ui::Plain = self {
+            table.load_preset(NOTHING);
+        }
+
+        self.println(&table.to_string());
+    }
+
+    /// Show a titled list of `(label, value)` rows.
+    pub fn summary(&self, title: &str, rows: &[(String, String)]) {
+        match self {
+            Ui::Inquire | Ui::Plain | Ui::Custom(_) => {
+                let width = rows
+                    .iter()
+                    .map(|(label, _)| label.chars().count())
+                    .max()
+                    .unwrap_or(0);
+
+                eprintln!("\n{title}\n");
+                for (label, value) in rows {
+                    let pad = width - label.chars().count();
+                    eprintln!("  {label}{}  {value}", " ".repeat(pad));
+                }
+                eprintln!();
+            }
+            Ui::Tui(tui) => {
+                tui.clear();
+                for (label, value) in rows {
+                    tui.answer(label, value);
+                }
+                tui.notice(title);
+            }
+        }
+    }
+
+    /// Show a message while waiting on something, returning a spinner to
+    /// finish once done.
+    pub fn spinner(&self, message: String) -> ProgressBar {
+        match self {
+            Ui::Inquire => {
+                let pb = MULTI_PROGRESS.add(
+                    ProgressBar::new_spinner()
+                        .with_style(with_ascii_ticks(ProgressStyle::default_spinner())),
+                );
+                pb.set_message(message);
+                pb.enable_steady_tick(Duration::from_millis(100));
+                pb
+            }
+            Ui::Plain | Ui::Custom(_) => {
+                eprintln!("{message}");
+                ProgressBar::hidden()
+            }
+            Ui::Tui(tui) => {
+                tui.status(&message);
+                ProgressBar::hidden()
+            }
+        }
+    }
+
+    /// Set the terminal title, for users following the installation from
+    /// another tab. Left alone in plain mode, where the escape sequence may
+    /// be read out.
+    pub fn set_title(&self, title: &str) {
+        let mut stderr = io::stderr();
+        if matches!(self, Ui::Plain) || !stderr.is_terminal() {
+            return;
+        }
+
+        execute!(stderr, SetTitle(title)).ok();
+    }
+
+    /// Ring the terminal bell.
+    pub fn bell(&self) {
+        if io::stderr().is_terminal() {
+            eprint!("\x07");
+        }
+    }
+
+    /// What asks the questions in this interface.
+    pub fn prompter(&self) -> &dyn Prompter {
+        match self {
+            Ui::Inquire => &Inquire,
+            Ui::Plain => &plain::Plain,
+            Ui::Tui(tui) => tui,
+            Ui::Custom(prompter) => prompter.as_ref(),
+        }
+    }
+
+    pub fn select<'a>(&'a self, message: &'a str, options: Vec<String>) -> SelectPrompt<'a> {
+        SelectPrompt {
+            prompter: self.prompter(),
+            message,
+            options,
+            help: None,
+            cursor: 0,
+            scorer: None,
+        }
+    }
+
+    pub fn multi_select<'a>(
+        &'a self,
+        message: &'a str,
+        options: Vec<String>,
+    ) -> MultiSelectPrompt<'a> {
+        MultiSelectPrompt {
+            prompter: self.prompter(),
+            message,
+            options,
+            help: None,
+            default: &[],
+            scorer: None,
+        }
+    }
+
+    pub fn text<'a>(&'a self, message: &'a str) -> TextPrompt<'a> {
+        TextPrompt {
+            prompter: self.prompter(),
+            message,
+            help: None,
+            default: None,
+            validators: vec![],
+        }
+    }
+
+    pub fn password<'a>(&'a self, message: &'a str) -> PasswordPrompt<'a> {
+        PasswordPrompt {
+            prompter: self.prompter(),
+            message,
+            help: None,
+            display_mode: PasswordDisplayMode::Hidden,
+            confirmation_message: None,
+            confirmation_error_message: None,
+            validators: vec![],
+        }
+    }
+
+    pub fn confirm<'a>(&'a self, message: &'a str) -> ConfirmPrompt<'a> {
+        ConfirmPrompt {
+            prompter: self.prompter(),
+            message,
+            help: None,
+            default: None,
+            error_message: None,
+        }
+    }
+
+    pub fn custom_type<'a, T>(&'a self, message: &'a str) -> CustomTypePrompt<'a, T> {
+        CustomTypePrompt {
+            prompter: self.prompter(),
+            message,
+            help: None,
+            default: None,
+            validators: vec![],
+        }
+    }
+}
+
+/// The questions the wizard is made of, apart from how they are drawn, so
+/// that the wizard itself doesn't depend on a terminal library. Selections
+/// return indices into `options`; Esc and Ctrl-C come back as
+/// [`InquireError::OperationCanceled`] and
+/// [`InquireError::OperationInterrupted`] as with inquire.
+pub trait Prompter {
+    fn select(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        options: &[String],
+        cursor: usize,
+        scorer: Option<Scorer<'_, String>>,
+    ) -> InquireResult<usize>;
+
+    fn multi_select(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        options: &[String],
+        default: &[usize],
+        scorer: Option<Scorer<'_, String>>,
+    ) -> InquireResult<Vec<usize>>;
+
+    /// Read a line of text until it passes all of `validators`.
+    fn text(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        default: Option<&str>,
+        validators: &[Box<dyn StringValidator>],
+    ) -> InquireResult<String>;
+
+    /// Read a password and have it typed again to confirm.
+    fn password(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        display_mode: PasswordDisplayMode,
+        confirmation_message: Option<&str>,
+        confirmation_error_message: Option<&str>,
+        validators: &[Box<dyn StringValidator>],
+    ) -> InquireResult<String>;
+
+    fn confirm(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        default: Option<bool>,
+        error_message: Option<&str>,
+    ) -> InquireResult<bool>;
+}
+
+/// inquire prompts on the current terminal.
+pub struct Inquire;
+
+impl Prompter for Inquire {
+    fn select(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        options: &[String],
+        cursor: usize,
+        scorer: Option<Scorer<'_, String>>,
+    ) -> InquireResult<usize> {
+        let help = list_help(help, options.len());
+        let mut prompt = Select::new(message, fit_options(options))
+            .with_starting_cursor(cursor)
+            .with_page_size(page_size());
+        if let Some(help) = &help {
+            prompt = prompt.with_help_message(help);
+        }
+        if let Some(scorer) = scorer {
+            prompt = prompt.with_scorer(scorer);
+        }
+
+        Ok(prompt.raw_prompt()?.index)
+    }
+
+    fn multi_select(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        options: &[String],
+        default: &[usize],
+        scorer: Option<Scorer<'_, String>>,
+    ) -> InquireResult<Vec<usize>> {
+        let help = list_help(help, options.len());
+        let mut prompt = MultiSelect::new(message, fit_options(options))
+            .with_default(default)
+            .with_page_size(page_size());
+        if let Some(help) = &help {
+            prompt = prompt.with_help_message(help);
+        }
+        if let Some(scorer) = scorer {
+            prompt = prompt.with_scorer(scorer);
+        }
+
+        Ok(prompt.raw_prompt()?.into_iter().map(|x| x.index).collect())
+    }
+
+    fn text(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        default: Option<&str>,
+        validators: &[Box<dyn StringValidator>],
+    ) -> InquireResult<String> {
+        let mut prompt = Text::new(message).with_validators(validators);
+        if let Some(help) = help {
+            prompt = prompt.with_help_message(help);
+        }
+        if let Some(default) = default {
+            prompt = prompt.with_default(default);
+        }
+
+        prompt.prompt()
+    }
+
+    fn password(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        display_mode: PasswordDisplayMode,
+        confirmation_message: Option<&str>,
+        confirmation_error_message: Option<&str>,
+        validators: &[Box<dyn StringValidator>],
+    ) -> InquireResult<String> {
+        let mut prompt = Password::new(message)
+            .with_display_mode(display_mode)
+            .with_validators(validators);
+        if let Some(help) = help {
+            prompt = prompt.with_help_message(help);
+        }
+        if let Some(message) = confirmation_message {
+            prompt = prompt.with_custom_confirmation_message(message);
+        }
+        if let Some(message) = confirmation_error_message {
+            prompt = prompt.with_custom_confirmation_error_message(message);
+        }
+
+        prompt.prompt()
+    }
+
+    fn confirm(
+        &self,
+        message: &str,
+        help: Option<&str>,
+        default: Option<bool>,
+        error_message: Option<&str>,
+    ) -> InquireResult<bool> {
+        let mut prompt = Confirm::new(message);
+        if let Some(help) = help {
+            prompt = prompt.with_help_message(help);
+        }
+        if let Some(default) = default {
+            prompt = prompt.with_default(default);
+        }
+        if let Some(message) = error_message {
+            prompt = prompt.with_error_message(message);
+        }
+
+        prompt.prompt()
+    }
+}
+
+pub struct SelectPrompt<'a> {
+    prompter: &'a dyn Prompter,
+    message: &'a str,
+    options: Vec<String>,
+    help: Option<&'a str>,
+    cursor: usize,
+    scorer: Option<Scorer<'a, String>>,
+}
+
+impl<'a> SelectPrompt<'a> {
+    pub fn with_help_message(mut self, help: &'a str) -> Self {
+        self.help = Some(help);
+        self
+    }
+
+    pub fn with_starting_cursor(mut self, cursor: usize) -> Self {
+        self.cursor = cursor;
+        self
+    }
+
+    pub fn with_scorer(mut self, scorer: Scorer<'a, String>) -> Self {
+        self.scorer = Some(scorer);
+        self
+    }
+
+    pub fn prompt(self) -> InquireResult<String> {
+        self.raw_prompt().map(|x| x.value)
+    }
+
+    pub fn raw_prompt(self) -> InquireResult<ListOption<String>> {
+        let index = self.prompter.select(
+            self.message,
+            self.help,
+            &self.options,
+            self.cursor,
+            self.scorer,
+        )?;
+
+        Ok(ListOption::new(index, self.options[index].clone()))
+    }
+}
+
+pub struct MultiSelectPrompt<'a> {
+    prompter: &'a dyn Prompter,
+    message: &'a str,
+    options: Vec<String>,
+    help: Option<&'a str>,
+    default: &'a [usize],
+    scorer: Option<Scorer<'a, String>>,
+}
+
+impl<'a> MultiSelectPrompt<'a> {
+    pub fn with_help_message(mut self, help: &'a str) -> Self {
+        self.help = Some(help);
+        self
+    }
+
+    pub fn with_default(mut self, default: &'a [usize]) -> Self {
+        self.default = default;
+        self
+    }
+
+    pub fn with_scorer(mut self, scorer: Scorer<'a, String>) -> Self {
+        self.scorer = Some(scorer);
+        self
+    }
+
+    pub fn raw_prompt(self) -> InquireResult<Vec<ListOption<String>>> {
+        let selected = self.prompter.multi_select(
+            self.message,
+            self.help,
+            &self.options,
+            self.default,
+            self.scorer,
+        )?;
+
+        Ok(selected
+            .into_iter()
+            .map(|i| ListOption::new(i, self.options[i].clone()))
+            .collect())
+    }
+}
+
+pub struct TextPrompt<'a> {
+    prompter: &'a dyn Prompter,
+    message: &'a str,
+    help: Option<&'a str>,
+    default: Option<&'a str>,
+    validators: Vec<Box<dyn StringValidator>>,
+}
+
+impl<'a> TextPrompt<'a> {
+    pub fn with_help_message(mut self, help: &'a str) -> Self {
+        self.help = Some(help);
+        self
+    }
+
+    pub fn with_default(mut self, default: &'a str) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    pub fn with_validator<V: StringValidator + 'static>(mut self, validator: V) -> Self {
+        self.validators.push(Box::new(validator));
+        self
+    }
+
+    pub fn prompt(self) -> InquireResult<String> {
+        self.prompter
+            .text(self.message, self.help, self.default, &self.validators)
+    }
+}
+
+pub struct PasswordPrompt<'a> {
+    prompter: &'a dyn Prompter,
+    message: &'a str,
+    help: Option<&'a str>,
+    display_mode: PasswordDisplayMode,
+    confirmation_message: Option<&'a str>,
+    confirmation_error_message: Option<&'a str>,
+    validators: Vec<Box<dyn StringValidator>>,
+}
+
+impl<'a> PasswordPrompt<'a> {
+    pub fn with_help_message(mut self, help: &'a str) -> Self {
+        self.help = Some(help);
+        self
+    }
+
+    pub fn with_display_mode(mut self, display_mode: PasswordDisplayMode) -> Self {
+        self.display_mode = display_mode;
+        self
+    }
+
+    pub fn with_custom_confirmation_message(mut self, message: &'a str) -> Self {
+        self.confirmation_message = Some(message);
+        self
+    }
+
+    pub fn with_custom_confirmation_error_message(mut self, message: &'a str) -> Self {
+        self.confirmation_error_message = Some(message);
+        self
+    }
+
+    pub fn with_validator<V: StringValidator + 'static>(mut self, validator: V) -> Self {
+        self.validators.push(Box::new(validator));
+        self
+    }
+
+    pub fn prompt(self) -> InquireResult<String> {
+        self.prompter.password(
+            self.message,
+            self.help,
+            self.display_mode,
+            self.confirmation_message,
+            self.confirmation_error_message,
+            &self.validators,
+        )
+    }
+}
+
+pub struct ConfirmPrompt<'a> {
+    prompter: &'a dyn Prompter,
+    message: &'a str,
+    help: Option<&'a str>,
+    default: Option<bool>,
+    error_message: Option<&'a str>,
+}
+
+impl<'a> ConfirmPrompt<'a> {
+    pub fn with_help_message(mut self, help: &'a str) -> Self {
+        self.help = Some(help);
+        self
+    }
+
+    pub fn with_default(mut self, default: bool) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    pub fn with_error_message(mut self, message: &'a str) -> Self {
+        self.error_message = Some(message);
+        self
+    }
+
+    pub fn prompt(self) -> InquireResult<bool> {
+        self.prompter
+            .confirm(self.message, self.help, self.default, self.error_message)
+    }
+}
+
+/// A value of type `T` typed in as text.
+pub struct CustomTypePrompt<'a, T> {
+    prompter: &'a dyn Prompter,
+    message: &'a str,
+    help: Option<&'a str>,
+    default: Option<T>,
+    validators: Vec<Box<dyn CustomTypeValidator<T>>>,
+}
+
+impl<'a, T> CustomTypePrompt<'a, T>
+where
+    T: Clone + Display + FromStr + 'static,
+{
+    pub fn with_help_message(mut self, help: &'a str) -> Self {
+        self.help = Some(help);
+        self
+    }
+
+    pub fn with_default(mut self, default: T) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    pub fn with_validator<V: CustomTypeValidator<T> + 'static>(mut self, validator: V) -> Self {
+        self.validators.push(Box::new(validator));
+        self
+    }
+
+    pub fn prompt(self) -> InquireResult<T> {
+        let validators = self.validators;
+        let validator: Box<dyn StringValidator> = Box::new(move |input: &str| {
+            let Ok(value) = input.parse::<T>() else {
+                return Ok(Validation::Invalid(fl!("invalid-value").into()));
+            };
+
+            for i in &validators {
+                if let Validation::Invalid(e) = i.validate(&value)? {
+                    return Ok(Validation::Invalid(e));
+                }
+            }
+
+            Ok(Validation::Valid)
+        });
+
+        let default = self.default.as_ref().map(|x| x.to_string());
+        self.prompter
+            .text(self.message, self.help, default.as_deref(), &[validator])?
+            .parse::<T>()
+            .map_err(|_| InquireError::InvalidConfiguration(fl!("invalid-value")))
+    }
+}
+
+/// Ask for a password twice with `read` until both match, telling about a
+/// mismatch with `mismatch`, for the interfaces where inquire doesn't.
+pub fn read_password(
+    message: &str,
+    confirmation_message: Option<&str>,
+    confirmation_error_message: Option<&str>,
+    validators: &[Box<dyn StringValidator>],
+    read: impl Fn(&str, &dyn Fn(&str) -> Result<Validation, CustomUserError>) -> InquireResult<String>,
+    mismatch: impl Fn(&str),
+) -> InquireResult<String> {
+    let confirmation = confirmation_message.unwrap_or(message);
+
+    loop {
+        let password = read(message, &|x| validate_str(validators, x))?;
+        let confirm = read(confirmation, &|_| Ok(Validation::Valid))?;
+
+        if password == confirm {
+            return Ok(password);
+        }
+
+        mismatch(
+            &confirmation_error_message
+                .map(|x| x.to_string())
+                .unwrap_or_else(|| fl!("confirm-password-not-matching")),
+        );
+    }
+}
+
+/// Whether `input` passes all of `validators`, with the first complaint
+/// if not.
+pub fn validate_str(
+    validators: &[Box<dyn StringValidator>],
+    input: &str,
+) -> Result<Validation, CustomUserError> {
+    for i in validators {
+        if let Validation::Invalid(e) = i.validate(input)? {
+            return Ok(Validation::Invalid(e));
+        }
+    }
+
+    Ok(Validation::Valid)
+}
+
+/// Width of the terminal, or that of a serial console if unknown.
+pub fn terminal_width() -> usize {
+    terminal::size().map_or(80, |(width, _)| width as usize)
+}
+
+/// `options` shortened to fit on one line of the terminal each, next to the
+/// cursor and checkbox of a list prompt.
+fn fit_options(options: &[String]) -> Vec<String> {
+    let width = terminal_width().saturating_sub(8);
+
+    options.iter().map(|x| ellipsize(x, width)).collect()
+}
+
+/// Cut `s` down to `width` columns, ending with an ellipsis if anything was
+/// cut.
+pub fn ellipsize(s: &str, width: usize) -> String {
+    if UnicodeWidthStr::width(s) <= width {
+        return s.to_string();
+    }
+
+    let ellipsis = if ascii() { "..." } else { "…" };
+
+    let mut used = UnicodeWidthStr::width(ellipsis);
+    let mut out = s
+        .chars()
+        .take_while(|c| {
+            used += c.width().unwrap_or(0);
+            used <= width
+        })
+        .collect::<String>();
+    out.push_str(ellipsis);
+
+    out
+}
+
+/// Options shown at once in a list, as many as fit in the terminal next to
+/// the question, filter and help lines.
+fn page_size() -> usize {
+    let height = terminal::size().map_or(24, |(_, height)| height as usize);
+
+    height.saturating_sub(4).max(3)
+}
+
+/// Help of a list prompt, mentioning how many options there are when they
+/// don't fit on one page.
+fn list_help(help: Option<&str>, total: usize) -> Option<String> {
+    // The default help of inquire has arrows in it
+    let help = match help {
+        None if ascii() => Some(fl!("list-keys")),
+        _ => help.map(|x| x.to_string()),
+    };
+
+    if total <= page_size() {
+        return help;
+    }
+
+    let count = fl!("list-count", total = total);
+    Some(match help {
+        Some(help) => format!("{count} | {help}"),
+        None => count,
+    })
+}
+
+pub fn error_message(e: ErrorMessage) -> String {
+    match e {
+        ErrorMessage::Default => fl!("invalid-value"),
+        ErrorMessage::Custom(s) => s,
+    }
+}
+
+/// Indices of the options matching `filter`, best match first.
+pub fn filter_options(
+    options: &[String],
+    filter: &str,
+    scorer: Option<Scorer<'_, String>>,
+) -> Vec<usize> {
+    if filter.is_empty() {
+        return (0..options.len()).collect();
+    }
+
+    let mut scored = options
+        .iter()
+        .enumerate()
+        .filter_map(|(i, x)| {
+            let score = match scorer {
+                Some(scorer) => scorer(filter, x, x, i),
+                None => FUZZY_MATCHER.fuzzy_match(x, filter),
+            };
+            score.map(|score| (i, score))
+        })
+        .collect::<Vec<_>>();
+    scored.sort_by_key(|(_, score)| Reverse(*score));
+
+    scored.into_iter().map(|(i, _)| i).collect()
+}
+
+#[test]
+fn test_ellipsize() {
+    assert_eq!(ellipsize("Asia/Shanghai", 20), "Asia/Shanghai");
+    assert_eq!(ellipsize("Asia/Shanghai", 13), "Asia/Shanghai");
+    assert_eq!(ellipsize("Asia/Shanghai", 8), "Asia/Sh…");
+    assert_eq!(ellipsize("中文（简体）", 7), "中文（…");
+}
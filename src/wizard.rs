@@ -0,0 +1,221 @@
+//! Order of the wizard steps and of the questions in them, and how the user
+//! moves between them, apart from the prompts themselves.
+
+use crate::tui::Step;
+
+/// Where the wizard is at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Ask(Step),
+    /// Gone back from the first step, so ask whether to quit
+    ConfirmQuit,
+    Done,
+    Quit,
+}
+
+/// How the user left the current state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    /// Answered the step, or chose to stay rather than quit
+    Answered,
+    /// Pressed Esc
+    Back,
+    /// Chose to quit, or pressed Ctrl-C
+    Quit,
+}
+
+/// How one question of a step went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Asked {
+    /// Answered, so that Esc on a later question comes back to it
+    Yes,
+    /// Not asked, there being nothing to choose
+    No,
+    /// Answered in a way that asks again from question `n` of the step on
+    Again(usize),
+    /// Answered in a way that leaves nothing more to ask in the step
+    Done,
+}
+
+/// Questions of one step answered so far, so that Esc goes back to the
+/// question before rather than to the step before.
+#[derive(Debug, Default)]
+pub struct Questions {
+    // Indices of the questions answered, in the order they were
+    answered: Vec<usize>,
+    // Where to start asking
+    start: usize,
+}
+
+impl Questions {
+    /// Index of the question to start the step from.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Note that question `n` was answered.
+    pub fn answered(&mut self, n: usize) {
+        self.answered.push(n);
+    }
+
+    /// The question to go back to from the current one, none on the first.
+    pub fn back(&mut self) -> Option<usize> {
+        self.answered.pop()
+    }
+
+    /// Ask again from question `n` on, forgetting the answers since.
+    pub fn back_to(&mut self, n: usize) -> usize {
+        self.answered.retain(|x| *x < n);
+        n
+    }
+
+    fn restart(&mut self) {
+        self.answered.clear();
+        self.start = 0;
+    }
+
+    /// Coming back from the next step, to the last question answered.
+    fn resume(&mut self) {
+        self.start = self.answered.pop().unwrap_or(0);
+    }
+}
+
+pub struct Wizard {
+    // Steps to ask, leaving out those answered beforehand
+    steps: Vec<Step>,
+    // Those of each of steps
+    questions: Vec<Questions>,
+    state: State,
+    // Index of the current step in steps
+    current: usize,
+}
+
+impl Wizard {
+    /// A wizard going through `steps`, other than those `answered` already.
+    pub fn new(steps: &[Step], answered: impl Fn(Step) -> bool) -> Self {
+        let steps = steps
+            .iter()
+            .copied()
+            .filter(|x| !answered(*x))
+            .collect::<Vec<_>>();
+        let state = steps.first().map_or(State::Done, |x| State::Ask(*x));
+        let questions = steps.iter().map(|_| Questions::default()).collect();
+
+        Self {
+            steps,
+            questions,
+            state,
+            current: 0,
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Those of the current step.
+    pub fn questions(&mut self) -> &mut Questions {
+        &mut self.questions[self.current]
+    }
+
+    /// Move on after `input`, returning the new state.
+    pub fn transition(&mut self, input: Input) -> State {
+        self.state = match (self.state, input) {
+            (State::Done | State::Quit, _) => self.state,
+            (_, Input::Quit) => State::Quit,
+            (State::Ask(_), Input::Answered) if self.current + 1 == self.steps.len() => State::Done,
+            (State::Ask(_), Input::Answered) => {
+                self.current += 1;
+                self.questions[self.current].restart();
+                State::Ask(self.steps[self.current])
+            }
+            (State::Ask(_), Input::Back) if self.current == 0 => State::ConfirmQuit,
+            (State::Ask(_), Input::Back) => {
+                self.current -= 1;
+                self.questions[self.current].resume();
+                State::Ask(self.steps[self.current])
+            }
+            // Staying, whether by saying so or with Esc
+            (State::ConfirmQuit, Input::Answered | Input::Back) => {
+                self.questions[0].restart();
+                State::Ask(self.steps[0])
+            }
+        };
+
+        self.state
+    }
+}
+
+#[test]
+fn test_wizard() {
+    let steps = [Step::Language, Step::Source, Step::Disk, Step::Confirm];
+
+    let mut wizard = Wizard::new(&steps, |_| false);
+    assert_eq!(wizard.state(), State::Ask(Step::Language));
+    assert_eq!(wizard.transition(Input::Answered), State::Ask(Step::Source));
+    assert_eq!(wizard.transition(Input::Answered), State::Ask(Step::Disk));
+    assert_eq!(wizard.transition(Input::Back), State::Ask(Step::Source));
+    assert_eq!(wizard.transition(Input::Back), State::Ask(Step::Language));
+    assert_eq!(wizard.transition(Input::Back), State::ConfirmQuit);
+    assert_eq!(wizard.transition(Input::Back), State::Ask(Step::Language));
+    assert_eq!(wizard.transition(Input::Back), State::ConfirmQuit);
+    assert_eq!(
+        wizard.transition(Input::Answered),
+        State::Ask(Step::Language)
+    );
+    for _ in 0..3 {
+        wizard.transition(Input::Answered);
+    }
+    assert_eq!(wizard.state(), State::Ask(Step::Confirm));
+    assert_eq!(wizard.transition(Input::Answered), State::Done);
+    assert_eq!(wizard.transition(Input::Back), State::Done);
+
+    let mut wizard = Wizard::new(&steps, |_| false);
+    wizard.transition(Input::Answered);
+    assert_eq!(wizard.transition(Input::Quit), State::Quit);
+    assert_eq!(wizard.transition(Input::Answered), State::Quit);
+
+    // Steps answered beforehand are skipped both ways
+    let mut wizard = Wizard::new(&steps, |x| matches!(x, Step::Language | Step::Disk));
+    assert_eq!(wizard.state(), State::Ask(Step::Source));
+    assert_eq!(
+        wizard.transition(Input::Answered),
+        State::Ask(Step::Confirm)
+    );
+    assert_eq!(wizard.transition(Input::Back), State::Ask(Step::Source));
+    assert_eq!(wizard.transition(Input::Back), State::ConfirmQuit);
+
+    assert_eq!(Wizard::new(&steps, |_| true).state(), State::Done);
+}
+
+#[test]
+fn test_questions() {
+    let steps = [Step::Source, Step::Disk];
+    let mut wizard = Wizard::new(&steps, |_| false);
+
+    let questions = wizard.questions();
+    assert_eq!(questions.start(), 0);
+    questions.answered(0);
+    questions.answered(2);
+    assert_eq!(questions.back(), Some(2));
+    questions.answered(2);
+    questions.answered(3);
+    assert_eq!(questions.back_to(1), 1);
+    assert_eq!(questions.back(), Some(0));
+    assert_eq!(questions.back(), None);
+    questions.answered(0);
+    questions.answered(1);
+
+    // Coming back from the next step, to the last question answered
+    wizard.transition(Input::Answered);
+    assert_eq!(wizard.questions().start(), 0);
+    wizard.transition(Input::Back);
+    assert_eq!(wizard.questions().start(), 1);
+    assert_eq!(wizard.questions().back(), Some(0));
+
+    // Starting over, going forward again
+    wizard.transition(Input::Answered);
+    wizard.transition(Input::Back);
+    assert_eq!(wizard.questions().start(), 0);
+    assert_eq!(wizard.questions().back(), None);
+}